@@ -0,0 +1,175 @@
+use std::path::Path;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+
+use crate::audit::Principal;
+use crate::error::S3Error;
+use crate::server::AppState;
+use crate::throttle::RateLimits;
+
+/// When the server started, for the uptime in ServerInfo.
+pub static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Space on the filesystem holding a path.
+pub struct DiskStats {
+    pub total: u64,
+    pub free: u64,
+}
+
+#[cfg(unix)]
+pub fn disk_stats(path: &Path) -> std::io::Result<DiskStats> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid NUL-terminated string and stat is a valid out pointer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let block = stat.f_frsize as u64;
+    Ok(DiskStats {
+        total: stat.f_blocks as u64 * block,
+        free: stat.f_bavail as u64 * block,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn disk_stats(_path: &Path) -> std::io::Result<DiskStats> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "disk statistics are only available on Unix",
+    ))
+}
+
+/// `/minio/health/live`: the process is up and serving requests.
+pub async fn health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// `/minio/health/ready`: the data directory is writable and has the
+/// configured minimum of free space.
+pub async fn health_ready(State(state): State<AppState>) -> Response {
+    match check_ready(&state).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(reason) => {
+            tracing::warn!("Readiness check failed: {}", reason);
+            (StatusCode::SERVICE_UNAVAILABLE, reason).into_response()
+        }
+    }
+}
+
+async fn check_ready(state: &AppState) -> Result<(), String> {
+    let data_dir = Path::new(&state.config.data_dir);
+    let probe = data_dir.join(format!(".health-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&probe, b"ok")
+        .await
+        .map_err(|e| format!("Data directory is not writable: {}", e))?;
+    let _ = tokio::fs::remove_file(&probe).await;
+
+    let stats = disk_stats(data_dir).map_err(|e| format!("Cannot read disk usage: {}", e))?;
+    if stats.free < state.config.ready_min_free_bytes {
+        return Err(format!(
+            "Only {} bytes free, {} required",
+            stats.free, state.config.ready_min_free_bytes
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub mode: &'static str,
+    pub version: &'static str,
+    pub region: String,
+    pub uptime: u64,
+    pub buckets: usize,
+    pub disk: DiskInfo,
+    pub erasure_coding: ErasureCodingInfo,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskInfo {
+    pub path: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErasureCodingInfo {
+    pub enabled: bool,
+    pub chunk_size: u64,
+    pub parity_shards: u32,
+}
+
+/// `GET /minio/admin/v3/info`: version, uptime, disk usage and erasure
+/// coding settings. Signed like any S3 request.
+pub async fn server_info(State(state): State<AppState>) -> Result<Json<ServerInfo>, S3Error> {
+    let config = &state.config;
+    let stats = disk_stats(Path::new(&config.data_dir)).map_err(S3Error::internal)?;
+    let buckets = state.storage.list_buckets().await.map_err(S3Error::internal)?;
+    Ok(Json(ServerInfo {
+        mode: "online",
+        version: env!("MAXIO_VERSION"),
+        region: config.region.clone(),
+        uptime: STARTED_AT.elapsed().as_secs(),
+        buckets: buckets.len(),
+        disk: DiskInfo {
+            path: config.data_dir.clone(),
+            total_bytes: stats.total,
+            used_bytes: stats.total.saturating_sub(stats.free),
+            free_bytes: stats.free,
+        },
+        erasure_coding: ErasureCodingInfo {
+            enabled: config.erasure_coding,
+            chunk_size: config.chunk_size,
+            parity_shards: config.parity_shards,
+        },
+    }))
+}
+
+fn require_root(state: &AppState, principal: &Principal) -> Result<(), S3Error> {
+    if state.credentials.is_root(&principal.0) {
+        Ok(())
+    } else {
+        Err(S3Error::access_denied("Only the root user can manage rate limits"))
+    }
+}
+
+/// `GET /minio/admin/v3/rate-limits`: the request rate and bandwidth limits
+/// in force per access key and per bucket.
+pub async fn get_rate_limits(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+) -> Result<Json<RateLimits>, S3Error> {
+    require_root(&state, &principal)?;
+    Ok(Json(state.throttle.limits()))
+}
+
+/// `PUT /minio/admin/v3/rate-limits`: replace the limits until the next
+/// restart, when the command-line defaults apply again.
+pub async fn put_rate_limits(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    body: Bytes,
+) -> Result<Json<RateLimits>, S3Error> {
+    require_root(&state, &principal)?;
+    let limits: RateLimits = serde_json::from_slice(&body)
+        .map_err(|e| S3Error::invalid_argument(&format!("Invalid rate limits: {}", e)))?;
+    tracing::info!("Rate limits changed by {}", principal.0);
+    state.throttle.set_limits(limits);
+    Ok(Json(state.throttle.limits()))
+}
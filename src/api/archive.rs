@@ -0,0 +1,431 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use axum::body::{Body, Bytes};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::{Compression, write::GzEncoder};
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+
+use crate::storage::StorageError;
+use crate::storage::filesystem::FilesystemStorage;
+
+/// Output is handed to the response in chunks of about this size.
+const FLUSH_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Stored (uncompressed) entries, with ZIP64 records where sizes need them.
+    Zip,
+    /// POSIX tar with PAX headers for long names and sizes, gzipped.
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "zip" => Some(Self::Zip),
+            "tar.gz" | "tgz" => Some(Self::TarGz),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Zip => "application/zip",
+            Self::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// An object to archive and the path it gets inside the archive.
+pub struct ArchiveEntry {
+    pub key: String,
+    pub name: String,
+}
+
+/// Stream an archive of `entries` as a response body. Objects are opened with
+/// `get_object` one at a time as the archive reaches them, so erasure-coded
+/// objects are verified (and rebuilt if need be) chunk by chunk and nothing
+/// touches disk. A read failure aborts the response instead of finishing a
+/// corrupt archive.
+pub fn stream(storage: Arc<FilesystemStorage>, bucket: String, entries: Vec<ArchiveEntry>, format: ArchiveFormat) -> Body {
+    let (tx, mut rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let mut out = Output::new(tx.clone(), format);
+        let written = match format {
+            ArchiveFormat::Zip => write_zip(&storage, &bucket, &entries, &mut out).await,
+            ArchiveFormat::TarGz => write_tar(&storage, &bucket, &entries, &mut out).await,
+        };
+        let finished = match written {
+            Ok(()) => out.finish().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = finished {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                tracing::warn!("Archive of bucket {} failed: {}", bucket, e);
+            }
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+    Body::from_stream(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
+
+/// Buffers archive bytes, gzipping them for tar.gz, and sends them on.
+struct Output {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+    gzip: Option<GzEncoder<Vec<u8>>>,
+    /// Archive bytes so far, before compression; ZIP offsets count these.
+    written: u64,
+}
+
+impl Output {
+    fn new(tx: mpsc::Sender<io::Result<Bytes>>, format: ArchiveFormat) -> Self {
+        Self {
+            tx,
+            buf: Vec::new(),
+            gzip: (format == ArchiveFormat::TarGz).then(|| GzEncoder::new(Vec::new(), Compression::default())),
+            written: 0,
+        }
+    }
+
+    async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.written += data.len() as u64;
+        let pending = match &mut self.gzip {
+            Some(gzip) => {
+                gzip.write_all(data)?;
+                gzip.get_mut()
+            }
+            None => {
+                self.buf.extend_from_slice(data);
+                &mut self.buf
+            }
+        };
+        let chunk = (pending.len() >= FLUSH_SIZE).then(|| std::mem::take(pending));
+        match chunk {
+            Some(chunk) => self.send(chunk).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn finish(&mut self) -> io::Result<()> {
+        let rest = match self.gzip.take() {
+            Some(gzip) => gzip.finish()?,
+            None => std::mem::take(&mut self.buf),
+        };
+        if rest.is_empty() { Ok(()) } else { self.send(rest).await }
+    }
+
+    async fn send(&self, chunk: Vec<u8>) -> io::Result<()> {
+        self.tx
+            .send(Ok(Bytes::from(chunk)))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "download cancelled"))
+    }
+}
+
+/// An opened object: its reader, size and modification time.
+struct Opened {
+    reader: crate::storage::ByteStream,
+    size: u64,
+    modified: DateTime<Utc>,
+}
+
+/// Open an entry's object; `None` if it was deleted since it was listed.
+async fn open(storage: &FilesystemStorage, bucket: &str, entry: &ArchiveEntry) -> io::Result<Option<Opened>> {
+    match storage.get_object(bucket, &entry.key).await {
+        Ok((reader, meta)) => Ok(Some(Opened {
+            reader,
+            size: meta.size,
+            modified: DateTime::parse_from_rfc3339(&meta.last_modified)
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        })),
+        Err(StorageError::NotFound(_)) => Ok(None),
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+/// Copy an object into the archive, returning its CRC-32. The header before
+/// it already promised `size` bytes, so any other amount is an error.
+async fn copy_object(opened: &mut Opened, name: &str, out: &mut Output) -> io::Result<u32> {
+    let mut crc = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; FLUSH_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let n = opened.reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        copied += n as u64;
+        if copied > opened.size {
+            break;
+        }
+        crc.update(&buf[..n]);
+        out.write(&buf[..n]).await?;
+    }
+    if copied != opened.size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} changed size while being archived", name),
+        ));
+    }
+    Ok(crc.finalize())
+}
+
+// --- ZIP ---
+
+const ZIP64_LIMIT: u64 = 0xFFFF_FFFF;
+/// Data descriptor follows the data (bit 3); names are UTF-8 (bit 11).
+const ZIP_FLAGS: u16 = 0x0808;
+const ZIP_VERSION: u16 = 20;
+const ZIP64_VERSION: u16 = 45;
+
+/// What the central directory needs to know about a written entry.
+struct ZipRecord {
+    name: String,
+    size: u64,
+    crc: u32,
+    offset: u64,
+    time: u16,
+    date: u16,
+}
+
+fn dos_time(t: DateTime<Utc>) -> (u16, u16) {
+    if t.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (t.hour() << 11) | (t.minute() << 5) | (t.second() / 2);
+    let date = (((t.year() - 1980) as u32).min(127) << 9) | (t.month() << 5) | t.day();
+    (time as u16, date as u16)
+}
+
+async fn write_zip(storage: &FilesystemStorage, bucket: &str, entries: &[ArchiveEntry], out: &mut Output) -> io::Result<()> {
+    let mut records = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Some(mut opened) = open(storage, bucket, entry).await? else {
+            continue;
+        };
+        let (time, date) = dos_time(opened.modified);
+        let zip64 = opened.size >= ZIP64_LIMIT;
+        let offset = out.written;
+
+        let mut header = Vec::with_capacity(30 + entry.name.len() + 20);
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&(if zip64 { ZIP64_VERSION } else { ZIP_VERSION }).to_le_bytes());
+        header.extend_from_slice(&ZIP_FLAGS.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored
+        header.extend_from_slice(&time.to_le_bytes());
+        header.extend_from_slice(&date.to_le_bytes());
+        // CRC and sizes come in the data descriptor
+        header.extend_from_slice(&0u32.to_le_bytes());
+        let size_field = if zip64 { u32::MAX } else { 0 };
+        header.extend_from_slice(&size_field.to_le_bytes());
+        header.extend_from_slice(&size_field.to_le_bytes());
+        header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(if zip64 { 20u16 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(entry.name.as_bytes());
+        if zip64 {
+            header.extend_from_slice(&0x0001u16.to_le_bytes());
+            header.extend_from_slice(&16u16.to_le_bytes());
+            header.extend_from_slice(&[0u8; 16]);
+        }
+        out.write(&header).await?;
+
+        let crc = copy_object(&mut opened, &entry.name, out).await?;
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&0x0807_4b50u32.to_le_bytes());
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        if zip64 {
+            descriptor.extend_from_slice(&opened.size.to_le_bytes());
+            descriptor.extend_from_slice(&opened.size.to_le_bytes());
+        } else {
+            descriptor.extend_from_slice(&(opened.size as u32).to_le_bytes());
+            descriptor.extend_from_slice(&(opened.size as u32).to_le_bytes());
+        }
+        out.write(&descriptor).await?;
+
+        records.push(ZipRecord {
+            name: entry.name.clone(),
+            size: opened.size,
+            crc,
+            offset,
+            time,
+            date,
+        });
+    }
+
+    let directory_offset = out.written;
+    for record in &records {
+        out.write(&central_record(record)).await?;
+    }
+    let directory_size = out.written - directory_offset;
+    out.write(&end_of_directory(records.len() as u64, directory_size, directory_offset)).await
+}
+
+fn central_record(record: &ZipRecord) -> Vec<u8> {
+    // Fields too large for 32 bits move to the ZIP64 extra field, in this order
+    let mut extra = Vec::new();
+    if record.size >= ZIP64_LIMIT {
+        extra.extend_from_slice(&record.size.to_le_bytes());
+        extra.extend_from_slice(&record.size.to_le_bytes());
+    }
+    if record.offset >= ZIP64_LIMIT {
+        extra.extend_from_slice(&record.offset.to_le_bytes());
+    }
+    let zip64 = !extra.is_empty();
+    if zip64 {
+        let mut field = Vec::with_capacity(4 + extra.len());
+        field.extend_from_slice(&0x0001u16.to_le_bytes());
+        field.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        field.extend_from_slice(&extra);
+        extra = field;
+    }
+    let size = record.size.min(ZIP64_LIMIT) as u32;
+    let version = if zip64 { ZIP64_VERSION } else { ZIP_VERSION };
+
+    let mut buf = Vec::with_capacity(46 + record.name.len() + extra.len());
+    buf.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+    buf.extend_from_slice(&((3 << 8) | ZIP64_VERSION).to_le_bytes()); // made by: Unix
+    buf.extend_from_slice(&version.to_le_bytes());
+    buf.extend_from_slice(&ZIP_FLAGS.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&record.time.to_le_bytes());
+    buf.extend_from_slice(&record.date.to_le_bytes());
+    buf.extend_from_slice(&record.crc.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    buf.extend_from_slice(&(record.name.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes()); // comment
+    buf.extend_from_slice(&0u16.to_le_bytes()); // disk
+    buf.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+    buf.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+    buf.extend_from_slice(&(record.offset.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    buf.extend_from_slice(record.name.as_bytes());
+    buf.extend_from_slice(&extra);
+    buf
+}
+
+fn end_of_directory(count: u64, size: u64, offset: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(98);
+    let zip64 = count >= 0xFFFF || size >= ZIP64_LIMIT || offset >= ZIP64_LIMIT;
+    if zip64 {
+        let record_offset = offset + size;
+        buf.extend_from_slice(&0x0606_4b50u32.to_le_bytes());
+        buf.extend_from_slice(&44u64.to_le_bytes());
+        buf.extend_from_slice(&((3 << 8) | ZIP64_VERSION).to_le_bytes());
+        buf.extend_from_slice(&ZIP64_VERSION.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&offset.to_le_bytes());
+        // Locator
+        buf.extend_from_slice(&0x0706_4b50u32.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&record_offset.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+    }
+    let count = count.min(0xFFFF) as u16;
+    buf.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    buf.extend_from_slice(&(size.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    buf.extend_from_slice(&(offset.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf
+}
+
+// --- tar ---
+
+const TAR_BLOCK: usize = 512;
+/// Largest size the 11 octal digits of a ustar size field hold.
+const TAR_MAX_SIZE: u64 = 0o77777777777;
+
+async fn write_tar(storage: &FilesystemStorage, bucket: &str, entries: &[ArchiveEntry], out: &mut Output) -> io::Result<()> {
+    for entry in entries {
+        let Some(mut opened) = open(storage, bucket, entry).await? else {
+            continue;
+        };
+        let mtime = opened.modified.timestamp().max(0) as u64;
+
+        // Names over 100 bytes and sizes over 8 GiB go in a PAX extended header
+        let mut pax = String::new();
+        if entry.name.len() > 100 {
+            pax.push_str(&pax_record("path", &entry.name));
+        }
+        if opened.size > TAR_MAX_SIZE {
+            pax.push_str(&pax_record("size", &opened.size.to_string()));
+        }
+        if !pax.is_empty() {
+            let pax_name = format!("PaxHeaders/{}", truncate_name(&entry.name, 89));
+            out.write(&tar_header(&pax_name, pax.len() as u64, mtime, b'x')).await?;
+            out.write(pax.as_bytes()).await?;
+            out.write(&[0u8; TAR_BLOCK][..tar_padding(pax.len() as u64)]).await?;
+        }
+
+        out.write(&tar_header(truncate_name(&entry.name, 100), opened.size, mtime, b'0')).await?;
+        copy_object(&mut opened, &entry.name, out).await?;
+        out.write(&[0u8; TAR_BLOCK][..tar_padding(opened.size)]).await?;
+    }
+    out.write(&[0u8; 2 * TAR_BLOCK]).await
+}
+
+fn tar_padding(size: u64) -> usize {
+    (TAR_BLOCK - (size % TAR_BLOCK as u64) as usize) % TAR_BLOCK
+}
+
+/// The longest prefix of `name` within `max` bytes that ends on a character boundary.
+fn truncate_name(name: &str, max: usize) -> &str {
+    let mut end = name.len().min(max);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+/// A `<length> <key>=<value>\n` record, where the length counts itself.
+fn pax_record(key: &str, value: &str) -> String {
+    let body = format!(" {}={}\n", key, value);
+    let mut len = body.len() + 1;
+    while len != body.len() + len.to_string().len() {
+        len = body.len() + len.to_string().len();
+    }
+    format!("{}{}", len, body)
+}
+
+fn tar_header(name: &str, size: u64, mtime: u64, typeflag: u8) -> [u8; TAR_BLOCK] {
+    fn octal(field: &mut [u8], value: u64) {
+        let digits = format!("{:0width$o}", value, width = field.len() - 1);
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+    }
+
+    let mut header = [0u8; TAR_BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size.min(TAR_MAX_SIZE));
+    octal(&mut header[136..148], mtime.min(TAR_MAX_SIZE));
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    let digits = format!("{:06o}\0", checksum);
+    header[148..155].copy_from_slice(digits.as_bytes());
+    header
+}
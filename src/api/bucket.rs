@@ -9,7 +9,10 @@ use axum::{
 
 use crate::error::S3Error;
 use crate::server::AppState;
-use crate::storage::{BucketMeta, StorageError};
+use crate::notify::is_valid_event_pattern;
+use crate::storage::{
+    BucketLogging, BucketMeta, NotificationConfig, NotificationRule, ReplicationConfig, ReplicationRule, StorageError,
+};
 use crate::xml::{response::to_xml, types::*};
 
 pub async fn list_buckets(State(state): State<AppState>) -> Result<Response<Body>, S3Error> {
@@ -59,6 +62,10 @@ pub async fn create_bucket(
         created_at: now,
         region: state.config.region.clone(),
         versioning: false,
+        notification: None,
+        replication: None,
+        logging: None,
+        quota: None,
     };
 
     let created = state
@@ -98,7 +105,11 @@ pub async fn head_bucket(
 pub async fn delete_bucket(
     State(state): State<AppState>,
     Path(bucket): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Result<Response<Body>, S3Error> {
+    if params.contains_key("replication") {
+        return delete_bucket_replication(state, bucket).await;
+    }
     match state.storage.delete_bucket(&bucket).await {
         Ok(true) => Ok(Response::builder()
             .status(StatusCode::NO_CONTENT)
@@ -119,6 +130,18 @@ pub async fn handle_bucket_put(
     if params.contains_key("versioning") {
         return put_bucket_versioning(State(state), Path(bucket), body).await;
     }
+    if params.contains_key("notification") {
+        return put_bucket_notification(state, bucket, body).await;
+    }
+    if params.contains_key("replication") {
+        return put_bucket_replication(state, bucket, body).await;
+    }
+    if params.contains_key("replication-reset") {
+        return resync_bucket_replication(state, bucket, params.get("arn").map(String::as_str)).await;
+    }
+    if params.contains_key("logging") {
+        return put_bucket_logging(state, bucket, body).await;
+    }
     create_bucket(State(state), Path(bucket)).await
 }
 
@@ -185,6 +208,497 @@ pub async fn get_bucket_versioning(
         .unwrap())
 }
 
+async fn put_bucket_notification(
+    state: AppState,
+    bucket: String,
+    body: Body,
+) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+
+    let body_bytes = axum::body::to_bytes(body, 1024 * 64)
+        .await
+        .map_err(S3Error::internal)?;
+    let mut rules = parse_notification_config(&String::from_utf8_lossy(&body_bytes))?;
+
+    let mut ids = std::collections::HashSet::new();
+    for rule in &mut rules {
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if !ids.insert(rule.id.clone()) {
+            return Err(S3Error::invalid_argument(&format!(
+                "Duplicate notification configuration ID: {}",
+                rule.id
+            )));
+        }
+        if !state.notifier.is_known_target(&rule.arn) {
+            return Err(S3Error::invalid_argument(&format!(
+                "A specified destination ARN does not exist or is not well-formed: {}",
+                rule.arn
+            )));
+        }
+        if rule.events.is_empty() {
+            return Err(S3Error::invalid_argument("Each notification configuration needs at least one Event."));
+        }
+        if let Some(event) = rule.events.iter().find(|e| !is_valid_event_pattern(e)) {
+            return Err(S3Error::invalid_argument(&format!("The event is not supported: {}", event)));
+        }
+    }
+
+    state
+        .storage
+        .set_notification(&bucket, Some(NotificationConfig { rules }))
+        .await
+        .map_err(S3Error::internal)?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Parse `<NotificationConfiguration>`. Queue, Topic and CloudFunction
+/// configurations are all accepted; they only differ in the ARN element.
+fn parse_notification_config(xml: &str) -> Result<Vec<NotificationRule>, S3Error> {
+    use quick_xml::events::Event;
+    const CONFIG_ELEMENTS: &[&str] = &["QueueConfiguration", "TopicConfiguration", "CloudFunctionConfiguration"];
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut rules = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut current: Option<NotificationRule> = None;
+    let mut filter = (String::new(), String::new());
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if path.len() == 1 && CONFIG_ELEMENTS.contains(&name.as_str()) {
+                    current = Some(NotificationRule {
+                        id: String::new(),
+                        arn: String::new(),
+                        events: Vec::new(),
+                        prefix: String::new(),
+                        suffix: String::new(),
+                    });
+                }
+                if name == "FilterRule" {
+                    filter = (String::new(), String::new());
+                }
+                path.push(name);
+            }
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().map_err(|_| S3Error::malformed_xml())?.into_owned();
+                let (Some(rule), Some(element)) = (current.as_mut(), path.last()) else {
+                    continue;
+                };
+                match element.as_str() {
+                    "Id" => rule.id = text,
+                    "Queue" | "Topic" | "CloudFunction" => rule.arn = text,
+                    "Event" => rule.events.push(text),
+                    "Name" => filter.0 = text,
+                    "Value" => filter.1 = text,
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => {
+                let name = path.pop().unwrap_or_default();
+                if name == "FilterRule" {
+                    let Some(rule) = current.as_mut() else { continue };
+                    match filter.0.to_ascii_lowercase().as_str() {
+                        "prefix" => rule.prefix = std::mem::take(&mut filter.1),
+                        "suffix" => rule.suffix = std::mem::take(&mut filter.1),
+                        _ => {
+                            return Err(S3Error::invalid_argument(&format!(
+                                "Invalid filter rule name: {}",
+                                filter.0
+                            )));
+                        }
+                    }
+                } else if path.len() == 1 && CONFIG_ELEMENTS.contains(&name.as_str()) {
+                    rules.extend(current.take());
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return Err(S3Error::malformed_xml()),
+            _ => {}
+        }
+    }
+    Ok(rules)
+}
+
+pub async fn get_bucket_notification(
+    state: AppState,
+    bucket: String,
+) -> Result<Response<Body>, S3Error> {
+    let config = state
+        .storage
+        .get_notification(&bucket)
+        .await
+        .map_err(S3Error::internal)?
+        .unwrap_or_default();
+
+    let result = NotificationConfiguration {
+        queue_configurations: config
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let mut filters = Vec::new();
+                if !rule.prefix.is_empty() {
+                    filters.push(FilterRule { name: "prefix".into(), value: rule.prefix });
+                }
+                if !rule.suffix.is_empty() {
+                    filters.push(FilterRule { name: "suffix".into(), value: rule.suffix });
+                }
+                QueueConfiguration {
+                    id: rule.id,
+                    queue: rule.arn,
+                    events: rule.events,
+                    filter: (!filters.is_empty()).then_some(NotificationFilter {
+                        s3_key: S3KeyFilter { rules: filters },
+                    }),
+                }
+            })
+            .collect(),
+    };
+
+    let xml = to_xml(&result).map_err(S3Error::internal)?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/xml")
+        .body(Body::from(xml))
+        .unwrap())
+}
+
+async fn put_bucket_replication(
+    state: AppState,
+    bucket: String,
+    body: Body,
+) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+
+    let body_bytes = axum::body::to_bytes(body, 1024 * 64)
+        .await
+        .map_err(S3Error::internal)?;
+    let mut rules = parse_replication_config(&String::from_utf8_lossy(&body_bytes))?;
+    if rules.is_empty() {
+        return Err(S3Error::malformed_xml());
+    }
+
+    let mut ids = std::collections::HashSet::new();
+    for rule in &mut rules {
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if !ids.insert(rule.id.clone()) {
+            return Err(S3Error::invalid_argument(&format!("Duplicate replication rule ID: {}", rule.id)));
+        }
+        let Some((_, dest_bucket)) = state.replicator.destination(&rule.destination) else {
+            return Err(S3Error::invalid_argument(&format!(
+                "A specified destination ARN does not exist or is not well-formed: {}",
+                rule.destination
+            )));
+        };
+        validate_bucket_name(&dest_bucket)?;
+    }
+
+    state
+        .storage
+        .set_replication(&bucket, Some(ReplicationConfig { rules }))
+        .await
+        .map_err(S3Error::internal)?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Parse `<ReplicationConfiguration>`. The rule prefix may be given directly
+/// on the rule (the older schema) or under `<Filter>`; `Role` is ignored.
+/// `DeleteReplication` is MinIO's extension for permanent version deletes.
+fn parse_replication_config(xml: &str) -> Result<Vec<ReplicationRule>, S3Error> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut rules = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut current: Option<ReplicationRule> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if path.len() == 1 && name == "Rule" {
+                    current = Some(ReplicationRule {
+                        id: String::new(),
+                        enabled: false,
+                        priority: 0,
+                        prefix: String::new(),
+                        destination: String::new(),
+                        delete_markers: false,
+                        deletes: false,
+                    });
+                }
+                path.push(name);
+            }
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().map_err(|_| S3Error::malformed_xml())?.into_owned();
+                let Some(rule) = current.as_mut() else { continue };
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match (parent, path.last().map(String::as_str)) {
+                    (Some("Rule"), Some("ID")) => rule.id = text,
+                    (Some("Rule"), Some("Status")) => {
+                        rule.enabled = match text.as_str() {
+                            "Enabled" => true,
+                            "Disabled" => false,
+                            _ => return Err(S3Error::malformed_xml()),
+                        }
+                    }
+                    (Some("Rule"), Some("Priority")) => {
+                        rule.priority = text.parse().map_err(|_| S3Error::malformed_xml())?
+                    }
+                    (Some("Rule" | "Filter" | "And"), Some("Prefix")) => rule.prefix = text,
+                    (Some("DeleteMarkerReplication"), Some("Status")) => rule.delete_markers = text == "Enabled",
+                    (Some("DeleteReplication"), Some("Status")) => rule.deletes = text == "Enabled",
+                    (Some("Destination"), Some("Bucket")) => rule.destination = text,
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => {
+                let name = path.pop().unwrap_or_default();
+                if path.len() == 1 && name == "Rule" {
+                    rules.extend(current.take());
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return Err(S3Error::malformed_xml()),
+            _ => {}
+        }
+    }
+    Ok(rules)
+}
+
+pub async fn get_bucket_replication(
+    state: AppState,
+    bucket: String,
+) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+    let config = state
+        .storage
+        .get_replication(&bucket)
+        .await
+        .map_err(S3Error::internal)?
+        .ok_or_else(|| S3Error::no_replication_config(&bucket))?;
+
+    let enabled = |on: bool| if on { "Enabled" } else { "Disabled" }.to_string();
+    let result = ReplicationConfiguration {
+        role: String::new(),
+        rules: config
+            .rules
+            .into_iter()
+            .map(|rule| ReplicationRuleXml {
+                id: rule.id,
+                status: enabled(rule.enabled),
+                priority: rule.priority,
+                filter: ReplicationFilter { prefix: rule.prefix },
+                delete_marker_replication: ReplicationToggle {
+                    status: enabled(rule.delete_markers),
+                },
+                delete_replication: ReplicationToggle {
+                    status: enabled(rule.deletes),
+                },
+                destination: ReplicationDestination { bucket: rule.destination },
+            })
+            .collect(),
+    };
+
+    let xml = to_xml(&result).map_err(S3Error::internal)?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/xml")
+        .body(Body::from(xml))
+        .unwrap())
+}
+
+/// Handle PUT /{bucket}?replication-reset[&arn=...] (as in MinIO's replication
+/// resync): queue every existing object again, e.g. to backfill a new site.
+async fn resync_bucket_replication(
+    state: AppState,
+    bucket: String,
+    arn: Option<&str>,
+) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+    let config = state
+        .storage
+        .get_replication(&bucket)
+        .await
+        .map_err(S3Error::internal)?
+        .ok_or_else(|| S3Error::no_replication_config(&bucket))?;
+    if let Some(arn) = arn
+        && !config.rules.iter().any(|r| r.destination == arn)
+    {
+        return Err(S3Error::invalid_argument(&format!("No replication rule targets {}", arn)));
+    }
+
+    let queued = state
+        .replicator
+        .resync(&state.storage, &bucket, &config, arn)
+        .await
+        .map_err(S3Error::internal)?;
+    let mut targets: Vec<_> = queued.into_iter().collect();
+    targets.sort();
+    let body = serde_json::json!({
+        "Targets": targets
+            .into_iter()
+            .map(|(arn, count)| serde_json::json!({ "Arn": arn, "Queued": count }))
+            .collect::<Vec<_>>(),
+    });
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap())
+}
+
+async fn delete_bucket_replication(state: AppState, bucket: String) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+    state
+        .storage
+        .set_replication(&bucket, None)
+        .await
+        .map_err(S3Error::internal)?;
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Handle PUT /{bucket}?logging. A `BucketLoggingStatus` without
+/// `LoggingEnabled` turns access logging off.
+async fn put_bucket_logging(state: AppState, bucket: String, body: Body) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(&bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+
+    let body_bytes = axum::body::to_bytes(body, 1024 * 64)
+        .await
+        .map_err(S3Error::internal)?;
+    let logging = parse_logging_status(&String::from_utf8_lossy(&body_bytes))?;
+    if let Some(logging) = &logging {
+        match state.storage.head_bucket(&logging.target_bucket).await {
+            Ok(true) => {}
+            Ok(false) => return Err(S3Error::invalid_target_bucket_for_logging(&logging.target_bucket)),
+            Err(e) => return Err(S3Error::internal(e)),
+        }
+    }
+
+    state
+        .storage
+        .set_logging(&bucket, logging)
+        .await
+        .map_err(S3Error::internal)?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::empty())
+        .unwrap())
+}
+
+fn parse_logging_status(xml: &str) -> Result<Option<BucketLogging>, S3Error> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut path: Vec<String> = Vec::new();
+    let mut root_seen = false;
+    let mut current: Option<BucketLogging> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if path.is_empty() {
+                    root_seen = name == "BucketLoggingStatus";
+                }
+                if path.len() == 1 && name == "LoggingEnabled" {
+                    current = Some(BucketLogging {
+                        target_bucket: String::new(),
+                        target_prefix: String::new(),
+                    });
+                }
+                path.push(name);
+            }
+            Ok(Event::Empty(e)) if path.is_empty() => {
+                root_seen = e.local_name().as_ref() == b"BucketLoggingStatus";
+            }
+            Ok(Event::Text(e)) => {
+                let text = e.unescape().map_err(|_| S3Error::malformed_xml())?.into_owned();
+                let Some(logging) = current.as_mut() else { continue };
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match (parent, path.last().map(String::as_str)) {
+                    (Some("LoggingEnabled"), Some("TargetBucket")) => logging.target_bucket = text,
+                    (Some("LoggingEnabled"), Some("TargetPrefix")) => logging.target_prefix = text,
+                    _ => {}
+                }
+            }
+            Ok(Event::End(_)) => {
+                path.pop();
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return Err(S3Error::malformed_xml()),
+            _ => {}
+        }
+    }
+    if !root_seen || current.as_ref().is_some_and(|l| l.target_bucket.is_empty()) {
+        return Err(S3Error::malformed_xml());
+    }
+    Ok(current)
+}
+
+pub async fn get_bucket_logging(state: AppState, bucket: String) -> Result<Response<Body>, S3Error> {
+    let logging = state
+        .storage
+        .get_logging(&bucket)
+        .await
+        .map_err(S3Error::internal)?;
+
+    let result = BucketLoggingStatus {
+        logging_enabled: logging.map(|l| LoggingEnabled {
+            target_bucket: l.target_bucket,
+            target_prefix: l.target_prefix,
+        }),
+    };
+
+    let xml = to_xml(&result).map_err(S3Error::internal)?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/xml")
+        .body(Body::from(xml))
+        .unwrap())
+}
+
 fn validate_bucket_name(name: &str) -> Result<(), S3Error> {
     if name.len() < 3 || name.len() > 63 {
         return Err(S3Error::invalid_bucket_name(name));
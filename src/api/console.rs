@@ -1,26 +1,37 @@
 use std::collections::{BTreeSet, HashMap};
-use std::net::SocketAddr;
 use std::time::Instant;
 
 use axum::{
-    extract::{ConnectInfo, Path, Query, Request, State},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, RawQuery, Request, State},
+    Extension,
+    http::{HeaderMap, Method, StatusCode},
     middleware::Next,
-    response::{IntoResponse, Response},
+    response::{IntoResponse, Redirect, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
-use futures::TryStreamExt;
+use base64::Engine;
+use futures::{StreamExt, TryStreamExt};
 use hmac::{Hmac, Mac};
 use sha2::{Digest, Sha256};
 
-use crate::auth::signature_v4;
+use crate::audit::Principal;
+use crate::auth::policy::Policy;
+use crate::auth::session::{Session, SESSION_MAX_AGE_SECS};
+use crate::auth::{post_policy, signature_v4};
+use crate::metrics::METRICS;
+use crate::proxy::ClientInfo;
 use crate::server::AppState;
+use crate::storage::{
+    BucketQuota, BucketUsage, ChecksumAlgorithm, ChecksumType, CompletedPart, ListingEntry, StorageError,
+};
+
+use super::archive::{self, ArchiveEntry, ArchiveFormat};
 
 type HmacSha256 = Hmac<Sha256>;
 
 const COOKIE_NAME: &str = "maxio_session";
-const TOKEN_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60; // 7 days
+const CSRF_HEADER: &str = "x-csrf-token";
 
 const RATE_LIMIT_MAX: u32 = 10;
 const RATE_LIMIT_WINDOW_SECS: u64 = 300; // 5 minutes
@@ -63,6 +74,7 @@ impl LoginRateLimiter {
         bucket.count += 1;
 
         if bucket.count > RATE_LIMIT_MAX {
+            METRICS.login_rejected();
             let remaining = RATE_LIMIT_WINDOW_SECS
                 .saturating_sub(now.duration_since(bucket.window_start).as_secs());
             Some(remaining.max(1))
@@ -72,57 +84,6 @@ impl LoginRateLimiter {
     }
 }
 
-fn extract_client_ip(headers: &HeaderMap, addr: &SocketAddr) -> String {
-    headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .unwrap_or_else(|| addr.ip().to_string())
-}
-
-fn generate_token(access_key: &str, secret_key: &str, issued_at: i64) -> String {
-    let issued_hex = format!("{:x}", issued_at);
-    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
-        .expect("HMAC can take key of any size");
-    mac.update(format!("{}:{}", access_key, issued_hex).as_bytes());
-    let sig = hex::encode(mac.finalize().into_bytes());
-    format!("{}.{}", issued_hex, sig)
-}
-
-fn verify_token(token: &str, access_key: &str, secret_key: &str) -> bool {
-    let Some((issued_hex, signature)) = token.split_once('.') else {
-        return false;
-    };
-
-    let Ok(issued_at) = i64::from_str_radix(issued_hex, 16) else {
-        return false;
-    };
-
-    let now = chrono::Utc::now().timestamp();
-    if now - issued_at > TOKEN_MAX_AGE_SECS || issued_at > now + 60 {
-        return false;
-    }
-
-    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
-        .expect("HMAC can take key of any size");
-    mac.update(format!("{}:{}", access_key, issued_hex).as_bytes());
-    let expected = hex::encode(mac.finalize().into_bytes());
-
-    constant_time_eq(signature.as_bytes(), expected.as_bytes())
-}
-
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
-    if a.len() != b.len() {
-        return false;
-    }
-    let mut diff = 0u8;
-    for (x, y) in a.iter().zip(b.iter()) {
-        diff |= x ^ y;
-    }
-    diff == 0
-}
-
 fn extract_cookie(headers: &HeaderMap) -> Option<String> {
     headers
         .get("cookie")
@@ -135,14 +96,54 @@ fn extract_cookie(headers: &HeaderMap) -> Option<String> {
         })
 }
 
-fn make_cookie(value: &str, max_age: i64, request_headers: &HeaderMap) -> String {
-    let is_secure = request_headers
-        .get("x-forwarded-proto")
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v == "https")
-        .unwrap_or(false);
+/// The CSRF token of a session: derived from the session token, which the
+/// browser holds in an HttpOnly cookie, so it needs no storage and a page on
+/// another origin can neither read nor compute it.
+fn csrf_token(session_token: &str) -> String {
+    hex::encode(Sha256::digest(format!("maxio-csrf:{}", session_token).as_bytes()))
+}
 
-    let secure_flag = if is_secure { "; Secure" } else { "" };
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Content types a page on another origin can post without a CORS preflight.
+fn is_form_content_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    ["application/x-www-form-urlencoded", "multipart/form-data", "text/plain"]
+        .iter()
+        .any(|form| essence.eq_ignore_ascii_case(form))
+}
+
+/// Refuse state-changing requests a browser sends on behalf of another
+/// origin. `Origin` (or failing that `Referer`) must name this server; a
+/// request carrying neither may still come from a cross-site form whose
+/// browser or proxy stripped them, so it must not use a form content type.
+async fn same_origin_middleware(Extension(client): Extension<ClientInfo>, request: Request, next: Next) -> Response {
+    if is_safe_method(request.method()) {
+        return next.run(request).await;
+    }
+    let headers = request.headers();
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let source = header("origin").or_else(|| header("referer"));
+    if let Some(source) = source {
+        let expected = format!("{}://{}", client.scheme, header("host").unwrap_or(""));
+        let same_origin = source
+            .strip_prefix(&expected)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        if !same_origin {
+            return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Cross-origin request refused"})))
+                .into_response();
+        }
+    } else if header("content-type").is_some_and(is_form_content_type) {
+        return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Cross-origin request refused"})))
+            .into_response();
+    }
+    next.run(request).await
+}
+
+fn make_cookie(value: &str, max_age: i64, scheme: &str) -> String {
+    let secure_flag = if scheme == "https" { "; Secure" } else { "" };
 
     format!(
         "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}{}",
@@ -152,17 +153,30 @@ fn make_cookie(value: &str, max_age: i64, request_headers: &HeaderMap) -> String
 
 async fn console_auth_middleware(
     State(state): State<AppState>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Response {
-    let authenticated = extract_cookie(request.headers())
-        .map(|token| verify_token(&token, &state.config.access_key, &state.config.secret_key))
-        .unwrap_or(false);
-
-    if !authenticated {
+    let token = extract_cookie(request.headers());
+    let session = token.as_deref().and_then(|token| state.sessions.authenticate(token));
+    let (Some(token), Some(session)) = (token, session) else {
         return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "Not authenticated"}))).into_response();
+    };
+    let read_only = matches!(*request.method(), Method::GET | Method::HEAD);
+    if !is_safe_method(request.method()) {
+        let presented = request.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok()).unwrap_or("");
+        if !signature_v4::constant_time_eq(presented.as_bytes(), csrf_token(&token).as_bytes()) {
+            return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Missing or invalid CSRF token"})))
+                .into_response();
+        }
     }
-    next.run(request).await
+    if !read_only && !session.policy.allows_write() && !request.uri().path().starts_with("/auth/") {
+        return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Access denied by policy"}))).into_response();
+    }
+    let principal = Principal(session.access_key.clone());
+    request.extensions_mut().insert(session);
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(principal);
+    response
 }
 
 #[derive(serde::Deserialize)]
@@ -174,11 +188,11 @@ pub struct LoginRequest {
 
 pub async fn login(
     State(state): State<AppState>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(client): Extension<ClientInfo>,
     headers: HeaderMap,
     Json(body): Json<LoginRequest>,
 ) -> Response {
-    let ip = extract_client_ip(&headers, &addr);
+    let ip = client.ip.to_string();
 
     if let Some(retry_after) = state.login_rate_limiter.check_and_increment(&ip) {
         return (
@@ -189,10 +203,7 @@ pub async fn login(
             .into_response();
     }
 
-    // Use constant-time comparison to prevent timing side-channel attacks
-    let key_match = constant_time_eq(body.access_key.as_bytes(), state.config.access_key.as_bytes());
-    let secret_match = constant_time_eq(body.secret_key.as_bytes(), state.config.secret_key.as_bytes());
-    if !key_match || !secret_match {
+    if !state.credentials.verify(&body.access_key, &body.secret_key) {
         return (
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": "Invalid credentials"})),
@@ -200,36 +211,196 @@ pub async fn login(
             .into_response();
     }
 
-    let now = chrono::Utc::now().timestamp();
-    let token = generate_token(&state.config.access_key, &state.config.secret_key, now);
-    let cookie = make_cookie(&token, TOKEN_MAX_AGE_SECS, &headers);
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let (token, _) = state
+        .sessions
+        .create(&body.access_key, Policy::ReadWrite, &ip, user_agent)
+        .await;
+    let cookie = make_cookie(&token, SESSION_MAX_AGE_SECS, client.scheme);
 
     let mut resp_headers = HeaderMap::new();
     resp_headers.insert("Set-Cookie", cookie.parse().unwrap());
 
-    (StatusCode::OK, resp_headers, Json(serde_json::json!({"ok": true}))).into_response()
+    (
+        StatusCode::OK,
+        resp_headers,
+        Extension(Principal(body.access_key)),
+        Json(serde_json::json!({"ok": true, "csrfToken": csrf_token(&token)})),
+    )
+        .into_response()
 }
 
 pub async fn check(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    let authenticated = extract_cookie(&headers)
-        .map(|token| verify_token(&token, &state.config.access_key, &state.config.secret_key))
-        .unwrap_or(false);
+    let token = extract_cookie(&headers);
+    match token.as_deref().and_then(|token| state.sessions.authenticate(token).map(|s| (token, s))) {
+        Some((token, session)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "ok": true,
+                "accessKey": session.access_key,
+                "root": state.credentials.is_root(&session.access_key),
+                "policy": session.policy,
+                "csrfToken": csrf_token(token),
+            })),
+        ),
+        None => (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "Not authenticated"}))),
+    }
+}
 
-    if authenticated {
-        (StatusCode::OK, Json(serde_json::json!({"ok": true})))
-    } else {
-        (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "Not authenticated"})))
+/// Single sign-on availability, for the login page.
+pub async fn oidc_info(State(state): State<AppState>) -> impl IntoResponse {
+    match state.oidc {
+        Some(ref oidc) => Json(serde_json::json!({"enabled": true, "name": oidc.display_name()})),
+        None => Json(serde_json::json!({"enabled": false})),
     }
 }
 
-pub async fn logout(headers: HeaderMap) -> impl IntoResponse {
-    let cookie = make_cookie("", 0, &headers);
+/// Send the browser back to the login page with an error to show.
+fn sso_failed(message: &str) -> Response {
+    let message = percent_encoding::utf8_percent_encode(message, percent_encoding::NON_ALPHANUMERIC);
+    Redirect::to(&format!("/ui/?sso_error={}", message)).into_response()
+}
+
+/// Redirect to the identity provider to sign in.
+pub async fn oidc_login(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(ref oidc) = state.oidc else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Single sign-on is not configured"})))
+            .into_response();
+    };
+    let host = headers
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost:9000");
+    let redirect_uri = format!("{}://{}/api/auth/oidc/callback", client.scheme, host);
+    match oidc.authorization_url(&redirect_uri).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(e) => {
+            tracing::warn!("OIDC login failed: {:#}", e);
+            sso_failed("Identity provider unavailable")
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct OidcCallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Where the identity provider sends the browser back with an authorization code.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackParams>,
+) -> Response {
+    let Some(ref oidc) = state.oidc else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Single sign-on is not configured"})))
+            .into_response();
+    };
+    if let Some(error) = params.error {
+        return sso_failed(params.error_description.as_deref().unwrap_or(&error));
+    }
+    let (Some(code), Some(login_state)) = (params.code, params.state) else {
+        return sso_failed("Invalid sign-in response");
+    };
+    let identity = match oidc.complete(&code, &login_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::warn!("OIDC login failed: {:#}", e);
+            return sso_failed(&e.to_string());
+        }
+    };
+
+    // SSO users are kept apart from access keys, which cannot contain ':'
+    let user = format!("oidc:{}", identity.username);
+    let ip = client.ip.to_string();
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let (token, _) = state.sessions.create(&user, identity.policy, &ip, user_agent).await;
+    let cookie = make_cookie(&token, SESSION_MAX_AGE_SECS, client.scheme);
+
+    let mut resp_headers = HeaderMap::new();
+    resp_headers.insert("Set-Cookie", cookie.parse().unwrap());
+    (resp_headers, Extension(Principal(user)), Redirect::to("/ui/")).into_response()
+}
+
+/// Clear the session cookie in the browser.
+fn logged_out(client: &ClientInfo, body: serde_json::Value) -> Response {
+    let cookie = make_cookie("", 0, client.scheme);
     let mut resp_headers = HeaderMap::new();
     resp_headers.insert("Set-Cookie", cookie.parse().unwrap());
-    (StatusCode::OK, resp_headers, Json(serde_json::json!({"ok": true})))
+    (StatusCode::OK, resp_headers, Json(body)).into_response()
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    Extension(client): Extension<ClientInfo>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(token) = extract_cookie(&headers) {
+        state.sessions.revoke_token(&token).await;
+    }
+    logged_out(&client, serde_json::json!({"ok": true}))
+}
+
+/// End every session of the signed-in user, this one included.
+pub async fn logout_all(
+    State(state): State<AppState>,
+    Extension(session): Extension<Session>,
+    Extension(client): Extension<ClientInfo>,
+) -> Response {
+    let revoked = state.sessions.revoke_all(&session.access_key).await;
+    logged_out(&client, serde_json::json!({"ok": true, "revoked": revoked}))
+}
+
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Extension(session): Extension<Session>,
+) -> impl IntoResponse {
+    let sessions: Vec<serde_json::Value> = state
+        .sessions
+        .list(&session.access_key)
+        .into_iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "createdAt": s.created_at,
+                "lastSeen": s.last_seen,
+                "expiresAt": s.expires_at,
+                "sourceIp": s.source_ip,
+                "userAgent": s.user_agent,
+                "current": s.id == session.id,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "sessions": sessions }))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    Extension(session): Extension<Session>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if state.sessions.revoke(&session.access_key, &id).await {
+        (StatusCode::OK, Json(serde_json::json!({"ok": true})))
+    } else {
+        (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Session not found"})))
+    }
 }
 
 pub async fn list_buckets(
@@ -248,6 +419,30 @@ pub async fn list_buckets(
     }
 }
 
+/// Tracked usage of every bucket and their sum, without walking the tree.
+pub async fn data_usage(State(state): State<AppState>) -> impl IntoResponse {
+    let buckets = match state.storage.list_buckets().await {
+        Ok(buckets) => buckets,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    };
+    let mut total = BucketUsage::default();
+    let mut list = Vec::with_capacity(buckets.len());
+    for bucket in buckets {
+        match state.storage.bucket_usage(&bucket.name).await {
+            Ok(usage) => {
+                total.add(usage);
+                let mut entry = serde_json::to_value(usage).unwrap_or_default();
+                entry["name"] = bucket.name.into();
+                list.push(entry);
+            }
+            // Deleted since it was listed
+            Err(StorageError::NotFound(_)) => {}
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+        }
+    }
+    (StatusCode::OK, Json(serde_json::json!({ "buckets": list, "total": total }))).into_response()
+}
+
 #[derive(serde::Deserialize)]
 pub struct CreateBucketRequest {
     name: String,
@@ -263,6 +458,10 @@ pub async fn create_bucket(
         created_at: now,
         region: state.config.region.clone(),
         versioning: false,
+        notification: None,
+        replication: None,
+        logging: None,
+        quota: None,
     };
 
     match state.storage.create_bucket(&meta).await {
@@ -290,8 +489,114 @@ pub async fn delete_bucket_api(
 pub struct ListObjectsParams {
     prefix: Option<String>,
     delimiter: Option<String>,
+    /// Substring of the names to keep, or a glob when it has `*` or `?`.
+    search: Option<String>,
+    /// `name` (default), `size` or `date`.
+    sort: Option<String>,
+    /// `asc` (default) or `desc`.
+    order: Option<String>,
+    /// `nextCursor` of the previous page.
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Most entries a console listing returns at once.
+const LIST_PAGE_LIMIT: usize = 1000;
+
+/// Where a row falls in a listing: folders first, then the sort value and
+/// key. The cursor of a page is the position of its last row.
+type ListPosition = (bool, String, String);
+
+struct ListRow {
+    folder: bool,
+    key: String,
+    meta: Option<crate::storage::ObjectMeta>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListSort {
+    Name,
+    Size,
+    Date,
+}
+
+impl ListSort {
+    fn parse(s: Option<&str>) -> Option<Self> {
+        match s.unwrap_or("name") {
+            "name" => Some(Self::Name),
+            "size" => Some(Self::Size),
+            "date" => Some(Self::Date),
+            _ => None,
+        }
+    }
+
+    fn position(self, row: &ListRow) -> ListPosition {
+        let value = match (self, &row.meta) {
+            (Self::Size, Some(meta)) if !row.folder => format!("{:020}", meta.size),
+            (Self::Date, Some(meta)) if !row.folder => meta.last_modified.clone(),
+            _ => String::new(),
+        };
+        (!row.folder, value, row.key.clone())
+    }
+
+    /// Folders have no size or date, so they stay in name order unless
+    /// sorting by name.
+    fn compare(self, a: &ListPosition, b: &ListPosition, desc: bool) -> std::cmp::Ordering {
+        a.0.cmp(&b.0).then_with(|| {
+            let order = (&a.1, &a.2).cmp(&(&b.1, &b.2));
+            let is_file = a.0;
+            if desc && (is_file || self == Self::Name) { order.reverse() } else { order }
+        })
+    }
+}
+
+fn encode_cursor(position: &ListPosition) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(position).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Option<ListPosition> {
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    serde_json::from_slice(&bytes).ok()
 }
 
+/// Case-insensitive match of a console search: a glob when the pattern has
+/// `*` or `?`, otherwise a substring.
+fn search_matches(name: &str, pattern: &str) -> bool {
+    let name = name.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    if !pattern.contains(['*', '?']) {
+        return name.contains(&pattern);
+    }
+    let name: Vec<char> = name.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut n, mut p) = (0, 0);
+    // Pattern index of the last `*` and the name index it currently stands in for
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            n += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn list_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({"error": message}))).into_response()
+}
+
+/// One page of a bucket listing. With the default `/` delimiter only the
+/// prefix's own directory is read, and object metadata is loaded for the
+/// page alone unless sorting by size or date needs all of it.
 pub async fn list_objects(
     State(state): State<AppState>,
     Path(bucket): Path<String>,
@@ -305,52 +610,140 @@ pub async fn list_objects(
 
     let prefix = params.prefix.unwrap_or_default();
     let delimiter = params.delimiter.unwrap_or_else(|| "/".to_string());
-
-    let all_objects = match state.storage.list_objects(&bucket, &prefix).await {
-        Ok(objects) => objects,
-        Err(e) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response();
-        }
+    let Some(sort) = ListSort::parse(params.sort.as_deref()) else {
+        return list_error(StatusCode::BAD_REQUEST, "sort must be name, size or date");
     };
+    let desc = match params.order.as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(_) => return list_error(StatusCode::BAD_REQUEST, "order must be asc or desc"),
+    };
+    let cursor = match params.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(c) => match decode_cursor(c) {
+            Some(position) => Some(position),
+            None => return list_error(StatusCode::BAD_REQUEST, "Invalid cursor"),
+        },
+        None => None,
+    };
+    let limit = params.limit.unwrap_or(LIST_PAGE_LIMIT).clamp(1, LIST_PAGE_LIMIT);
+
+    let mut rows = Vec::new();
+    if delimiter == "/" {
+        let entries = match state.storage.list_level(&bucket, &prefix).await {
+            Ok(entries) => entries,
+            Err(e) => return list_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+        rows.extend(entries.into_iter().map(|entry| match entry {
+            ListingEntry::Object(key) => ListRow { folder: false, key, meta: None },
+            ListingEntry::Prefix(key) => ListRow { folder: true, key, meta: None },
+        }));
+    } else {
+        // Any other delimiter, or none for a flat listing, needs the whole subtree
+        let objects = match state.storage.list_objects(&bucket, &prefix).await {
+            Ok(objects) => objects,
+            Err(e) => return list_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+        let mut prefix_set = BTreeSet::new();
+        for obj in objects {
+            let suffix = &obj.key[prefix.len()..];
+            match suffix.find(delimiter.as_str()).filter(|_| !delimiter.is_empty()) {
+                Some(pos) => {
+                    prefix_set.insert(format!("{}{}", prefix, &suffix[..pos + delimiter.len()]));
+                }
+                None if !obj.key.ends_with('/') => rows.push(ListRow { folder: false, key: obj.key.clone(), meta: Some(obj) }),
+                None => {}
+            }
+        }
+        rows.extend(prefix_set.into_iter().map(|key| ListRow { folder: true, key, meta: None }));
+    }
 
-    let mut files = Vec::new();
-    let mut prefix_set = BTreeSet::new();
-
-    for obj in &all_objects {
-        let suffix = &obj.key[prefix.len()..];
-        if let Some(pos) = suffix.find(delimiter.as_str()) {
-            let common = format!("{}{}", prefix, &suffix[..pos + delimiter.len()]);
-            prefix_set.insert(common);
-        } else if !obj.key.ends_with('/') {
-            files.push(serde_json::json!({
-                "key": obj.key,
-                "size": obj.size,
-                "lastModified": obj.last_modified,
-                "etag": obj.etag,
-            }));
-        }
-    }
-
-    // Determine which prefixes are empty (only contain a folder marker, no real objects)
-    let mut empty_prefixes: Vec<&String> = Vec::new();
-    for p in &prefix_set {
-        let has_children = all_objects.iter().any(|obj| {
-            obj.key.starts_with(p.as_str()) && obj.key != *p
+    if let Some(pattern) = params.search.as_deref().filter(|s| !s.is_empty()) {
+        rows.retain(|row| {
+            let name = &row.key[prefix.len()..];
+            let name = if row.folder { name.strip_suffix(delimiter.as_str()).unwrap_or(name) } else { name };
+            search_matches(name, pattern)
         });
-        if !has_children {
-            empty_prefixes.push(p);
-        }
+    }
+    if sort != ListSort::Name {
+        load_list_meta(&state, &bucket, &mut rows).await;
     }
 
-    let prefixes: Vec<&String> = prefix_set.iter().collect();
+    let mut rows: Vec<(ListPosition, ListRow)> = rows.into_iter().map(|row| (sort.position(&row), row)).collect();
+    rows.sort_by(|a, b| sort.compare(&a.0, &b.0, desc));
+    let start = match &cursor {
+        Some(cursor) => rows.partition_point(|(position, _)| sort.compare(position, cursor, desc).is_le()),
+        None => 0,
+    };
+    let mut page: Vec<(ListPosition, ListRow)> = rows.drain(start..).take(limit + 1).collect();
+    let next_cursor = (page.len() > limit).then(|| {
+        page.truncate(limit);
+        encode_cursor(&page[limit - 1].0)
+    });
+    let mut page: Vec<ListRow> = page.into_iter().map(|(_, row)| row).collect();
+    if sort == ListSort::Name {
+        load_list_meta(&state, &bucket, &mut page).await;
+    }
+
+    let files: Vec<serde_json::Value> = page
+        .iter()
+        .filter_map(|row| row.meta.as_ref())
+        .map(|meta| serde_json::json!({
+            "key": meta.key,
+            "size": meta.size,
+            "lastModified": meta.last_modified,
+            "etag": meta.etag,
+        }))
+        .collect();
+    let prefixes: Vec<&String> = page.iter().filter(|row| row.folder).map(|row| &row.key).collect();
+
+    // Folders are only deletable while they hold nothing but their marker
+    let mut empty_prefixes = Vec::new();
+    if delimiter == "/" {
+        let folders: Vec<String> = prefixes.iter().map(|p| p.to_string()).collect();
+        let checks = futures::stream::iter(folders)
+            .map(|p| {
+                let (storage, bucket) = (state.storage.clone(), bucket.clone());
+                async move {
+                    let empty = storage.prefix_is_empty(&bucket, &p).await.unwrap_or(false);
+                    (p, empty)
+                }
+            })
+            .buffered(16)
+            .collect::<Vec<_>>()
+            .await;
+        empty_prefixes.extend(checks.into_iter().filter(|(_, empty)| *empty).map(|(p, _)| p));
+    }
 
     (StatusCode::OK, Json(serde_json::json!({
         "files": files,
         "prefixes": prefixes,
         "emptyPrefixes": empty_prefixes,
+        "nextCursor": next_cursor,
     }))).into_response()
 }
 
+/// Fill in the metadata of file rows, dropping files that vanished or have none.
+async fn load_list_meta(state: &AppState, bucket: &str, rows: &mut Vec<ListRow>) {
+    let missing: Vec<(usize, String)> = rows
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| !row.folder && row.meta.is_none())
+        .map(|(i, row)| (i, row.key.clone()))
+        .collect();
+    let loaded = futures::stream::iter(missing)
+        .map(|(i, key)| {
+            let (storage, bucket) = (state.storage.clone(), bucket.to_string());
+            async move { (i, storage.head_object(&bucket, &key).await.ok()) }
+        })
+        .buffer_unordered(32)
+        .collect::<Vec<_>>()
+        .await;
+    for (i, meta) in loaded {
+        rows[i].meta = meta;
+    }
+    rows.retain(|row| row.folder || row.meta.is_some());
+}
+
 pub async fn upload_object(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
@@ -368,21 +761,242 @@ pub async fn upload_object(
         .and_then(|v| v.to_str().ok())
         .unwrap_or("application/octet-stream");
 
+    let _reservation = match state.storage.reserve_quota(&bucket, &key, super::object::declared_length(&headers)).await {
+        Ok(reservation) => reservation,
+        Err(e) => return quota_error(e),
+    };
+
     let stream = body.into_data_stream();
     let reader = tokio_util::io::StreamReader::new(
         stream.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)),
     );
 
     match state.storage.put_object(&bucket, &key, content_type, Box::pin(reader), None).await {
-        Ok(result) => (StatusCode::OK, Json(serde_json::json!({
+        Ok(result) => {
+            (StatusCode::OK, Json(serde_json::json!({
             "ok": true,
             "etag": result.etag,
             "size": result.size,
-        }))).into_response(),
+        }))).into_response()
+        }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
     }
 }
 
+// --- Multipart uploads ---
+//
+// The browser creates an upload, PUTs parts (in parallel, in any order, and
+// again after a dropped connection), reads back the parts the server already
+// has to resume, then completes. Part checksums ride in the usual
+// `x-amz-checksum-*` headers and are verified as the part is stored.
+
+fn upload_error(e: StorageError) -> Response {
+    let status = match e {
+        StorageError::UploadNotFound(_) | StorageError::NotFound(_) => StatusCode::NOT_FOUND,
+        StorageError::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+        StorageError::ChecksumMismatch(_) | StorageError::InvalidKey(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(serde_json::json!({"error": e.to_string()}))).into_response()
+}
+
+fn part_json(part: &crate::storage::PartMeta) -> serde_json::Value {
+    serde_json::json!({
+        "partNumber": part.part_number,
+        "etag": part.etag,
+        "size": part.size,
+        "checksum": part.checksum_value,
+    })
+}
+
+fn upload_json(upload: &crate::storage::MultipartUploadMeta) -> serde_json::Value {
+    serde_json::json!({
+        "uploadId": upload.upload_id,
+        "key": upload.key,
+        "contentType": upload.content_type,
+        "initiated": upload.initiated,
+        "checksumAlgorithm": upload.checksum_algorithm.map(|a| a.as_str()),
+        "checksumType": upload.checksum_type.map(|t| t.as_str()),
+    })
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadRequest {
+    key: String,
+    content_type: Option<String>,
+    /// Algorithm every part must be checksummed with, e.g. `SHA256`.
+    checksum_algorithm: Option<String>,
+    /// `COMPOSITE` (default) or `FULL_OBJECT`.
+    checksum_type: Option<String>,
+}
+
+pub async fn create_upload(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Json(body): Json<CreateUploadRequest>,
+) -> impl IntoResponse {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Bucket not found"}))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+    if body.key.is_empty() || body.key.ends_with('/') {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "Invalid object key"}))).into_response();
+    }
+    let checksum_algorithm = match body.checksum_algorithm.as_deref() {
+        Some(s) => match ChecksumAlgorithm::from_header_str(s) {
+            Some(algo) => Some(algo),
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "Unsupported checksumAlgorithm"}))).into_response(),
+        },
+        None => None,
+    };
+    let checksum_type = match body.checksum_type.as_deref() {
+        Some(s) => match ChecksumType::from_header_str(s) {
+            Some(checksum_type) => Some(checksum_type),
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "checksumType must be COMPOSITE or FULL_OBJECT"}))).into_response(),
+        },
+        None => None,
+    };
+    let content_type = body.content_type.as_deref().filter(|t| !t.is_empty()).unwrap_or("application/octet-stream");
+
+    match state.storage.create_multipart_upload(&bucket, &body.key, content_type, checksum_algorithm, checksum_type).await {
+        Ok(upload) => (StatusCode::OK, Json(upload_json(&upload))).into_response(),
+        Err(e) => upload_error(e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ListUploadsParams {
+    key: Option<String>,
+}
+
+/// Uploads in progress, oldest first, optionally for one key: what a
+/// browser looks through to resume a file it was sending.
+pub async fn list_uploads(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Query(params): Query<ListUploadsParams>,
+) -> impl IntoResponse {
+    match state.storage.list_multipart_uploads(&bucket).await {
+        Ok(mut uploads) => {
+            uploads.retain(|u| params.key.as_ref().is_none_or(|key| u.key == *key));
+            uploads.sort_by(|a, b| a.initiated.cmp(&b.initiated));
+            let uploads: Vec<_> = uploads.iter().map(upload_json).collect();
+            (StatusCode::OK, Json(serde_json::json!({"uploads": uploads}))).into_response()
+        }
+        Err(e) => upload_error(e),
+    }
+}
+
+pub async fn get_upload(
+    State(state): State<AppState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.storage.list_parts(&bucket, &upload_id).await {
+        Ok((upload, parts)) => {
+            let mut json = upload_json(&upload);
+            json["parts"] = parts.iter().map(part_json).collect();
+            (StatusCode::OK, Json(json)).into_response()
+        }
+        Err(e) => upload_error(e),
+    }
+}
+
+pub async fn upload_part(
+    State(state): State<AppState>,
+    Path((bucket, upload_id, part_number)): Path<(String, String, u32)>,
+    headers: HeaderMap,
+    body: axum::body::Body,
+) -> impl IntoResponse {
+    let upload = match state.storage.get_multipart_upload(&bucket, &upload_id).await {
+        Ok(upload) => upload,
+        Err(e) => return upload_error(e),
+    };
+    if let Err(e) = state.storage.check_quota(&bucket, &upload.key, super::object::declared_length(&headers)).await {
+        return upload_error(e);
+    }
+
+    let checksum = super::object::extract_checksum(&headers);
+    let stream = body.into_data_stream();
+    let reader = tokio_util::io::StreamReader::new(stream.map_err(std::io::Error::other));
+    match state.storage.upload_part(&bucket, &upload_id, part_number, Box::pin(reader), checksum).await {
+        Ok(part) => (StatusCode::OK, Json(part_json(&part))).into_response(),
+        Err(e) => upload_error(e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteUploadPart {
+    part_number: u32,
+    etag: String,
+    checksum: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteUploadRequest {
+    /// Parts to assemble; every uploaded part, in order, when omitted.
+    parts: Option<Vec<CompleteUploadPart>>,
+    /// Checksum the client computed for the whole object.
+    checksum: Option<String>,
+}
+
+pub async fn complete_upload(
+    State(state): State<AppState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+    body: Option<Json<CompleteUploadRequest>>,
+) -> impl IntoResponse {
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    let (upload, uploaded) = match state.storage.list_parts(&bucket, &upload_id).await {
+        Ok(listing) => listing,
+        Err(e) => return upload_error(e),
+    };
+    let parts: Vec<CompletedPart> = match body.parts {
+        Some(parts) => parts
+            .into_iter()
+            .map(|p| CompletedPart {
+                part_number: p.part_number,
+                etag: p.etag,
+                checksum_value: p.checksum,
+            })
+            .collect(),
+        None => uploaded
+            .into_iter()
+            .map(|p| CompletedPart {
+                part_number: p.part_number,
+                etag: p.etag,
+                checksum_value: p.checksum_value,
+            })
+            .collect(),
+    };
+
+    match state.storage.complete_multipart_upload(&bucket, &upload_id, &parts, body.checksum.as_deref()).await {
+        Ok(result) => {
+            (StatusCode::OK, Json(serde_json::json!({
+                "ok": true,
+                "key": upload.key,
+                "etag": result.etag,
+                "size": result.size,
+                "versionId": result.version_id,
+                "checksum": result.checksum_value,
+            }))).into_response()
+        }
+        Err(e) => upload_error(e),
+    }
+}
+
+pub async fn abort_upload(
+    State(state): State<AppState>,
+    Path((bucket, upload_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.storage.abort_multipart_upload(&bucket, &upload_id).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response(),
+        Err(e) => upload_error(e),
+    }
+}
+
 pub async fn delete_object_api(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
@@ -419,6 +1033,98 @@ pub async fn download_object(
         .into_response()
 }
 
+/// Stream a ZIP or tar.gz of objects: every object under `prefix`, or with
+/// one or more `key` parameters just those (a key ending in `/` takes the
+/// whole folder). Selected entries are named relative to `prefix`; a whole
+/// prefix keeps its own folder name.
+/// `GET /buckets/{bucket}/archive?prefix=photos/&key=photos/a.jpg&format=zip`
+pub async fn download_archive(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    RawQuery(query): RawQuery,
+) -> Response {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Bucket not found"}))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+
+    let mut prefix = String::new();
+    let mut selection = Vec::new();
+    let mut format = ArchiveFormat::Zip;
+    for (name, value) in super::listen::query_pairs(query.as_deref().unwrap_or("")) {
+        match name.as_str() {
+            "prefix" => prefix = value,
+            "key" => selection.push(value),
+            "format" => match ArchiveFormat::parse(&value) {
+                Some(f) => format = f,
+                None => return list_error(StatusCode::BAD_REQUEST, "format must be zip or tar.gz"),
+            },
+            _ => {}
+        }
+    }
+
+    let folder = prefix.trim_end_matches('/');
+    let parent = &folder[..folder.rfind('/').map_or(0, |i| i + 1)];
+    let (base, archive_name) = if selection.is_empty() {
+        selection.push(prefix.clone());
+        (parent.to_string(), &folder[parent.len()..])
+    } else {
+        (prefix.clone(), &folder[parent.len()..])
+    };
+    let archive_name = if archive_name.is_empty() { bucket.as_str() } else { archive_name };
+
+    // List everything up front so the caps apply before a byte is sent
+    let max_objects = state.config.archive_max_objects;
+    let max_size = state.config.archive_max_size;
+    let mut objects = std::collections::BTreeMap::new();
+    for key in &selection {
+        let found = if key.is_empty() || key.ends_with('/') {
+            match state.storage.list_objects(&bucket, key).await {
+                Ok(found) => found,
+                Err(e) => return list_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+            }
+        } else {
+            match state.storage.head_object(&bucket, key).await {
+                Ok(meta) => vec![meta],
+                Err(_) => return list_error(StatusCode::NOT_FOUND, &format!("Object not found: {}", key)),
+            }
+        };
+        // Folder markers have no content of their own
+        objects.extend(found.into_iter().filter(|o| !o.key.ends_with('/')).map(|o| (o.key, o.size)));
+        if max_objects > 0 && objects.len() as u64 > max_objects {
+            let message = format!("Archives are limited to {} objects", max_objects);
+            return list_error(StatusCode::PAYLOAD_TOO_LARGE, &message);
+        }
+    }
+    if objects.is_empty() {
+        return list_error(StatusCode::NOT_FOUND, "No objects to archive");
+    }
+    let total: u64 = objects.values().sum();
+    if max_size > 0 && total > max_size {
+        let message = format!("Archives are limited to {} bytes; the selection holds {}", max_size, total);
+        return list_error(StatusCode::PAYLOAD_TOO_LARGE, &message);
+    }
+
+    let entries = objects
+        .into_keys()
+        .map(|key| ArchiveEntry {
+            name: key.strip_prefix(base.as_str()).unwrap_or(&key).to_string(),
+            key,
+        })
+        .collect();
+    let filename = sanitize_filename(&format!("{}.{}", archive_name, format.extension()));
+    let body = archive::stream(state.storage.clone(), bucket.clone(), entries, format);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", format.content_type())
+        .header("Content-Disposition", format!("attachment; filename=\"{}\"", filename))
+        .body(body)
+        .unwrap()
+        .into_response()
+}
+
 /// Sanitize a filename for use in Content-Disposition headers.
 /// Removes characters that could enable header injection.
 fn sanitize_filename(name: &str) -> String {
@@ -428,26 +1134,120 @@ fn sanitize_filename(name: &str) -> String {
 }
 
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PresignParams {
     expires: Option<u64>,
+    /// GET (default), PUT, HEAD or DELETE
+    method: Option<String>,
+    /// Content-Type the uploader must send (PUT only)
+    content_type: Option<String>,
+    /// Checksum the uploader must send, e.g. `checksumAlgorithm=SHA256&checksum=<base64>` (PUT only)
+    checksum_algorithm: Option<String>,
+    checksum: Option<String>,
+    version_id: Option<String>,
+    /// Presign UploadPart URLs for this multipart upload instead of the object itself
+    upload_id: Option<String>,
+    /// Number of part URLs to generate with `uploadId` (1..=10000, default 1)
+    parts: Option<u32>,
+    response_content_type: Option<String>,
+    response_content_disposition: Option<String>,
+    response_cache_control: Option<String>,
+    response_content_encoding: Option<String>,
+    response_content_language: Option<String>,
+    response_expires: Option<String>,
+}
+
+fn presign_error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(serde_json::json!({"error": msg}))).into_response()
 }
 
 pub async fn presign_object(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
     Query(params): Query<PresignParams>,
+    Extension(session): Extension<Session>,
+    Extension(client): Extension<ClientInfo>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    // Verify object exists
-    match state.storage.head_object(&bucket, &key).await {
-        Ok(_) => {}
-        Err(_) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({"error": "Object not found"})),
-            )
-                .into_response()
+    let method = params.method.as_deref().unwrap_or("GET").to_ascii_uppercase();
+    if !matches!(method.as_str(), "GET" | "PUT" | "HEAD" | "DELETE") {
+        return presign_error(StatusCode::BAD_REQUEST, "method must be GET, PUT, HEAD or DELETE");
+    }
+    let reads = method == "GET" || method == "HEAD";
+    if !reads && !session.policy.allows_write() {
+        return presign_error(StatusCode::FORBIDDEN, "Access denied by policy");
+    }
+
+    // Headers the client must send with exactly these values
+    let mut signed: Vec<(String, String)> = Vec::new();
+    if let Some(ref ct) = params.content_type {
+        if method != "PUT" {
+            return presign_error(StatusCode::BAD_REQUEST, "contentType is only valid for PUT");
+        }
+        signed.push(("content-type".to_string(), ct.clone()));
+    }
+    match (&params.checksum_algorithm, &params.checksum) {
+        (Some(algo), Some(value)) if method == "PUT" => {
+            let Some(algo) = crate::storage::ChecksumAlgorithm::from_header_str(algo) else {
+                return presign_error(StatusCode::BAD_REQUEST, "Unsupported checksumAlgorithm");
+            };
+            signed.push((algo.header_name().to_string(), value.clone()));
+        }
+        (None, None) => {}
+        _ => {
+            return presign_error(
+                StatusCode::BAD_REQUEST,
+                "checksumAlgorithm and checksum must be given together, for PUT only",
+            );
+        }
+    }
+
+    let overrides = [
+        ("response-cache-control", &params.response_cache_control),
+        ("response-content-disposition", &params.response_content_disposition),
+        ("response-content-encoding", &params.response_content_encoding),
+        ("response-content-language", &params.response_content_language),
+        ("response-content-type", &params.response_content_type),
+        ("response-expires", &params.response_expires),
+    ];
+    let mut query: Vec<(String, String)> = Vec::new();
+    for (name, value) in overrides {
+        if let Some(value) = value {
+            if !reads {
+                return presign_error(
+                    StatusCode::BAD_REQUEST,
+                    "Response header overrides are only valid for GET and HEAD",
+                );
+            }
+            query.push((name.to_string(), value.clone()));
+        }
+    }
+    if let Some(ref vid) = params.version_id {
+        if method == "PUT" {
+            return presign_error(StatusCode::BAD_REQUEST, "versionId is not valid for PUT");
         }
+        query.push(("versionId".to_string(), vid.clone()));
+    }
+
+    if let Some(ref upload_id) = params.upload_id {
+        if method != "PUT" || params.version_id.is_some() {
+            return presign_error(StatusCode::BAD_REQUEST, "uploadId requires method PUT");
+        }
+        match state.storage.list_parts(&bucket, upload_id).await {
+            Ok((upload, _)) if upload.key == key => {}
+            _ => return presign_error(StatusCode::NOT_FOUND, "Upload not found"),
+        }
+    } else if reads || method == "DELETE" {
+        // Reads and deletes target an existing object (or version)
+        let found = match params.version_id {
+            Some(ref vid) => state.storage.head_object_version(&bucket, &key, vid).await.is_ok(),
+            None => state.storage.head_object(&bucket, &key).await.is_ok(),
+        };
+        if !found {
+            return presign_error(StatusCode::NOT_FOUND, "Object not found");
+        }
+    } else if !matches!(state.storage.head_bucket(&bucket).await, Ok(true)) {
+        return presign_error(StatusCode::NOT_FOUND, "Bucket not found");
     }
 
     let expires_secs = params.expires.unwrap_or(3600).min(604800);
@@ -458,79 +1258,281 @@ pub async fn presign_object(
         .and_then(|v| v.to_str().ok())
         .unwrap_or("localhost:9000");
 
-    let now = chrono::Utc::now();
-    let date_stamp = now.format("%Y%m%d").to_string();
-    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
-    let region = &state.config.region;
-    let access_key = &state.config.access_key;
-
-    let credential = format!("{}/{}/{}/s3/aws4_request", access_key, date_stamp, region);
-    let path = format!("/{}/{}", bucket, key);
-
-    // Build query string params (sorted alphabetically, excluding Signature)
-    let qs_params = [
-        ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
-        ("X-Amz-Credential", credential.clone()),
-        ("X-Amz-Date", amz_date.clone()),
-        ("X-Amz-Expires", expires_secs.to_string()),
-        ("X-Amz-SignedHeaders", "host".to_string()),
-    ];
+    let (access_key, secret_key) = signing_keys(&state, &session);
+    let signer = Presigner {
+        state: &state,
+        access_key,
+        secret_key,
+        scheme: client.scheme,
+        host,
+        path: format!("/{}/{}", bucket, key),
+        expires_secs,
+        now: chrono::Utc::now(),
+    };
+    let required_headers: serde_json::Map<String, serde_json::Value> = signed
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone().into()))
+        .collect();
 
-    const S3_ENCODE: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
-        .remove(b'-')
-        .remove(b'_')
-        .remove(b'.')
-        .remove(b'~');
-    let encode =
-        |s: &str| -> String { percent_encoding::utf8_percent_encode(s, S3_ENCODE).to_string() };
+    if let Some(upload_id) = params.upload_id {
+        let count = params.parts.unwrap_or(1);
+        if !(1..=10000).contains(&count) {
+            return presign_error(StatusCode::BAD_REQUEST, "parts must be between 1 and 10000");
+        }
+        let urls: Vec<serde_json::Value> = (1..=count)
+            .map(|part_number| {
+                let mut part_query = query.clone();
+                part_query.push(("partNumber".to_string(), part_number.to_string()));
+                part_query.push(("uploadId".to_string(), upload_id.clone()));
+                serde_json::json!({
+                    "partNumber": part_number,
+                    "url": signer.sign(&method, part_query, &signed),
+                })
+            })
+            .collect();
+        return (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "method": method,
+                "uploadId": upload_id,
+                "parts": urls,
+                "headers": required_headers,
+                "expiresIn": expires_secs,
+            })),
+        )
+            .into_response();
+    }
 
-    let canonical_qs: String = qs_params
-        .iter()
-        .map(|(k, v)| format!("{}={}", encode(k), encode(v)))
-        .collect::<Vec<_>>()
-        .join("&");
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "url": signer.sign(&method, query, &signed),
+            "method": method,
+            "headers": required_headers,
+            "expiresIn": expires_secs,
+        })),
+    )
+        .into_response()
+}
 
-    let canonical_headers = format!("host:{}\n", host);
-    let canonical_request = format!(
-        "GET\n{}\n{}\n{}\nhost\nUNSIGNED-PAYLOAD",
-        path, canonical_qs, canonical_headers
-    );
+/// Key pair for signatures made on behalf of a console user: their own, so the
+/// URLs stop working when that user is removed, or the root pair for SSO users,
+/// who have no S3 key of their own.
+fn signing_keys<'a>(state: &'a AppState, session: &'a Session) -> (&'a str, &'a str) {
+    match state.credentials.secret_for(&session.access_key) {
+        Some(secret_key) => (&session.access_key, secret_key),
+        None => {
+            let root = state.credentials.root_access_key();
+            (root, state.credentials.secret_for(root).unwrap_or_default())
+        }
+    }
+}
 
-    let scope = format!("{}/{}/s3/aws4_request", date_stamp, region);
-    let canonical_hash = hex::encode(Sha256::digest(canonical_request.as_bytes()));
-    let string_to_sign = format!(
-        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
-        amz_date, scope, canonical_hash
-    );
+/// Builds SigV4 query-string presigned URLs for one object path.
+struct Presigner<'a> {
+    state: &'a AppState,
+    access_key: &'a str,
+    secret_key: &'a str,
+    scheme: &'a str,
+    host: &'a str,
+    path: String,
+    expires_secs: u64,
+    now: chrono::DateTime<chrono::Utc>,
+}
 
-    let signing_key =
-        signature_v4::derive_signing_key(&state.config.secret_key, &date_stamp, region);
+impl Presigner<'_> {
+    /// `query` holds extra (unencoded) query parameters; `signed` extra headers
+    /// (lowercase names) that become part of the signature besides `host`.
+    fn sign(&self, method: &str, mut query: Vec<(String, String)>, signed: &[(String, String)]) -> String {
+        let date_stamp = self.now.format("%Y%m%d").to_string();
+        let amz_date = self.now.format("%Y%m%dT%H%M%SZ").to_string();
+        let region = &self.state.config.region;
+        let credential = format!("{}/{}/{}/s3/aws4_request", self.access_key, date_stamp, region);
+
+        let mut headers: Vec<(String, String)> = signed.to_vec();
+        headers.push(("host".to_string(), self.host.to_string()));
+        headers.sort();
+        let signed_headers = headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        query.push(("X-Amz-Algorithm".to_string(), "AWS4-HMAC-SHA256".to_string()));
+        query.push(("X-Amz-Credential".to_string(), credential));
+        query.push(("X-Amz-Date".to_string(), amz_date.clone()));
+        query.push(("X-Amz-Expires".to_string(), self.expires_secs.to_string()));
+        query.push(("X-Amz-SignedHeaders".to_string(), signed_headers.clone()));
+
+        const S3_ENCODE: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+            .remove(b'-')
+            .remove(b'_')
+            .remove(b'.')
+            .remove(b'~');
+        let encode =
+            |s: &str| -> String { percent_encoding::utf8_percent_encode(s, S3_ENCODE).to_string() };
+
+        // Canonical query string is sorted by encoded parameter name
+        let mut encoded: Vec<(String, String)> =
+            query.iter().map(|(k, v)| (encode(k), encode(v))).collect();
+        encoded.sort();
+        let canonical_qs: String = encoded
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let canonical_uri: String = self
+            .path
+            .split('/')
+            .map(encode)
+            .collect::<Vec<_>>()
+            .join("/");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.trim()))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\nUNSIGNED-PAYLOAD",
+            method, canonical_uri, canonical_qs, canonical_headers, signed_headers
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date_stamp, region);
+        let canonical_hash = hex::encode(Sha256::digest(canonical_request.as_bytes()));
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, canonical_hash
+        );
+
+        let signing_key = signature_v4::derive_signing_key(self.secret_key, &date_stamp, region);
+
+        let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
+        mac.update(string_to_sign.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        format!(
+            "{}://{}{}?{}&X-Amz-Signature={}",
+            self.scheme, self.host, canonical_uri, canonical_qs, signature
+        )
+    }
+}
 
-    let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
-    mac.update(string_to_sign.as_bytes());
-    let signature = hex::encode(mac.finalize().into_bytes());
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostPolicyRequest {
+    /// Exact object key; alternatively `key_prefix` lets the form choose the key.
+    key: Option<String>,
+    key_prefix: Option<String>,
+    expires: Option<u64>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    content_type: Option<String>,
+    content_type_prefix: Option<String>,
+    success_action_status: Option<u16>,
+    success_action_redirect: Option<String>,
+}
 
-    // Determine scheme
-    let scheme = if headers
-        .get("x-forwarded-proto")
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v == "https")
-        .unwrap_or(false)
+/// Generate a signed POST policy and the form fields for a browser upload.
+pub async fn create_post_policy(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Extension(session): Extension<Session>,
+    Extension(client): Extension<ClientInfo>,
+    headers: HeaderMap,
+    Json(body): Json<PostPolicyRequest>,
+) -> impl IntoResponse {
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        _ => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": "Bucket not found"})),
+            )
+                .into_response()
+        }
+    }
+    if body.key.is_none() == body.key_prefix.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Exactly one of key or keyPrefix is required"})),
+        )
+            .into_response();
+    }
+    if let Some(status) = body.success_action_status
+        && ![200, 201, 204].contains(&status)
     {
-        "https"
-    } else {
-        "http"
-    };
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "successActionStatus must be 200, 201 or 204"})),
+        )
+            .into_response();
+    }
 
-    let presigned_url = format!(
-        "{}://{}{}?{}&X-Amz-Signature={}",
-        scheme, host, path, canonical_qs, signature
-    );
+    let expires_secs = body.expires.unwrap_or(3600).min(604800);
+    let now = chrono::Utc::now();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let region = &state.config.region;
+    let (access_key, secret_key) = signing_keys(&state, &session);
+    let credential = format!("{}/{}/{}/s3/aws4_request", access_key, date_stamp, region);
+    let expiration = (now + chrono::Duration::seconds(expires_secs as i64))
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+
+    // Form fields the browser must send back, each pinned by an exact-match condition
+    let mut fields = serde_json::Map::new();
+    fields.insert("x-amz-algorithm".into(), "AWS4-HMAC-SHA256".into());
+    fields.insert("x-amz-credential".into(), credential.into());
+    fields.insert("x-amz-date".into(), amz_date.into());
+    let mut conditions = vec![serde_json::json!({"bucket": bucket})];
+
+    match (&body.key, &body.key_prefix) {
+        (Some(key), _) => {
+            fields.insert("key".into(), key.clone().into());
+        }
+        (None, Some(prefix)) => {
+            conditions.push(serde_json::json!(["starts-with", "$key", prefix]));
+        }
+        (None, None) => unreachable!(),
+    }
+    if let Some(ref ct) = body.content_type {
+        fields.insert("Content-Type".into(), ct.clone().into());
+    } else if let Some(ref prefix) = body.content_type_prefix {
+        conditions.push(serde_json::json!(["starts-with", "$Content-Type", prefix]));
+    }
+    if let Some(status) = body.success_action_status {
+        fields.insert("success_action_status".into(), status.to_string().into());
+    }
+    if let Some(ref url) = body.success_action_redirect {
+        fields.insert("success_action_redirect".into(), url.clone().into());
+    }
+    for (name, value) in &fields {
+        conditions.push(serde_json::json!({ name.as_str(): value }));
+    }
+    if body.min_size.is_some() || body.max_size.is_some() {
+        conditions.push(serde_json::json!([
+            "content-length-range",
+            body.min_size.unwrap_or(0),
+            body.max_size.unwrap_or(5 * 1024 * 1024 * 1024),
+        ]));
+    }
+
+    let policy = serde_json::json!({ "expiration": expiration, "conditions": conditions });
+    let policy_b64 = base64::engine::general_purpose::STANDARD.encode(policy.to_string());
+    let signature = post_policy::sign_policy(&policy_b64, secret_key, &date_stamp, region);
+    fields.insert("policy".into(), policy_b64.into());
+    fields.insert("x-amz-signature".into(), signature.into());
+
+    let host = headers
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost:9000");
 
     (
         StatusCode::OK,
         Json(serde_json::json!({
-            "url": presigned_url,
+            "url": format!("{}://{}/{}", client.scheme, host, bucket),
+            "fields": fields,
             "expiresIn": expires_secs,
         })),
     )
@@ -557,6 +1559,10 @@ pub async fn create_folder(
     }
 
     let key = format!("{}/", name);
+    let _reservation = match state.storage.reserve_quota(&bucket, &key, Some(0)).await {
+        Ok(reservation) => reservation,
+        Err(e) => return quota_error(e),
+    };
     match state.storage.put_object(&bucket, &key, "application/x-directory", Box::pin(tokio::io::empty()), None).await {
         Ok(_) => (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response(),
         Err(e) => (
@@ -593,6 +1599,62 @@ pub async fn set_versioning(
     }
 }
 
+fn quota_error(e: StorageError) -> Response {
+    let status = match e {
+        StorageError::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(serde_json::json!({"error": e.to_string()}))).into_response()
+}
+
+pub async fn get_quota(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+) -> impl IntoResponse {
+    let quota = match state.storage.get_quota(&bucket).await {
+        Ok(quota) => quota.unwrap_or_default(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    };
+    match state.storage.bucket_usage(&bucket).await {
+        Ok(usage) => (StatusCode::OK, Json(serde_json::json!({
+            "maxSize": quota.max_size,
+            "maxObjects": quota.max_objects,
+            "size": usage.size,
+            "objects": usage.objects,
+        }))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetQuotaRequest {
+    #[serde(default)]
+    max_size: u64,
+    #[serde(default)]
+    max_objects: u64,
+}
+
+/// Quotas cap what bucket users may store, so only the root user sets them.
+pub async fn set_quota(
+    State(state): State<AppState>,
+    Extension(session): Extension<Session>,
+    Path(bucket): Path<String>,
+    Json(body): Json<SetQuotaRequest>,
+) -> impl IntoResponse {
+    if !state.credentials.is_root(&session.access_key) {
+        return (StatusCode::FORBIDDEN, Json(serde_json::json!({"error": "Only the root user can change quotas"}))).into_response();
+    }
+    let quota = BucketQuota {
+        max_size: body.max_size,
+        max_objects: body.max_objects,
+    };
+    match state.storage.set_quota(&bucket, Some(quota)).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+}
+
 #[derive(serde::Deserialize)]
 pub struct ListVersionsParams {
     key: String,
@@ -662,31 +1724,105 @@ pub async fn download_version(
         .into_response()
 }
 
+#[derive(serde::Deserialize)]
+pub struct EventFeedParams {
+    #[serde(default)]
+    prefix: String,
+}
+
+/// Server-sent events for object changes in a bucket, so the console can
+/// refresh without polling.
+pub async fn event_feed(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Query(params): Query<EventFeedParams>,
+) -> Response {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use tokio::sync::broadcast::error::RecvError;
+
+    match state.storage.head_bucket(&bucket).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Bucket not found"}))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+    }
+
+    let receiver = state.notifier.subscribe();
+    let stream = futures::stream::unfold(receiver, move |mut receiver| {
+        let (bucket, prefix) = (bucket.clone(), params.prefix.clone());
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.bucket == bucket && event.key.starts_with(&prefix) => {
+                        let data = serde_json::json!({
+                            "eventName": format!("s3:{}", event.name.as_str()),
+                            "key": event.key,
+                            "size": event.size,
+                            "etag": event.etag,
+                            "versionId": event.version_id,
+                            "eventTime": event.time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                        });
+                        let sse = Event::default().event("object").data(data.to_string());
+                        return Some((Ok::<_, std::convert::Infallible>(sse), receiver));
+                    }
+                    Ok(_) => {}
+                    // Tell the client to reload rather than silently missing changes
+                    Err(RecvError::Lagged(_)) => {
+                        return Some((Ok(Event::default().event("resync").data("{}")), receiver));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
 pub fn console_router(state: AppState) -> Router<AppState> {
     let public = Router::new()
         .route("/auth/login", post(login))
-        .route("/auth/check", get(check));
+        .route("/auth/check", get(check))
+        .route("/auth/oidc", get(oidc_info))
+        .route("/auth/oidc/login", get(oidc_login))
+        .route("/auth/oidc/callback", get(oidc_callback));
 
     let protected = Router::new()
         .route("/auth/logout", post(logout))
+        .route("/auth/logout-all", post(logout_all))
+        .route("/auth/sessions", get(list_sessions))
+        .route("/auth/sessions/{id}", delete(revoke_session))
         .route("/buckets", get(list_buckets))
         .route("/buckets", post(create_bucket))
+        .route("/usage", get(data_usage))
         .route("/buckets/{bucket}", delete(delete_bucket_api))
         .route("/buckets/{bucket}/folders", post(create_folder))
         .route("/buckets/{bucket}/objects", get(list_objects))
         .route("/buckets/{bucket}/objects/{*key}", delete(delete_object_api))
         .route("/buckets/{bucket}/upload/{*key}", put(upload_object))
+        .route("/buckets/{bucket}/uploads", get(list_uploads))
+        .route("/buckets/{bucket}/uploads", post(create_upload))
+        .route("/buckets/{bucket}/uploads/{upload_id}", get(get_upload))
+        .route("/buckets/{bucket}/uploads/{upload_id}", delete(abort_upload))
+        .route("/buckets/{bucket}/uploads/{upload_id}/parts/{part_number}", put(upload_part))
+        .route("/buckets/{bucket}/uploads/{upload_id}/complete", post(complete_upload))
         .route("/buckets/{bucket}/download/{*key}", get(download_object))
+        .route("/buckets/{bucket}/archive", get(download_archive))
         .route("/buckets/{bucket}/presign/{*key}", get(presign_object))
+        .route("/buckets/{bucket}/post-policy", post(create_post_policy))
         .route("/buckets/{bucket}/versioning", get(get_versioning))
         .route("/buckets/{bucket}/versioning", put(set_versioning))
+        .route("/buckets/{bucket}/quota", get(get_quota))
+        .route("/buckets/{bucket}/quota", put(set_quota))
         .route("/buckets/{bucket}/versions", get(list_versions))
         .route("/buckets/{bucket}/versions/{version_id}/objects/{*key}", delete(delete_version))
         .route("/buckets/{bucket}/versions/{version_id}/download/{*key}", get(download_version))
+        .route("/buckets/{bucket}/events", get(event_feed))
         .layer(axum::middleware::from_fn_with_state(
             state,
             console_auth_middleware,
         ));
 
-    public.merge(protected)
+    public
+        .merge(protected)
+        .layer(axum::middleware::from_fn(same_origin_middleware))
 }
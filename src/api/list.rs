@@ -2,7 +2,7 @@ use std::collections::{BTreeSet, HashMap};
 
 use axum::{
     body::Body,
-    extract::{Path, Query, State},
+    extract::{Path, Query, RawQuery, State},
     response::Response,
 };
 use http::StatusCode;
@@ -17,6 +17,7 @@ pub async fn handle_bucket_get(
     State(state): State<AppState>,
     Path(bucket): Path<String>,
     Query(params): Query<HashMap<String, String>>,
+    RawQuery(raw_query): RawQuery,
 ) -> Result<Response<Body>, S3Error> {
     tracing::debug!("GET /{} params={:?}", bucket, params);
 
@@ -34,6 +35,21 @@ pub async fn handle_bucket_get(
         return super::bucket::get_bucket_versioning(state, bucket).await;
     }
 
+    if params.contains_key("events") {
+        return super::listen::listen_bucket_notification(state, bucket, raw_query.as_deref().unwrap_or(""))
+            .await;
+    }
+
+    if params.contains_key("notification") {
+        return super::bucket::get_bucket_notification(state, bucket).await;
+    }
+    if params.contains_key("replication") {
+        return super::bucket::get_bucket_replication(state, bucket).await;
+    }
+    if params.contains_key("logging") {
+        return super::bucket::get_bucket_logging(state, bucket).await;
+    }
+
     if params.contains_key("versions") {
         return list_object_versions(state, bucket, params).await;
     }
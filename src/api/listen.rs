@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::StatusCode,
+    response::Response,
+};
+use bytes::Bytes;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::error::S3Error;
+use crate::notify::{EventName, is_valid_event_pattern};
+use crate::server::AppState;
+
+/// Idle streams get a single space this often so clients and proxies keep the
+/// connection open; JSON decoders skip the whitespace.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Which events a listener asked for.
+pub struct ListenFilter {
+    pub events: Vec<String>,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl ListenFilter {
+    /// Build from query pairs: repeated `events`, `prefix` and `suffix`.
+    /// Without `events` every supported event is selected.
+    pub fn from_query(pairs: &[(String, String)]) -> Result<Self, S3Error> {
+        let mut filter = Self {
+            events: Vec::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+        };
+        for (name, value) in pairs {
+            match name.as_str() {
+                "events" => {
+                    if !is_valid_event_pattern(value) {
+                        return Err(S3Error::invalid_argument(&format!("The event is not supported: {}", value)));
+                    }
+                    filter.events.push(value.clone());
+                }
+                "prefix" => filter.prefix = value.clone(),
+                "suffix" => filter.suffix = value.clone(),
+                _ => {}
+            }
+        }
+        if filter.events.is_empty() {
+            filter.events = EventName::ALL.iter().map(|e| format!("s3:{}", e.as_str())).collect();
+        }
+        Ok(filter)
+    }
+}
+
+/// Decode a raw query string into (name, value) pairs, keeping repeated names.
+pub fn query_pairs(raw: &str) -> Vec<(String, String)> {
+    let decode = |s: &str| {
+        percent_encoding::percent_decode_str(&s.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    raw.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+/// Handle GET /{bucket}?events=... (MinIO's ListenBucketNotification): hold the
+/// response open and stream each matching event as one line of JSON.
+pub async fn listen_bucket_notification(
+    state: AppState,
+    bucket: String,
+    raw_query: &str,
+) -> Result<Response<Body>, S3Error> {
+    let filter = ListenFilter::from_query(&query_pairs(raw_query))?;
+    let receiver = state.notifier.subscribe();
+    let keepalive = tokio::time::interval_at(
+        tokio::time::Instant::now() + KEEPALIVE_INTERVAL,
+        KEEPALIVE_INTERVAL,
+    );
+
+    let stream = futures::stream::unfold(
+        (receiver, keepalive, state, bucket, filter),
+        |(mut receiver, mut keepalive, state, bucket, filter)| async move {
+            let chunk = loop {
+                tokio::select! {
+                    received = receiver.recv() => match received {
+                        Ok(event) => {
+                            if event.bucket != bucket
+                                || !event.matches(&filter.events, &filter.prefix, &filter.suffix)
+                            {
+                                continue;
+                            }
+                            let mut line = state.notifier.payload(&event, "Config").to_string();
+                            line.push('\n');
+                            break Bytes::from(line);
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::warn!("Notification listener on {} missed {} event(s)", bucket, missed);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    },
+                    _ = keepalive.tick() => break Bytes::from_static(b" "),
+                }
+            };
+            Some((
+                Ok::<_, std::io::Error>(chunk),
+                (receiver, keepalive, state, bucket, filter),
+            ))
+        },
+    );
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/x-ndjson")
+        .header("cache-control", "no-cache")
+        .body(Body::from_stream(stream))
+        .unwrap())
+}
@@ -1,6 +1,12 @@
+pub mod admin;
+pub mod archive;
 pub mod bucket;
 pub mod console;
 pub mod list;
+pub mod listen;
 pub mod multipart;
 pub mod object;
+pub mod post_object;
 pub mod router;
+pub mod sts;
+pub mod virtual_host;
@@ -9,10 +9,10 @@ use axum::{
 
 use crate::error::S3Error;
 use crate::server::AppState;
-use crate::storage::{ChecksumAlgorithm, StorageError};
+use crate::storage::{ChecksumAlgorithm, ChecksumType, CompletedPart, StorageError};
 use crate::xml::{response::to_xml, types::*};
 
-use super::object::{body_to_reader, extract_checksum};
+use super::object::{body_to_reader, declared_length, extract_checksum};
 
 const COMPLETE_BODY_MAX: usize = 1024 * 1024;
 
@@ -31,11 +31,19 @@ pub async fn create_multipart_upload(
         .get("x-amz-checksum-algorithm")
         .and_then(|v| v.to_str().ok())
         .and_then(ChecksumAlgorithm::from_header_str);
+    let checksum_type = match headers.get("x-amz-checksum-type").and_then(|v| v.to_str().ok()) {
+        Some(v) => Some(
+            ChecksumType::from_header_str(v)
+                .ok_or_else(|| S3Error::invalid_argument("invalid x-amz-checksum-type"))?,
+        ),
+        None => None,
+    };
     let upload = state
         .storage
-        .create_multipart_upload(&bucket, &key, content_type, checksum_algorithm)
+        .create_multipart_upload(&bucket, &key, content_type, checksum_algorithm, checksum_type)
         .await
         .map_err(map_storage_err)?;
+    let upload_checksum = upload.checksum_algorithm.zip(upload.checksum_type);
 
     let xml = to_xml(&InitiateMultipartUploadResult {
         bucket,
@@ -44,16 +52,20 @@ pub async fn create_multipart_upload(
     })
     .map_err(S3Error::internal)?;
 
-    Ok(Response::builder()
+    let mut builder = Response::builder()
         .status(StatusCode::OK)
-        .header("content-type", "application/xml")
-        .body(Body::from(xml))
-        .unwrap())
+        .header("content-type", "application/xml");
+    if let Some((algo, checksum_type)) = upload_checksum {
+        builder = builder
+            .header("x-amz-checksum-algorithm", algo.as_str())
+            .header("x-amz-checksum-type", checksum_type.as_str());
+    }
+    Ok(builder.body(Body::from(xml)).unwrap())
 }
 
 pub async fn upload_part(
     State(state): State<AppState>,
-    Path((bucket, _key)): Path<(String, String)>,
+    Path((bucket, key)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
     body: Body,
@@ -69,6 +81,12 @@ pub async fn upload_part(
         .parse::<u32>()
         .map_err(|_| S3Error::invalid_part("invalid part number"))?;
 
+    state
+        .storage
+        .check_quota(&bucket, &key, declared_length(&headers))
+        .await
+        .map_err(map_storage_err)?;
+
     let checksum = extract_checksum(&headers);
     let reader = body_to_reader(&headers, body).await?;
     let part = state
@@ -90,12 +108,15 @@ pub async fn complete_multipart_upload(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
     body: Body,
 ) -> Result<Response<Body>, S3Error> {
     ensure_bucket_exists(&state, &bucket).await?;
     let upload_id = params
         .get("uploadId")
         .ok_or_else(|| S3Error::invalid_argument("missing uploadId"))?;
+    // Full-object (or composite) checksum the client expects for the final object
+    let expected_checksum = extract_checksum(&headers).and_then(|(_, value)| value);
 
     let bytes = axum::body::to_bytes(body, COMPLETE_BODY_MAX)
         .await
@@ -105,10 +126,9 @@ pub async fn complete_multipart_upload(
 
     let result = state
         .storage
-        .complete_multipart_upload(&bucket, upload_id, &parts)
+        .complete_multipart_upload(&bucket, upload_id, &parts, expected_checksum.as_deref())
         .await
         .map_err(map_storage_err)?;
-
     let xml = to_xml(&CompleteMultipartUploadResult {
         location: format!("/{}/{}", bucket, key),
         bucket,
@@ -122,6 +142,9 @@ pub async fn complete_multipart_upload(
         .header("content-type", "application/xml");
     if let (Some(algo), Some(val)) = (&result.checksum_algorithm, &result.checksum_value) {
         builder = builder.header(algo.header_name(), val.as_str());
+        if let Some(checksum_type) = result.checksum_type {
+            builder = builder.header("x-amz-checksum-type", checksum_type.as_str());
+        }
     }
     Ok(builder.body(Body::from(xml)).unwrap())
 }
@@ -233,7 +256,10 @@ fn map_storage_err(err: StorageError) -> S3Error {
     match err {
         StorageError::ChecksumMismatch(_) => S3Error::bad_checksum("x-amz-checksum"),
         StorageError::UploadNotFound(upload_id) => S3Error::no_such_upload(&upload_id),
+        StorageError::QuotaExceeded(msg) => S3Error::quota_exceeded(&msg),
         StorageError::InvalidKey(msg) if msg.contains("part too small") => S3Error::entity_too_small(),
+        StorageError::InvalidKey(msg) if msg.contains("checksum mismatch for part") => S3Error::invalid_part(&msg),
+        StorageError::InvalidKey(msg) if msg.contains("checksum") => S3Error::invalid_request(&msg),
         StorageError::InvalidKey(msg)
             if msg.contains("part")
                 || msg.contains("etag")
@@ -247,7 +273,7 @@ fn map_storage_err(err: StorageError) -> S3Error {
     }
 }
 
-fn parse_complete_parts(xml: &str) -> Result<Vec<(u32, String)>, S3Error> {
+fn parse_complete_parts(xml: &str) -> Result<Vec<CompletedPart>, S3Error> {
     let mut reader = quick_xml::Reader::from_str(xml);
     reader.config_mut().trim_text(true);
 
@@ -255,8 +281,10 @@ fn parse_complete_parts(xml: &str) -> Result<Vec<(u32, String)>, S3Error> {
     let mut in_part = false;
     let mut in_part_number = false;
     let mut in_etag = false;
+    let mut in_checksum = false;
     let mut part_number: Option<u32> = None;
     let mut etag: Option<String> = None;
+    let mut checksum: Option<String> = None;
 
     loop {
         match reader.read_event() {
@@ -265,9 +293,11 @@ fn parse_complete_parts(xml: &str) -> Result<Vec<(u32, String)>, S3Error> {
                     in_part = true;
                     part_number = None;
                     etag = None;
+                    checksum = None;
                 }
                 b"PartNumber" if in_part => in_part_number = true,
                 b"ETag" if in_part => in_etag = true,
+                name if in_part && name.starts_with(b"Checksum") => in_checksum = true,
                 _ => {}
             },
             Ok(quick_xml::events::Event::Text(e)) => {
@@ -284,15 +314,24 @@ fn parse_complete_parts(xml: &str) -> Result<Vec<(u32, String)>, S3Error> {
                     };
                     etag = Some(normalized);
                     in_etag = false;
+                } else if in_checksum {
+                    let value = e.unescape().map_err(|_| S3Error::malformed_xml())?.into_owned();
+                    checksum = Some(value);
+                    in_checksum = false;
                 }
             }
             Ok(quick_xml::events::Event::End(e)) => match e.name().as_ref() {
                 b"PartNumber" => in_part_number = false,
                 b"ETag" => in_etag = false,
+                name if name.starts_with(b"Checksum") => in_checksum = false,
                 b"Part" => {
                     let n = part_number.ok_or_else(S3Error::malformed_xml)?;
                     let tag = etag.clone().ok_or_else(S3Error::malformed_xml)?;
-                    parts.push((n, tag));
+                    parts.push(CompletedPart {
+                        part_number: n,
+                        etag: tag,
+                        checksum_value: checksum.take(),
+                    });
                     in_part = false;
                 }
                 _ => {}
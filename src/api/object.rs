@@ -2,7 +2,7 @@ use axum::{
     body::Body,
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use futures::TryStreamExt;
 use std::collections::HashMap;
@@ -10,11 +10,19 @@ use tokio::io::{AsyncBufReadExt, AsyncReadExt};
 use tokio_util::io::ReaderStream;
 
 use crate::error::S3Error;
+use crate::notify::EventName;
+use crate::replication;
 use crate::server::AppState;
-use crate::storage::{ChecksumAlgorithm, StorageError};
-use crate::xml::{response::to_xml, types::CopyObjectResult};
+use crate::storage::{ChecksumAlgorithm, ChecksumType, ObjectMeta, StorageError};
+use crate::xml::{
+    response::to_xml,
+    types::{
+        ChecksumFields, CopyObjectResult, GetObjectAttributesResponse, ObjectChecksum, ObjectPartEntry,
+        ObjectParts,
+    },
+};
 
-use super::multipart;
+use super::{multipart, post_object};
 
 /// Extract checksum algorithm and optional expected value from request headers.
 pub(crate) fn extract_checksum(headers: &HeaderMap) -> Option<(ChecksumAlgorithm, Option<String>)> {
@@ -23,6 +31,7 @@ pub(crate) fn extract_checksum(headers: &HeaderMap) -> Option<(ChecksumAlgorithm
         ("x-amz-checksum-crc32c", ChecksumAlgorithm::CRC32C),
         ("x-amz-checksum-sha1", ChecksumAlgorithm::SHA1),
         ("x-amz-checksum-sha256", ChecksumAlgorithm::SHA256),
+        ("x-amz-checksum-crc64nvme", ChecksumAlgorithm::CRC64NVME),
     ];
 
     // Check for a value header first (implies the algorithm)
@@ -40,12 +49,32 @@ pub(crate) fn extract_checksum(headers: &HeaderMap) -> Option<(ChecksumAlgorithm
         .map(|algo| (algo, None))
 }
 
+/// Size of the object a request body carries, when the client declared it.
+/// A request with neither a length nor chunked framing has no body.
+pub(crate) fn declared_length(headers: &HeaderMap) -> Option<u64> {
+    if is_aws_chunked(headers) {
+        return headers.get("x-amz-decoded-content-length")?.to_str().ok()?.parse().ok();
+    }
+    match headers.get("content-length") {
+        Some(v) => v.to_str().ok()?.parse().ok(),
+        None if headers.contains_key("transfer-encoding") => None,
+        None => Some(0),
+    }
+}
+
+fn is_aws_chunked(headers: &HeaderMap) -> bool {
+    headers.get("x-amz-content-sha256").and_then(|v| v.to_str().ok()) == Some("STREAMING-AWS4-HMAC-SHA256-PAYLOAD")
+}
+
 fn add_checksum_header(
     builder: http::response::Builder,
     meta: &crate::storage::ObjectMeta,
 ) -> http::response::Builder {
     if let (Some(algo), Some(val)) = (&meta.checksum_algorithm, &meta.checksum_value) {
-        builder.header(algo.header_name(), val.as_str())
+        let checksum_type = meta.checksum_type.unwrap_or(ChecksumType::FullObject);
+        builder
+            .header(algo.header_name(), val.as_str())
+            .header("x-amz-checksum-type", checksum_type.as_str())
     } else {
         builder
     }
@@ -84,6 +113,15 @@ pub async fn put_object(
         .and_then(|v| v.to_str().ok())
         .unwrap_or("application/octet-stream");
 
+    let _reservation = state
+        .storage
+        .reserve_quota(&bucket, &key, declared_length(&headers))
+        .await
+        .map_err(|e| match e {
+            StorageError::QuotaExceeded(msg) => S3Error::quota_exceeded(&msg),
+            _ => S3Error::internal(e),
+        })?;
+
     let mut reader = body_to_reader(&headers, body).await?;
 
     // If Content-MD5 is provided, buffer the body and verify before writing
@@ -107,16 +145,32 @@ pub async fn put_object(
     }
 
     let checksum = extract_checksum(&headers);
+    let replica = replication::replica_source(&headers)?;
 
-    let result = state
-        .storage
-        .put_object(&bucket, &key, content_type, reader, checksum)
-        .await
-        .map_err(|e| match e {
-            StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
-            StorageError::ChecksumMismatch(_) => S3Error::bad_checksum("x-amz-checksum"),
-            _ => S3Error::internal(e),
-        })?;
+    let put_error = |e: StorageError| match e {
+        StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+        StorageError::ChecksumMismatch(_) => S3Error::bad_checksum("x-amz-checksum"),
+        StorageError::QuotaExceeded(msg) => S3Error::quota_exceeded(&msg),
+        _ => S3Error::internal(e),
+    };
+    let result = match &replica {
+        Some(source) => state
+            .storage
+            .put_object_replica(&bucket, &key, content_type, reader, source)
+            .await
+            .map_err(put_error)?,
+        None => Some(
+            state
+                .storage
+                .put_object(&bucket, &key, content_type, reader, checksum)
+                .await
+                .map_err(put_error)?,
+        ),
+    };
+    // A replicated write older than what we have is acknowledged but ignored
+    let Some(result) = result else {
+        return Ok(Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap());
+    };
 
     let mut builder = Response::builder()
         .status(StatusCode::OK)
@@ -187,14 +241,21 @@ async fn copy_object(
     let checksum = src_meta.checksum_algorithm.map(|algo| (algo, None));
 
     // Write destination
+    let put_error = |e: StorageError| match e {
+        StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+        StorageError::QuotaExceeded(msg) => S3Error::quota_exceeded(&msg),
+        _ => S3Error::internal(e),
+    };
+    let _reservation = state
+        .storage
+        .reserve_quota(&bucket, &key, Some(src_meta.size))
+        .await
+        .map_err(put_error)?;
     let result = state
         .storage
-        .put_object(&bucket, &key, &content_type, reader, checksum)
+        .put_object_as(EventName::ObjectCreatedCopy, &bucket, &key, &content_type, reader, checksum)
         .await
-        .map_err(|e| match e {
-            StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
-            _ => S3Error::internal(e),
-        })?;
+        .map_err(put_error)?;
 
     // Get destination metadata for LastModified
     let dst_meta = state
@@ -278,6 +339,107 @@ fn parse_range(header: &str, file_size: u64) -> Result<Option<(u64, u64)>, ()> {
     }
 }
 
+/// Parse the `partNumber` query parameter (1-based, up to 10000 like S3).
+fn parse_part_number(params: &HashMap<String, String>) -> Result<Option<u32>, S3Error> {
+    match params.get("partNumber") {
+        None => Ok(None),
+        Some(v) => match v.parse::<u32>() {
+            Ok(n) if (1..=10000).contains(&n) => Ok(Some(n)),
+            _ => Err(S3Error::invalid_argument("Part number must be an integer between 1 and 10000, inclusive")),
+        },
+    }
+}
+
+/// Look up the metadata of the requested object (or version) and the byte
+/// range covered by `part_number` within it.
+async fn resolve_part(
+    state: &AppState,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&String>,
+    part_number: u32,
+) -> Result<(ObjectMeta, u64, u64), S3Error> {
+    let meta = match version_id {
+        Some(vid) => state.storage.head_object_version(bucket, key, vid).await,
+        None => state.storage.head_object(bucket, key).await,
+    }
+    .map_err(|e| match e {
+        StorageError::VersionNotFound(vid) => S3Error::no_such_version(&vid),
+        StorageError::NotFound(_) => S3Error::no_such_key(key),
+        StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+        _ => S3Error::internal(e),
+    })?;
+    if meta.is_delete_marker {
+        return Err(S3Error::no_such_key(key));
+    }
+    let (offset, length) = meta
+        .part_range(part_number)
+        .ok_or_else(S3Error::invalid_part_number)?;
+    Ok((meta, offset, length))
+}
+
+/// Headers shared by GET and HEAD responses for a single part of an object.
+fn part_response(meta: &ObjectMeta, part_number: u32, offset: u64, length: u64) -> http::response::Builder {
+    let mut builder = Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header("Content-Type", &meta.content_type)
+        .header("Content-Length", length.to_string())
+        .header("Content-Range", part_content_range(offset, length, meta.size))
+        .header("Accept-Ranges", "bytes")
+        .header("ETag", &meta.etag)
+        .header("Last-Modified", to_http_date(&meta.last_modified))
+        .header("x-amz-mp-parts-count", meta.parts_count().to_string());
+    if let Some(vid) = &meta.version_id {
+        builder = builder.header("x-amz-version-id", vid.as_str());
+    }
+    if let Some(status) = &meta.replication_status {
+        builder = builder.header("x-amz-replication-status", status.as_str());
+    }
+    match &meta.parts {
+        // Multipart objects report the checksum of the requested part
+        Some(parts) => {
+            let part = &parts[part_number as usize - 1];
+            if let (Some(algo), Some(val)) = (&meta.checksum_algorithm, &part.checksum_value) {
+                builder = builder.header(algo.header_name(), val.as_str());
+            }
+            builder
+        }
+        None => add_checksum_header(builder, meta),
+    }
+}
+
+/// Content-Range value for a part; empty parts have no satisfiable byte range.
+fn part_content_range(offset: u64, length: u64, total: u64) -> String {
+    if length == 0 {
+        format!("bytes */{}", total)
+    } else {
+        format!("bytes {}-{}/{}", offset, offset + length - 1, total)
+    }
+}
+
+/// Apply `response-*` query overrides (e.g. from a presigned URL) to GET/HEAD headers.
+fn with_response_overrides(
+    mut builder: http::response::Builder,
+    params: &HashMap<String, String>,
+) -> http::response::Builder {
+    const OVERRIDES: &[(&str, &str)] = &[
+        ("response-content-type", "content-type"),
+        ("response-content-language", "content-language"),
+        ("response-expires", "expires"),
+        ("response-cache-control", "cache-control"),
+        ("response-content-disposition", "content-disposition"),
+        ("response-content-encoding", "content-encoding"),
+    ];
+    if let Some(headers) = builder.headers_mut() {
+        for (param, header) in OVERRIDES {
+            if let Some(value) = params.get(*param).and_then(|v| http::HeaderValue::from_str(v).ok()) {
+                headers.insert(*header, value);
+            }
+        }
+    }
+    builder
+}
+
 pub async fn get_object(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
@@ -287,6 +449,33 @@ pub async fn get_object(
     if params.contains_key("uploadId") {
         return multipart::list_parts(State(state), Path((bucket, key)), Query(params)).await;
     }
+    if params.contains_key("attributes") {
+        return get_object_attributes(&state, &bucket, &key, &params, &headers).await;
+    }
+
+    if let Some(part_number) = parse_part_number(&params)? {
+        let version_id = params.get("versionId");
+        let (meta, offset, length) = resolve_part(&state, &bucket, &key, version_id, part_number).await?;
+        let (reader, _) = match version_id {
+            Some(vid) => {
+                state
+                    .storage
+                    .get_object_version_range(&bucket, &key, vid, offset, length)
+                    .await
+            }
+            None => state.storage.get_object_range(&bucket, &key, offset, length).await,
+        }
+        .map_err(|e| match e {
+            StorageError::VersionNotFound(vid) => S3Error::no_such_version(&vid),
+            StorageError::NotFound(_) => S3Error::no_such_key(&key),
+            _ => S3Error::internal(e),
+        })?;
+
+        let stream = ReaderStream::new(reader);
+        let body = Body::from_stream(stream);
+        let builder = with_response_overrides(part_response(&meta, part_number, offset, length), &params);
+        return Ok(builder.body(body).unwrap());
+    }
 
     let range_header = headers
         .get("range")
@@ -318,16 +507,15 @@ pub async fn get_object(
                 let stream = ReaderStream::new(reader);
                 let body = Body::from_stream(stream);
 
-                return Ok(Response::builder()
+                let builder = Response::builder()
                     .status(StatusCode::PARTIAL_CONTENT)
                     .header("Content-Type", &meta.content_type)
                     .header("Content-Length", length.to_string())
                     .header("Content-Range", format!("bytes {}-{}/{}", start, end, meta.size))
                     .header("Accept-Ranges", "bytes")
                     .header("ETag", &meta.etag)
-                    .header("Last-Modified", to_http_date(&meta.last_modified))
-                    .body(body)
-                    .unwrap());
+                    .header("Last-Modified", to_http_date(&meta.last_modified));
+                return Ok(with_response_overrides(builder, &params).body(body).unwrap());
             }
             Ok(None) => {
                 // Unparseable or multi-range — fall through to full 200
@@ -374,15 +562,156 @@ pub async fn get_object(
     if let Some(vid) = &meta.version_id {
         builder = builder.header("x-amz-version-id", vid.as_str());
     }
+    if let Some(status) = &meta.replication_status {
+        builder = builder.header("x-amz-replication-status", status.as_str());
+    }
     builder = add_checksum_header(builder, &meta);
+    builder = with_response_overrides(builder, &params);
     Ok(builder.body(body).unwrap())
 }
 
+fn checksum_fields(algo: ChecksumAlgorithm, value: &str) -> ChecksumFields {
+    let value = Some(value.to_string());
+    match algo {
+        ChecksumAlgorithm::CRC32 => ChecksumFields { crc32: value, ..Default::default() },
+        ChecksumAlgorithm::CRC32C => ChecksumFields { crc32c: value, ..Default::default() },
+        ChecksumAlgorithm::CRC64NVME => ChecksumFields { crc64nvme: value, ..Default::default() },
+        ChecksumAlgorithm::SHA1 => ChecksumFields { sha1: value, ..Default::default() },
+        ChecksumAlgorithm::SHA256 => ChecksumFields { sha256: value, ..Default::default() },
+    }
+}
+
+/// Handle GET /{bucket}/{key}?attributes — GetObjectAttributes.
+/// The `x-amz-object-attributes` header selects which fields are returned.
+async fn get_object_attributes(
+    state: &AppState,
+    bucket: &str,
+    key: &str,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Result<Response<Body>, S3Error> {
+    let requested: Vec<String> = headers
+        .get_all("x-amz-object-attributes")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if requested.is_empty() {
+        return Err(S3Error::invalid_argument("x-amz-object-attributes header is required"));
+    }
+    for attr in &requested {
+        if !matches!(
+            attr.as_str(),
+            "ETag" | "Checksum" | "ObjectParts" | "StorageClass" | "ObjectSize"
+        ) {
+            return Err(S3Error::invalid_argument(&format!("Invalid attribute name: {}", attr)));
+        }
+    }
+    let wants = |name: &str| requested.iter().any(|a| a == name);
+
+    let max_parts = match headers.get("x-amz-max-parts").and_then(|v| v.to_str().ok()) {
+        Some(v) => v
+            .parse::<u32>()
+            .map_err(|_| S3Error::invalid_argument("x-amz-max-parts must be an integer"))?
+            .min(1000),
+        None => 1000,
+    };
+    let part_number_marker = match headers.get("x-amz-part-number-marker").and_then(|v| v.to_str().ok()) {
+        Some(v) => v
+            .parse::<u32>()
+            .map_err(|_| S3Error::invalid_argument("x-amz-part-number-marker must be an integer"))?,
+        None => 0,
+    };
+
+    let meta = match params.get("versionId") {
+        Some(vid) => state.storage.head_object_version(bucket, key, vid).await,
+        None => state.storage.head_object(bucket, key).await,
+    }
+    .map_err(|e| match e {
+        StorageError::VersionNotFound(vid) => S3Error::no_such_version(&vid),
+        StorageError::NotFound(_) => S3Error::no_such_key(key),
+        StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+        _ => S3Error::internal(e),
+    })?;
+    if meta.is_delete_marker {
+        let mut err = S3Error::no_such_key(key).into_response();
+        err.headers_mut().insert("x-amz-delete-marker", http::HeaderValue::from_static("true"));
+        return Ok(err);
+    }
+
+    let checksum = match (&meta.checksum_algorithm, &meta.checksum_value) {
+        (Some(algo), Some(value)) if wants("Checksum") => Some(ObjectChecksum {
+            fields: checksum_fields(*algo, value),
+            checksum_type: Some(meta.checksum_type.unwrap_or(ChecksumType::FullObject).as_str().to_string()),
+        }),
+        _ => None,
+    };
+
+    // Only multipart objects have a part list
+    let object_parts = match &meta.parts {
+        Some(parts) if wants("ObjectParts") => {
+            let selected: Vec<_> = parts
+                .iter()
+                .filter(|p| p.part_number > part_number_marker)
+                .take(max_parts as usize)
+                .collect();
+            let next_marker = selected.last().map(|p| p.part_number).unwrap_or(part_number_marker);
+            let is_truncated = parts.iter().any(|p| p.part_number > next_marker);
+            Some(ObjectParts {
+                total_parts_count: parts.len() as u32,
+                part_number_marker,
+                next_part_number_marker: next_marker,
+                max_parts,
+                is_truncated,
+                parts: selected
+                    .into_iter()
+                    .map(|p| ObjectPartEntry {
+                        part_number: p.part_number,
+                        size: p.size,
+                        checksum: match (&meta.checksum_algorithm, &p.checksum_value) {
+                            (Some(algo), Some(value)) => checksum_fields(*algo, value),
+                            _ => ChecksumFields::default(),
+                        },
+                    })
+                    .collect(),
+            })
+        }
+        _ => None,
+    };
+
+    let xml = to_xml(&GetObjectAttributesResponse {
+        etag: wants("ETag").then(|| meta.etag.trim_matches('"').to_string()),
+        checksum,
+        object_parts,
+        storage_class: wants("StorageClass").then(|| "STANDARD".to_string()),
+        object_size: wants("ObjectSize").then_some(meta.size),
+    })
+    .map_err(S3Error::internal)?;
+
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/xml")
+        .header("Last-Modified", to_http_date(&meta.last_modified));
+    if let Some(vid) = &meta.version_id {
+        builder = builder.header("x-amz-version-id", vid.as_str());
+    }
+    Ok(builder.body(Body::from(xml)).unwrap())
+}
+
 pub async fn head_object(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Response<Body>, S3Error> {
+    if let Some(part_number) = parse_part_number(&params)? {
+        let (meta, offset, length) =
+            resolve_part(&state, &bucket, &key, params.get("versionId"), part_number).await?;
+        let builder = with_response_overrides(part_response(&meta, part_number, offset, length), &params);
+        return Ok(builder.body(Body::empty()).unwrap());
+    }
+
     let meta = if let Some(version_id) = params.get("versionId") {
         state
             .storage
@@ -416,7 +745,11 @@ pub async fn head_object(
     if let Some(vid) = &meta.version_id {
         builder = builder.header("x-amz-version-id", vid.as_str());
     }
+    if let Some(status) = &meta.replication_status {
+        builder = builder.header("x-amz-replication-status", status.as_str());
+    }
     builder = add_checksum_header(builder, &meta);
+    builder = with_response_overrides(builder, &params);
     Ok(builder.body(Body::empty()).unwrap())
 }
 
@@ -424,6 +757,7 @@ pub async fn delete_object(
     State(state): State<AppState>,
     Path((bucket, key)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> Result<Response<Body>, S3Error> {
     if params.contains_key("uploadId") {
         return multipart::abort_multipart_upload(State(state), Path((bucket, key)), Query(params))
@@ -432,14 +766,15 @@ pub async fn delete_object(
 
     // Permanent version deletion
     if let Some(version_id) = params.get("versionId") {
-        let deleted_meta = state
-            .storage
-            .delete_object_version(&bucket, &key, version_id)
-            .await
-            .map_err(|e| match e {
-                StorageError::VersionNotFound(_) => S3Error::no_such_version(version_id),
-                _ => S3Error::internal(e),
-            })?;
+        let deleted = if replication::is_replica_request(&headers) {
+            state.storage.delete_object_version_replica(&bucket, &key, version_id).await
+        } else {
+            state.storage.delete_object_version(&bucket, &key, version_id).await
+        };
+        let deleted_meta = deleted.map_err(|e| match e {
+            StorageError::VersionNotFound(_) => S3Error::no_such_version(version_id),
+            _ => S3Error::internal(e),
+        })?;
 
         let mut builder = Response::builder().status(StatusCode::NO_CONTENT);
         builder = builder.header("x-amz-version-id", version_id.as_str());
@@ -449,8 +784,23 @@ pub async fn delete_object(
         return Ok(builder.body(Body::empty()).unwrap());
     }
 
-    let result = state.storage.delete_object(&bucket, &key).await
-        .map_err(|e| S3Error::internal(e))?;
+    let replica = replication::replica_source(&headers)?;
+    let result = match &replica {
+        Some(source) => state
+            .storage
+            .delete_object_replica(&bucket, &key, source)
+            .await
+            .map_err(|e| match e {
+                StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+                _ => S3Error::internal(e),
+            })?,
+        None => Some(state.storage.delete_object(&bucket, &key).await
+            .map_err(S3Error::internal)?),
+    };
+    // A replicated delete older than the current object is ignored
+    let Some(result) = result else {
+        return Ok(Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap());
+    };
 
     let mut builder = Response::builder().status(StatusCode::NO_CONTENT);
     if let Some(vid) = &result.version_id {
@@ -477,6 +827,7 @@ pub async fn post_object(
             State(state),
             Path((bucket, key)),
             Query(params),
+            headers,
             body,
         )
         .await;
@@ -484,6 +835,19 @@ pub async fn post_object(
     Err(S3Error::not_implemented("Unsupported POST object operation"))
 }
 
+/// Handle POST /{bucket} — browser form uploads (multipart/form-data) or DeleteObjects.
+pub async fn post_bucket(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response<Body>, S3Error> {
+    if post_object::is_form_upload(&headers) {
+        return post_object::post_form_upload(&state, &bucket, &headers, body).await;
+    }
+    delete_objects(State(state), Path(bucket), body).await
+}
+
 const DELETE_BODY_MAX: usize = 1024 * 1024;
 
 /// Handle POST /{bucket}?delete — multi-object delete (DeleteObjects API).
@@ -576,17 +940,14 @@ pub(crate) async fn body_to_reader(
     headers: &HeaderMap,
     body: Body,
 ) -> Result<std::pin::Pin<Box<dyn tokio::io::AsyncRead + Send>>, S3Error> {
-    let is_aws_chunked = headers
-        .get("x-amz-content-sha256")
-        .and_then(|v| v.to_str().ok())
-        == Some("STREAMING-AWS4-HMAC-SHA256-PAYLOAD");
-
     let stream = body.into_data_stream();
     let raw_reader = tokio_util::io::StreamReader::new(
         stream.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)),
     );
 
-    if is_aws_chunked {
+    if is_aws_chunked(headers) {
+        // Quotas are checked against the declared length, so hold the body to it
+        let declared = declared_length(headers);
         let mut buf_reader = tokio::io::BufReader::new(raw_reader);
         let mut decoded = Vec::new();
         loop {
@@ -605,6 +966,9 @@ pub(crate) async fn body_to_reader(
             if chunk_size == 0 {
                 break;
             }
+            if declared.is_some_and(|len| (decoded.len() + chunk_size) as u64 > len) {
+                return Err(S3Error::incomplete_body());
+            }
             let mut chunk = vec![0u8; chunk_size];
             buf_reader
                 .read_exact(&mut chunk)
@@ -614,6 +978,9 @@ pub(crate) async fn body_to_reader(
             let mut crlf = [0u8; 2];
             let _ = buf_reader.read_exact(&mut crlf).await;
         }
+        if declared.is_some_and(|len| decoded.len() as u64 != len) {
+            return Err(S3Error::incomplete_body());
+        }
         Ok(Box::pin(std::io::Cursor::new(decoded)))
     } else {
         Ok(Box::pin(raw_reader))
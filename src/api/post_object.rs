@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::auth::middleware::{check_policy, resolve_signer};
+use crate::auth::post_policy::{self, PostPolicy};
+use crate::error::S3Error;
+use crate::notify::EventName;
+use crate::server::AppState;
+use crate::storage::{PutResult, StorageError};
+use crate::xml::{response::to_xml, types::PostResponse};
+
+/// Upper bound for a single non-file form field (matches S3's 20 KB form limit).
+const FIELD_MAX: usize = 20 * 1024;
+
+/// Whether a request body is an HTML form upload.
+pub(crate) fn is_form_upload(headers: &HeaderMap) -> bool {
+    headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase().starts_with("multipart/form-data"))
+        .unwrap_or(false)
+}
+
+/// Handle POST /{bucket} with a multipart/form-data body — browser-based
+/// uploads authorized by a signed POST policy instead of an Authorization header.
+pub async fn post_form_upload(
+    state: &AppState,
+    bucket: &str,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Response<Body>, S3Error> {
+    match state.storage.head_bucket(bucket).await {
+        Ok(true) => {}
+        Ok(false) => return Err(S3Error::no_such_bucket(bucket)),
+        Err(e) => return Err(S3Error::internal(e)),
+    }
+
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let boundary = multer::parse_boundary(content_type)
+        .map_err(|_| S3Error::invalid_argument("missing multipart/form-data boundary"))?;
+    let mut multipart = multer::Multipart::new(body.into_data_stream(), boundary);
+
+    // Fields before the file are collected; anything after the file is ignored, as in S3
+    let mut fields: HashMap<String, String> = HashMap::new();
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                return Err(S3Error::invalid_argument(
+                    "POST requires exactly one file upload per request.",
+                ));
+            }
+            Err(_) => return Err(S3Error::malformed_post_request()),
+        };
+        let name = field.name().unwrap_or("").to_ascii_lowercase();
+
+        if name == "file" {
+            let filename = field.file_name().unwrap_or("").to_string();
+            let file_content_type = field.content_type().map(|m| m.to_string());
+            let policy = authorize(state, bucket, &mut fields, &filename)?;
+            let key = fields.get("key").cloned().unwrap_or_default();
+            if key.is_empty() {
+                return Err(S3Error::invalid_argument("Bucket POST must contain a field named 'key'."));
+            }
+            let object_content_type = fields
+                .get("content-type")
+                .cloned()
+                .or(file_content_type)
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+
+            // Spool the file so a rejected upload never touches an existing object
+            let staging_dir = std::path::Path::new(&state.config.data_dir).join(".tmp");
+            tokio::fs::create_dir_all(&staging_dir)
+                .await
+                .map_err(S3Error::internal)?;
+            let staging_path = staging_dir.join(format!("post-{}", uuid::Uuid::new_v4()));
+            let result = spool_and_store(
+                state,
+                bucket,
+                &key,
+                &object_content_type,
+                &policy,
+                &mut field,
+                &staging_path,
+            )
+            .await;
+            let _ = tokio::fs::remove_file(&staging_path).await;
+            let result = result?;
+            return Ok(success_response(bucket, &key, &result.etag, result.version_id.as_deref(), &fields));
+        }
+
+        let mut value = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|_| S3Error::malformed_post_request())? {
+            value.extend_from_slice(&chunk);
+            if value.len() > FIELD_MAX {
+                return Err(S3Error::invalid_argument(&format!(
+                    "Form field '{}' exceeds the maximum allowed size",
+                    name
+                )));
+            }
+        }
+        fields.insert(name, String::from_utf8_lossy(&value).into_owned());
+    }
+}
+
+/// Verify the policy signature and conditions for the collected form fields.
+fn authorize(
+    state: &AppState,
+    bucket: &str,
+    fields: &mut HashMap<String, String>,
+    filename: &str,
+) -> Result<PostPolicy, S3Error> {
+    let policy_b64 = fields
+        .get("policy")
+        .cloned()
+        .ok_or_else(|| S3Error::access_denied("Bucket POST must contain a field named 'policy'."))?;
+
+    let algorithm = fields.get("x-amz-algorithm").map(String::as_str).unwrap_or("");
+    if algorithm != "AWS4-HMAC-SHA256" {
+        return Err(S3Error::invalid_argument("Unsupported x-amz-algorithm; only AWS4-HMAC-SHA256 is supported"));
+    }
+    let credential = fields.get("x-amz-credential").map(String::as_str).unwrap_or("");
+    let parts: Vec<&str> = credential.split('/').collect();
+    if parts.len() != 5 || parts[3] != "s3" || parts[4] != "aws4_request" {
+        return Err(S3Error::access_denied("Invalid x-amz-credential form field"));
+    }
+    let (access_key, date, region) = (parts[0], parts[1], parts[2]);
+    let session_token = fields.get("x-amz-security-token").map(String::as_str);
+    let signer = resolve_signer(state, access_key, session_token)?;
+    if region != state.config.region {
+        return Err(S3Error::access_denied("Invalid region in credential scope"));
+    }
+    if !fields.contains_key("x-amz-date") {
+        return Err(S3Error::access_denied("Bucket POST must contain a field named 'x-amz-date'."));
+    }
+    let signature = fields.get("x-amz-signature").map(String::as_str).unwrap_or("");
+    if !post_policy::verify_policy_signature(&policy_b64, signature, &signer.secret_key, date, region) {
+        return Err(S3Error::signature_mismatch());
+    }
+    check_policy(signer.policy, "POST")?;
+
+    let policy = PostPolicy::from_base64(&policy_b64).map_err(|e| S3Error::invalid_policy_document(&e))?;
+
+    fields.insert("bucket".to_string(), bucket.to_string());
+    if let Some(key) = fields.get_mut("key") {
+        *key = key.replace("${filename}", filename);
+    }
+    policy.check_fields(fields).map_err(|e| S3Error::access_denied(&e))?;
+    Ok(policy)
+}
+
+/// Write the file field to `staging_path`, enforce the policy's size range, then
+/// store it as the object.
+async fn spool_and_store(
+    state: &AppState,
+    bucket: &str,
+    key: &str,
+    content_type: &str,
+    policy: &PostPolicy,
+    field: &mut multer::Field<'_>,
+    staging_path: &std::path::Path,
+) -> Result<PutResult, S3Error> {
+    let (min, max) = policy.content_length_range().unwrap_or((0, u64::MAX));
+    let mut file = tokio::fs::File::create(staging_path)
+        .await
+        .map_err(S3Error::internal)?;
+    let mut size = 0u64;
+    while let Some(chunk) = field.chunk().await.map_err(|_| S3Error::malformed_post_request())? {
+        size += chunk.len() as u64;
+        if size > max {
+            return Err(S3Error::entity_too_large());
+        }
+        file.write_all(&chunk).await.map_err(S3Error::internal)?;
+    }
+    file.flush().await.map_err(S3Error::internal)?;
+    drop(file);
+    if size < min {
+        return Err(S3Error::entity_too_small());
+    }
+
+    let put_error = |e: StorageError| match e {
+        StorageError::InvalidKey(msg) => S3Error::invalid_argument(&msg),
+        StorageError::QuotaExceeded(msg) => S3Error::quota_exceeded(&msg),
+        _ => S3Error::internal(e),
+    };
+    let _reservation = state.storage.reserve_quota(bucket, key, Some(size)).await.map_err(put_error)?;
+    let staged = tokio::fs::File::open(staging_path)
+        .await
+        .map_err(S3Error::internal)?;
+    state
+        .storage
+        .put_object_as(EventName::ObjectCreatedPost, bucket, key, content_type, Box::pin(tokio::io::BufReader::new(staged)), None)
+        .await
+        .map_err(put_error)
+}
+
+fn success_response(
+    bucket: &str,
+    key: &str,
+    etag: &str,
+    version_id: Option<&str>,
+    fields: &HashMap<String, String>,
+) -> Response<Body> {
+    let location = format!("/{}/{}", bucket, key);
+
+    let redirect = fields
+        .get("success_action_redirect")
+        .or_else(|| fields.get("redirect"))
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"));
+    if let Some(url) = redirect {
+        const QUERY_ENCODE: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+            .remove(b'-')
+            .remove(b'_')
+            .remove(b'.')
+            .remove(b'~');
+        let encode =
+            |s: &str| -> String { percent_encoding::utf8_percent_encode(s, QUERY_ENCODE).to_string() };
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let target = format!(
+            "{}{}bucket={}&key={}&etag={}",
+            url,
+            separator,
+            encode(bucket),
+            encode(key),
+            encode(etag)
+        );
+        return Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header("Location", target)
+            .header("ETag", etag)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let status = match fields.get("success_action_status").map(String::as_str) {
+        Some("200") => StatusCode::OK,
+        Some("201") => StatusCode::CREATED,
+        _ => StatusCode::NO_CONTENT,
+    };
+    let mut builder = Response::builder()
+        .status(status)
+        .header("ETag", etag)
+        .header("Location", &location);
+    if let Some(vid) = version_id {
+        builder = builder.header("x-amz-version-id", vid);
+    }
+    if status == StatusCode::CREATED {
+        let xml = to_xml(&PostResponse {
+            location,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            etag: etag.to_string(),
+        })
+        .unwrap_or_default();
+        return builder
+            .header("content-type", "application/xml")
+            .body(Body::from(xml))
+            .unwrap();
+    }
+    builder.body(Body::empty()).unwrap()
+}
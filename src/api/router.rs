@@ -5,11 +5,11 @@ use axum::{
 
 use crate::server::AppState;
 
-use super::{bucket, list, object};
+use super::{bucket, list, object, sts};
 
 pub fn s3_router() -> Router<AppState> {
     Router::new()
-        .route("/", get(bucket::list_buckets))
+        .route("/", get(bucket::list_buckets).post(sts::handle_sts))
         // Bucket routes — with and without trailing slash
         .route("/{bucket}", put(bucket::handle_bucket_put))
         .route("/{bucket}/", put(bucket::handle_bucket_put))
@@ -19,9 +19,9 @@ pub fn s3_router() -> Router<AppState> {
         .route("/{bucket}/", delete(bucket::delete_bucket))
         .route("/{bucket}", get(list::handle_bucket_get))
         .route("/{bucket}/", get(list::handle_bucket_get))
-        // POST for DeleteObjects (multi-object delete)
-        .route("/{bucket}", post(object::delete_objects))
-        .route("/{bucket}/", post(object::delete_objects))
+        // POST for DeleteObjects (multi-object delete) and browser form uploads
+        .route("/{bucket}", post(object::post_bucket))
+        .route("/{bucket}/", post(object::post_bucket))
         // Object routes
         .route("/{bucket}/{*key}", post(object::post_object))
         .route("/{bucket}/{*key}", put(object::put_object))
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use sha2::{Digest, Sha256};
+
+use crate::audit::Principal;
+use crate::auth::middleware::verify_request;
+use crate::auth::policy::Policy;
+use crate::auth::sts::{self, TemporaryCredentials};
+use crate::error::{ErrorCode, S3Error};
+use crate::server::AppState;
+use crate::xml::{
+    response::to_xml,
+    types::{
+        AssumeRoleResponse, AssumeRoleResult, AssumeRoleWithWebIdentityResponse,
+        AssumeRoleWithWebIdentityResult, StsCredentials, StsResponseMetadata, STS_XMLNS,
+    },
+};
+
+/// An STS error, rendered in the STS (query protocol) error format.
+pub struct StsError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl StsError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_parameter(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidParameterValue", message)
+    }
+
+    fn missing_parameter(name: &str) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "MissingParameter",
+            format!("The request must contain the parameter {}", name),
+        )
+    }
+
+    fn access_denied(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "AccessDenied", message)
+    }
+}
+
+impl From<S3Error> for StsError {
+    fn from(e: S3Error) -> Self {
+        Self {
+            status: e.code.status_code(),
+            code: e.code.as_str(),
+            message: e.message,
+        }
+    }
+}
+
+impl IntoResponse for StsError {
+    fn into_response(self) -> Response {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let kind = if self.status.is_server_error() { "Receiver" } else { "Sender" };
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <ErrorResponse xmlns=\"{}\">\
+             <Error><Type>{}</Type><Code>{}</Code><Message>{}</Message></Error>\
+             <RequestId>{}</RequestId>\
+             </ErrorResponse>",
+            STS_XMLNS,
+            kind,
+            self.code,
+            quick_xml::escape::escape(&self.message),
+            request_id,
+        );
+        let mut response = (self.status, [("content-type", "application/xml")], xml).into_response();
+        response.extensions_mut().insert(ErrorCode(self.code));
+        response
+    }
+}
+
+/// `POST /` with a form-encoded `Action`: the STS API.
+pub async fn handle_sts(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, StsError> {
+    // Parameters may come in the query string (as mc sends them) or the body
+    let mut params = parse_form(uri.query().unwrap_or(""));
+    params.extend(parse_form(&String::from_utf8_lossy(&body)));
+
+    match params.get("Action").map(String::as_str) {
+        Some("AssumeRole") => assume_role(&state, &uri, headers, &body, &params),
+        Some("AssumeRoleWithWebIdentity") => assume_role_with_web_identity(&state, &params).await,
+        Some(other) => Err(StsError::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidAction",
+            format!("Could not find operation {}", other),
+        )),
+        None => Err(StsError::missing_parameter("Action")),
+    }
+}
+
+/// Temporary credentials for the caller's own key, optionally narrowed by a
+/// session policy.
+fn assume_role(
+    state: &AppState,
+    uri: &Uri,
+    mut headers: HeaderMap,
+    body: &[u8],
+    params: &HashMap<String, String>,
+) -> Result<Response, StsError> {
+    // STS clients sign the body hash without sending it as a header
+    if !headers.contains_key("x-amz-content-sha256") {
+        let hash = hex::encode(Sha256::digest(body));
+        headers.insert("x-amz-content-sha256", hash.parse().unwrap());
+    }
+    let caller = verify_request(state, "POST", uri.path(), uri.query().unwrap_or(""), &headers, "sts")?;
+    if caller.temporary {
+        return Err(StsError::access_denied("Temporary credentials cannot assume a role"));
+    }
+    let duration = duration_secs(params)?;
+    let policy = session_policy(params, caller.policy)?;
+
+    let creds = state
+        .credentials
+        .issue_temporary(&caller.access_key, Some(&caller.access_key), policy, duration);
+    let xml = to_xml(&AssumeRoleResponse {
+        xmlns: STS_XMLNS,
+        result: AssumeRoleResult {
+            credentials: xml_credentials(&creds),
+        },
+        metadata: response_metadata(),
+    })
+    .map_err(S3Error::internal)?;
+    Ok(sts_response(xml, caller.access_key))
+}
+
+/// Temporary credentials for the holder of an ID token from the OpenID
+/// Connect provider, with the policy its claims map to.
+async fn assume_role_with_web_identity(
+    state: &AppState,
+    params: &HashMap<String, String>,
+) -> Result<Response, StsError> {
+    let token = params
+        .get("WebIdentityToken")
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| StsError::missing_parameter("WebIdentityToken"))?;
+    let duration = duration_secs(params)?;
+    let Some(ref oidc) = state.oidc else {
+        return Err(StsError::invalid_parameter("No OpenID Connect provider is configured"));
+    };
+
+    let claims = oidc.verify_web_identity(token).await.map_err(|e| {
+        tracing::debug!("Web identity token rejected: {:#}", e);
+        StsError::new(StatusCode::BAD_REQUEST, "InvalidIdentityToken", e.to_string())
+    })?;
+    let identity = oidc
+        .identity(&claims)
+        .map_err(|e| StsError::access_denied(e.to_string()))?;
+    let policy = session_policy(params, identity.policy)?;
+
+    let creds = state
+        .credentials
+        .issue_temporary(&identity.username, None, policy, duration);
+    let subject = claims
+        .get("sub")
+        .and_then(|v| v.as_str())
+        .unwrap_or(&identity.username)
+        .to_string();
+    let xml = to_xml(&AssumeRoleWithWebIdentityResponse {
+        xmlns: STS_XMLNS,
+        result: AssumeRoleWithWebIdentityResult {
+            credentials: xml_credentials(&creds),
+            subject,
+        },
+        metadata: response_metadata(),
+    })
+    .map_err(S3Error::internal)?;
+    Ok(sts_response(xml, format!("oidc:{}", identity.username)))
+}
+
+fn duration_secs(params: &HashMap<String, String>) -> Result<i64, StsError> {
+    let Some(value) = params.get("DurationSeconds") else {
+        return Ok(sts::DEFAULT_DURATION_SECS);
+    };
+    match value.parse::<i64>() {
+        Ok(secs) if (sts::MIN_DURATION_SECS..=sts::MAX_DURATION_SECS).contains(&secs) => Ok(secs),
+        _ => Err(StsError::invalid_parameter(format!(
+            "DurationSeconds must be between {} and {}",
+            sts::MIN_DURATION_SECS,
+            sts::MAX_DURATION_SECS
+        ))),
+    }
+}
+
+/// The caller's policy, narrowed by the optional `Policy` parameter. MaxIO
+/// only knows its canned policies, so the parameter is a canned policy name
+/// or a policy document equivalent to one of them.
+fn session_policy(params: &HashMap<String, String>, base: Policy) -> Result<Policy, StsError> {
+    let Some(doc) = params.get("Policy").filter(|p| !p.trim().is_empty()) else {
+        return Ok(base);
+    };
+    let requested = match Policy::parse(doc) {
+        Some(policy) => policy,
+        None => policy_document_equivalent(doc).ok_or_else(|| {
+            StsError::new(
+                StatusCode::BAD_REQUEST,
+                "MalformedPolicyDocument",
+                "Session policies must be equivalent to the readonly or readwrite policy",
+            )
+        })?,
+    };
+    Ok(base.min(requested))
+}
+
+/// `readonly` for documents allowing only reads on every bucket, `readwrite`
+/// for documents allowing everything.
+fn policy_document_equivalent(doc: &str) -> Option<Policy> {
+    let doc: serde_json::Value = serde_json::from_str(doc).ok()?;
+    let statements = match doc.get("Statement")? {
+        serde_json::Value::Array(items) => items.clone(),
+        single => vec![single.clone()],
+    };
+    let strings = |v: Option<&serde_json::Value>| -> Option<Vec<String>> {
+        match v? {
+            serde_json::Value::String(s) => Some(vec![s.clone()]),
+            serde_json::Value::Array(items) => items.iter().map(|i| i.as_str().map(str::to_string)).collect(),
+            _ => None,
+        }
+    };
+
+    let mut policy = None;
+    for statement in &statements {
+        if statement.get("Effect")?.as_str()? != "Allow" {
+            return None;
+        }
+        let resources = strings(statement.get("Resource"))?;
+        if !resources.iter().all(|r| r == "*" || r == "arn:aws:s3:::*") {
+            return None;
+        }
+        for action in strings(statement.get("Action"))? {
+            let granted = match action.as_str() {
+                "*" | "s3:*" => Policy::ReadWrite,
+                a if a.starts_with("s3:Get") || a.starts_with("s3:List") => Policy::ReadOnly,
+                _ => return None,
+            };
+            policy = policy.max(Some(granted));
+        }
+    }
+    policy
+}
+
+fn xml_credentials(creds: &TemporaryCredentials) -> StsCredentials {
+    StsCredentials {
+        access_key_id: creds.access_key.clone(),
+        secret_access_key: creds.secret_key.clone(),
+        session_token: creds.session_token.clone(),
+        expiration: creds.expiration.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    }
+}
+
+fn response_metadata() -> StsResponseMetadata {
+    StsResponseMetadata {
+        request_id: uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+fn sts_response(xml: String, principal: String) -> Response {
+    (
+        StatusCode::OK,
+        [("content-type", "text/xml")],
+        Extension(Principal(principal)),
+        xml,
+    )
+        .into_response()
+}
+
+/// Decode `application/x-www-form-urlencoded` pairs.
+fn parse_form(input: &str) -> HashMap<String, String> {
+    let decode = |s: &str| {
+        percent_encoding::percent_decode_str(&s.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(k), decode(v))
+        })
+        .collect()
+}
@@ -0,0 +1,66 @@
+use axum::{
+    extract::{Request, State},
+    http::Uri,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::server::AppState;
+
+/// Path the client actually requested (and signed) before a virtual-hosted
+/// bucket was moved from the Host header into the path.
+#[derive(Clone, Debug)]
+pub struct OriginalPath(pub String);
+
+/// Extract the bucket from a `{bucket}.{domain}` Host header.
+pub fn bucket_from_host<'a>(host: &'a str, domains: &[String]) -> Option<&'a str> {
+    // Strip the port, keeping bracketed IPv6 literals intact
+    let host = match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    domains.iter().find_map(|domain| {
+        let domain = domain.trim().trim_end_matches('.');
+        if domain.is_empty() {
+            return None;
+        }
+        let prefix = host.strip_suffix(domain)?.strip_suffix('.')?;
+        (!prefix.is_empty()).then_some(prefix)
+    })
+}
+
+/// Rewrite virtual-hosted–style requests (`bucket.example.com/key`) to the
+/// path-style routes (`/bucket/key`). Requests to the bare domain, or to any
+/// host outside the configured domains, pass through unchanged.
+pub async fn virtual_host_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if state.config.domains.is_empty() {
+        return next.run(request).await;
+    }
+    let host = request
+        .headers()
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .map(|h| h.to_ascii_lowercase());
+    let bucket = host
+        .as_deref()
+        .and_then(|h| bucket_from_host(h, &state.config.domains))
+        .map(str::to_string);
+
+    if let Some(bucket) = bucket {
+        let path = request.uri().path().to_string();
+        let rewritten = match request.uri().query() {
+            Some(q) => format!("/{}{}?{}", bucket, path, q),
+            None => format!("/{}{}", bucket, path),
+        };
+        if let Ok(uri) = rewritten.parse::<Uri>() {
+            tracing::debug!("Virtual-hosted bucket '{}': {} -> {}", bucket, path, uri);
+            request.extensions_mut().insert(OriginalPath(path));
+            *request.uri_mut() = uri;
+        }
+    }
+    next.run(request).await
+}
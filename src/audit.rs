@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::Context as _;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http_body::{Frame, SizeHint};
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::error::ErrorCode;
+use crate::metrics::{counting, is_s3_signed, s3_api_name};
+use crate::notify::webhook::Webhook;
+use crate::proxy::ClientInfo;
+use crate::queue::DurableQueue;
+use crate::server::AppState;
+use crate::storage::filesystem::FilesystemStorage;
+
+/// Records waiting for the audit worker before new ones are dropped.
+const CHANNEL_CAPACITY: usize = 10_000;
+/// Rotated audit files kept next to the live one (`audit.log.1` is the newest).
+const KEEP_ROTATED: usize = 5;
+/// Access log lines buffered per target before a log object is written early.
+const MAX_BUFFERED_LINES: usize = 10_000;
+
+/// Query parameters that name the bucket subresource in access log operations
+/// such as `REST.GET.VERSIONING`.
+const SUBRESOURCES: [&str; 9] = [
+    "versioning",
+    "notification",
+    "replication",
+    "logging",
+    "location",
+    "uploads",
+    "versions",
+    "delete",
+    "events",
+];
+
+/// The access key a request was authenticated with. The S3 and console
+/// authentication layers leave it in the response extensions.
+#[derive(Debug, Clone)]
+pub struct Principal(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interface {
+    S3,
+    Console,
+}
+
+/// One request, as written to the audit log and audit webhooks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub time: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub api: Interface,
+    /// S3 operation (e.g. `PutObject`) or console route (e.g. `GET /api/buckets`).
+    pub operation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    pub method: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub duration_ms: u64,
+    /// Extra fields of the S3 server access log format.
+    #[serde(skip)]
+    access: AccessDetails,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AccessDetails {
+    operation: String,
+    request_uri: String,
+    http_version: String,
+    raw_key: Option<String>,
+    referer: Option<String>,
+    host: Option<String>,
+    auth_type: Option<&'static str>,
+}
+
+impl AuditEntry {
+    /// Start a record for the request, or `None` for requests that are not
+    /// audited (UI assets, health probes, metrics scrapes).
+    fn from_request(request: &Request) -> Option<Self> {
+        let uri = request.uri();
+        let path = uri.path();
+        let api = if path == "/api" || path.starts_with("/api/") {
+            Interface::Console
+        } else if path == "/ui"
+            || path.starts_with("/ui/")
+            || path.starts_with("/minio/health/")
+            || (request.method() == Method::GET && path == "/metrics" && !is_s3_signed(request))
+        {
+            return None;
+        } else {
+            Interface::S3
+        };
+
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let query = uri.query().unwrap_or("");
+        let mut entry = Self {
+            time: Utc::now(),
+            request_id: None,
+            api,
+            operation: String::new(),
+            access_key: None,
+            source_ip: request
+                .extensions()
+                .get::<ClientInfo>()
+                .map(|client| client.ip.to_string()),
+            user_agent: header("user-agent"),
+            method: request.method().to_string(),
+            path: path.to_string(),
+            bucket: None,
+            key: None,
+            version_id: query_param(query, "versionId"),
+            status: 0,
+            error_code: None,
+            bytes_in: 0,
+            bytes_out: 0,
+            duration_ms: 0,
+            access: AccessDetails {
+                request_uri: uri
+                    .path_and_query()
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
+                http_version: format!("{:?}", request.version()),
+                referer: header("referer"),
+                host: header("host"),
+                ..Default::default()
+            },
+        };
+
+        match api {
+            Interface::S3 => {
+                entry.operation = s3_api_name(request.method(), uri, request.headers()).to_string();
+                if !path.starts_with("/minio/admin/") {
+                    let (bucket, raw_key) = match path.trim_start_matches('/').split_once('/') {
+                        Some((bucket, key)) => (bucket, Some(key).filter(|k| !k.is_empty())),
+                        None => (path.trim_start_matches('/'), None),
+                    };
+                    entry.bucket = Some(bucket.to_string()).filter(|b| !b.is_empty());
+                    entry.key = raw_key.map(decode);
+                    entry.access.raw_key = raw_key.map(str::to_string);
+                }
+                entry.access.operation =
+                    rest_operation(request.method(), entry.key.is_some(), query);
+                entry.access.auth_type = if query.contains("X-Amz-Signature=") {
+                    Some("QueryString")
+                } else if request.headers().contains_key("authorization") {
+                    Some("AuthHeader")
+                } else {
+                    None
+                };
+            }
+            Interface::Console => entry.describe_console_route(path.trim_start_matches("/api/")),
+        }
+        Some(entry)
+    }
+
+    /// Pull bucket, key and version out of a console route such as
+    /// `buckets/{bucket}/versions/{version_id}/download/{key}`.
+    fn describe_console_route(&mut self, route: &str) {
+        let segments: Vec<&str> = route.split('/').collect();
+        let mut template = vec![segments[0].to_string()];
+        if segments[0] == "buckets" && segments.len() > 1 {
+            self.bucket = Some(decode(segments[1]));
+            template.push("{bucket}".into());
+            let mut rest = &segments[2..];
+            if rest.len() > 2 && rest[0] == "versions" {
+                self.version_id = Some(decode(rest[1]));
+                template.extend(["versions".into(), "{versionId}".into()]);
+                rest = &rest[2..];
+            }
+            if let Some((action, key)) = rest.split_first() {
+                template.push(action.to_string());
+                if !key.is_empty() {
+                    self.key = Some(decode(&key.join("/")));
+                    template.push("{key}".into());
+                }
+            }
+        } else {
+            template.extend(segments[1..].iter().map(|s| s.to_string()));
+        }
+        self.operation = format!("{} /api/{}", self.method, template.join("/"));
+    }
+
+    /// A line in the S3 server access log format.
+    fn access_log_line(&self, owner: &str) -> String {
+        let field =
+            |value: Option<&str>| value.filter(|v| !v.is_empty()).unwrap_or("-").to_string();
+        let quoted = |value: Option<&str>| format!("\"{}\"", field(value).replace('"', "\\\""));
+        let count = |n: u64| {
+            if n == 0 {
+                "-".to_string()
+            } else {
+                n.to_string()
+            }
+        };
+        [
+            owner.to_string(),
+            field(self.bucket.as_deref()),
+            self.time.format("[%d/%b/%Y:%H:%M:%S %z]").to_string(),
+            field(self.source_ip.as_deref()),
+            field(self.access_key.as_deref()),
+            field(self.request_id.as_deref()),
+            self.access.operation.clone(),
+            field(self.access.raw_key.as_deref()),
+            format!(
+                "\"{} {} {}\"",
+                self.method, self.access.request_uri, self.access.http_version
+            ),
+            self.status.to_string(),
+            field(self.error_code.as_deref()),
+            count(self.bytes_out),
+            "-".to_string(),
+            self.duration_ms.to_string(),
+            "-".to_string(),
+            quoted(self.access.referer.as_deref()),
+            quoted(self.user_agent.as_deref()),
+            field(self.version_id.as_deref()),
+            "-".to_string(),
+            if self.access.auth_type.is_some() {
+                "SigV4"
+            } else {
+                "-"
+            }
+            .to_string(),
+            "-".to_string(),
+            field(self.access.auth_type),
+            field(self.access.host.as_deref()),
+            "-".to_string(),
+        ]
+        .join(" ")
+    }
+}
+
+fn decode(value: &str) -> String {
+    percent_encoding::percent_decode_str(value)
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| decode(v))
+}
+
+/// Access log operation, e.g. `REST.PUT.OBJECT` or `REST.GET.VERSIONING`.
+fn rest_operation(method: &Method, has_key: bool, query: &str) -> String {
+    let has = |name: &str| {
+        query
+            .split('&')
+            .any(|p| p == name || p.starts_with(&format!("{}=", name)))
+    };
+    let resource = if has_key {
+        if has("uploads") {
+            "UPLOADS"
+        } else if has("uploadId") {
+            "UPLOAD"
+        } else {
+            "OBJECT"
+        }
+        .to_string()
+    } else {
+        SUBRESOURCES
+            .iter()
+            .find(|name| has(name))
+            .map_or("BUCKET".to_string(), |name| name.to_ascii_uppercase())
+    };
+    format!("REST.{}.{}", method, resource)
+}
+
+/// Completes an audit record once the response body has been sent (or the
+/// client went away), when the byte counts and latency are known.
+struct AuditedBody {
+    inner: Body,
+    sent: u64,
+    pending: Option<PendingEntry>,
+}
+
+struct PendingEntry {
+    entry: AuditEntry,
+    received: Arc<AtomicU64>,
+    started: Instant,
+    audit: Arc<AuditLog>,
+}
+
+impl http_body::Body for AuditedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &polled
+            && let Some(data) = frame.data_ref()
+        {
+            self.sent += data.len() as u64;
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for AuditedBody {
+    fn drop(&mut self) {
+        if let Some(PendingEntry {
+            mut entry,
+            received,
+            started,
+            audit,
+        }) = self.pending.take()
+        {
+            entry.bytes_in = received.load(Ordering::Relaxed);
+            entry.bytes_out = self.sent;
+            entry.duration_ms = started.elapsed().as_millis() as u64;
+            audit.record(entry);
+        }
+    }
+}
+
+/// Record every S3 and console request for the audit log and for bucket
+/// access logging.
+pub async fn audit_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(mut entry) = AuditEntry::from_request(&request) else {
+        return next.run(request).await;
+    };
+    if !state.audit.wants(&entry) {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let received = Arc::new(AtomicU64::new(0));
+    let request = request.map(|body| counting(body, received.clone()));
+    let response = next.run(request).await;
+
+    entry.status = response.status().as_u16();
+    entry.request_id = response
+        .headers()
+        .get("x-amz-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    entry.access_key = response
+        .extensions()
+        .get::<Principal>()
+        .map(|p| p.0.clone());
+    entry.error_code = response
+        .extensions()
+        .get::<ErrorCode>()
+        .map(|c| c.0.to_string());
+
+    let pending = PendingEntry {
+        entry,
+        received,
+        started,
+        audit: state.audit.clone(),
+    };
+    response.map(|body| {
+        Body::new(AuditedBody {
+            inner: body,
+            sent: 0,
+            pending: Some(pending),
+        })
+    })
+}
+
+/// Hands finished request records to a background worker that writes the
+/// audit log file, queues audit webhooks, and collects access log lines for
+/// buckets with logging enabled.
+pub struct AuditLog {
+    sender: mpsc::Sender<AuditEntry>,
+    has_sinks: bool,
+}
+
+impl AuditLog {
+    /// Open the audit log file and the `--audit-webhook ID=URL` targets
+    /// (queueing under `{data_dir}/.audit/{ID}`), and start the worker.
+    pub fn new(config: &Config, storage: Arc<FilesystemStorage>) -> anyhow::Result<Self> {
+        let mut webhooks = Vec::new();
+        let mut ids = std::collections::HashSet::new();
+        for spec in &config.audit_webhooks {
+            let (id, url) = spec
+                .split_once('=')
+                .with_context(|| format!("invalid --audit-webhook '{}', expected ID=URL", spec))?;
+            let (id, url) = (id.trim(), url.trim());
+            if id.is_empty() || id.contains(['/', '\\', ':']) || id.starts_with('.') {
+                anyhow::bail!("invalid audit target ID '{}'", id);
+            }
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("audit target {} must use an http(s) URL", id);
+            }
+            if !ids.insert(id) {
+                anyhow::bail!("duplicate audit target ID '{}'", id);
+            }
+            let dir = Path::new(&config.data_dir).join(".audit").join(id);
+            let queue =
+                DurableQueue::open(&format!("audit target {}", id), dir, Webhook::new(url)?)
+                    .with_context(|| format!("opening queue for audit target {}", id))?;
+            webhooks.push(queue);
+        }
+
+        let file = match &config.audit_log_file {
+            Some(path) => Some(
+                AuditFile::open(PathBuf::from(path), config.audit_log_max_size)
+                    .with_context(|| format!("opening audit log {}", path))?,
+            ),
+            None => None,
+        };
+
+        let has_sinks = file.is_some() || !webhooks.is_empty();
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let worker = Worker {
+            file,
+            webhooks,
+            storage,
+            owner: config.access_key.clone(),
+            flush_interval: Duration::from_secs(config.access_log_flush_interval.max(1)),
+            access_logs: HashMap::new(),
+        };
+        tokio::spawn(worker.run(receiver));
+        Ok(Self { sender, has_sinks })
+    }
+
+    /// Whether the record goes anywhere: to the audit sinks, or possibly to
+    /// the access log of the bucket it touched.
+    fn wants(&self, entry: &AuditEntry) -> bool {
+        self.has_sinks || (entry.api == Interface::S3 && entry.bucket.is_some())
+    }
+
+    fn record(&self, entry: AuditEntry) {
+        if let Err(mpsc::error::TrySendError::Full(_)) = self.sender.try_send(entry) {
+            tracing::warn!("Audit worker is falling behind, dropping a record");
+        }
+    }
+}
+
+/// The audit log file, rotated to `{path}.1` .. `{path}.N` when it grows
+/// past `max_size`.
+struct AuditFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+    size: u64,
+    max_size: u64,
+}
+
+impl AuditFile {
+    fn open(path: PathBuf, max_size: u64) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file: tokio::fs::File::from_std(file),
+            size,
+            max_size,
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    async fn write(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        for n in (1..KEEP_ROTATED).rev() {
+            let _ = tokio::fs::rename(self.rotated(n), self.rotated(n + 1)).await;
+        }
+        tokio::fs::rename(&self.path, self.rotated(1)).await?;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        self.file = file;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+struct Worker {
+    file: Option<AuditFile>,
+    webhooks: Vec<Arc<DurableQueue>>,
+    storage: Arc<FilesystemStorage>,
+    /// Bucket owner in access log lines.
+    owner: String,
+    flush_interval: Duration,
+    /// Access log lines waiting to be written, per (target bucket, prefix).
+    access_logs: HashMap<(String, String), Vec<String>>,
+}
+
+impl Worker {
+    async fn run(mut self, mut receiver: mpsc::Receiver<AuditEntry>) {
+        let mut flush = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.flush_interval,
+            self.flush_interval,
+        );
+        loop {
+            tokio::select! {
+                entry = receiver.recv() => match entry {
+                    Some(entry) => self.handle(entry).await,
+                    None => {
+                        self.flush_access_logs().await;
+                        return;
+                    }
+                },
+                _ = flush.tick() => self.flush_access_logs().await,
+            }
+        }
+    }
+
+    async fn handle(&mut self, entry: AuditEntry) {
+        if self.file.is_some() || !self.webhooks.is_empty() {
+            self.audit(&entry).await;
+        }
+        if entry.api != Interface::S3 {
+            return;
+        }
+        let Some(bucket) = &entry.bucket else { return };
+        // Unknown buckets (e.g. NoSuchBucket responses) have no logging config
+        let Ok(Some(logging)) = self.storage.get_logging(bucket).await else {
+            return;
+        };
+        let target = (logging.target_bucket, logging.target_prefix);
+        let lines = self.access_logs.entry(target.clone()).or_default();
+        lines.push(entry.access_log_line(&self.owner));
+        if lines.len() >= MAX_BUFFERED_LINES
+            && let Some(lines) = self.access_logs.remove(&target)
+        {
+            self.write_access_log(target, lines).await;
+        }
+    }
+
+    async fn audit(&mut self, entry: &AuditEntry) {
+        let value = match serde_json::to_value(entry) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!("Cannot serialize audit record: {}", e);
+                return;
+            }
+        };
+        if let Some(file) = &mut self.file {
+            let mut line = value.to_string();
+            line.push('\n');
+            if let Err(e) = file.write(line.as_bytes()).await {
+                tracing::error!("Cannot write audit log {}: {}", file.path.display(), e);
+            }
+        }
+        for queue in &self.webhooks {
+            if let Err(e) = queue.enqueue(&value).await {
+                tracing::error!("Cannot queue audit record for {}: {}", queue.name(), e);
+            }
+        }
+    }
+
+    async fn flush_access_logs(&mut self) {
+        for (target, lines) in std::mem::take(&mut self.access_logs) {
+            self.write_access_log(target, lines).await;
+        }
+    }
+
+    /// Store buffered lines as one log object named like S3 does:
+    /// `{prefix}YYYY-mm-DD-HH-MM-SS-{unique}`.
+    async fn write_access_log(&self, (bucket, prefix): (String, String), lines: Vec<String>) {
+        let key = format!(
+            "{}{}-{}",
+            prefix,
+            Utc::now().format("%Y-%m-%d-%H-%M-%S"),
+            &uuid::Uuid::new_v4().simple().to_string()[..16].to_ascii_uppercase()
+        );
+        let mut body = lines.join("\n");
+        body.push('\n');
+        let reader = std::io::Cursor::new(body.into_bytes());
+        if let Err(e) = self
+            .storage
+            .put_object(&bucket, &key, "text/plain", Box::pin(reader), None)
+            .await
+        {
+            tracing::warn!("Cannot write access log {}/{}: {}", bucket, key, e);
+        }
+    }
+}
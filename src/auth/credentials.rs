@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::config::Config;
+
+use super::policy::Policy;
+use super::signature_v4::constant_time_eq;
+use super::sts::{self, TokenError};
+
+/// Key pair a request was signed with, as resolved from its access key.
+pub struct Signer {
+    pub secret_key: String,
+    pub policy: Policy,
+    /// Issued by STS rather than configured.
+    pub temporary: bool,
+}
+
+pub enum CredentialError {
+    UnknownAccessKey,
+    InvalidToken,
+    ExpiredToken,
+}
+
+/// Key pairs the server accepts: the root pair and any users from `--user`.
+pub struct Credentials {
+    root_access_key: String,
+    secrets: HashMap<String, String>,
+    /// Keys replication peers sign with (`--replication-access-key`).
+    replicators: HashSet<String>,
+}
+
+impl Credentials {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut secrets = HashMap::new();
+        secrets.insert(config.access_key.clone(), config.secret_key.clone());
+        for spec in &config.users {
+            // Never echo the entry itself: it contains a secret
+            let Some((access_key, secret_key)) = spec.split_once('=') else {
+                anyhow::bail!("invalid --user entry, expected ACCESS_KEY=SECRET_KEY");
+            };
+            let (access_key, secret_key) = (access_key.trim(), secret_key.trim());
+            if access_key.len() < 3
+                || access_key.len() > 128
+                || !access_key.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@+".contains(c))
+            {
+                anyhow::bail!("invalid access key '{}' in --user", access_key);
+            }
+            if secret_key.len() < 8 {
+                anyhow::bail!("secret key of user '{}' must be at least 8 characters", access_key);
+            }
+            if secrets.insert(access_key.to_string(), secret_key.to_string()).is_some() {
+                anyhow::bail!("duplicate access key '{}' in --user", access_key);
+            }
+        }
+        let mut replicators = HashSet::new();
+        for access_key in &config.replication_access_keys {
+            let access_key = access_key.trim();
+            if !secrets.contains_key(access_key) {
+                anyhow::bail!("unknown access key '{}' in --replication-access-key", access_key);
+            }
+            replicators.insert(access_key.to_string());
+        }
+        Ok(Self {
+            root_access_key: config.access_key.clone(),
+            secrets,
+            replicators,
+        })
+    }
+
+    /// Secret key for signature checks, if the access key is known.
+    pub fn secret_for(&self, access_key: &str) -> Option<&str> {
+        self.secrets.get(access_key).map(String::as_str)
+    }
+
+    pub fn root_access_key(&self) -> &str {
+        &self.root_access_key
+    }
+
+    pub fn is_root(&self, access_key: &str) -> bool {
+        access_key == self.root_access_key
+    }
+
+    /// May write replicas on behalf of a replication peer.
+    pub fn is_replicator(&self, access_key: &str) -> bool {
+        self.replicators.contains(access_key)
+    }
+
+    /// Resolve the signing key of a request. Temporary credentials must come
+    /// with their session token (`x-amz-security-token`).
+    pub fn resolve(&self, access_key: &str, session_token: Option<&str>) -> Result<Signer, CredentialError> {
+        let Some(token) = session_token else {
+            return self
+                .secret_for(access_key)
+                .map(|secret_key| Signer {
+                    secret_key: secret_key.to_string(),
+                    policy: Policy::ReadWrite,
+                    temporary: false,
+                })
+                .ok_or(CredentialError::UnknownAccessKey);
+        };
+        let root_secret = &self.secrets[&self.root_access_key];
+        let claims = sts::verify(root_secret, access_key, token).map_err(|e| match e {
+            TokenError::Invalid => CredentialError::InvalidToken,
+            TokenError::Expired => CredentialError::ExpiredToken,
+        })?;
+        // Removing a user revokes the credentials they assumed
+        if let Some(ref parent) = claims.parent
+            && !self.secrets.contains_key(parent)
+        {
+            return Err(CredentialError::InvalidToken);
+        }
+        Ok(Signer {
+            secret_key: sts::secret_key(root_secret, access_key),
+            policy: claims.policy,
+            temporary: true,
+        })
+    }
+
+    /// Issue temporary credentials (STS), sealed with the root secret.
+    pub fn issue_temporary(
+        &self,
+        subject: &str,
+        parent: Option<&str>,
+        policy: Policy,
+        duration_secs: i64,
+    ) -> sts::TemporaryCredentials {
+        sts::issue(&self.secrets[&self.root_access_key], subject, parent, policy, duration_secs)
+    }
+
+    /// Check a key pair as typed into a login form.
+    pub fn verify(&self, access_key: &str, secret_key: &str) -> bool {
+        match self.secret_for(access_key) {
+            Some(expected) => constant_time_eq(secret_key.as_bytes(), expected.as_bytes()),
+            None => false,
+        }
+    }
+}
@@ -4,15 +4,31 @@ use axum::{
     response::Response,
 };
 use chrono::{NaiveDateTime, Utc};
+use http::HeaderMap;
 
+use crate::api::virtual_host::OriginalPath;
+use crate::audit::Principal;
 use crate::error::S3Error;
+use crate::replication;
 use crate::server::AppState;
 
+use super::credentials::{CredentialError, Signer};
+use super::policy::Policy;
 use super::signature_v4;
 
+/// Who signed a request, once the signature checks out.
+pub struct Authenticated {
+    pub access_key: String,
+    pub policy: Policy,
+    /// Signed with temporary (STS) credentials.
+    pub temporary: bool,
+    /// Headers covered by the signature.
+    pub signed_headers: Vec<String>,
+}
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, S3Error> {
     let method = request.method().as_str().to_string();
@@ -27,7 +43,41 @@ pub async fn auth_middleware(
         return handle_presigned(&state, &method, &query, request, next).await;
     }
 
-    let auth_header = match request.headers().get("authorization") {
+    // Browser form uploads carry their credentials in a signed POST policy,
+    // which the handler verifies once the form fields have been read.
+    if is_browser_post(&request) {
+        tracing::debug!("Browser POST upload, deferring auth to POST policy");
+        return Ok(next.run(request).await);
+    }
+
+    // STS calls are form posts to the service root; AssumeRoleWithWebIdentity
+    // is unsigned, so the handler authenticates each action itself.
+    if is_sts_request(&request) {
+        return Ok(next.run(request).await);
+    }
+
+    let path = signing_path(&request);
+    let auth = verify_request(&state, &method, &path, &query, request.headers(), "s3")?;
+    check_policy(auth.policy, &method)?;
+    check_replica_headers(&state, &auth, request.headers())?;
+
+    request.extensions_mut().insert(Principal(auth.access_key.clone()));
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(Principal(auth.access_key));
+    tracing::debug!("{} {} -> {}", method, uri, response.status());
+    Ok(response)
+}
+
+/// Verify the Authorization header signature of a request to `service`.
+pub fn verify_request(
+    state: &AppState,
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &HeaderMap,
+    service: &str,
+) -> Result<Authenticated, S3Error> {
+    let auth_header = match headers.get("authorization") {
         Some(h) => h
             .to_str()
             .map_err(|_| S3Error::access_denied("Invalid Authorization header"))?,
@@ -50,15 +100,13 @@ pub async fn auth_middleware(
         parsed.signed_headers
     );
 
-    if parsed.access_key != state.config.access_key {
-        tracing::debug!(
-            "Access key mismatch: got '{}', expected '{}'",
-            parsed.access_key,
-            state.config.access_key
-        );
-        return Err(S3Error::invalid_access_key());
+    if parsed.service != service {
+        return Err(S3Error::access_denied("Invalid service in credential scope"));
     }
 
+    let session_token = headers.get("x-amz-security-token").and_then(|v| v.to_str().ok());
+    let signer = resolve_signer(state, &parsed.access_key, session_token)?;
+
     if parsed.region != state.config.region {
         tracing::debug!(
             "Region mismatch: got '{}', expected '{}'",
@@ -69,8 +117,7 @@ pub async fn auth_middleware(
     }
 
     // Validate request timestamp is within ±15 minutes (AWS SigV4 spec)
-    let amz_date = request
-        .headers()
+    let amz_date = headers
         .get("x-amz-date")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
@@ -90,13 +137,10 @@ pub async fn auth_middleware(
         return Err(S3Error::access_denied("Invalid or missing X-Amz-Date header"));
     }
 
-    let path = request.uri().path().to_string();
-
     tracing::debug!("Verifying signature for {} {} ?{}", method, path, query);
 
     for h in &parsed.signed_headers {
-        let val = request
-            .headers()
+        let val = headers
             .get(h.as_str())
             .and_then(|v| v.to_str().ok())
             .unwrap_or("<missing>");
@@ -104,12 +148,12 @@ pub async fn auth_middleware(
     }
 
     let valid = signature_v4::verify_signature(
-        &method,
-        &path,
-        &query,
-        request.headers(),
+        method,
+        path,
+        query,
+        headers,
         &parsed,
-        &state.config.secret_key,
+        &signer.secret_key,
     );
 
     if !valid {
@@ -118,27 +162,126 @@ pub async fn auth_middleware(
     }
 
     tracing::debug!("Signature verification OK");
-    let response = next.run(request).await;
-    tracing::debug!("{} {} -> {}", method, uri, response.status());
-    Ok(response)
+    Ok(Authenticated {
+        access_key: parsed.access_key,
+        policy: signer.policy,
+        temporary: signer.temporary,
+        signed_headers: parsed.signed_headers,
+    })
+}
+
+/// Secret key and policy for an access key, validating the session token
+/// that temporary credentials carry.
+pub(crate) fn resolve_signer(
+    state: &AppState,
+    access_key: &str,
+    session_token: Option<&str>,
+) -> Result<Signer, S3Error> {
+    state
+        .credentials
+        .resolve(access_key, session_token)
+        .map_err(|e| match e {
+            CredentialError::UnknownAccessKey => {
+                tracing::debug!("Unknown access key '{}'", access_key);
+                S3Error::invalid_access_key()
+            }
+            CredentialError::InvalidToken => S3Error::invalid_token(),
+            CredentialError::ExpiredToken => S3Error::expired_token(),
+        })
+}
+
+/// Read-only credentials may only send GET and HEAD requests.
+pub(crate) fn check_policy(policy: Policy, method: &str) -> Result<(), S3Error> {
+    if policy.allows_write() || method == "GET" || method == "HEAD" {
+        Ok(())
+    } else {
+        Err(S3Error::access_denied("Access Denied."))
+    }
+}
+
+/// Replica headers make a write keep the source's version ID, mtime and ETag
+/// and skip replication, so only a replication peer may send them: signed with
+/// a `--replication-access-key` and covered by the signature.
+fn check_replica_headers(state: &AppState, auth: &Authenticated, headers: &HeaderMap) -> Result<(), S3Error> {
+    if !replication::is_replica_request(headers) {
+        return Ok(());
+    }
+    let peer = !auth.temporary && state.credentials.is_replicator(&auth.access_key);
+    let signed = replication::REPLICA_HEADERS
+        .iter()
+        .filter(|h| headers.contains_key(**h))
+        .all(|h| auth.signed_headers.iter().any(|s| s == h));
+    if peer && signed {
+        Ok(())
+    } else {
+        tracing::debug!("Replica headers from non-replication key '{}'", auth.access_key);
+        Err(S3Error::access_denied("Access Denied."))
+    }
+}
+
+/// The path the client signed: the original request path for virtual-hosted
+/// requests, whose bucket was moved from the Host header into the URI.
+fn signing_path(request: &Request) -> String {
+    match request.extensions().get::<OriginalPath>() {
+        Some(OriginalPath(path)) => path.clone(),
+        None => request.uri().path().to_string(),
+    }
+}
+
+/// POST / with a form-encoded body.
+fn is_sts_request(request: &Request) -> bool {
+    request.method() == http::Method::POST
+        && request.uri().path() == "/"
+        && request
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded"))
+            .unwrap_or(false)
+}
+
+/// POST /{bucket} with a multipart/form-data body and no Authorization header.
+fn is_browser_post(request: &Request) -> bool {
+    if request.method() != http::Method::POST || request.headers().contains_key("authorization") {
+        return false;
+    }
+    let is_form = request
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase().starts_with("multipart/form-data"))
+        .unwrap_or(false);
+    let path = request.uri().path().trim_matches('/');
+    is_form && !path.is_empty() && !path.contains('/')
 }
 
 async fn handle_presigned(
     state: &AppState,
     method: &str,
     query: &str,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, S3Error> {
     tracing::debug!("Presigned URL detected");
 
+    // Presigned URLs never sign headers, so they cannot carry replica writes
+    if replication::is_replica_request(request.headers()) {
+        return Err(S3Error::access_denied("Access Denied."));
+    }
+
     let (parsed, timestamp, expires_secs) = signature_v4::parse_presigned_query(query)
         .map_err(|e| S3Error::access_denied(e))?;
 
-    if parsed.access_key != state.config.access_key {
-        return Err(S3Error::invalid_access_key());
+    if parsed.service != "s3" {
+        return Err(S3Error::access_denied("Invalid service in credential scope"));
     }
 
+    let session_token = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("X-Amz-Security-Token="))
+        .map(|v| percent_encoding::percent_decode_str(v).decode_utf8_lossy().into_owned());
+    let signer = resolve_signer(state, &parsed.access_key, session_token.as_deref())?;
+
     if parsed.region != state.config.region {
         return Err(S3Error::access_denied("Invalid region in credential scope"));
     }
@@ -155,7 +298,7 @@ async fn handle_presigned(
         return Err(S3Error::expired_presigned_url());
     }
 
-    let path = request.uri().path().to_string();
+    let path = signing_path(&request);
 
     tracing::debug!("Verifying presigned signature for {} {} ?{}", method, path, query);
 
@@ -166,15 +309,18 @@ async fn handle_presigned(
         request.headers(),
         &parsed,
         &timestamp,
-        &state.config.secret_key,
+        &signer.secret_key,
     );
 
     if !valid {
         tracing::debug!("Presigned signature verification FAILED");
         return Err(S3Error::signature_mismatch());
     }
+    check_policy(signer.policy, method)?;
 
     tracing::debug!("Presigned signature verification OK");
-    let response = next.run(request).await;
+    request.extensions_mut().insert(Principal(parsed.access_key.clone()));
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(Principal(parsed.access_key));
     Ok(response)
 }
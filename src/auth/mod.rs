@@ -1,2 +1,8 @@
+pub mod credentials;
 pub mod middleware;
+pub mod oidc;
+pub mod policy;
+pub mod post_policy;
+pub mod session;
 pub mod signature_v4;
+pub mod sts;
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+
+use super::policy::Policy;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time a user has to finish signing in at the identity provider.
+const PENDING_LOGIN_TTL_SECS: i64 = 600;
+/// Tolerated clock difference with the identity provider.
+const CLOCK_SKEW_SECS: i64 = 60;
+
+const B64URL: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Endpoints from the provider's discovery document.
+#[derive(Debug, Clone, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    // RSA
+    n: Option<String>,
+    e: Option<String>,
+    // EC
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+/// Discovery document and signing keys, fetched on first use.
+struct Provider {
+    discovery: Discovery,
+    keys: Vec<Jwk>,
+}
+
+/// What is remembered between redirecting to the provider and its callback.
+struct PendingLogin {
+    nonce: String,
+    code_verifier: String,
+    redirect_uri: String,
+    created_at: DateTime<Utc>,
+}
+
+/// A verified sign-in.
+pub struct OidcIdentity {
+    pub username: String,
+    pub policy: Policy,
+}
+
+/// OpenID Connect authorization-code login (with PKCE) for the console.
+pub struct Oidc {
+    config_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_url: Option<String>,
+    scopes: String,
+    display_name: String,
+    username_claim: String,
+    policy_claim: String,
+    groups_claim: String,
+    group_policies: HashMap<String, Policy>,
+    client: reqwest::Client,
+    provider: tokio::sync::RwLock<Option<Provider>>,
+    /// Logins in progress by `state` parameter.
+    pending: Mutex<HashMap<String, PendingLogin>>,
+}
+
+impl Oidc {
+    /// `None` unless `--oidc-config-url` is set. The provider is contacted on
+    /// the first login, so the server starts even while it is unreachable.
+    pub fn new(config: &Config) -> anyhow::Result<Option<Self>> {
+        let Some(config_url) = config.oidc_config_url.clone() else {
+            return Ok(None);
+        };
+        let Some(client_id) = config.oidc_client_id.clone() else {
+            bail!("--oidc-client-id is required with --oidc-config-url");
+        };
+        let mut group_policies = HashMap::new();
+        for spec in &config.oidc_group_policies {
+            let Some((group, policy)) = spec.split_once('=') else {
+                bail!("invalid --oidc-group-policy entry '{}', expected GROUP=POLICY", spec);
+            };
+            let policy = Policy::parse(policy)
+                .with_context(|| format!("unknown policy '{}' in --oidc-group-policy", policy.trim()))?;
+            group_policies.insert(group.trim().to_string(), policy);
+        }
+        let mut scopes = config.oidc_scopes.clone();
+        if !scopes.iter().any(|s| s == "openid") {
+            scopes.insert(0, "openid".to_string());
+        }
+        Ok(Some(Self {
+            config_url,
+            client_id,
+            client_secret: config.oidc_client_secret.clone(),
+            redirect_url: config.oidc_redirect_url.clone(),
+            scopes: scopes.join(" "),
+            display_name: config.oidc_display_name.clone(),
+            username_claim: config.oidc_username_claim.clone(),
+            policy_claim: config.oidc_policy_claim.clone(),
+            groups_claim: config.oidc_groups_claim.clone(),
+            group_policies,
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+            provider: tokio::sync::RwLock::new(None),
+            pending: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Label of the sign-in button.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    /// Start a login and return the provider URL to send the browser to.
+    /// `default_redirect_uri` is used unless `--oidc-redirect-url` is set.
+    pub async fn authorization_url(&self, default_redirect_uri: &str) -> anyhow::Result<String> {
+        let discovery = self.discovery().await?;
+        let state = random_token();
+        let nonce = random_token();
+        let code_verifier = random_token();
+        let code_challenge = B64URL.encode(Sha256::digest(code_verifier.as_bytes()));
+        let redirect_uri = self
+            .redirect_url
+            .clone()
+            .unwrap_or_else(|| default_redirect_uri.to_string());
+
+        let mut url = reqwest::Url::parse(&discovery.authorization_endpoint)
+            .context("invalid authorization_endpoint")?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("scope", &self.scopes)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        let now = Utc::now();
+        let mut pending = self.pending.lock().unwrap();
+        prune_pending(&mut pending, now);
+        pending.insert(
+            state,
+            PendingLogin {
+                nonce,
+                code_verifier,
+                redirect_uri,
+                created_at: now,
+            },
+        );
+        Ok(url.to_string())
+    }
+
+    /// Finish a login from the callback's `code` and `state`: redeem the code,
+    /// verify the ID token and map its claims to a policy.
+    pub async fn complete(&self, code: &str, state: &str) -> anyhow::Result<OidcIdentity> {
+        let pending = {
+            let mut pending = self.pending.lock().unwrap();
+            prune_pending(&mut pending, Utc::now());
+            pending.remove(state)
+        };
+        let Some(pending) = pending else {
+            bail!("Sign-in attempt expired, please try again");
+        };
+        let discovery = self.discovery().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", pending.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+        if let Some(ref secret) = self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let resp = self
+            .client
+            .post(&discovery.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .context("token request failed")?;
+        if !resp.status().is_success() {
+            bail!("Identity provider rejected the login (token endpoint returned {})", resp.status());
+        }
+        let tokens: TokenResponse = resp.json().await.context("invalid token response")?;
+
+        let claims = self.verify_id_token(&tokens.id_token, &discovery).await?;
+        if claims.get("nonce").and_then(Value::as_str) != Some(pending.nonce.as_str()) {
+            bail!("ID token nonce mismatch");
+        }
+        self.identity(&claims)
+    }
+
+    /// Verify a token presented to AssumeRoleWithWebIdentity, returning its
+    /// claims. It must be an ID token from the configured provider, issued for
+    /// our client ID.
+    pub async fn verify_web_identity(&self, token: &str) -> anyhow::Result<Value> {
+        let discovery = self.discovery().await?;
+        self.verify_id_token(token, &discovery).await
+    }
+
+    async fn discovery(&self) -> anyhow::Result<Discovery> {
+        if let Some(ref provider) = *self.provider.read().await {
+            return Ok(provider.discovery.clone());
+        }
+        let mut provider = self.provider.write().await;
+        if let Some(ref provider) = *provider {
+            return Ok(provider.discovery.clone());
+        }
+        let discovery: Discovery = self.fetch_json(&self.config_url).await?;
+        let keys = self.fetch_keys(&discovery).await?;
+        *provider = Some(Provider {
+            discovery: discovery.clone(),
+            keys,
+        });
+        Ok(discovery)
+    }
+
+    async fn fetch_keys(&self, discovery: &Discovery) -> anyhow::Result<Vec<Jwk>> {
+        let set: JwkSet = self.fetch_json(&discovery.jwks_uri).await?;
+        Ok(set.keys)
+    }
+
+    async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> anyhow::Result<T> {
+        let resp = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("cannot reach identity provider at {}", url))?;
+        if !resp.status().is_success() {
+            bail!("identity provider returned {} for {}", resp.status(), url);
+        }
+        resp.json().await.with_context(|| format!("invalid JSON from {}", url))
+    }
+
+    /// Check the ID token's signature and standard claims, returning its claims.
+    async fn verify_id_token(&self, token: &str, discovery: &Discovery) -> anyhow::Result<Value> {
+        let mut parts = token.split('.');
+        let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            bail!("malformed ID token");
+        };
+        let header: JwtHeader =
+            serde_json::from_slice(&B64URL.decode(header_b64)?).context("malformed ID token header")?;
+        if !matches!(header.alg.as_str(), "RS256" | "ES256") {
+            bail!("unsupported ID token algorithm {}", header.alg);
+        }
+        let signature = B64URL.decode(signature_b64).context("malformed ID token signature")?;
+        let message = format!("{}.{}", header_b64, payload_b64);
+
+        // An unknown key ID usually means the provider rotated its keys
+        let mut key = self.find_key(&header).await;
+        if key.is_none() {
+            let keys = self.fetch_keys(discovery).await?;
+            if let Some(ref mut provider) = *self.provider.write().await {
+                provider.keys = keys;
+            }
+            key = self.find_key(&header).await;
+        }
+        let Some(key) = key else {
+            bail!("ID token signed with an unknown key");
+        };
+        verify_signature(&key, &header.alg, message.as_bytes(), &signature)?;
+
+        let claims: Value =
+            serde_json::from_slice(&B64URL.decode(payload_b64)?).context("malformed ID token payload")?;
+        if claims.get("iss").and_then(Value::as_str) != Some(discovery.issuer.as_str()) {
+            bail!("ID token issuer mismatch");
+        }
+        let audience_ok = match claims.get("aud") {
+            Some(Value::String(aud)) => *aud == self.client_id,
+            Some(Value::Array(auds)) => auds.iter().any(|a| a.as_str() == Some(self.client_id.as_str())),
+            _ => false,
+        };
+        if !audience_ok {
+            bail!("ID token was issued for another client");
+        }
+        let now = Utc::now().timestamp();
+        match claims.get("exp").and_then(Value::as_i64) {
+            Some(exp) if exp + CLOCK_SKEW_SECS > now => {}
+            _ => bail!("ID token expired"),
+        }
+        Ok(claims)
+    }
+
+    async fn find_key(&self, header: &JwtHeader) -> Option<Jwk> {
+        let kty = match header.alg.as_str() {
+            "RS256" => "RSA",
+            "ES256" => "EC",
+            _ => return None,
+        };
+        let provider = self.provider.read().await;
+        provider
+            .as_ref()?
+            .keys
+            .iter()
+            .find(|k| k.kty == kty && (header.kid.is_none() || k.kid == header.kid))
+            .cloned()
+    }
+
+    /// Username and most permissive policy granted by the policy claim or by
+    /// any group mapped with `--oidc-group-policy`.
+    pub fn identity(&self, claims: &Value) -> anyhow::Result<OidcIdentity> {
+        let username = claims
+            .get(&self.username_claim)
+            .or_else(|| claims.get("sub"))
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .context("ID token has no subject")?
+            .to_string();
+
+        let mut policy = None;
+        for name in claim_values(claims.get(&self.policy_claim)) {
+            match Policy::parse(&name) {
+                Some(p) => policy = policy.max(Some(p)),
+                None => tracing::warn!("Ignoring unknown policy '{}' for OIDC user {}", name, username),
+            }
+        }
+        for group in claim_values(claims.get(&self.groups_claim)) {
+            policy = policy.max(self.group_policies.get(&group).copied());
+        }
+        let Some(policy) = policy else {
+            bail!("No MaxIO policy is assigned to {}", username);
+        };
+        Ok(OidcIdentity { username, policy })
+    }
+}
+
+/// A claim holding one name, a comma-separated list or an array of names.
+fn claim_values(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => s
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn verify_signature(key: &Jwk, alg: &str, message: &[u8], signature: &[u8]) -> anyhow::Result<()> {
+    use ring::signature::{self, UnparsedPublicKey};
+
+    let field = |v: &Option<String>| -> anyhow::Result<Vec<u8>> {
+        let v = v.as_deref().context("incomplete signing key")?;
+        Ok(B64URL.decode(v)?)
+    };
+    let valid = match alg {
+        "RS256" => {
+            let components = signature::RsaPublicKeyComponents {
+                n: field(&key.n)?,
+                e: field(&key.e)?,
+            };
+            components
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok()
+        }
+        "ES256" => {
+            if key.crv.as_deref() != Some("P-256") {
+                bail!("unsupported EC curve in signing key");
+            }
+            let mut point = vec![0x04];
+            point.extend(field(&key.x)?);
+            point.extend(field(&key.y)?);
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                .verify(message, signature)
+                .is_ok()
+        }
+        other => bail!("unsupported ID token algorithm {}", other),
+    };
+    if !valid {
+        bail!("invalid ID token signature");
+    }
+    Ok(())
+}
+
+fn prune_pending(pending: &mut HashMap<String, PendingLogin>, now: DateTime<Utc>) {
+    pending.retain(|_, p| (now - p.created_at).num_seconds() < PENDING_LOGIN_TTL_SECS);
+}
+
+fn random_token() -> String {
+    B64URL.encode(rand::random::<[u8; 32]>())
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Built-in access policies for console sessions, named after MinIO's canned
+/// policies so IdP claims written for MinIO keep working.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Browse and download only.
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+impl Policy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "readonly" => Some(Policy::ReadOnly),
+            "readwrite" | "consoleAdmin" => Some(Policy::ReadWrite),
+            _ => None,
+        }
+    }
+
+    pub fn allows_write(self) -> bool {
+        self == Policy::ReadWrite
+    }
+}
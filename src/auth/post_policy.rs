@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::signature_v4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A single condition from a POST policy document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// `{"field": "value"}` or `["eq", "$field", "value"]`
+    Eq(String, String),
+    /// `["starts-with", "$field", "prefix"]`
+    StartsWith(String, String),
+    /// `["content-length-range", min, max]`
+    ContentLengthRange(u64, u64),
+}
+
+/// Decoded POST policy document (the base64 `policy` form field).
+#[derive(Debug, Clone)]
+pub struct PostPolicy {
+    pub expiration: DateTime<Utc>,
+    pub conditions: Vec<Condition>,
+}
+
+/// Form fields that never need a matching policy condition.
+const EXEMPT_FIELDS: &[&str] = &["policy", "x-amz-signature", "file"];
+
+impl PostPolicy {
+    /// Parse a base64-encoded policy document.
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let raw = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| "policy is not valid base64".to_string())?;
+        let doc: serde_json::Value =
+            serde_json::from_slice(&raw).map_err(|_| "policy is not valid JSON".to_string())?;
+
+        let expiration = doc
+            .get("expiration")
+            .and_then(|v| v.as_str())
+            .ok_or("policy is missing expiration")?;
+        let expiration = DateTime::parse_from_rfc3339(expiration)
+            .map_err(|_| "policy expiration is not a valid ISO 8601 date".to_string())?
+            .with_timezone(&Utc);
+
+        let mut conditions = Vec::new();
+        let list = doc
+            .get("conditions")
+            .and_then(|v| v.as_array())
+            .ok_or("policy is missing conditions")?;
+        for cond in list {
+            conditions.push(parse_condition(cond)?);
+        }
+        Ok(Self { expiration, conditions })
+    }
+
+    /// Allowed (min, max) size of the uploaded file, if the policy restricts it.
+    pub fn content_length_range(&self) -> Option<(u64, u64)> {
+        self.conditions.iter().find_map(|c| match c {
+            Condition::ContentLengthRange(min, max) => Some((*min, *max)),
+            _ => None,
+        })
+    }
+
+    /// Check the submitted form fields (lowercased names) against the policy.
+    /// Every field other than the signature, policy and file must be covered by a
+    /// condition, and every condition must be satisfied.
+    pub fn check_fields(&self, fields: &HashMap<String, String>) -> Result<(), String> {
+        if Utc::now() > self.expiration {
+            return Err("Invalid according to Policy: Policy expired.".into());
+        }
+
+        for cond in &self.conditions {
+            match cond {
+                Condition::Eq(name, expected) => {
+                    let actual = fields.get(name).map(String::as_str).unwrap_or("");
+                    if actual != expected {
+                        return Err(format!(
+                            "Invalid according to Policy: Policy Condition failed: [\"eq\", \"${}\", \"{}\"]",
+                            name, expected
+                        ));
+                    }
+                }
+                Condition::StartsWith(name, prefix) => {
+                    let actual = fields.get(name).map(String::as_str).unwrap_or("");
+                    // Content-Type may list several comma-separated values
+                    let ok = if name == "content-type" {
+                        actual.split(',').all(|v| v.trim().starts_with(prefix.as_str()))
+                    } else {
+                        actual.starts_with(prefix.as_str())
+                    };
+                    if !ok {
+                        return Err(format!(
+                            "Invalid according to Policy: Policy Condition failed: [\"starts-with\", \"${}\", \"{}\"]",
+                            name, prefix
+                        ));
+                    }
+                }
+                Condition::ContentLengthRange(..) => {}
+            }
+        }
+
+        for name in fields.keys() {
+            if EXEMPT_FIELDS.contains(&name.as_str()) || name.starts_with("x-ignore-") {
+                continue;
+            }
+            let covered = self.conditions.iter().any(|c| match c {
+                Condition::Eq(n, _) | Condition::StartsWith(n, _) => n == name,
+                Condition::ContentLengthRange(..) => false,
+            });
+            if !covered {
+                return Err(format!(
+                    "Invalid according to Policy: Extra input fields: {}",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_condition(cond: &serde_json::Value) -> Result<Condition, String> {
+    let field_name = |v: &serde_json::Value| -> Result<String, String> {
+        let name = v.as_str().ok_or("condition field must be a string")?;
+        Ok(name.trim_start_matches('$').to_ascii_lowercase())
+    };
+    let as_u64 = |v: &serde_json::Value| -> Result<u64, String> {
+        v.as_u64()
+            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+            .ok_or_else(|| "content-length-range bounds must be integers".to_string())
+    };
+
+    if let Some(obj) = cond.as_object() {
+        let (name, value) = obj
+            .iter()
+            .next()
+            .filter(|_| obj.len() == 1)
+            .ok_or("object conditions must have exactly one entry")?;
+        let value = value.as_str().ok_or("condition value must be a string")?;
+        return Ok(Condition::Eq(name.to_ascii_lowercase(), value.to_string()));
+    }
+
+    let arr = cond.as_array().ok_or("condition must be an object or array")?;
+    if arr.len() != 3 {
+        return Err("array conditions must have three elements".into());
+    }
+    let op = arr[0].as_str().ok_or("condition operator must be a string")?;
+    match op.to_ascii_lowercase().as_str() {
+        "eq" => Ok(Condition::Eq(
+            field_name(&arr[1])?,
+            arr[2].as_str().ok_or("condition value must be a string")?.to_string(),
+        )),
+        "starts-with" => Ok(Condition::StartsWith(
+            field_name(&arr[1])?,
+            arr[2].as_str().ok_or("condition value must be a string")?.to_string(),
+        )),
+        "content-length-range" => Ok(Condition::ContentLengthRange(as_u64(&arr[1])?, as_u64(&arr[2])?)),
+        other => Err(format!("unknown condition operator: {}", other)),
+    }
+}
+
+/// SigV4 signature of a POST policy: HMAC of the base64 policy with the signing key.
+pub fn sign_policy(policy_b64: &str, secret_key: &str, date: &str, region: &str) -> String {
+    let signing_key = signature_v4::derive_signing_key(secret_key, date, region);
+    let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
+    mac.update(policy_b64.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Verify the `x-amz-signature` form field against the policy.
+pub fn verify_policy_signature(
+    policy_b64: &str,
+    signature: &str,
+    secret_key: &str,
+    date: &str,
+    region: &str,
+) -> bool {
+    let expected = sign_policy(policy_b64, secret_key, date, region);
+    signature_v4::constant_time_eq(expected.as_bytes(), signature.as_bytes())
+}
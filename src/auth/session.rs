@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+
+use super::policy::Policy;
+
+/// Sessions end this long after login, however active they are.
+pub const SESSION_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60; // 7 days
+
+/// A console login. The cookie carries a random token; only its SHA-256 is
+/// kept, so the sessions file cannot be used to hijack sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Public identifier, used to list and revoke sessions.
+    pub id: String,
+    pub access_key: String,
+    #[serde(default)]
+    pub policy: Policy,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub source_ip: String,
+    pub user_agent: String,
+}
+
+/// Server-side console sessions, persisted to `{data_dir}/.console/sessions.json`
+/// so logins survive restarts and revocations stick.
+pub struct SessionStore {
+    path: PathBuf,
+    idle_timeout: Duration,
+    /// Sessions by token hash.
+    sessions: Mutex<HashMap<String, Session>>,
+    /// Serializes writes of the sessions file.
+    persist_lock: tokio::sync::Mutex<()>,
+}
+
+impl SessionStore {
+    pub fn open(config: &Config) -> anyhow::Result<Self> {
+        let path = Path::new(&config.data_dir).join(".console").join("sessions.json");
+        let mut sessions: HashMap<String, Session> = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        // Activity is not persisted; restart the idle clock of surviving sessions
+        let now = Utc::now();
+        sessions.retain(|_, s| s.expires_at > now);
+        for session in sessions.values_mut() {
+            session.last_seen = now;
+        }
+        Ok(Self {
+            path,
+            idle_timeout: Duration::seconds(config.console_session_idle_timeout as i64),
+            sessions: Mutex::new(sessions),
+            persist_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Start a session and return the cookie token for it.
+    pub async fn create(
+        &self,
+        access_key: &str,
+        policy: Policy,
+        source_ip: &str,
+        user_agent: &str,
+    ) -> (String, Session) {
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let now = Utc::now();
+        let session = Session {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            access_key: access_key.to_string(),
+            policy,
+            created_at: now,
+            last_seen: now,
+            expires_at: now + Duration::seconds(SESSION_MAX_AGE_SECS),
+            source_ip: source_ip.to_string(),
+            user_agent: user_agent.to_string(),
+        };
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            self.prune(&mut sessions, now);
+            sessions.insert(hash_token(&token), session.clone());
+        }
+        self.persist().await;
+        (token, session)
+    }
+
+    /// The live session for a cookie token, marking it as active.
+    pub fn authenticate(&self, token: &str) -> Option<Session> {
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        let hash = hash_token(token);
+        let session = sessions.get_mut(&hash)?;
+        if !self.is_live(session, now) {
+            sessions.remove(&hash);
+            return None;
+        }
+        session.last_seen = now;
+        Some(session.clone())
+    }
+
+    /// Active sessions of a user, most recent login first.
+    pub fn list(&self, access_key: &str) -> Vec<Session> {
+        let now = Utc::now();
+        let mut list: Vec<Session> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| s.access_key == access_key && self.is_live(s, now))
+            .cloned()
+            .collect();
+        list.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+        list
+    }
+
+    /// End the session a cookie token belongs to (logout).
+    pub async fn revoke_token(&self, token: &str) {
+        let removed = self.sessions.lock().unwrap().remove(&hash_token(token)).is_some();
+        if removed {
+            self.persist().await;
+        }
+    }
+
+    /// End one of a user's sessions by its public ID.
+    pub async fn revoke(&self, access_key: &str, id: &str) -> bool {
+        let removed = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let before = sessions.len();
+            sessions.retain(|_, s| !(s.access_key == access_key && s.id == id));
+            sessions.len() != before
+        };
+        if removed {
+            self.persist().await;
+        }
+        removed
+    }
+
+    /// End every session of a user. Returns how many there were.
+    pub async fn revoke_all(&self, access_key: &str) -> usize {
+        let removed = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let before = sessions.len();
+            sessions.retain(|_, s| s.access_key != access_key);
+            before - sessions.len()
+        };
+        if removed > 0 {
+            self.persist().await;
+        }
+        removed
+    }
+
+    fn is_live(&self, session: &Session, now: DateTime<Utc>) -> bool {
+        session.expires_at > now && now - session.last_seen < self.idle_timeout
+    }
+
+    fn prune(&self, sessions: &mut HashMap<String, Session>, now: DateTime<Utc>) {
+        sessions.retain(|_, s| self.is_live(s, now));
+    }
+
+    /// Write the sessions file (via a temporary file and rename). Failures are
+    /// logged: sessions keep working in memory.
+    async fn persist(&self) {
+        let _guard = self.persist_lock.lock().await;
+        let data = match serde_json::to_vec(&*self.sessions.lock().unwrap()) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::error!("Cannot serialize console sessions: {}", e);
+                return;
+            }
+        };
+        let tmp_path = self.path.with_extension("json.tmp");
+        let result = async {
+            if let Some(dir) = self.path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::write(&tmp_path, data).await?;
+            tokio::fs::rename(&tmp_path, &self.path).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Cannot write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
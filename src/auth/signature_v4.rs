@@ -16,6 +16,8 @@ pub struct ParsedAuth {
     pub access_key: String,
     pub date: String,
     pub region: String,
+    /// Service of the credential scope: `s3`, or `sts` for the STS API.
+    pub service: String,
     pub signed_headers: Vec<String>,
     pub signature: String,
 }
@@ -54,6 +56,7 @@ pub fn parse_authorization_header(header: &str) -> Result<ParsedAuth, &'static s
         access_key: cred_parts[0].to_string(),
         date: cred_parts[1].to_string(),
         region: cred_parts[2].to_string(),
+        service: cred_parts[3].to_string(),
         signed_headers: signed_headers.split(';').map(|s| s.to_string()).collect(),
         signature: signature.to_string(),
     })
@@ -80,7 +83,7 @@ pub fn verify_signature(
 
     tracing::debug!("String to sign:\n{}", string_to_sign);
 
-    let signing_key = derive_signing_key(secret_key, &parsed.date, &parsed.region);
+    let signing_key = derive_service_signing_key(secret_key, &parsed.date, &parsed.region, &parsed.service);
 
     let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
     mac.update(string_to_sign.as_bytes());
@@ -142,10 +145,16 @@ pub fn parse_presigned_query(query: &str) -> Result<(ParsedAuth, String, u64), &
         return Err("Invalid Credential format");
     }
 
+    // SignedHeaders is URL-encoded too when more than `host` is signed (content-type%3Bhost)
+    let signed_headers = percent_encoding::percent_decode_str(signed_headers)
+        .decode_utf8()
+        .map_err(|_| "Invalid SignedHeaders encoding")?;
+
     let parsed = ParsedAuth {
         access_key: cred_parts[0].to_string(),
         date: cred_parts[1].to_string(),
         region: cred_parts[2].to_string(),
+        service: cred_parts[3].to_string(),
         signed_headers: signed_headers.split(';').map(|s| s.to_string()).collect(),
         signature: signature.to_string(),
     };
@@ -186,7 +195,7 @@ pub fn verify_presigned_signature(
 
     tracing::debug!("Presigned string to sign:\n{}", string_to_sign);
 
-    let signing_key = derive_signing_key(secret_key, &parsed.date, &parsed.region);
+    let signing_key = derive_service_signing_key(secret_key, &parsed.date, &parsed.region, &parsed.service);
 
     let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
     mac.update(string_to_sign.as_bytes());
@@ -226,10 +235,14 @@ fn canonical_uri(uri: &str) -> String {
     if path.is_empty() || path == "/" {
         return "/".to_string();
     }
-    // URI-encode each path segment individually, preserving '/' separators
+    // URI-encode each path segment individually, preserving '/' separators.
+    // The path arrives percent-encoded, so decode first to avoid double encoding.
     let segments: Vec<String> = path
         .split('/')
-        .map(|s| percent_encoding::utf8_percent_encode(s, S3_URI_ENCODE).to_string())
+        .map(|s| {
+            let decoded = percent_encoding::percent_decode_str(s).decode_utf8_lossy();
+            percent_encoding::utf8_percent_encode(&decoded, S3_URI_ENCODE).to_string()
+        })
         .collect();
     segments.join("/")
 }
@@ -293,7 +306,7 @@ fn build_string_to_sign(
     timestamp: &str,
     parsed: &ParsedAuth,
 ) -> String {
-    let scope = format!("{}/{}/s3/aws4_request", parsed.date, parsed.region);
+    let scope = format!("{}/{}/{}/aws4_request", parsed.date, parsed.region, parsed.service);
 
     let hash = Sha256::digest(canonical_request.as_bytes());
     let canonical_hash = hex::encode(hash);
@@ -305,6 +318,10 @@ fn build_string_to_sign(
 }
 
 pub fn derive_signing_key(secret_key: &str, date: &str, region: &str) -> Vec<u8> {
+    derive_service_signing_key(secret_key, date, region, "s3")
+}
+
+pub fn derive_service_signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
     let key = format!("AWS4{}", secret_key);
 
     let mut mac = HmacSha256::new_from_slice(key.as_bytes()).unwrap();
@@ -316,7 +333,7 @@ pub fn derive_signing_key(secret_key: &str, date: &str, region: &str) -> Vec<u8>
     let date_region_key = mac.finalize().into_bytes();
 
     let mut mac = HmacSha256::new_from_slice(&date_region_key).unwrap();
-    mac.update(b"s3");
+    mac.update(service.as_bytes());
     let date_region_service_key = mac.finalize().into_bytes();
 
     let mut mac = HmacSha256::new_from_slice(&date_region_service_key).unwrap();
@@ -324,7 +341,7 @@ pub fn derive_signing_key(secret_key: &str, date: &str, region: &str) -> Vec<u8>
     mac.finalize().into_bytes().to_vec()
 }
 
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
     }
@@ -0,0 +1,113 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::policy::Policy;
+use super::signature_v4::constant_time_eq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bounds and default of `DurationSeconds`, as in AWS.
+pub const MIN_DURATION_SECS: i64 = 900;
+pub const MAX_DURATION_SECS: i64 = 43200;
+pub const DEFAULT_DURATION_SECS: i64 = 3600;
+
+const B64URL: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// What a session token vouches for. Tokens are stateless: the claims travel
+/// in the token, sealed with an HMAC keyed by the root secret, so temporary
+/// credentials survive restarts and all expire when the root secret changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClaims {
+    #[serde(rename = "ak")]
+    pub access_key: String,
+    #[serde(rename = "exp")]
+    pub expires_at: i64,
+    pub policy: Policy,
+    /// Web identity username, or the parent access key for AssumeRole.
+    #[serde(rename = "sub")]
+    pub subject: String,
+    /// Access key that called AssumeRole; the credentials die with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+pub struct TemporaryCredentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: String,
+    pub expiration: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenError {
+    Invalid,
+    Expired,
+}
+
+pub fn issue(
+    root_secret: &str,
+    subject: &str,
+    parent: Option<&str>,
+    policy: Policy,
+    duration_secs: i64,
+) -> TemporaryCredentials {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let suffix: String = rand::random::<[u8; 16]>()
+        .iter()
+        .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+        .collect();
+    let access_key = format!("ASIA{}", suffix);
+    let expiration = Utc::now() + chrono::Duration::seconds(duration_secs);
+
+    let claims = SessionClaims {
+        access_key: access_key.clone(),
+        expires_at: expiration.timestamp(),
+        policy,
+        subject: subject.to_string(),
+        parent: parent.map(str::to_string),
+    };
+    let payload = B64URL.encode(serde_json::to_vec(&claims).expect("claims serialize"));
+    let seal = B64URL.encode(mac(root_secret, "token", &payload));
+
+    TemporaryCredentials {
+        secret_key: secret_key(root_secret, &access_key),
+        access_key,
+        session_token: format!("{}.{}", payload, seal),
+        expiration,
+    }
+}
+
+/// Check a session token presented together with `access_key`.
+pub fn verify(root_secret: &str, access_key: &str, token: &str) -> Result<SessionClaims, TokenError> {
+    let (payload, seal) = token.split_once('.').ok_or(TokenError::Invalid)?;
+    let expected = B64URL.encode(mac(root_secret, "token", payload));
+    if !constant_time_eq(seal.as_bytes(), expected.as_bytes()) {
+        return Err(TokenError::Invalid);
+    }
+    let claims: SessionClaims = B64URL
+        .decode(payload)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(TokenError::Invalid)?;
+    if claims.access_key != access_key {
+        return Err(TokenError::Invalid);
+    }
+    if claims.expires_at <= Utc::now().timestamp() {
+        return Err(TokenError::Expired);
+    }
+    Ok(claims)
+}
+
+/// The secret key belonging to a temporary access key.
+pub fn secret_key(root_secret: &str, access_key: &str) -> String {
+    B64URL.encode(&mac(root_secret, "secret", access_key)[..30])
+}
+
+fn mac(root_secret: &str, purpose: &str, data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(root_secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(format!("maxio-sts-{}:{}", purpose, data).as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
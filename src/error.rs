@@ -8,6 +8,11 @@ pub struct S3Error {
     pub resource: Option<String>,
 }
 
+/// The S3 error code of an error response, kept in the response extensions
+/// for access logging.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorCode(pub &'static str);
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum S3ErrorCode {
@@ -15,21 +20,33 @@ pub enum S3ErrorCode {
     BadDigest,
     BucketAlreadyOwnedByYou,
     BucketNotEmpty,
+    IncompleteBody,
     InternalError,
     InvalidAccessKeyId,
     InvalidArgument,
     InvalidBucketName,
     InvalidPart,
+    InvalidPartNumber,
+    InvalidPolicyDocument,
+    InvalidRequest,
+    InvalidTargetBucketForLogging,
+    MalformedPOSTRequest,
     MalformedXML,
     NoSuchBucket,
     NoSuchKey,
     NoSuchUpload,
     NoSuchVersion,
+    QuotaExceeded,
+    ReplicationConfigurationNotFoundError,
     InvalidRange,
     NotImplemented,
     EntityTooSmall,
+    EntityTooLarge,
     ExpiredPresignedUrl,
+    ExpiredToken,
+    InvalidToken,
     SignatureDoesNotMatch,
+    SlowDown,
 }
 
 impl S3ErrorCode {
@@ -39,21 +56,33 @@ impl S3ErrorCode {
             Self::BadDigest => "BadDigest",
             Self::BucketAlreadyOwnedByYou => "BucketAlreadyOwnedByYou",
             Self::BucketNotEmpty => "BucketNotEmpty",
+            Self::IncompleteBody => "IncompleteBody",
             Self::InternalError => "InternalError",
             Self::InvalidAccessKeyId => "InvalidAccessKeyId",
             Self::InvalidArgument => "InvalidArgument",
             Self::InvalidBucketName => "InvalidBucketName",
             Self::InvalidPart => "InvalidPart",
+            Self::InvalidPartNumber => "InvalidPartNumber",
+            Self::InvalidPolicyDocument => "InvalidPolicyDocument",
+            Self::InvalidRequest => "InvalidRequest",
+            Self::InvalidTargetBucketForLogging => "InvalidTargetBucketForLogging",
+            Self::MalformedPOSTRequest => "MalformedPOSTRequest",
             Self::MalformedXML => "MalformedXML",
             Self::NoSuchBucket => "NoSuchBucket",
             Self::NoSuchKey => "NoSuchKey",
             Self::NoSuchUpload => "NoSuchUpload",
             Self::NoSuchVersion => "NoSuchVersion",
+            Self::QuotaExceeded => "QuotaExceeded",
+            Self::ReplicationConfigurationNotFoundError => "ReplicationConfigurationNotFoundError",
             Self::InvalidRange => "InvalidRange",
             Self::NotImplemented => "NotImplemented",
             Self::EntityTooSmall => "EntityTooSmall",
+            Self::EntityTooLarge => "EntityTooLarge",
             Self::ExpiredPresignedUrl => "AccessDenied",
+            Self::ExpiredToken => "ExpiredToken",
+            Self::InvalidToken => "InvalidToken",
             Self::SignatureDoesNotMatch => "SignatureDoesNotMatch",
+            Self::SlowDown => "SlowDown",
         }
     }
 
@@ -63,13 +92,16 @@ impl S3ErrorCode {
             | Self::ExpiredPresignedUrl
             | Self::InvalidAccessKeyId
             | Self::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
-            Self::NoSuchBucket | Self::NoSuchKey | Self::NoSuchUpload | Self::NoSuchVersion => {
-                StatusCode::NOT_FOUND
-            }
+            Self::NoSuchBucket
+            | Self::NoSuchKey
+            | Self::NoSuchUpload
+            | Self::NoSuchVersion
+            | Self::ReplicationConfigurationNotFoundError => StatusCode::NOT_FOUND,
             Self::BucketAlreadyOwnedByYou | Self::BucketNotEmpty => StatusCode::CONFLICT,
             Self::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::InvalidRange => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::InvalidRange | Self::InvalidPartNumber => StatusCode::RANGE_NOT_SATISFIABLE,
             Self::NotImplemented => StatusCode::NOT_IMPLEMENTED,
+            Self::SlowDown => StatusCode::SERVICE_UNAVAILABLE,
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -109,6 +141,22 @@ impl S3Error {
         }
     }
 
+    pub fn no_replication_config(bucket: &str) -> Self {
+        Self {
+            code: S3ErrorCode::ReplicationConfigurationNotFoundError,
+            message: "The replication configuration was not found.".into(),
+            resource: Some(format!("/{}", bucket)),
+        }
+    }
+
+    pub fn invalid_target_bucket_for_logging(bucket: &str) -> Self {
+        Self {
+            code: S3ErrorCode::InvalidTargetBucketForLogging,
+            message: format!("The target bucket for logging does not exist: {}", bucket),
+            resource: Some(format!("/{}", bucket)),
+        }
+    }
+
     pub fn bucket_already_owned(bucket: &str) -> Self {
         Self {
             code: S3ErrorCode::BucketAlreadyOwnedByYou,
@@ -168,6 +216,14 @@ impl S3Error {
         }
     }
 
+    pub fn malformed_post_request() -> Self {
+        Self {
+            code: S3ErrorCode::MalformedPOSTRequest,
+            message: "The body of your POST request is not well-formed multipart/form-data.".into(),
+            resource: None,
+        }
+    }
+
     pub fn invalid_part(msg: &str) -> Self {
         Self {
             code: S3ErrorCode::InvalidPart,
@@ -176,6 +232,22 @@ impl S3Error {
         }
     }
 
+    pub fn invalid_request(msg: &str) -> Self {
+        Self {
+            code: S3ErrorCode::InvalidRequest,
+            message: msg.to_string(),
+            resource: None,
+        }
+    }
+
+    pub fn incomplete_body() -> Self {
+        Self {
+            code: S3ErrorCode::IncompleteBody,
+            message: "The request body does not match the declared x-amz-decoded-content-length".into(),
+            resource: None,
+        }
+    }
+
     pub fn entity_too_small() -> Self {
         Self {
             code: S3ErrorCode::EntityTooSmall,
@@ -185,6 +257,22 @@ impl S3Error {
         }
     }
 
+    pub fn entity_too_large() -> Self {
+        Self {
+            code: S3ErrorCode::EntityTooLarge,
+            message: "Your proposed upload exceeds the maximum allowed size".into(),
+            resource: None,
+        }
+    }
+
+    pub fn invalid_policy_document(msg: &str) -> Self {
+        Self {
+            code: S3ErrorCode::InvalidPolicyDocument,
+            message: msg.to_string(),
+            resource: None,
+        }
+    }
+
     pub fn expired_presigned_url() -> Self {
         Self {
             code: S3ErrorCode::ExpiredPresignedUrl,
@@ -193,6 +281,38 @@ impl S3Error {
         }
     }
 
+    pub fn expired_token() -> Self {
+        Self {
+            code: S3ErrorCode::ExpiredToken,
+            message: "The provided token has expired.".into(),
+            resource: None,
+        }
+    }
+
+    pub fn invalid_token() -> Self {
+        Self {
+            code: S3ErrorCode::InvalidToken,
+            message: "The provided token is malformed or otherwise invalid.".into(),
+            resource: None,
+        }
+    }
+
+    pub fn quota_exceeded(msg: &str) -> Self {
+        Self {
+            code: S3ErrorCode::QuotaExceeded,
+            message: msg.to_string(),
+            resource: None,
+        }
+    }
+
+    pub fn slow_down() -> Self {
+        Self {
+            code: S3ErrorCode::SlowDown,
+            message: "Please reduce your request rate.".into(),
+            resource: None,
+        }
+    }
+
     pub fn access_denied(msg: &str) -> Self {
         Self {
             code: S3ErrorCode::AccessDenied,
@@ -234,6 +354,14 @@ impl S3Error {
         }
     }
 
+    pub fn invalid_part_number() -> Self {
+        Self {
+            code: S3ErrorCode::InvalidPartNumber,
+            message: "The requested partnumber is not satisfiable".into(),
+            resource: None,
+        }
+    }
+
     pub fn not_implemented(msg: &str) -> Self {
         Self {
             code: S3ErrorCode::NotImplemented,
@@ -262,7 +390,7 @@ impl IntoResponse for S3Error {
         );
 
         let status = self.code.status_code();
-        (
+        let mut response = (
             status,
             [
                 ("content-type", "application/xml"),
@@ -270,6 +398,8 @@ impl IntoResponse for S3Error {
             ],
             xml,
         )
-            .into_response()
+            .into_response();
+        response.extensions_mut().insert(ErrorCode(self.code.as_str()));
+        response
     }
 }
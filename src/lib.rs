@@ -1,8 +1,16 @@
 pub mod api;
+pub mod audit;
 pub mod auth;
 pub mod config;
 pub mod embedded;
 pub mod error;
+pub mod metrics;
+pub mod notify;
+pub mod proxy;
+pub mod queue;
+pub mod replication;
 pub mod server;
 pub mod storage;
+pub mod throttle;
+pub mod tls;
 pub mod xml;
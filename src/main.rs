@@ -1,16 +1,26 @@
 mod api;
+mod audit;
 mod auth;
 mod config;
 mod embedded;
 mod error;
+mod metrics;
+mod notify;
+mod proxy;
+mod queue;
+mod replication;
 mod server;
 mod storage;
+mod throttle;
+mod tls;
 mod xml;
 
+use axum::serve::ListenerExt;
 use clap::Parser;
 use config::Config;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
@@ -30,13 +40,46 @@ async fn main() -> anyhow::Result<()> {
         config.parity_shards,
     ).await?;
 
+    let notifier = notify::Notifier::new(&config)?;
+    for arn in notifier.target_arns() {
+        tracing::info!("Notification target: {}", arn);
+    }
+
+    let storage = Arc::new(storage);
+    storage::crawler::spawn(storage.clone(), Duration::from_secs(config.usage_scan_interval));
+    let replicator = replication::Replicator::new(&config, storage.clone())?;
+    for id in replicator.target_ids() {
+        tracing::info!("Replication target: {}", id);
+    }
+
+    let audit = audit::AuditLog::new(&config, storage.clone())?;
+    let credentials = auth::credentials::Credentials::new(&config)?;
+    let sessions = auth::session::SessionStore::open(&config)?;
+    let oidc = auth::oidc::Oidc::new(&config)?;
+    let trusted_proxies = proxy::TrustedProxies::new(&config)?;
+    if let Some(ref url) = config.oidc_config_url {
+        tracing::info!("Console single sign-on: {}", url);
+    }
+    if !config.trusted_proxies.is_empty() {
+        tracing::info!("Trusted proxies: {}", config.trusted_proxies.join(", "));
+    }
+
     let state = server::AppState {
-        storage: Arc::new(storage),
+        storage,
         config: Arc::new(config.clone()),
         login_rate_limiter: Arc::new(api::console::LoginRateLimiter::new()),
+        notifier: Arc::new(notifier),
+        replicator: Arc::new(replicator),
+        audit: Arc::new(audit),
+        credentials: Arc::new(credentials),
+        sessions: Arc::new(sessions),
+        oidc: oidc.map(Arc::new),
+        trusted_proxies: Arc::new(trusted_proxies),
+        throttle: Arc::new(throttle::Throttle::new(&config)),
     };
 
     let app = server::build_router(state);
+    let tls_acceptor = tls::acceptor(&config)?;
 
     let addr = format!("{}:{}", config.address, config.port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -51,6 +94,9 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Secret Key: [REDACTED]");
     tracing::info!("Data dir:   {}", config.data_dir);
     tracing::info!("Region:     {}", config.region);
+    if !config.domains.is_empty() {
+        tracing::info!("Domains:    {} (virtual-hosted-style enabled)", config.domains.join(", "));
+    }
     if config.erasure_coding {
         tracing::info!("Erasure coding: enabled (chunk size: {}MB)", config.chunk_size / (1024 * 1024));
         if config.parity_shards > 0 {
@@ -60,11 +106,38 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!("--parity-shards ignored: requires --erasure-coding to be enabled");
     }
     let display_host = if config.address == "0.0.0.0" { "localhost" } else { &config.address };
-    tracing::info!("Web UI:     http://{}:{}/ui/", display_host, config.port);
+    let scheme = if tls_acceptor.is_some() { "https" } else { "http" };
+    tracing::info!("Web UI:     {}://{}:{}/ui/", scheme, display_host, config.port);
+
+    // tls::acceptor refuses a redirect port without a certificate
+    if let Some(redirect_port) = config.tls_redirect_port {
+        let redirect_addr = format!("{}:{}", config.address, redirect_port);
+        let redirect_listener = tokio::net::TcpListener::bind(&redirect_addr).await?;
+        tracing::info!("HTTP -> HTTPS redirect listening on {}", redirect_addr);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(redirect_listener, tls::redirect_router(config.port)).await {
+                tracing::error!("HTTP redirect listener failed: {}", e);
+            }
+        });
+    }
 
-    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    match tls_acceptor {
+        Some(acceptor) => {
+            if config.tls_client_ca.is_some() {
+                tracing::info!("TLS:        client certificates required");
+            }
+            // tap_io gives the listener the ConnectInfo<SocketAddr> impl the console relies on
+            let listener = tls::TlsListener::new(listener, acceptor)?.tap_io(|_| {});
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+        }
+        None => {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+
+use crate::server::AppState;
+use crate::storage::filesystem::FilesystemStorage;
+
+/// Upper bounds (seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Process-wide metrics. Global rather than in `AppState` because some
+/// sources (the chunk reader, the login rate limiter) have no access to it.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.buckets.iter_mut()) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Request count per (S3 operation, status code).
+    requests: Mutex<HashMap<(&'static str, u16), u64>>,
+    latency: Mutex<HashMap<&'static str, Histogram>>,
+    received_bytes: AtomicU64,
+    sent_bytes: AtomicU64,
+    ec_reconstructions: AtomicU64,
+    ec_reconstruction_failures: AtomicU64,
+    login_rejections: AtomicU64,
+    throttled_requests: AtomicU64,
+}
+
+impl Metrics {
+    fn observe_request(&self, api: &'static str, status: StatusCode, elapsed: Duration) {
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((api, status.as_u16()))
+            .or_default() += 1;
+        self.latency
+            .lock()
+            .unwrap()
+            .entry(api)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// A data chunk failed its integrity check and Reed-Solomon recovery was tried.
+    pub fn ec_reconstruction(&self, succeeded: bool) {
+        let counter = if succeeded {
+            &self.ec_reconstructions
+        } else {
+            &self.ec_reconstruction_failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A console login was refused by the rate limiter.
+    pub fn login_rejected(&self) {
+        self.login_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An S3 request was refused with SlowDown by a request rate limit.
+    pub fn request_throttled(&self) {
+        self.throttled_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Everything in the Prometheus text format. Bucket statistics are
+    /// gathered from storage at scrape time.
+    pub async fn render(&self, storage: &FilesystemStorage) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "maxio_s3_requests_total",
+            "counter",
+            "S3 requests by operation and status code.",
+        );
+        let requests: BTreeMap<_, _> = self.requests.lock().unwrap().clone().into_iter().collect();
+        for ((api, code), count) in requests {
+            let _ = writeln!(
+                out,
+                "maxio_s3_requests_total{{api=\"{}\",code=\"{}\"}} {}",
+                api, code, count
+            );
+        }
+
+        header(
+            &mut out,
+            "maxio_s3_request_duration_seconds",
+            "histogram",
+            "S3 request latency by operation.",
+        );
+        {
+            let latency = self.latency.lock().unwrap();
+            let mut apis: Vec<_> = latency.keys().copied().collect();
+            apis.sort();
+            for api in apis {
+                let histogram = &latency[api];
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                    let _ = writeln!(
+                        out,
+                        "maxio_s3_request_duration_seconds_bucket{{api=\"{}\",le=\"{}\"}} {}",
+                        api, bound, count
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "maxio_s3_request_duration_seconds_bucket{{api=\"{}\",le=\"+Inf\"}} {}",
+                    api, histogram.count
+                );
+                let _ = writeln!(
+                    out,
+                    "maxio_s3_request_duration_seconds_sum{{api=\"{}\"}} {}",
+                    api, histogram.sum
+                );
+                let _ = writeln!(
+                    out,
+                    "maxio_s3_request_duration_seconds_count{{api=\"{}\"}} {}",
+                    api, histogram.count
+                );
+            }
+        }
+
+        counter(
+            &mut out,
+            "maxio_s3_received_bytes_total",
+            "Bytes received in S3 request bodies.",
+            &self.received_bytes,
+        );
+        counter(
+            &mut out,
+            "maxio_s3_sent_bytes_total",
+            "Bytes sent in S3 response bodies.",
+            &self.sent_bytes,
+        );
+        counter(
+            &mut out,
+            "maxio_ec_reconstructions_total",
+            "Chunks recovered with Reed-Solomon parity after failing their integrity check.",
+            &self.ec_reconstructions,
+        );
+        counter(
+            &mut out,
+            "maxio_ec_reconstruction_failures_total",
+            "Chunks that failed their integrity check and could not be recovered.",
+            &self.ec_reconstruction_failures,
+        );
+        counter(
+            &mut out,
+            "maxio_console_login_rate_limited_total",
+            "Console logins refused by the rate limiter.",
+            &self.login_rejections,
+        );
+        counter(
+            &mut out,
+            "maxio_s3_requests_throttled_total",
+            "S3 requests refused with SlowDown by a per-key or per-bucket rate limit.",
+            &self.throttled_requests,
+        );
+
+        let buckets = match storage.list_buckets().await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                tracing::warn!("Cannot list buckets for metrics: {}", e);
+                Vec::new()
+            }
+        };
+        let mut objects = String::new();
+        let mut sizes = String::new();
+        let mut uploads = String::new();
+        for bucket in &buckets {
+            let name = escape_label(&bucket.name);
+            let Ok(usage) = storage.bucket_usage(&bucket.name).await else {
+                continue;
+            };
+            let _ = writeln!(
+                objects,
+                "maxio_bucket_objects{{bucket=\"{}\"}} {}",
+                name,
+                usage.objects.saturating_sub(usage.versions)
+            );
+            let _ = writeln!(
+                sizes,
+                "maxio_bucket_size_bytes{{bucket=\"{}\"}} {}",
+                name,
+                usage.size.saturating_sub(usage.versions_size)
+            );
+            let _ = writeln!(
+                uploads,
+                "maxio_multipart_uploads_in_flight{{bucket=\"{}\"}} {}",
+                name, usage.multipart_uploads
+            );
+        }
+        header(
+            &mut out,
+            "maxio_bucket_objects",
+            "gauge",
+            "Objects in a bucket (current versions).",
+        );
+        out.push_str(&objects);
+        header(
+            &mut out,
+            "maxio_bucket_size_bytes",
+            "gauge",
+            "Total size of the current objects in a bucket.",
+        );
+        out.push_str(&sizes);
+        header(
+            &mut out,
+            "maxio_multipart_uploads_in_flight",
+            "gauge",
+            "Multipart uploads started but not completed or aborted.",
+        );
+        out.push_str(&uploads);
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: &AtomicU64) {
+    header(out, name, "counter", help);
+    let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Passes a body through while counting its bytes. The size hint is kept,
+/// so responses keep their Content-Length.
+struct CountingBody<C> {
+    inner: Body,
+    counter: C,
+}
+
+impl<C: Deref<Target = AtomicU64> + Unpin> http_body::Body for CountingBody<C> {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &polled
+            && let Some(data) = frame.data_ref()
+        {
+            self.counter.fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+pub(crate) fn counting<C>(body: Body, counter: C) -> Body
+where
+    C: Deref<Target = AtomicU64> + Send + Unpin + 'static,
+{
+    Body::new(CountingBody {
+        inner: body,
+        counter,
+    })
+}
+
+/// Record count, latency and body sizes of every S3 request, including
+/// those rejected by authentication.
+pub async fn track_s3_request(request: Request, next: Next) -> Response {
+    let api = s3_api_name(request.method(), request.uri(), request.headers());
+    let start = Instant::now();
+    let request = request.map(|body| counting(body, &METRICS.received_bytes));
+    let response = next.run(request).await;
+    METRICS.observe_request(api, response.status(), start.elapsed());
+    response.map(|body| counting(body, &METRICS.sent_bytes))
+}
+
+/// Serve `GET /metrics`, optionally guarded by a bearer token. Signed
+/// requests pass through, so a bucket named `metrics` stays reachable.
+pub async fn metrics_endpoint(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET
+        || request.uri().path() != "/metrics"
+        || is_s3_signed(&request)
+    {
+        return next.run(request).await;
+    }
+    if let Some(token) = &state.config.metrics_token {
+        let presented = request
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if presented != Some(token.as_str()) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [("www-authenticate", "Bearer")],
+                "A valid bearer token is required\n",
+            )
+                .into_response();
+        }
+    }
+    let body = METRICS.render(&state.storage).await;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; version=0.0.4; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+pub(crate) fn is_s3_signed(request: &Request) -> bool {
+    let query = request.uri().query().unwrap_or("");
+    query.contains("X-Amz-Signature=")
+        || request
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("AWS4-HMAC-SHA256"))
+}
+
+/// The S3 operation a request maps to, for metric labels.
+pub(crate) fn s3_api_name(method: &Method, uri: &Uri, headers: &http::HeaderMap) -> &'static str {
+    let path = uri.path().trim_start_matches('/');
+    if path == "minio/admin/v3/info" {
+        return "ServerInfo";
+    }
+    let query: Vec<&str> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .map(|pair| pair.split_once('=').map_or(pair, |(name, _)| name))
+        .collect();
+    let has = |name: &str| query.contains(&name);
+
+    if path.is_empty() {
+        return if *method == Method::GET {
+            "ListBuckets"
+        } else {
+            "Unknown"
+        };
+    }
+    let is_object = path.trim_end_matches('/').contains('/');
+    if !is_object {
+        return match *method {
+            Method::HEAD => "HeadBucket",
+            Method::GET if has("location") => "GetBucketLocation",
+            Method::GET if has("versioning") => "GetBucketVersioning",
+            Method::GET if has("notification") => "GetBucketNotification",
+            Method::GET if has("replication") => "GetBucketReplication",
+            Method::GET if has("logging") => "GetBucketLogging",
+            Method::GET if has("events") => "ListenBucketNotification",
+            Method::GET if has("uploads") => "ListMultipartUploads",
+            Method::GET if has("versions") => "ListObjectVersions",
+            Method::GET if uri.query().is_some_and(|q| q.contains("list-type=2")) => {
+                "ListObjectsV2"
+            }
+            Method::GET => "ListObjectsV1",
+            Method::PUT if has("versioning") => "PutBucketVersioning",
+            Method::PUT if has("notification") => "PutBucketNotification",
+            Method::PUT if has("replication") => "PutBucketReplication",
+            Method::PUT if has("logging") => "PutBucketLogging",
+            Method::PUT if has("replication-reset") => "ResetBucketReplication",
+            Method::PUT => "CreateBucket",
+            Method::DELETE if has("replication") => "DeleteBucketReplication",
+            Method::DELETE => "DeleteBucket",
+            Method::POST if has("delete") => "DeleteObjects",
+            Method::POST => "PostObject",
+            _ => "Unknown",
+        };
+    }
+    match *method {
+        Method::HEAD => "HeadObject",
+        Method::GET if has("uploadId") => "ListParts",
+        Method::GET if has("attributes") => "GetObjectAttributes",
+        Method::GET => "GetObject",
+        Method::PUT if has("uploadId") && headers.contains_key("x-amz-copy-source") => {
+            "UploadPartCopy"
+        }
+        Method::PUT if has("uploadId") => "UploadPart",
+        Method::PUT if headers.contains_key("x-amz-copy-source") => "CopyObject",
+        Method::PUT => "PutObject",
+        Method::DELETE if has("uploadId") => "AbortMultipartUpload",
+        Method::DELETE => "DeleteObject",
+        Method::POST if has("uploads") => "CreateMultipartUpload",
+        Method::POST if has("uploadId") => "CompleteMultipartUpload",
+        _ => "Unknown",
+    }
+}
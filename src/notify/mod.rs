@@ -0,0 +1,294 @@
+pub mod webhook;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+
+use crate::config::Config;
+use crate::queue::DurableQueue;
+use crate::storage::filesystem::FilesystemStorage;
+use crate::storage::{DeleteResult, NotificationRule, PutResult};
+use webhook::Webhook;
+
+/// Events buffered per live listener before it starts missing some.
+const LISTEN_BUFFER: usize = 1024;
+
+/// Object events that can trigger a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum EventName {
+    ObjectCreatedPut,
+    ObjectCreatedPost,
+    ObjectCreatedCopy,
+    ObjectCreatedCompleteMultipartUpload,
+    ObjectRemovedDelete,
+    ObjectRemovedDeleteMarkerCreated,
+}
+
+impl EventName {
+    pub const ALL: [EventName; 6] = [
+        Self::ObjectCreatedPut,
+        Self::ObjectCreatedPost,
+        Self::ObjectCreatedCopy,
+        Self::ObjectCreatedCompleteMultipartUpload,
+        Self::ObjectRemovedDelete,
+        Self::ObjectRemovedDeleteMarkerCreated,
+    ];
+
+    /// Name without the `s3:` prefix, as used in event records.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ObjectCreatedPut => "ObjectCreated:Put",
+            Self::ObjectCreatedPost => "ObjectCreated:Post",
+            Self::ObjectCreatedCopy => "ObjectCreated:Copy",
+            Self::ObjectCreatedCompleteMultipartUpload => "ObjectCreated:CompleteMultipartUpload",
+            Self::ObjectRemovedDelete => "ObjectRemoved:Delete",
+            Self::ObjectRemovedDeleteMarkerCreated => "ObjectRemoved:DeleteMarkerCreated",
+        }
+    }
+
+    /// Whether a configured pattern such as `s3:ObjectCreated:*` covers this event.
+    pub fn matches(&self, pattern: &str) -> bool {
+        let Some(pattern) = pattern.strip_prefix("s3:") else {
+            return false;
+        };
+        match pattern.strip_suffix('*') {
+            Some(prefix) => self.as_str().starts_with(prefix),
+            None => self.as_str() == pattern,
+        }
+    }
+}
+
+/// Whether `pattern` names at least one supported event.
+pub fn is_valid_event_pattern(pattern: &str) -> bool {
+    let wildcard_ok = match pattern.strip_suffix('*') {
+        // Wildcards only replace a whole name component, e.g. `s3:ObjectCreated:*`
+        Some(prefix) => prefix.ends_with(':'),
+        None => true,
+    };
+    wildcard_ok && EventName::ALL.iter().any(|e| e.matches(pattern))
+}
+
+/// An object change to report.
+#[derive(Debug, Clone)]
+pub struct ObjectEvent {
+    pub name: EventName,
+    pub bucket: String,
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+    pub version_id: Option<String>,
+    pub time: DateTime<Utc>,
+    /// Made by a replication peer rather than a client.
+    pub replica: bool,
+}
+
+impl ObjectEvent {
+    pub fn created(name: EventName, bucket: &str, key: &str, result: &PutResult) -> Self {
+        Self {
+            name,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: result.size,
+            etag: result.etag.clone(),
+            version_id: result.version_id.clone(),
+            time: Utc::now(),
+            replica: false,
+        }
+    }
+
+    pub fn removed(bucket: &str, key: &str, result: &DeleteResult) -> Self {
+        Self {
+            name: if result.is_delete_marker {
+                EventName::ObjectRemovedDeleteMarkerCreated
+            } else {
+                EventName::ObjectRemovedDelete
+            },
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: 0,
+            etag: String::new(),
+            version_id: result.version_id.clone(),
+            time: Utc::now(),
+            replica: false,
+        }
+    }
+
+    pub fn with_replica(mut self, replica: bool) -> Self {
+        self.replica = replica;
+        self
+    }
+
+    /// Whether the key filters and any of the event patterns select this event.
+    pub fn matches(&self, patterns: &[String], prefix: &str, suffix: &str) -> bool {
+        self.key.starts_with(prefix)
+            && self.key.ends_with(suffix)
+            && patterns.iter().any(|p| self.name.matches(p))
+    }
+}
+
+fn rule_matches(rule: &NotificationRule, event: &ObjectEvent) -> bool {
+    event.matches(&rule.events, &rule.prefix, &rule.suffix)
+}
+
+/// Keys in event records are URL-encoded like S3 does (spaces as `+`).
+fn encode_key(key: &str) -> String {
+    const KEY_ENCODE: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+        .remove(b'-')
+        .remove(b'_')
+        .remove(b'.')
+        .remove(b'~')
+        .remove(b'/');
+    percent_encoding::utf8_percent_encode(key, KEY_ENCODE)
+        .to_string()
+        .replace("%20", "+")
+}
+
+/// Delivers bucket notifications to the webhook targets from the configuration,
+/// and to live listeners (ListenBucketNotification, the console event feed).
+pub struct Notifier {
+    region: String,
+    principal: String,
+    targets: HashMap<String, Arc<DurableQueue>>,
+    live: broadcast::Sender<ObjectEvent>,
+}
+
+impl Notifier {
+    /// Parse the `--notify-webhook ID=URL` targets and start a dispatcher for
+    /// each, queueing under `{data_dir}/.notify/{ID}`.
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut targets = HashMap::new();
+        for spec in &config.notify_webhooks {
+            let (id, url) = spec
+                .split_once('=')
+                .with_context(|| format!("invalid --notify-webhook '{}', expected ID=URL", spec))?;
+            let (id, url) = (id.trim(), url.trim());
+            if id.is_empty() || id.contains(['/', '\\', ':']) || id.starts_with('.') {
+                anyhow::bail!("invalid notification target ID '{}'", id);
+            }
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("notification target {} must use an http(s) URL", id);
+            }
+            if targets.contains_key(id) {
+                anyhow::bail!("duplicate notification target ID '{}'", id);
+            }
+            let dir = Path::new(&config.data_dir).join(".notify").join(id);
+            let queue = DurableQueue::open(&format!("notification target {}", id), dir, Webhook::new(url)?)
+                .with_context(|| format!("opening queue for notification target {}", id))?;
+            targets.insert(id.to_string(), queue);
+        }
+        Ok(Self {
+            region: config.region.clone(),
+            principal: config.access_key.clone(),
+            targets,
+            live: broadcast::channel(LISTEN_BUFFER).0,
+        })
+    }
+
+    /// ARNs of the configured targets, for use in `QueueConfiguration`.
+    pub fn target_arns(&self) -> Vec<String> {
+        let mut ids: Vec<&String> = self.targets.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| format!("arn:maxio:sqs:{}:{}:webhook", self.region, id))
+            .collect()
+    }
+
+    /// The target an ARN refers to. MinIO-style ARNs (`arn:minio:sqs::ID:webhook`)
+    /// are accepted too, with or without a region.
+    fn target(&self, arn: &str) -> Option<&Arc<DurableQueue>> {
+        let parts: Vec<&str> = arn.split(':').collect();
+        match parts.as_slice() {
+            ["arn", "maxio" | "minio", "sqs", region, id, "webhook"]
+                if region.is_empty() || *region == self.region =>
+            {
+                self.targets.get(*id)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_known_target(&self, arn: &str) -> bool {
+        self.target(arn).is_some()
+    }
+
+    /// Receive every event from now on, for all buckets.
+    pub fn subscribe(&self) -> broadcast::Receiver<ObjectEvent> {
+        self.live.subscribe()
+    }
+
+    /// Publish the event to live listeners and queue it for every target whose
+    /// rule on the bucket matches it. Failures are logged; they never fail the
+    /// request that caused the event.
+    pub async fn emit(&self, storage: &FilesystemStorage, event: ObjectEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.live.send(event.clone());
+        if self.targets.is_empty() {
+            return;
+        }
+        let config = match storage.get_notification(&event.bucket).await {
+            Ok(Some(config)) => config,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Cannot load notification config for {}: {}", event.bucket, e);
+                return;
+            }
+        };
+        for rule in config.rules.iter().filter(|r| rule_matches(r, &event)) {
+            let Some(target) = self.target(&rule.arn) else {
+                tracing::warn!("Notification rule {} refers to unknown target {}", rule.id, rule.arn);
+                continue;
+            };
+            let payload = self.payload(&event, &rule.id);
+            if let Err(e) = target.enqueue(&payload).await {
+                tracing::error!("Cannot queue event for {}: {}", target.name(), e);
+            }
+        }
+    }
+
+    /// S3 event message: `{"EventName", "Key", "Records": [...]}`.
+    pub fn payload(&self, event: &ObjectEvent, configuration_id: &str) -> serde_json::Value {
+        let mut object = serde_json::json!({
+            "key": encode_key(&event.key),
+            "sequencer": format!("{:016X}", event.time.timestamp_nanos_opt().unwrap_or_default()),
+        });
+        if !matches!(
+            event.name,
+            EventName::ObjectRemovedDelete | EventName::ObjectRemovedDeleteMarkerCreated
+        ) {
+            object["size"] = event.size.into();
+            object["eTag"] = event.etag.trim_matches('"').into();
+        }
+        if let Some(vid) = &event.version_id {
+            object["versionId"] = vid.as_str().into();
+        }
+
+        let record = serde_json::json!({
+            "eventVersion": "2.1",
+            "eventSource": "maxio:s3",
+            "awsRegion": self.region,
+            "eventTime": event.time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "eventName": event.name.as_str(),
+            "userIdentity": { "principalId": self.principal },
+            "s3": {
+                "s3SchemaVersion": "1.0",
+                "configurationId": configuration_id,
+                "bucket": {
+                    "name": event.bucket,
+                    "ownerIdentity": { "principalId": self.principal },
+                    "arn": format!("arn:aws:s3:::{}", event.bucket),
+                },
+                "object": object,
+            },
+        });
+        serde_json::json!({
+            "EventName": format!("s3:{}", event.name.as_str()),
+            "Key": format!("{}/{}", event.bucket, event.key),
+            "Records": [record],
+        })
+    }
+}
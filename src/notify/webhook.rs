@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use crate::queue::{Deliver, DeliveryError};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs queued event messages to a webhook URL.
+pub struct Webhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            client: reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build()?,
+        })
+    }
+}
+
+impl Deliver for Webhook {
+    async fn deliver(&self, item: Vec<u8>) -> Result<(), DeliveryError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(item)
+            .send()
+            .await
+            .map_err(|e| DeliveryError::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error()
+            || status == reqwest::StatusCode::REQUEST_TIMEOUT
+            || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        {
+            Err(DeliveryError::Retry(format!("HTTP {}", status)))
+        } else {
+            // The target rejected the event outright
+            Err(DeliveryError::Drop(format!("rejected with HTTP {}", status)))
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use anyhow::{Context, bail};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config::Config;
+use crate::server::AppState;
+
+/// An address range in CIDR notation; a bare address is a /32 or /128.
+#[derive(Debug, Clone, Copy)]
+struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().with_context(|| format!("invalid address in {}", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).with_context(|| format!("invalid prefix in {}", s))?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4 peers of a dual-stack listener show up as IPv4-mapped IPv6.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+/// Reverse proxies whose `X-Forwarded-For` and `X-Forwarded-Proto` headers
+/// are believed. Requests from anywhere else are taken at face value.
+#[derive(Debug)]
+pub struct TrustedProxies {
+    networks: Vec<IpNetwork>,
+    tls: bool,
+}
+
+/// Who sent a request and over which scheme, as seen past trusted proxies.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    pub scheme: &'static str,
+}
+
+impl TrustedProxies {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let mut networks = Vec::new();
+        for entry in config.trusted_proxies.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            match IpNetwork::parse(entry) {
+                Ok(network) => networks.push(network),
+                Err(e) => bail!("--trusted-proxy: {:#}", e),
+            }
+        }
+        Ok(Self {
+            networks,
+            tls: config.tls_enabled(),
+        })
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|n| n.contains(ip))
+    }
+
+    /// The client is the nearest untrusted hop of `X-Forwarded-For`, read
+    /// right to left from the connecting peer.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> ClientInfo {
+        let peer = canonical(peer);
+        let tls_scheme = if self.tls { "https" } else { "http" };
+        if !self.is_trusted(peer) {
+            return ClientInfo { ip: peer, scheme: tls_scheme };
+        }
+
+        let mut ip = peer;
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect::<Vec<_>>();
+        for hop in forwarded.iter().rev() {
+            let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            ip = canonical(hop);
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+
+        // The first proxy records the scheme the client used
+        let forwarded_https = headers
+            .get("x-forwarded-proto")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("https"));
+        let scheme = if self.tls || forwarded_https { "https" } else { "http" };
+        ClientInfo { ip, scheme }
+    }
+}
+
+/// Resolve the `ClientInfo` of every request for handlers, audit and logs.
+pub async fn client_info_middleware(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let client = state.trusted_proxies.resolve(peer, request.headers());
+    request.extensions_mut().insert(client);
+    next.run(request).await
+}
@@ -0,0 +1,219 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::fs;
+use tokio::sync::Notify;
+
+/// Items kept on disk per queue (parked ones included) before new ones are
+/// refused.
+const QUEUE_LIMIT: usize = 100_000;
+/// Delivery attempts per item before it is parked so the items behind it
+/// can go out.
+const MAX_ATTEMPTS: u32 = 4;
+/// How often parked items are put back in line for another round.
+const PARKED_RETRY: Duration = Duration::from_secs(300);
+/// First retry delay after a failed delivery; doubles up to `RETRY_MAX`.
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+/// How long an idle queue sleeps before rescanning its directory.
+const IDLE_POLL: Duration = Duration::from_secs(30);
+/// Subdirectory holding parked items.
+const PARKED_DIR: &str = "parked";
+
+pub enum DeliveryError {
+    /// Worth retrying later (network failure, timeout, overloaded target).
+    Retry(String),
+    /// Retrying will not help; the item is dropped.
+    Drop(String),
+}
+
+/// Where a queue's items go, e.g. a webhook or a replication target.
+pub trait Deliver: Send + Sync + 'static {
+    fn deliver(&self, item: Vec<u8>) -> impl Future<Output = Result<(), DeliveryError>> + Send;
+}
+
+/// A persistent FIFO of JSON items, one file each in `dir`, named so that
+/// lexical order is delivery order. A file is only removed once its item was
+/// delivered (or dropped), so pending work survives restarts. Items that keep
+/// failing are moved to `dir/parked` and retried every `PARKED_RETRY` and
+/// on restart.
+pub struct DurableQueue {
+    name: String,
+    dir: PathBuf,
+    wake: Notify,
+    len: AtomicUsize,
+}
+
+impl DurableQueue {
+    /// Open (or create) the queue directory and start delivering to `target`,
+    /// including anything left over from a previous run.
+    pub fn open<D: Deliver>(name: &str, dir: PathBuf, target: D) -> anyhow::Result<Arc<Self>> {
+        std::fs::create_dir_all(&dir)?;
+        let mut pending = 0;
+        for dir in [dir.clone(), dir.join(PARKED_DIR)] {
+            pending += std::fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .filter(|e| is_item_file(&e.path()))
+                .count();
+        }
+        if pending > 0 {
+            tracing::info!("{}: {} queued item(s) to deliver", name, pending);
+        }
+        let queue = Arc::new(Self {
+            name: name.to_string(),
+            dir,
+            wake: Notify::new(),
+            len: AtomicUsize::new(pending),
+        });
+        tokio::spawn(queue.clone().run(target));
+        Ok(queue)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of items waiting for delivery.
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Persist an item and wake the worker. The file is written under a
+    /// temporary name and renamed so the worker never sees a partial item.
+    /// Fails once `QUEUE_LIMIT` items are waiting.
+    pub async fn enqueue(&self, item: &serde_json::Value) -> std::io::Result<()> {
+        if self.len() >= QUEUE_LIMIT {
+            return Err(std::io::Error::other(format!("{} has {} items queued", self.name, QUEUE_LIMIT)));
+        }
+        let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let name = format!("{:020}-{}", nanos, uuid::Uuid::new_v4().simple());
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp_path, serde_json::to_vec(item)?).await?;
+        fs::rename(&tmp_path, self.dir.join(format!("{}.json", name))).await?;
+        self.len.fetch_add(1, Ordering::Relaxed);
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Queued item files in `dir`, oldest first.
+    async fn pending(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut entries = fs::read_dir(dir).await?;
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if is_item_file(&entry.path()) {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Move `path` into or out of the parked directory.
+    async fn move_item(&self, path: &Path, to: &Path) {
+        let Some(name) = path.file_name() else {
+            return;
+        };
+        let moved = match fs::create_dir_all(to).await {
+            Ok(()) => fs::rename(path, to.join(name)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = moved {
+            tracing::warn!("{}: cannot move {}: {}", self.name, path.display(), e);
+        }
+    }
+
+    /// Put parked items back in line, ahead of newer ones.
+    async fn unpark(&self) {
+        let parked = match Self::pending(&self.dir.join(PARKED_DIR)).await {
+            Ok(parked) => parked,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                tracing::warn!("{}: cannot read parked items: {}", self.name, e);
+                return;
+            }
+        };
+        if !parked.is_empty() {
+            tracing::info!("{}: retrying {} parked item(s)", self.name, parked.len());
+        }
+        for path in parked {
+            self.move_item(&path, &self.dir).await;
+        }
+    }
+
+    async fn run<D: Deliver>(self: Arc<Self>, target: D) {
+        let mut backoff = RETRY_MIN;
+        // Parked items get another round on startup too
+        self.unpark().await;
+        let mut unparked = tokio::time::Instant::now();
+        loop {
+            if unparked.elapsed() >= PARKED_RETRY {
+                self.unpark().await;
+                unparked = tokio::time::Instant::now();
+            }
+            let files = match Self::pending(&self.dir).await {
+                Ok(files) => files,
+                Err(e) => {
+                    tracing::warn!("{}: cannot read queue: {}", self.name, e);
+                    Vec::new()
+                }
+            };
+            if files.is_empty() {
+                let _ = tokio::time::timeout(IDLE_POLL, self.wake.notified()).await;
+                continue;
+            }
+
+            for path in files {
+                let item = match fs::read(&path).await {
+                    Ok(item) => item,
+                    Err(e) => {
+                        tracing::warn!("{}: cannot read {}: {}", self.name, path.display(), e);
+                        continue;
+                    }
+                };
+                // Retry the oldest item a few times so delivery order is
+                // preserved, then park it rather than hold up the rest
+                let mut attempts = 0;
+                let done = loop {
+                    attempts += 1;
+                    match target.deliver(item.clone()).await {
+                        Ok(()) => break true,
+                        Err(DeliveryError::Drop(msg)) => {
+                            tracing::error!("{}: dropping item: {}", self.name, msg);
+                            break true;
+                        }
+                        Err(DeliveryError::Retry(msg)) if attempts >= MAX_ATTEMPTS => {
+                            tracing::error!(
+                                "{}: delivery failed {} times ({}), parking item for {:?}",
+                                self.name,
+                                attempts,
+                                msg,
+                                PARKED_RETRY
+                            );
+                            break false;
+                        }
+                        Err(DeliveryError::Retry(msg)) => {
+                            tracing::warn!("{}: delivery failed ({}), retrying in {:?}", self.name, msg, backoff);
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(RETRY_MAX);
+                        }
+                    }
+                };
+                backoff = RETRY_MIN;
+                if !done {
+                    self.move_item(&path, &self.dir.join(PARKED_DIR)).await;
+                } else if fs::remove_file(&path).await.is_ok() {
+                    self.len.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+fn is_item_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::signature_v4;
+use crate::config::Config;
+use crate::error::S3Error;
+use crate::notify::{EventName, ObjectEvent};
+use crate::queue::{Deliver, DeliveryError, DurableQueue};
+use crate::storage::filesystem::FilesystemStorage;
+use crate::storage::{ObjectMeta, ReplicaSource, ReplicationConfig, ReplicationRule, ReplicationStatus, StorageError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Marks a write made by a replication peer. The receiver stores it as a
+/// replica and does not replicate it again, which breaks the loop between
+/// two sites replicating to each other.
+pub const REPLICA_HEADER: &str = "x-maxio-replication-request";
+/// Identity of the write on the source side, preserved by the replica.
+const SOURCE_MTIME_HEADER: &str = "x-maxio-source-mtime";
+const SOURCE_VERSION_HEADER: &str = "x-maxio-source-version-id";
+const SOURCE_ETAG_HEADER: &str = "x-maxio-source-etag";
+/// Headers a replica write must cover with its signature when it sends them.
+pub const REPLICA_HEADERS: [&str; 4] = [REPLICA_HEADER, SOURCE_MTIME_HEADER, SOURCE_VERSION_HEADER, SOURCE_ETAG_HEADER];
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum time without progress on a replication request.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+const S3_ENCODE: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn encode(s: &str) -> String {
+    percent_encoding::utf8_percent_encode(s, S3_ENCODE).to_string()
+}
+
+pub fn is_replica_request(headers: &http::HeaderMap) -> bool {
+    headers.contains_key(REPLICA_HEADER)
+}
+
+/// The source identity sent along with a replicated write, if this is one.
+pub fn replica_source(headers: &http::HeaderMap) -> Result<Option<ReplicaSource>, S3Error> {
+    if !is_replica_request(headers) {
+        return Ok(None);
+    }
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let mtime = header(SOURCE_MTIME_HEADER)
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(&v).ok())
+        .ok_or_else(|| S3Error::invalid_argument(&format!("Missing or invalid {}", SOURCE_MTIME_HEADER)))?;
+    Ok(Some(ReplicaSource {
+        version_id: header(SOURCE_VERSION_HEADER),
+        last_modified: format_mtime(mtime.with_timezone(&chrono::Utc)),
+        etag: header(SOURCE_ETAG_HEADER),
+    }))
+}
+
+/// Object timestamps as stored in metadata.
+fn format_mtime(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// An S3-compatible endpoint objects are replicated to, parsed from
+/// `http(s)://ACCESS_KEY:SECRET_KEY@host[:port][?region=REGION]`.
+struct RemoteTarget {
+    endpoint: String,
+    host: String,
+    access_key: String,
+    secret_key: String,
+    region: String,
+    client: reqwest::Client,
+}
+
+impl RemoteTarget {
+    fn parse(url: &str, default_region: &str) -> anyhow::Result<Self> {
+        let parsed = reqwest::Url::parse(url).context("invalid URL")?;
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            anyhow::bail!("URL must use http or https");
+        }
+        let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+        let access_key = decode(parsed.username());
+        let secret_key = decode(parsed.password().unwrap_or(""));
+        if access_key.is_empty() || secret_key.is_empty() {
+            anyhow::bail!("URL must include credentials as ACCESS_KEY:SECRET_KEY@");
+        }
+        let host_name = parsed.host_str().context("URL has no host")?;
+        let host = match parsed.port() {
+            Some(port) => format!("{}:{}", host_name, port),
+            None => host_name.to_string(),
+        };
+        let region = parsed
+            .query_pairs()
+            .find(|(k, _)| k == "region")
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_else(|| default_region.to_string());
+        Ok(Self {
+            endpoint: format!("{}://{}", parsed.scheme(), host),
+            host,
+            access_key,
+            secret_key,
+            region,
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .read_timeout(READ_TIMEOUT)
+                .build()?,
+        })
+    }
+
+    /// A SigV4-signed request for `/{bucket}/{key}`. `headers` are extra
+    /// (lowercase) headers to sign; the payload is sent unsigned.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        bucket: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, String)],
+    ) -> reqwest::RequestBuilder {
+        let now = chrono::Utc::now();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let path = format!(
+            "/{}/{}",
+            encode(bucket),
+            key.split('/').map(encode).collect::<Vec<_>>().join("/")
+        );
+        let mut encoded_query: Vec<(String, String)> =
+            query.iter().map(|(k, v)| (encode(k), encode(v))).collect();
+        encoded_query.sort();
+        let query_string = encoded_query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut signed: Vec<(String, String)> = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        signed.push(("host".to_string(), self.host.clone()));
+        signed.push(("x-amz-content-sha256".to_string(), "UNSIGNED-PAYLOAD".to_string()));
+        signed.push(("x-amz-date".to_string(), amz_date.clone()));
+        signed.sort();
+        let signed_names = signed.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = signed
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.trim()))
+            .collect();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\nUNSIGNED-PAYLOAD",
+            method, path, query_string, canonical_headers, signed_names
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date_stamp, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = signature_v4::derive_signing_key(&self.secret_key, &date_stamp, &self.region);
+        let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
+        mac.update(string_to_sign.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let mut url = format!("{}{}", self.endpoint, path);
+        if !query_string.is_empty() {
+            url = format!("{}?{}", url, query_string);
+        }
+        let mut builder = self.client.request(method, url);
+        for (name, value) in &signed {
+            if name != "host" {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+        builder.header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed_names, signature
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TaskOp {
+    Put,
+    /// Delete the current object (a delete marker in versioned buckets).
+    Delete,
+    /// Permanently delete one version.
+    DeleteVersion,
+}
+
+/// One queued replication step.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplicationTask {
+    op: TaskOp,
+    bucket: String,
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version_id: Option<String>,
+    /// When the delete happened on the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    dest_bucket: String,
+}
+
+/// Applies queued tasks to one remote target.
+struct ReplicationWorker {
+    target: RemoteTarget,
+    storage: Arc<FilesystemStorage>,
+}
+
+/// Whether a failed response may succeed later without changing the request
+/// (e.g. once the destination bucket exists or credentials are fixed). The
+/// queue parks items that keep failing, so these don't hold up the rest.
+fn retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || matches!(status.as_u16(), 401 | 403 | 404 | 408 | 429)
+}
+
+/// Record how replication of the task's object went; failures are logged.
+async fn record_status(storage: &FilesystemStorage, task: &ReplicationTask, status: ReplicationStatus) {
+    if let Err(e) = storage
+        .set_replication_status(&task.bucket, &task.key, task.version_id.as_deref(), status)
+        .await
+    {
+        tracing::warn!("Cannot record replication status of {}/{}: {}", task.bucket, task.key, e);
+    }
+}
+
+/// Headers that identify a replicated write to the receiving side.
+fn replica_headers(version_id: Option<&str>, last_modified: &str, etag: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut headers = vec![
+        (REPLICA_HEADER, "true".to_string()),
+        (SOURCE_MTIME_HEADER, last_modified.to_string()),
+    ];
+    if let Some(vid) = version_id {
+        headers.push((SOURCE_VERSION_HEADER, vid.to_string()));
+    }
+    if let Some(etag) = etag {
+        headers.push((SOURCE_ETAG_HEADER, etag.to_string()));
+    }
+    headers
+}
+
+impl ReplicationWorker {
+    async fn put(&self, task: &ReplicationTask) -> Result<(), DeliveryError> {
+        let fetched = match &task.version_id {
+            Some(vid) => self.storage.get_object_version(&task.bucket, &task.key, vid).await,
+            None => self.storage.get_object(&task.bucket, &task.key).await,
+        };
+        let (reader, meta) = match fetched {
+            Ok(found) => found,
+            // Deleted or overwritten since; later tasks carry the newer state
+            Err(StorageError::NotFound(_) | StorageError::VersionNotFound(_)) => return Ok(()),
+            Err(e) => return Err(DeliveryError::Retry(e.to_string())),
+        };
+
+        let mut headers = replica_headers(meta.version_id.as_deref(), &meta.last_modified, Some(&meta.etag));
+        headers.push(("content-type", meta.content_type.clone()));
+        let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(reader));
+        let result = self
+            .target
+            .request(reqwest::Method::PUT, &task.dest_bucket, &task.key, &[], &headers)
+            .header("content-length", meta.size)
+            .body(body)
+            .send()
+            .await;
+
+        let outcome = match result {
+            Ok(resp) if resp.status().is_success() => Ok(()),
+            Ok(resp) if retryable(resp.status()) => Err(DeliveryError::Retry(format!("HTTP {}", resp.status()))),
+            Ok(resp) => Err(DeliveryError::Drop(format!("{} rejected with HTTP {}", task.key, resp.status()))),
+            Err(e) => Err(DeliveryError::Retry(e.to_string())),
+        };
+        // A replica resent by a resync keeps its marker
+        if meta.replication_status == Some(ReplicationStatus::Replica) {
+            return outcome;
+        }
+        let status = match outcome {
+            Ok(()) => ReplicationStatus::Completed,
+            Err(_) => ReplicationStatus::Failed,
+        };
+        record_status(&self.storage, task, status).await;
+        outcome
+    }
+
+    async fn delete(&self, task: &ReplicationTask) -> Result<(), DeliveryError> {
+        let request = match task.op {
+            TaskOp::DeleteVersion => {
+                let vid = task.version_id.as_deref().unwrap_or_default();
+                self.target.request(
+                    reqwest::Method::DELETE,
+                    &task.dest_bucket,
+                    &task.key,
+                    &[("versionId", vid)],
+                    &[(REPLICA_HEADER, "true".to_string())],
+                )
+            }
+            _ => {
+                let last_modified = task.last_modified.clone().unwrap_or_else(|| format_mtime(chrono::Utc::now()));
+                let headers = replica_headers(task.version_id.as_deref(), &last_modified, None);
+                self.target
+                    .request(reqwest::Method::DELETE, &task.dest_bucket, &task.key, &[], &headers)
+            }
+        };
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| DeliveryError::Retry(e.to_string()))?;
+        let status = resp.status();
+        if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else if retryable(status) {
+            Err(DeliveryError::Retry(format!("HTTP {}", status)))
+        } else {
+            Err(DeliveryError::Drop(format!("delete of {} rejected with HTTP {}", task.key, status)))
+        }
+    }
+}
+
+impl Deliver for ReplicationWorker {
+    async fn deliver(&self, item: Vec<u8>) -> Result<(), DeliveryError> {
+        let task: ReplicationTask = serde_json::from_slice(&item)
+            .map_err(|e| DeliveryError::Drop(format!("malformed task: {}", e)))?;
+        match task.op {
+            TaskOp::Put => self.put(&task).await,
+            TaskOp::Delete | TaskOp::DeleteVersion => self.delete(&task).await,
+        }
+    }
+}
+
+/// The enabled rule with the highest priority whose prefix covers `key`.
+fn rule_for<'a>(config: &'a ReplicationConfig, key: &str) -> Option<&'a ReplicationRule> {
+    config
+        .rules
+        .iter()
+        .filter(|r| r.enabled && key.starts_with(&r.prefix))
+        .max_by_key(|r| r.priority)
+}
+
+/// Queues object changes for the replication targets from the configuration.
+pub struct Replicator {
+    targets: HashMap<String, Arc<DurableQueue>>,
+}
+
+impl Replicator {
+    /// Parse the `--replication-target ID=URL` targets and start a worker for
+    /// each, queueing under `{data_dir}/.replication/{ID}`.
+    pub fn new(config: &Config, storage: Arc<FilesystemStorage>) -> anyhow::Result<Self> {
+        let mut targets = HashMap::new();
+        for spec in &config.replication_targets {
+            let (id, url) = spec
+                .split_once('=')
+                .with_context(|| format!("invalid --replication-target '{}', expected ID=URL", spec))?;
+            let id = id.trim();
+            if id.is_empty() || id.contains(['/', '\\', ':']) || id.starts_with('.') {
+                anyhow::bail!("invalid replication target ID '{}'", id);
+            }
+            if targets.contains_key(id) {
+                anyhow::bail!("duplicate replication target ID '{}'", id);
+            }
+            let target = RemoteTarget::parse(url.trim(), &config.region)
+                .with_context(|| format!("replication target {}", id))?;
+            let worker = ReplicationWorker { target, storage: storage.clone() };
+            let dir = Path::new(&config.data_dir).join(".replication").join(id);
+            let queue = DurableQueue::open(&format!("replication target {}", id), dir, worker)
+                .with_context(|| format!("opening queue for replication target {}", id))?;
+            targets.insert(id.to_string(), queue);
+        }
+        Ok(Self { targets })
+    }
+
+    /// Configured target IDs, sorted.
+    pub fn target_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.targets.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Target ID and bucket named by a destination ARN
+    /// (`arn:maxio:replication::ID:bucket`, or the `arn:minio:` form).
+    pub fn destination(&self, arn: &str) -> Option<(String, String)> {
+        let parts: Vec<&str> = arn.split(':').collect();
+        match parts.as_slice() {
+            ["arn", "maxio" | "minio", "replication", _, id, bucket]
+                if self.targets.contains_key(*id) && !bucket.is_empty() =>
+            {
+                Some((id.to_string(), bucket.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Queue a task for the rule's destination, optionally marking the object
+    /// as pending replication (or failed, if the queue refuses the task).
+    async fn enqueue(
+        &self,
+        storage: &FilesystemStorage,
+        rule: &ReplicationRule,
+        task: ReplicationTask,
+        mark_pending: bool,
+    ) {
+        let Some((target_id, dest_bucket)) = self.destination(&rule.destination) else {
+            tracing::warn!("Replication rule {} refers to unknown destination {}", rule.id, rule.destination);
+            return;
+        };
+        let task = ReplicationTask { dest_bucket, ..task };
+        if mark_pending {
+            record_status(storage, &task, ReplicationStatus::Pending).await;
+        }
+        let item = serde_json::to_value(&task).unwrap_or_default();
+        if let Err(e) = self.targets[&target_id].enqueue(&item).await {
+            tracing::error!("Cannot queue replication of {}/{}: {}", task.bucket, task.key, e);
+            if mark_pending {
+                record_status(storage, &task, ReplicationStatus::Failed).await;
+            }
+        }
+    }
+
+    /// Queue the change if a replication rule on the bucket covers it.
+    /// Changes made by a peer are not sent anywhere again. Failures are
+    /// logged; they never fail the request that caused the change.
+    pub async fn object_changed(&self, storage: &FilesystemStorage, event: &ObjectEvent) {
+        if self.targets.is_empty() || event.replica {
+            return;
+        }
+        let config = match storage.get_replication(&event.bucket).await {
+            Ok(Some(config)) => config,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("Cannot load replication config for {}: {}", event.bucket, e);
+                return;
+            }
+        };
+        let Some(rule) = rule_for(&config, &event.key) else {
+            return;
+        };
+
+        let op = match event.name {
+            EventName::ObjectRemovedDeleteMarkerCreated if rule.delete_markers => TaskOp::Delete,
+            EventName::ObjectRemovedDelete if event.version_id.is_none() && rule.delete_markers => TaskOp::Delete,
+            EventName::ObjectRemovedDelete if event.version_id.is_some() && rule.deletes => TaskOp::DeleteVersion,
+            EventName::ObjectRemovedDelete | EventName::ObjectRemovedDeleteMarkerCreated => return,
+            _ => TaskOp::Put,
+        };
+        let task = ReplicationTask {
+            op,
+            bucket: event.bucket.clone(),
+            key: event.key.clone(),
+            version_id: event.version_id.clone(),
+            last_modified: (op == TaskOp::Delete).then(|| format_mtime(event.time)),
+            dest_bucket: String::new(),
+        };
+        self.enqueue(storage, rule, task, op == TaskOp::Put).await;
+    }
+
+    /// Queue every existing object (all versions and delete markers in
+    /// versioned buckets, oldest first) for the rules' destinations, limited
+    /// to one destination ARN if given. Returns the number queued per ARN.
+    pub async fn resync(
+        &self,
+        storage: &FilesystemStorage,
+        bucket: &str,
+        config: &ReplicationConfig,
+        arn: Option<&str>,
+    ) -> Result<HashMap<String, usize>, StorageError> {
+        let mut objects: Vec<ObjectMeta> = Vec::new();
+        if storage.is_versioned(bucket).await? {
+            objects = storage.list_object_versions(bucket, "").await?;
+            objects.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.version_id.cmp(&b.version_id)));
+        }
+        // Objects written before versioning was enabled have no version entry
+        let unversioned = storage.list_objects(bucket, "").await?;
+        objects.extend(unversioned.into_iter().filter(|m| m.version_id.is_none()));
+
+        let mut queued: HashMap<String, usize> = HashMap::new();
+        for meta in objects {
+            let Some(rule) = rule_for(config, &meta.key) else {
+                continue;
+            };
+            if arn.is_some_and(|arn| arn != rule.destination) {
+                continue;
+            }
+            let op = if !meta.is_delete_marker {
+                TaskOp::Put
+            } else if rule.delete_markers {
+                TaskOp::Delete
+            } else {
+                continue;
+            };
+            let task = ReplicationTask {
+                op,
+                bucket: bucket.to_string(),
+                key: meta.key.clone(),
+                version_id: meta.version_id.clone(),
+                last_modified: (op == TaskOp::Delete).then(|| meta.last_modified.clone()),
+                dest_bucket: String::new(),
+            };
+            // Replicas are sent too (the peer may have lost them) but keep their marker
+            let mark_pending = op == TaskOp::Put && meta.replication_status != Some(ReplicationStatus::Replica);
+            self.enqueue(storage, rule, task, mark_pending).await;
+            *queued.entry(rule.destination.clone()).or_default() += 1;
+        }
+        Ok(queued)
+    }
+}
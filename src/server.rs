@@ -1,12 +1,24 @@
 use axum::Router;
 use axum::routing::get;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
+use crate::api::admin::{get_rate_limits, health_live, health_ready, put_rate_limits, server_info, STARTED_AT};
 use crate::api::console::{console_router, LoginRateLimiter};
 use crate::api::router::s3_router;
+use crate::api::virtual_host::virtual_host_middleware;
+use crate::audit::{audit_middleware, AuditLog};
+use crate::auth::credentials::Credentials;
 use crate::auth::middleware::auth_middleware;
+use crate::auth::oidc::Oidc;
+use crate::auth::session::SessionStore;
 use crate::config::Config;
 use crate::embedded::ui_handler;
+use crate::metrics::{metrics_endpoint, track_s3_request};
+use crate::notify::{Notifier, ObjectEvent};
+use crate::proxy::{client_info_middleware, TrustedProxies};
+use crate::throttle::{throttle_middleware, Throttle};
+use crate::replication::Replicator;
 use crate::storage::filesystem::FilesystemStorage;
 
 #[derive(Clone)]
@@ -14,22 +26,71 @@ pub struct AppState {
     pub storage: Arc<FilesystemStorage>,
     pub config: Arc<Config>,
     pub login_rate_limiter: Arc<LoginRateLimiter>,
+    pub notifier: Arc<Notifier>,
+    pub replicator: Arc<Replicator>,
+    pub audit: Arc<AuditLog>,
+    pub credentials: Arc<Credentials>,
+    pub sessions: Arc<SessionStore>,
+    /// Console single sign-on, when configured.
+    pub oidc: Option<Arc<Oidc>>,
+    pub trusted_proxies: Arc<TrustedProxies>,
+    pub throttle: Arc<Throttle>,
+}
+
+impl AppState {
+    /// Pass every object change reported by storage on to replication and
+    /// bucket notifications, in order.
+    async fn dispatch_changes(self, mut changes: mpsc::UnboundedReceiver<ObjectEvent>) {
+        while let Some(event) = changes.recv().await {
+            self.replicator.object_changed(&self.storage, &event).await;
+            self.notifier.emit(&self.storage, event).await;
+        }
+    }
 }
 
 pub fn build_router(state: AppState) -> Router {
-    let s3_routes = s3_router().layer(axum::middleware::from_fn_with_state(
-        state.clone(),
-        auth_middleware,
-    ));
+    if let Some(changes) = state.storage.watch_changes() {
+        tokio::spawn(state.clone().dispatch_changes(changes));
+    }
 
-    Router::new()
+    let s3_routes = s3_router()
+        .layer(axum::middleware::from_fn_with_state(state.clone(), throttle_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ))
+        .layer(axum::middleware::from_fn(track_s3_request));
+
+    let admin_routes = Router::new()
+        .route("/minio/admin/v3/info", get(server_info))
+        .route("/minio/admin/v3/rate-limits", get(get_rate_limits).put(put_rate_limits))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    std::sync::LazyLock::force(&STARTED_AT);
+    let app = Router::new()
         .nest("/api", console_router(state.clone()))
+        // MinIO-compatible probe paths, reachable without credentials.
+        .route("/minio/health/live", get(health_live).head(health_live))
+        .route("/minio/health/ready", get(health_ready).head(health_ready))
+        .merge(admin_routes)
         .route("/ui", get(ui_handler))
         .route("/ui/", get(ui_handler))
         .route("/ui/{*path}", get(ui_handler))
         .merge(s3_routes)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), metrics_endpoint))
         .layer(axum::middleware::from_fn(request_id_middleware))
-        .with_state(state)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), audit_middleware))
+        .with_state(state.clone());
+
+    // Host-based bucket rewriting has to happen before routing, so it wraps
+    // the whole router as a fallback service.
+    Router::new()
+        .fallback_service(app)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), virtual_host_middleware))
+        .layer(axum::middleware::from_fn_with_state(state, client_info_middleware))
 }
 
 async fn request_id_middleware(
@@ -1,4 +1,5 @@
 use super::ChunkManifest;
+use crate::metrics::METRICS;
 use sha2::{Digest, Sha256};
 use std::io;
 use std::path::{Path, PathBuf};
@@ -138,7 +139,9 @@ impl VerifiedChunkReader {
                         &self.chunks_dir,
                         &self.manifest,
                         self.current_chunk,
-                    )?;
+                    )
+                    .inspect_err(|_| METRICS.ec_reconstruction(false))?;
+                    METRICS.ec_reconstruction(true);
                     self.buf = data;
                     self.buf_pos = self.skip_bytes;
                     self.skip_bytes = 0;
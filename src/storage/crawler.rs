@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::{Instant, MissedTickBehavior};
+
+use super::StorageError;
+use super::filesystem::FilesystemStorage;
+
+/// Recount the usage of every bucket at startup and then every `interval`
+/// (unless zero), correcting any drift in the figures tracked as objects
+/// change.
+pub fn spawn(storage: Arc<FilesystemStorage>, interval: Duration) {
+    tokio::spawn(async move {
+        crawl(&storage).await;
+        if interval.is_zero() {
+            return;
+        }
+        let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            crawl(&storage).await;
+        }
+    });
+}
+
+async fn crawl(storage: &FilesystemStorage) {
+    let buckets = match storage.list_buckets().await {
+        Ok(buckets) => buckets,
+        Err(e) => {
+            tracing::warn!("Usage crawler cannot list buckets: {}", e);
+            return;
+        }
+    };
+    for bucket in buckets {
+        match storage.recalculate_usage(&bucket.name).await {
+            // Deleted since it was listed
+            Ok(_) | Err(StorageError::NotFound(_)) => {}
+            Err(e) => tracing::warn!("Usage crawler failed on bucket {}: {}", bucket.name, e),
+        }
+    }
+}
@@ -1,20 +1,29 @@
-use super::{BucketMeta, ByteStream, ChecksumAlgorithm, ChunkInfo, ChunkKind, ChunkManifest, DeleteResult, MultipartUploadMeta, ObjectMeta, PartMeta, PutResult, StorageError};
+use super::{BucketLogging, BucketMeta, BucketQuota, BucketUsage, ByteStream, ChecksumAlgorithm, ChecksumType, ChunkInfo, ChunkKind, ChunkManifest, CompletedPart, DeleteResult, ListingEntry, MultipartUploadMeta, NotificationConfig, ObjectMeta, PartInfo, PartMeta, PartsManifest, PutResult, ReplicaSource, ReplicationConfig, ReplicationStatus, StorageError};
 use super::chunk_reader::VerifiedChunkReader;
+use super::composite_reader::CompositeReader;
+use crate::notify::{EventName, ObjectEvent};
 use base64::Engine;
 use md5::{Digest, Md5};
 use rand::RngExt;
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
 
 enum ChecksumHasher {
     Crc32(crc32fast::Hasher),
     Crc32c(u32),
     Sha1(sha1::Sha1),
     Sha256(sha2::Sha256),
+    Crc64Nvme(crc::Digest<'static, u64>),
 }
 
+static CRC64_NVME: crc::Crc<u64> = crc::Crc::<u64>::new(&crc::CRC_64_NVME);
+
 impl ChecksumHasher {
     fn new(algo: ChecksumAlgorithm) -> Self {
         match algo {
@@ -22,6 +31,7 @@ impl ChecksumHasher {
             ChecksumAlgorithm::CRC32C => Self::Crc32c(0),
             ChecksumAlgorithm::SHA1 => Self::Sha1(<sha1::Sha1 as Digest>::new()),
             ChecksumAlgorithm::SHA256 => Self::Sha256(<sha2::Sha256 as Digest>::new()),
+            ChecksumAlgorithm::CRC64NVME => Self::Crc64Nvme(CRC64_NVME.digest()),
         }
     }
 
@@ -31,6 +41,7 @@ impl ChecksumHasher {
             Self::Crc32c(v) => *v = crc32c::crc32c_append(*v, data),
             Self::Sha1(h) => Digest::update(h, data),
             Self::Sha256(h) => Digest::update(h, data),
+            Self::Crc64Nvme(h) => h.update(data),
         }
     }
 
@@ -41,16 +52,187 @@ impl ChecksumHasher {
             Self::Crc32c(v) => b64.encode(v.to_be_bytes()),
             Self::Sha1(h) => b64.encode(Digest::finalize(h)),
             Self::Sha256(h) => b64.encode(Digest::finalize(h)),
+            Self::Crc64Nvme(h) => b64.encode(h.finalize().to_be_bytes()),
         }
     }
 
 }
 
+/// Checksum of the concatenated raw part checksums, suffixed with `-N`.
+/// Returns None when no part carried a checksum.
+fn composite_checksum(algo: ChecksumAlgorithm, parts: &[PartMeta]) -> Option<String> {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let mut raw_checksums = Vec::new();
+    for part in parts {
+        if let Some(ref val) = part.checksum_value
+            && let Ok(raw) = b64.decode(val)
+        {
+            raw_checksums.extend_from_slice(&raw);
+        }
+    }
+    if raw_checksums.is_empty() {
+        return None;
+    }
+    let mut composite_hasher = ChecksumHasher::new(algo);
+    composite_hasher.update(&raw_checksums);
+    Some(format!("{}-{}", composite_hasher.finalize_base64(), parts.len()))
+}
+
+/// Checksum of the whole object, derived by combining the part CRCs without
+/// re-reading any data. Returns None unless every part carries a checksum.
+fn full_object_checksum(algo: ChecksumAlgorithm, parts: &[PartMeta]) -> Option<String> {
+    // Reflected polynomials; all three CRCs use init = xorout = all ones,
+    // which is what the zlib-style combine below assumes.
+    let (poly, width) = match algo {
+        ChecksumAlgorithm::CRC32 => (0xEDB8_8320u64, 32),
+        ChecksumAlgorithm::CRC32C => (0x82F6_3B78u64, 32),
+        ChecksumAlgorithm::CRC64NVME => (0x9A6C_9329_AC4B_C9B5u64, 64),
+        _ => return None,
+    };
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let mut combined: Option<u64> = None;
+    for part in parts {
+        let raw = b64.decode(part.checksum_value.as_ref()?).ok()?;
+        if raw.len() != width / 8 {
+            return None;
+        }
+        let crc = raw.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        combined = Some(match combined {
+            None => crc,
+            Some(prev) => crc_combine(poly, width, prev, crc, part.size),
+        });
+    }
+    let value = combined?;
+    Some(match width {
+        32 => b64.encode((value as u32).to_be_bytes()),
+        _ => b64.encode(value.to_be_bytes()),
+    })
+}
+
+/// CRC of `A || B` from crc(A), crc(B) and len(B), using zlib's GF(2) matrix method.
+fn crc_combine(poly: u64, width: usize, mut crc1: u64, crc2: u64, mut len2: u64) -> u64 {
+    fn times(mat: &[u64], mut vec: u64) -> u64 {
+        let mut sum = 0;
+        let mut i = 0;
+        while vec != 0 {
+            if vec & 1 != 0 {
+                sum ^= mat[i];
+            }
+            vec >>= 1;
+            i += 1;
+        }
+        sum
+    }
+    fn square(dst: &mut [u64], mat: &[u64]) {
+        for n in 0..mat.len() {
+            dst[n] = times(mat, mat[n]);
+        }
+    }
+
+    if len2 == 0 {
+        return crc1;
+    }
+    // Operator for one zero bit, then two and four zero bits
+    let mut odd = vec![0u64; width];
+    let mut even = vec![0u64; width];
+    odd[0] = poly;
+    for (n, row) in odd.iter_mut().enumerate().skip(1) {
+        *row = 1 << (n - 1);
+    }
+    square(&mut even, &odd);
+    square(&mut odd, &even);
+
+    // Apply len2 zero bytes to crc1, one bit of len2 at a time
+    loop {
+        square(&mut even, &odd);
+        if len2 & 1 != 0 {
+            crc1 = times(&even, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+        square(&mut odd, &even);
+        if len2 & 1 != 0 {
+            crc1 = times(&odd, crc1);
+        }
+        len2 >>= 1;
+        if len2 == 0 {
+            break;
+        }
+    }
+    crc1 ^ crc2
+}
+
 pub struct FilesystemStorage {
     buckets_dir: PathBuf,
     erasure_coding: bool,
     chunk_size: u64,
     parity_shards: u32,
+    /// Usage per bucket, loaded from `.usage.json` on first use and kept up
+    /// to date by every write and delete.
+    usage: tokio::sync::Mutex<HashMap<String, BucketUsage>>,
+    /// Quota claimed by writes that are still in progress, per bucket.
+    reserved: Reservations,
+    key_locks: KeyLocks,
+    /// Where object changes are reported, once someone watches them.
+    changes: OnceLock<mpsc::UnboundedSender<ObjectEvent>>,
+}
+
+/// Striped locks that serialise writes to the same key, so each write's
+/// usage change is measured against the state the previous one left.
+struct KeyLocks([tokio::sync::Mutex<()>; 64]);
+
+impl KeyLocks {
+    fn new() -> Self {
+        Self(std::array::from_fn(|_| tokio::sync::Mutex::new(())))
+    }
+
+    async fn lock(&self, bucket: &str, name: &str) -> tokio::sync::MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        (bucket, name).hash(&mut hasher);
+        self.0[hasher.finish() as usize % self.0.len()].lock().await
+    }
+}
+
+/// Quota claimed by writes in flight, per bucket.
+type Reservations = Arc<Mutex<HashMap<String, BucketUsage>>>;
+
+/// Room in a bucket's quota held for one write until it has been counted in
+/// the bucket's usage; released on drop.
+#[must_use]
+pub struct QuotaReservation {
+    reserved: Option<(Reservations, String, BucketUsage)>,
+}
+
+impl Drop for QuotaReservation {
+    fn drop(&mut self) {
+        if let Some((reserved, bucket, claim)) = self.reserved.take() {
+            let mut reserved = reserved.lock().unwrap();
+            if let Some(total) = reserved.get_mut(&bucket) {
+                *total = total.replace(claim, BucketUsage::default());
+                if *total == BucketUsage::default() {
+                    reserved.remove(&bucket);
+                }
+            }
+        }
+    }
+}
+
+/// Parity bytes listed in an erasure-coding manifest.
+async fn parity_bytes(manifest_path: &Path) -> u64 {
+    let Ok(data) = fs::read_to_string(manifest_path).await else {
+        return 0;
+    };
+    let Ok(manifest) = serde_json::from_str::<ChunkManifest>(&data) else {
+        return 0;
+    };
+    manifest
+        .chunks
+        .iter()
+        .filter(|c| c.kind == ChunkKind::Parity)
+        .map(|c| c.size)
+        .sum()
 }
 
 /// Validate that an object key does not contain path traversal components.
@@ -90,11 +272,61 @@ fn validate_upload_id(upload_id: &str) -> Result<(), StorageError> {
     Ok(())
 }
 
+/// A peer-supplied version ID becomes part of file names.
+fn validate_replica_source(source: &ReplicaSource) -> Result<(), StorageError> {
+    if let Some(vid) = &source.version_id
+        && (vid.is_empty()
+            || vid.len() > 64
+            || !vid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    {
+        return Err(StorageError::InvalidKey(format!("Invalid version ID: {}", vid)));
+    }
+    Ok(())
+}
+
+/// The ETag a replicated object keeps: the source's, since multipart ETags
+/// cannot be recomputed from the data. Single-part ETags must match the data.
+fn replica_etag(computed: String, replica: Option<&ReplicaSource>) -> Result<String, StorageError> {
+    match replica.and_then(|r| r.etag.as_ref()) {
+        Some(etag) if etag.contains('-') => Ok(etag.clone()),
+        Some(etag) if *etag != computed => Err(StorageError::ChecksumMismatch(format!(
+            "expected ETag {}, got {}",
+            etag, computed
+        ))),
+        _ => Ok(computed),
+    }
+}
+
 impl FilesystemStorage {
     pub async fn new(data_dir: &str, erasure_coding: bool, chunk_size: u64, parity_shards: u32) -> Result<Self, anyhow::Error> {
         let buckets_dir = Path::new(data_dir).join("buckets");
         fs::create_dir_all(&buckets_dir).await?;
-        Ok(Self { buckets_dir, erasure_coding, chunk_size, parity_shards })
+        Ok(Self {
+            buckets_dir,
+            erasure_coding,
+            chunk_size,
+            parity_shards,
+            usage: tokio::sync::Mutex::new(HashMap::new()),
+            reserved: Arc::new(Mutex::new(HashMap::new())),
+            key_locks: KeyLocks::new(),
+            changes: OnceLock::new(),
+        })
+    }
+
+    /// Receive an event for every object written or deleted from now on, in
+    /// the order the changes were made. There is only one receiver; later
+    /// calls return `None`.
+    pub fn watch_changes(&self) -> Option<mpsc::UnboundedReceiver<ObjectEvent>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.changes.set(sender).ok().map(|()| receiver)
+    }
+
+    /// Report an object change. Called with the key lock held, so events for
+    /// one key arrive in the order the changes were made.
+    fn changed(&self, event: ObjectEvent) {
+        if let Some(changes) = self.changes.get() {
+            let _ = changes.send(event);
+        }
     }
 
     // --- Bucket operations ---
@@ -132,10 +364,15 @@ impl FilesystemStorage {
             return Err(StorageError::BucketNotEmpty);
         }
 
+        // Usage is saved under this lock, and only while the bucket exists
+        let mut usage = self.usage.lock().await;
+
         // Remove metadata and internal dirs before the bucket dir itself.
         // Use remove_dir (not remove_dir_all) for the bucket dir so it fails
         // atomically if a concurrent put_object added files in between.
         let _ = fs::remove_file(bucket_dir.join(".bucket.json")).await;
+        let _ = fs::remove_file(bucket_dir.join(".usage.json")).await;
+        usage.remove(name);
         let _ = fs::remove_dir_all(bucket_dir.join(".uploads")).await;
         let _ = fs::remove_dir_all(bucket_dir.join(".versions")).await;
         match fs::remove_dir(&bucket_dir).await {
@@ -149,6 +386,10 @@ impl FilesystemStorage {
                     created_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
                     region: String::new(),
                     versioning: false,
+                    notification: None,
+                    replication: None,
+                    logging: None,
+                    quota: None,
                 };
                 let _ = fs::write(
                     bucket_dir.join(".bucket.json"),
@@ -220,6 +461,28 @@ impl FilesystemStorage {
         ec_dir.is_dir()
     }
 
+    /// Directory holding the part files of a composite multipart object.
+    fn parts_dir(&self, bucket: &str, key: &str) -> PathBuf {
+        self.buckets_dir
+            .join(bucket)
+            .join(format!("{}.mp", key))
+    }
+
+    fn is_composite_path(parts_dir: &Path) -> bool {
+        parts_dir.is_dir()
+    }
+
+    async fn read_parts_manifest(&self, parts_dir: &Path, key: &str) -> Result<PartsManifest, StorageError> {
+        let data = fs::read_to_string(parts_dir.join("manifest.json")).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                StorageError::NotFound(key.to_string())
+            } else {
+                StorageError::Io(e)
+            }
+        })?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
     async fn read_manifest(&self, bucket: &str, key: &str) -> Result<ChunkManifest, StorageError> {
         let path = self.manifest_path(bucket, key);
         let data = fs::read_to_string(&path).await.map_err(|e| {
@@ -259,8 +522,73 @@ impl FilesystemStorage {
         bucket: &str,
         key: &str,
         content_type: &str,
-        mut body: ByteStream,
+        body: ByteStream,
+        checksum: Option<(ChecksumAlgorithm, Option<String>)>,
+    ) -> Result<PutResult, StorageError> {
+        self.put_object_as(EventName::ObjectCreatedPut, bucket, key, content_type, body, checksum).await
+    }
+
+    /// `put_object`, reported as `event` (a copy or a browser form upload).
+    pub async fn put_object_as(
+        &self,
+        event: EventName,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        body: ByteStream,
+        checksum: Option<(ChecksumAlgorithm, Option<String>)>,
+    ) -> Result<PutResult, StorageError> {
+        let _lock = self.key_locks.lock(bucket, key).await;
+        let before = self.key_usage(bucket, key).await;
+        let result = self.write_object(bucket, key, content_type, body, checksum, None).await;
+        let after = self.key_usage(bucket, key).await;
+        self.record_usage(bucket, before, after).await;
+        if let Ok(result) = &result {
+            self.changed(ObjectEvent::created(event, bucket, key, result));
+        }
+        result
+    }
+
+    /// Store an object received from a replication peer, keeping the source's
+    /// version ID, timestamp and ETag. Returns `None` when the write is stale:
+    /// the version is already here, or (unversioned) the current object is newer.
+    pub async fn put_object_replica(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        body: ByteStream,
+        source: &ReplicaSource,
+    ) -> Result<Option<PutResult>, StorageError> {
+        validate_key(key)?;
+        validate_replica_source(source)?;
+        let _lock = self.key_locks.lock(bucket, key).await;
+        if !self.replica_is_newer(bucket, key, source).await? {
+            return Ok(None);
+        }
+        let before = self.key_usage(bucket, key).await;
+        let result = self.write_object(bucket, key, content_type, body, None, Some(source)).await;
+        // An older version arriving late must not replace the current one
+        let restored = match &result {
+            Ok(result) => self.restore_latest_version(bucket, key, result.version_id.as_deref()).await,
+            Err(_) => Ok(()),
+        };
+        let after = self.key_usage(bucket, key).await;
+        self.record_usage(bucket, before, after).await;
+        restored?;
+        let result = result?;
+        self.changed(ObjectEvent::created(EventName::ObjectCreatedPut, bucket, key, &result).with_replica(true));
+        Ok(Some(result))
+    }
+
+    async fn write_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        body: ByteStream,
         checksum: Option<(ChecksumAlgorithm, Option<String>)>,
+        replica: Option<&ReplicaSource>,
     ) -> Result<PutResult, StorageError> {
         validate_key(key)?;
 
@@ -269,10 +597,29 @@ impl FilesystemStorage {
             return self.put_folder_marker(bucket, key).await;
         }
 
-        if self.erasure_coding {
-            return self.put_object_chunked(bucket, key, content_type, body, checksum.as_ref().map(|(a, _)| *a)).await;
-        }
+        let result = if self.erasure_coding {
+            self.put_object_chunked(bucket, key, content_type, body, checksum.as_ref().map(|(a, _)| *a), replica)
+                .await?
+        } else {
+            self.write_object_file(bucket, key, content_type, body, checksum, replica).await?
+        };
 
+        // A previous multipart upload may have left a composite object behind.
+        // It stays readable until the new object's metadata has replaced it.
+        let _ = fs::remove_dir_all(self.parts_dir(bucket, key)).await;
+        Ok(result)
+    }
+
+    /// Write a plain (not erasure-coded) object body and its metadata.
+    async fn write_object_file(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: &str,
+        mut body: ByteStream,
+        checksum: Option<(ChecksumAlgorithm, Option<String>)>,
+        replica: Option<&ReplicaSource>,
+    ) -> Result<PutResult, StorageError> {
         let obj_path = self.object_path(bucket, key);
         if let Some(parent) = obj_path.parent() {
             fs::create_dir_all(parent).await?;
@@ -298,8 +645,7 @@ impl FilesystemStorage {
         }
         file.flush().await?;
 
-        let etag = hex::encode(hasher.finalize());
-        let etag_quoted = format!("\"{}\"", etag);
+        let etag_quoted = replica_etag(format!("\"{}\"", hex::encode(hasher.finalize())), replica)?;
 
         // Validate and compute checksum
         let (checksum_algorithm, checksum_value) = if let Some((algo, expected)) = checksum {
@@ -316,26 +662,22 @@ impl FilesystemStorage {
             (None, None)
         };
 
-        let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-
-        let versioned = self.is_versioned(bucket).await.unwrap_or(false);
-        let version_id = if versioned {
-            Some(Self::generate_version_id())
-        } else {
-            None
-        };
+        let (version_id, last_modified) = self.new_version_identity(bucket, replica).await;
 
         let meta = ObjectMeta {
             key: key.to_string(),
             size,
             etag: etag_quoted.clone(),
             content_type: content_type.to_string(),
-            last_modified: now,
+            last_modified,
             version_id: version_id.clone(),
             is_delete_marker: false,
             storage_format: None,
             checksum_algorithm,
             checksum_value: checksum_value.clone(),
+            checksum_type: checksum_algorithm.map(|_| ChecksumType::FullObject),
+            parts: None,
+            replication_status: replica.map(|_| ReplicationStatus::Replica),
         };
 
         let meta_path = self.meta_path(bucket, key);
@@ -345,7 +687,7 @@ impl FilesystemStorage {
         let json = serde_json::to_string_pretty(&meta)?;
         fs::write(&meta_path, json).await?;
 
-        if versioned {
+        if version_id.is_some() {
             self.write_version(bucket, key, &meta, &obj_path).await?;
         }
 
@@ -355,6 +697,7 @@ impl FilesystemStorage {
             version_id,
             checksum_algorithm,
             checksum_value,
+            checksum_type: meta.checksum_type,
         })
     }
 
@@ -365,6 +708,7 @@ impl FilesystemStorage {
         content_type: &str,
         mut body: ByteStream,
         checksum_algo: Option<ChecksumAlgorithm>,
+        replica: Option<&ReplicaSource>,
     ) -> Result<PutResult, StorageError> {
         let ec_dir = self.ec_dir(bucket, key);
         if let Some(parent) = ec_dir.parent() {
@@ -434,18 +778,10 @@ impl FilesystemStorage {
         let manifest_json = serde_json::to_string_pretty(&manifest)?;
         fs::write(self.manifest_path(bucket, key), manifest_json).await?;
 
-        let etag = hex::encode(md5_hasher.finalize());
-        let etag_quoted = format!("\"{}\"", etag);
+        let etag_quoted = replica_etag(format!("\"{}\"", hex::encode(md5_hasher.finalize())), replica)?;
         let checksum_value = checksum_hasher.map(|h| h.finalize_base64());
 
-        let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-
-        let versioned = self.is_versioned(bucket).await.unwrap_or(false);
-        let version_id = if versioned {
-            Some(Self::generate_version_id())
-        } else {
-            None
-        };
+        let (version_id, last_modified) = self.new_version_identity(bucket, replica).await;
 
         let storage_format = if has_parity { "chunked-v2" } else { "chunked-v1" };
         let meta = ObjectMeta {
@@ -453,12 +789,15 @@ impl FilesystemStorage {
             size: total_size,
             etag: etag_quoted.clone(),
             content_type: content_type.to_string(),
-            last_modified: now,
+            last_modified,
             version_id: version_id.clone(),
             is_delete_marker: false,
             storage_format: Some(storage_format.to_string()),
             checksum_algorithm: checksum_algo,
             checksum_value: checksum_value.clone(),
+            checksum_type: checksum_algo.map(|_| ChecksumType::FullObject),
+            parts: None,
+            replication_status: replica.map(|_| ReplicationStatus::Replica),
         };
 
         let meta_path = self.meta_path(bucket, key);
@@ -467,7 +806,7 @@ impl FilesystemStorage {
         }
         fs::write(&meta_path, serde_json::to_string_pretty(&meta)?).await?;
 
-        if versioned {
+        if version_id.is_some() {
             self.write_version_chunked(bucket, key, &meta).await?;
         }
 
@@ -477,6 +816,7 @@ impl FilesystemStorage {
             version_id,
             checksum_algorithm: checksum_algo,
             checksum_value,
+            checksum_type: meta.checksum_type,
         })
     }
 
@@ -571,6 +911,7 @@ impl FilesystemStorage {
         upload_id: &str,
         upload_meta: &MultipartUploadMeta,
         selected: &[PartMeta],
+        checksum_value: Option<String>,
     ) -> Result<PutResult, StorageError> {
         let key = &upload_meta.key;
         let ec_dir = self.ec_dir(bucket, key);
@@ -642,28 +983,10 @@ impl FilesystemStorage {
 
         let etag = format!("\"{}-{}\"", hex::encode(etag_hasher.finalize()), selected.len());
 
-        // Compute composite checksum if algorithm was specified
-        let (checksum_algorithm, checksum_value) = if let Some(algo) = upload_meta.checksum_algorithm {
-            let b64 = base64::engine::general_purpose::STANDARD;
-            let mut raw_checksums = Vec::new();
-            for part in selected {
-                if let Some(ref val) = part.checksum_value {
-                    if let Ok(raw) = b64.decode(val) {
-                        raw_checksums.extend_from_slice(&raw);
-                    }
-                }
-            }
-            if !raw_checksums.is_empty() {
-                let mut composite_hasher = ChecksumHasher::new(algo);
-                composite_hasher.update(&raw_checksums);
-                let composite = format!("{}-{}", composite_hasher.finalize_base64(), selected.len());
-                (Some(algo), Some(composite))
-            } else {
-                (Some(algo), None)
-            }
-        } else {
-            (None, None)
-        };
+        let checksum_algorithm = upload_meta.checksum_algorithm;
+        let checksum_type = checksum_value
+            .as_ref()
+            .map(|_| upload_meta.checksum_type.unwrap_or(ChecksumType::Composite));
 
         let storage_format = if has_parity { "chunked-v2" } else { "chunked-v1" };
         let object_meta = ObjectMeta {
@@ -677,6 +1000,9 @@ impl FilesystemStorage {
             storage_format: Some(storage_format.to_string()),
             checksum_algorithm,
             checksum_value: checksum_value.clone(),
+            checksum_type,
+            parts: Some(selected.iter().map(PartInfo::from).collect()),
+            replication_status: None,
         };
 
         let meta_path = self.meta_path(bucket, key);
@@ -692,6 +1018,7 @@ impl FilesystemStorage {
             version_id: None,
             checksum_algorithm,
             checksum_value,
+            checksum_type,
         })
     }
 
@@ -725,6 +1052,9 @@ impl FilesystemStorage {
             storage_format: None,
             checksum_algorithm: None,
             checksum_value: None,
+            checksum_type: None,
+            parts: None,
+            replication_status: None,
         };
 
         let meta_path = folder_dir.join(".folder.meta.json");
@@ -737,6 +1067,7 @@ impl FilesystemStorage {
             version_id: None,
             checksum_algorithm: None,
             checksum_value: None,
+            checksum_type: None,
         })
     }
 
@@ -753,6 +1084,12 @@ impl FilesystemStorage {
             let reader = VerifiedChunkReader::new(ec_dir, manifest);
             return Ok((Box::pin(reader), meta));
         }
+        let parts_dir = self.parts_dir(bucket, key);
+        if Self::is_composite_path(&parts_dir) {
+            let manifest = self.read_parts_manifest(&parts_dir, key).await?;
+            let reader = CompositeReader::new(parts_dir, manifest);
+            return Ok((Box::pin(reader), meta));
+        }
         let obj_path = self.object_path(bucket, key);
         let file = fs::File::open(&obj_path).await.map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
@@ -780,6 +1117,12 @@ impl FilesystemStorage {
             let reader = VerifiedChunkReader::with_range(ec_dir, manifest, offset, length);
             return Ok((Box::pin(reader), meta));
         }
+        let parts_dir = self.parts_dir(bucket, key);
+        if Self::is_composite_path(&parts_dir) {
+            let manifest = self.read_parts_manifest(&parts_dir, key).await?;
+            let reader = CompositeReader::with_range(parts_dir, manifest, offset, length);
+            return Ok((Box::pin(reader), meta));
+        }
         let obj_path = self.object_path(bucket, key);
         let mut file = fs::File::open(&obj_path).await.map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
@@ -809,10 +1152,21 @@ impl FilesystemStorage {
         key: &str,
     ) -> Result<DeleteResult, StorageError> {
         validate_key(key)?;
+        let _lock = self.key_locks.lock(bucket, key).await;
+        let before = self.key_usage(bucket, key).await;
+        let result = self.remove_object(bucket, key).await;
+        let after = self.key_usage(bucket, key).await;
+        self.record_usage(bucket, before, after).await;
+        if let Ok(result) = &result {
+            self.changed(ObjectEvent::removed(bucket, key, result));
+        }
+        result
+    }
 
+    async fn remove_object(&self, bucket: &str, key: &str) -> Result<DeleteResult, StorageError> {
         let versioned = self.is_versioned(bucket).await.unwrap_or(false);
         if versioned {
-            return self.write_delete_marker(bucket, key).await;
+            return self.write_delete_marker(bucket, key, None).await;
         }
 
         let obj_path = self.object_path(bucket, key);
@@ -822,6 +1176,7 @@ impl FilesystemStorage {
         let _ = fs::remove_file(&obj_path).await;
         let _ = fs::remove_file(&meta_path).await;
         let _ = fs::remove_dir_all(&ec_dir).await;
+        let _ = fs::remove_dir_all(self.parts_dir(bucket, key)).await;
 
         // Clean up empty parent directories (but not the bucket dir itself)
         let bucket_dir = self.buckets_dir.join(bucket);
@@ -843,6 +1198,40 @@ impl FilesystemStorage {
         })
     }
 
+    /// Apply a delete from a replication peer: a delete marker with the
+    /// source's version ID in versioned buckets, otherwise a plain delete.
+    /// Returns `None` when the write is stale, like `put_object_replica`.
+    pub async fn delete_object_replica(
+        &self,
+        bucket: &str,
+        key: &str,
+        source: &ReplicaSource,
+    ) -> Result<Option<DeleteResult>, StorageError> {
+        validate_key(key)?;
+        validate_replica_source(source)?;
+        let _lock = self.key_locks.lock(bucket, key).await;
+        if !self.replica_is_newer(bucket, key, source).await? {
+            return Ok(None);
+        }
+        let before = self.key_usage(bucket, key).await;
+        let result = if self.is_versioned(bucket).await.unwrap_or(false) {
+            self.remove_object_replica(bucket, key, source).await
+        } else {
+            self.remove_object(bucket, key).await
+        };
+        let after = self.key_usage(bucket, key).await;
+        self.record_usage(bucket, before, after).await;
+        let result = result?;
+        self.changed(ObjectEvent::removed(bucket, key, &result).with_replica(true));
+        Ok(Some(result))
+    }
+
+    async fn remove_object_replica(&self, bucket: &str, key: &str, source: &ReplicaSource) -> Result<DeleteResult, StorageError> {
+        let result = self.write_delete_marker(bucket, key, Some(source)).await?;
+        self.restore_latest_version(bucket, key, result.version_id.as_deref()).await?;
+        Ok(result)
+    }
+
     pub async fn list_objects(
         &self,
         bucket: &str,
@@ -856,14 +1245,105 @@ impl FilesystemStorage {
         Ok(results)
     }
 
+    /// Keys and subprefixes one `/` level below `prefix`, in no particular
+    /// order. Only the directory holding the prefix is read, and no metadata.
+    pub async fn list_level(
+        &self,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Vec<ListingEntry>, StorageError> {
+        let parent = &prefix[..prefix.rfind('/').map_or(0, |i| i + 1)];
+        let dir = self.buckets_dir.join(bucket).join(parent.trim_end_matches('/'));
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut results = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let fname = entry.file_name().to_string_lossy().to_string();
+            if fname.ends_with(".meta.json")
+                || fname == ".bucket.json"
+                || fname == ".usage.json"
+                || fname == ".uploads"
+                || fname == ".versions"
+                || fname == ".folder"
+            {
+                continue;
+            }
+            let is_dir = entry.file_type().await?.is_dir();
+            let name = match fname.strip_suffix(".ec").or_else(|| fname.strip_suffix(".mp")) {
+                Some(key) if is_dir => key,
+                _ => fname.as_str(),
+            };
+            let key = format!("{}{}", parent, name);
+            if !key.starts_with(prefix) {
+                continue;
+            }
+            if name.len() < fname.len() || !is_dir {
+                results.push(ListingEntry::Object(key));
+            } else if self.has_objects(&entry.path()).await? {
+                // Directories left holding only old versions aren't listed
+                results.push(ListingEntry::Prefix(format!("{}/", key)));
+            }
+        }
+        // A key can have both an EC and a composite part directory
+        results.sort();
+        results.dedup();
+        Ok(results)
+    }
+
+    /// Whether nothing but its own folder marker is stored below `prefix`.
+    pub async fn prefix_is_empty(&self, bucket: &str, prefix: &str) -> Result<bool, StorageError> {
+        let dir = self.buckets_dir.join(bucket).join(prefix.trim_end_matches('/'));
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let fname = entry.file_name().to_string_lossy().to_string();
+            if fname == ".folder" || fname == ".versions" || fname.ends_with(".meta.json") {
+                continue;
+            }
+            if !entry.file_type().await?.is_dir() || self.has_objects(&entry.path()).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     pub async fn create_multipart_upload(
         &self,
         bucket: &str,
         key: &str,
         content_type: &str,
         checksum_algorithm: Option<ChecksumAlgorithm>,
+        checksum_type: Option<ChecksumType>,
     ) -> Result<MultipartUploadMeta, StorageError> {
         validate_key(key)?;
+        // CRC64NVME only exists as a full-object checksum; SHA digests can't be combined
+        let checksum_type = match (checksum_algorithm, checksum_type) {
+            (None, Some(_)) => {
+                return Err(StorageError::InvalidKey(
+                    "checksum type requires a checksum algorithm".into(),
+                ));
+            }
+            (None, None) => None,
+            (Some(algo), Some(ChecksumType::FullObject)) if !algo.supports_full_object() => {
+                return Err(StorageError::InvalidKey(
+                    "full object checksum type is not supported for this algorithm".into(),
+                ));
+            }
+            (Some(ChecksumAlgorithm::CRC64NVME), Some(ChecksumType::Composite)) => {
+                return Err(StorageError::InvalidKey(
+                    "composite checksum type is not supported for CRC64NVME".into(),
+                ));
+            }
+            (Some(ChecksumAlgorithm::CRC64NVME), None) => Some(ChecksumType::FullObject),
+            (Some(_), t) => Some(t.unwrap_or(ChecksumType::Composite)),
+        };
         let upload_id = uuid::Uuid::new_v4().to_string();
         let upload_dir = self.upload_dir(bucket, &upload_id);
         fs::create_dir_all(&upload_dir).await?;
@@ -875,10 +1355,16 @@ impl FilesystemStorage {
             content_type: content_type.to_string(),
             initiated: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
             checksum_algorithm,
+            checksum_type,
         };
 
         let meta_json = serde_json::to_string_pretty(&meta)?;
         fs::write(self.upload_meta_path(bucket, &upload_id), meta_json).await?;
+        let started = BucketUsage {
+            multipart_uploads: 1,
+            ..Default::default()
+        };
+        self.record_usage(bucket, BucketUsage::default(), started).await;
         Ok(meta)
     }
 
@@ -899,6 +1385,26 @@ impl FilesystemStorage {
             return Err(StorageError::UploadNotFound(upload_id.to_string()));
         }
 
+        // Parts of an upload created with a checksum algorithm always get that
+        // checksum, whether or not the client sent one.
+        let upload_meta = self.read_upload_meta(bucket, upload_id).await?;
+        let checksum = match (upload_meta.checksum_algorithm, checksum) {
+            (Some(expected), Some((algo, _))) if algo != expected => {
+                return Err(StorageError::InvalidKey(format!(
+                    "checksum algorithm {:?} does not match CreateMultipartUpload's {:?}",
+                    algo, expected
+                )));
+            }
+            (Some(expected), None) => Some((expected, None)),
+            (_, checksum) => checksum,
+        };
+
+        // Uploading a part number again replaces the earlier part
+        let _lock = self.key_locks.lock(bucket, &format!("{}/{}", upload_id, part_number)).await;
+        let replaced_size = match self.read_part_meta(bucket, upload_id, part_number).await {
+            Ok(previous) => previous.size,
+            Err(_) => 0,
+        };
         let part_path = self.part_path(bucket, upload_id, part_number);
         let mut file = fs::File::create(&part_path).await?;
         let mut hasher = Md5::new();
@@ -957,6 +1463,11 @@ impl FilesystemStorage {
             let _ = fs::remove_file(&part_path).await;
             return Err(e.into());
         }
+        let part_usage = |size| BucketUsage {
+            multipart_size: size,
+            ..Default::default()
+        };
+        self.record_usage(bucket, part_usage(replaced_size), part_usage(size)).await;
         Ok(meta)
     }
 
@@ -964,9 +1475,31 @@ impl FilesystemStorage {
         &self,
         bucket: &str,
         upload_id: &str,
-        parts: &[(u32, String)],
+        parts: &[CompletedPart],
+        expected_checksum: Option<&str>,
     ) -> Result<PutResult, StorageError> {
         validate_upload_id(upload_id)?;
+        let key = self.read_upload_meta(bucket, upload_id).await?.key;
+        let _lock = self.key_locks.lock(bucket, &key).await;
+        let mut before = self.key_usage(bucket, &key).await;
+        before.add(self.upload_usage(bucket, upload_id).await);
+        let result = self.assemble_multipart_upload(bucket, upload_id, parts, expected_checksum).await;
+        let mut after = self.key_usage(bucket, &key).await;
+        after.add(self.upload_usage(bucket, upload_id).await);
+        self.record_usage(bucket, before, after).await;
+        // The quota reservation is released only now that the object is counted
+        let (result, _reservation) = result?;
+        self.changed(ObjectEvent::created(EventName::ObjectCreatedCompleteMultipartUpload, bucket, &key, &result));
+        Ok(result)
+    }
+
+    async fn assemble_multipart_upload(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+        expected_checksum: Option<&str>,
+    ) -> Result<(PutResult, QuotaReservation), StorageError> {
         if parts.is_empty() {
             return Err(StorageError::InvalidKey(
                 "at least one part is required to complete upload".into(),
@@ -975,74 +1508,100 @@ impl FilesystemStorage {
 
         let upload_meta = self.read_upload_meta(bucket, upload_id).await?;
         let mut selected = Vec::with_capacity(parts.len());
-        for (idx, (part_number, requested_etag)) in parts.iter().enumerate() {
-            let meta = self.read_part_meta(bucket, upload_id, *part_number).await?;
-            if meta.etag != *requested_etag {
+        for (idx, requested) in parts.iter().enumerate() {
+            let part_number = requested.part_number;
+            let meta = self.read_part_meta(bucket, upload_id, part_number).await?;
+            if meta.etag != requested.etag {
                 return Err(StorageError::InvalidKey(format!(
                     "etag mismatch for part {}",
                     part_number
                 )));
             }
+            if let Some(ref checksum) = requested.checksum_value
+                && meta.checksum_value.as_ref() != Some(checksum)
+            {
+                return Err(StorageError::InvalidKey(format!(
+                    "checksum mismatch for part {}",
+                    part_number
+                )));
+            }
             if idx + 1 < parts.len() && meta.size < 5 * 1024 * 1024 {
                 return Err(StorageError::InvalidKey("part too small".into()));
             }
             selected.push(meta);
         }
+        let object_size = selected.iter().map(|p| p.size).sum();
+        let reservation = self.reserve_quota(bucket, &upload_meta.key, Some(object_size)).await?;
 
-        if self.erasure_coding {
-            return self.complete_multipart_chunked(bucket, upload_id, &upload_meta, &selected).await;
+        let checksum_value = match (upload_meta.checksum_algorithm, upload_meta.checksum_type) {
+            (Some(algo), Some(ChecksumType::FullObject)) => full_object_checksum(algo, &selected),
+            (Some(algo), _) => composite_checksum(algo, &selected),
+            (None, _) => None,
+        };
+        if let Some(expected) = expected_checksum
+            && checksum_value.as_deref() != Some(expected)
+        {
+            return Err(StorageError::ChecksumMismatch(format!(
+                "expected {}, got {}",
+                expected,
+                checksum_value.as_deref().unwrap_or("none")
+            )));
         }
 
-        let obj_path = self.object_path(bucket, &upload_meta.key);
-        if let Some(parent) = obj_path.parent() {
-            fs::create_dir_all(parent).await?;
+        if self.erasure_coding {
+            let result = self
+                .complete_multipart_chunked(bucket, upload_id, &upload_meta, &selected, checksum_value)
+                .await?;
+            return Ok((result, reservation));
         }
-        let mut out = fs::File::create(&obj_path).await?;
+
+        // Assemble the composite layout inside the upload dir by moving (not
+        // copying) the part files, then swap it into place as `{key}.mp/`.
+        let assembled = self.upload_dir(bucket, upload_id).join("assembled");
+        let _ = fs::remove_dir_all(&assembled).await;
+        fs::create_dir_all(&assembled).await?;
+
         let mut total_size = 0u64;
         let mut etag_hasher = Md5::new();
+        let mut part_infos = Vec::with_capacity(selected.len());
 
         for part in &selected {
-            let mut part_file = fs::File::open(self.part_path(bucket, upload_id, part.part_number)).await?;
-            let mut buf = vec![0u8; 64 * 1024];
-            loop {
-                let n = part_file.read(&mut buf).await?;
-                if n == 0 {
-                    break;
-                }
-                out.write_all(&buf[..n]).await?;
-                total_size += n as u64;
-            }
+            let info = PartInfo::from(part);
+            fs::rename(
+                self.part_path(bucket, upload_id, part.part_number),
+                assembled.join(info.file_name()),
+            )
+            .await?;
+            total_size += part.size;
 
             let raw_md5 = hex::decode(part.etag.trim_matches('"'))
                 .map_err(|_| StorageError::InvalidKey("invalid part etag".into()))?;
             etag_hasher.update(raw_md5);
+            part_infos.push(info);
         }
-        out.flush().await?;
+
+        let manifest = PartsManifest {
+            version: 1,
+            total_size,
+            parts: part_infos,
+        };
+        fs::write(assembled.join("manifest.json"), serde_json::to_string_pretty(&manifest)?).await?;
+
+        let parts_dir = self.parts_dir(bucket, &upload_meta.key);
+        if let Some(parent) = parts_dir.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let _ = fs::remove_dir_all(&parts_dir).await;
+        fs::rename(&assembled, &parts_dir).await?;
+        // Drop any flat file left by an earlier single-PUT of the same key
+        let _ = fs::remove_file(self.object_path(bucket, &upload_meta.key)).await;
 
         let etag = format!("\"{}-{}\"", hex::encode(etag_hasher.finalize()), selected.len());
 
-        // Compute composite checksum if algorithm was specified
-        let (checksum_algorithm, checksum_value) = if let Some(algo) = upload_meta.checksum_algorithm {
-            let b64 = base64::engine::general_purpose::STANDARD;
-            let mut raw_checksums = Vec::new();
-            for part in &selected {
-                if let Some(ref val) = part.checksum_value {
-                    if let Ok(raw) = b64.decode(val) {
-                        raw_checksums.extend_from_slice(&raw);
-                    }
-                }
-            }
-            if !raw_checksums.is_empty() {
-                let mut composite_hasher = ChecksumHasher::new(algo);
-                composite_hasher.update(&raw_checksums);
-                let composite = format!("{}-{}", composite_hasher.finalize_base64(), selected.len());
-                (Some(algo), Some(composite))
-            } else {
-                (Some(algo), None)
-            }
-        } else {
-            (None, None)
-        };
+        let checksum_algorithm = upload_meta.checksum_algorithm;
+        let checksum_type = checksum_value
+            .as_ref()
+            .map(|_| upload_meta.checksum_type.unwrap_or(ChecksumType::Composite));
 
         let object_meta = ObjectMeta {
             key: upload_meta.key.clone(),
@@ -1052,9 +1611,12 @@ impl FilesystemStorage {
             last_modified: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
             version_id: None,
             is_delete_marker: false,
-            storage_format: None,
+            storage_format: Some("multipart-v1".to_string()),
             checksum_algorithm,
             checksum_value: checksum_value.clone(),
+            checksum_type,
+            parts: Some(manifest.parts),
+            replication_status: None,
         };
         let meta_path = self.meta_path(bucket, &upload_meta.key);
         if let Some(parent) = meta_path.parent() {
@@ -1063,13 +1625,15 @@ impl FilesystemStorage {
         fs::write(meta_path, serde_json::to_string_pretty(&object_meta)?).await?;
         let _ = fs::remove_dir_all(self.upload_dir(bucket, upload_id)).await;
 
-        Ok(PutResult {
+        let result = PutResult {
             size: total_size,
             etag,
             version_id: None,
             checksum_algorithm,
             checksum_value,
-        })
+            checksum_type,
+        };
+        Ok((result, reservation))
     }
 
     pub async fn abort_multipart_upload(
@@ -1082,10 +1646,21 @@ impl FilesystemStorage {
         if !fs::try_exists(&upload_dir).await? {
             return Err(StorageError::UploadNotFound(upload_id.to_string()));
         }
+        let before = self.upload_usage(bucket, upload_id).await;
         fs::remove_dir_all(upload_dir).await?;
+        self.record_usage(bucket, before, BucketUsage::default()).await;
         Ok(())
     }
 
+    pub async fn get_multipart_upload(
+        &self,
+        bucket: &str,
+        upload_id: &str,
+    ) -> Result<MultipartUploadMeta, StorageError> {
+        validate_upload_id(upload_id)?;
+        self.read_upload_meta(bucket, upload_id).await
+    }
+
     pub async fn list_parts(
         &self,
         bucket: &str,
@@ -1133,6 +1708,226 @@ impl FilesystemStorage {
         Ok(uploads)
     }
 
+    // --- Usage and quotas ---
+
+    pub async fn get_quota(&self, bucket: &str) -> Result<Option<BucketQuota>, StorageError> {
+        Ok(self.read_bucket_meta(bucket).await?.quota)
+    }
+
+    /// Set or clear a bucket's quota; a quota with no limits is cleared.
+    pub async fn set_quota(&self, bucket: &str, quota: Option<BucketQuota>) -> Result<(), StorageError> {
+        let mut meta = self.read_bucket_meta(bucket).await?;
+        meta.quota = quota.filter(|q| *q != BucketQuota::default());
+        self.write_bucket_meta(&meta).await
+    }
+
+    pub async fn bucket_usage(&self, bucket: &str) -> Result<BucketUsage, StorageError> {
+        let mut cache = self.usage.lock().await;
+        self.cached_usage(&mut cache, bucket).await
+    }
+
+    async fn cached_usage(&self, cache: &mut HashMap<String, BucketUsage>, bucket: &str) -> Result<BucketUsage, StorageError> {
+        if let Some(usage) = self.stored_usage(cache, bucket).await {
+            return Ok(usage);
+        }
+        let usage = self.scan_usage(bucket).await?;
+        self.store_usage(cache, bucket, usage).await?;
+        Ok(usage)
+    }
+
+    /// Count a bucket's usage from scratch, replacing the tracked figures.
+    /// Writes carry on during the count, so one racing with it may be
+    /// missed until the next.
+    pub async fn recalculate_usage(&self, bucket: &str) -> Result<BucketUsage, StorageError> {
+        let usage = self.scan_usage(bucket).await?;
+        let mut cache = self.usage.lock().await;
+        self.store_usage(&mut cache, bucket, usage).await?;
+        Ok(usage)
+    }
+
+    /// Refuse a write of `size` bytes to `key` that would take the bucket
+    /// past its quota. Overwriting in an unversioned bucket frees what it
+    /// replaces. Writes of unknown size (`None`) are refused outright when
+    /// the bucket's size is capped.
+    pub async fn check_quota(&self, bucket: &str, key: &str, size: Option<u64>) -> Result<(), StorageError> {
+        self.reserve_quota(bucket, key, size).await.map(drop)
+    }
+
+    /// Like `check_quota`, but the room stays claimed until the returned
+    /// reservation is dropped, so concurrent writes cannot all pass against
+    /// the same headroom. Drop it once the write has been counted.
+    pub async fn reserve_quota(&self, bucket: &str, key: &str, size: Option<u64>) -> Result<QuotaReservation, StorageError> {
+        let meta = self.read_bucket_meta(bucket).await?;
+        let Some(quota) = meta.quota else {
+            return Ok(QuotaReservation { reserved: None });
+        };
+        if quota.max_size > 0 && size.is_none() {
+            return Err(StorageError::QuotaExceeded(format!(
+                "bucket {} has a size quota, so uploads must declare their length",
+                bucket
+            )));
+        }
+        let replaced = if meta.versioning {
+            BucketUsage::default()
+        } else {
+            self.key_usage(bucket, key).await
+        };
+        let claim = BucketUsage {
+            size: size.unwrap_or(0),
+            objects: 1,
+            ..Default::default()
+        };
+
+        // Writes count themselves under the usage lock before releasing their
+        // claim, so while it is held each write shows in the usage, its claim
+        // or both, never neither
+        let mut cache = self.usage.lock().await;
+        let mut usage = self.cached_usage(&mut cache, bucket).await?;
+        let mut reserved = self.reserved.lock().unwrap();
+        usage.add(reserved.get(bucket).copied().unwrap_or_default());
+        let projected = usage.replace(replaced, claim);
+        if quota.max_size > 0 && projected.size > quota.max_size {
+            return Err(StorageError::QuotaExceeded(format!(
+                "bucket {} is limited to {} bytes",
+                bucket, quota.max_size
+            )));
+        }
+        if quota.max_objects > 0 && projected.objects > quota.max_objects {
+            return Err(StorageError::QuotaExceeded(format!(
+                "bucket {} is limited to {} objects",
+                bucket, quota.max_objects
+            )));
+        }
+        reserved.entry(bucket.to_string()).or_default().add(claim);
+        Ok(QuotaReservation {
+            reserved: Some((self.reserved.clone(), bucket.to_string(), claim)),
+        })
+    }
+
+    /// What one key adds to its bucket's usage: the current object plus
+    /// every noncurrent version and delete marker.
+    async fn key_usage(&self, bucket: &str, key: &str) -> BucketUsage {
+        let mut usage = BucketUsage::default();
+        let current = self.read_object_meta(bucket, key).await.ok();
+        if let Some(meta) = &current {
+            usage.size += meta.size;
+            usage.objects += 1;
+            if meta.storage_format.as_deref() == Some("chunked-v2") {
+                usage.ec_overhead += parity_bytes(&self.manifest_path(bucket, key)).await;
+            }
+        }
+        // Folder markers are never versioned
+        if key.ends_with('/') {
+            return usage;
+        }
+        let current_version = current.as_ref().and_then(|m| m.version_id.as_deref());
+        let ver_dir = self.versions_dir(bucket, key);
+        let Ok(mut entries) = fs::read_dir(&ver_dir).await else {
+            return usage;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let fname = entry.file_name().to_string_lossy().to_string();
+            let Some(vid) = fname.strip_suffix(".meta.json") else {
+                continue;
+            };
+            if current_version == Some(vid) {
+                continue;
+            }
+            let Ok(data) = fs::read_to_string(entry.path()).await else {
+                continue;
+            };
+            let Ok(meta) = serde_json::from_str::<ObjectMeta>(&data) else {
+                continue;
+            };
+            if meta.is_delete_marker {
+                usage.delete_markers += 1;
+                continue;
+            }
+            usage.size += meta.size;
+            usage.objects += 1;
+            usage.versions += 1;
+            usage.versions_size += meta.size;
+            if meta.storage_format.as_deref() == Some("chunked-v2") {
+                usage.ec_overhead += parity_bytes(&ver_dir.join(format!("{}.ec", vid)).join("manifest.json")).await;
+            }
+        }
+        usage
+    }
+
+    /// What an unfinished multipart upload adds to its bucket's usage.
+    async fn upload_usage(&self, bucket: &str, upload_id: &str) -> BucketUsage {
+        match self.list_parts(bucket, upload_id).await {
+            Ok((_, parts)) => BucketUsage {
+                multipart_uploads: 1,
+                multipart_size: parts.iter().map(|p| p.size).sum(),
+                ..Default::default()
+            },
+            Err(_) => BucketUsage::default(),
+        }
+    }
+
+    /// Replace the share `before` of a bucket's usage with `after`.
+    async fn record_usage(&self, bucket: &str, before: BucketUsage, after: BucketUsage) {
+        if after == before {
+            return;
+        }
+        let mut cache = self.usage.lock().await;
+        let usage = match self.stored_usage(&cache, bucket).await {
+            Some(usage) => usage.replace(before, after),
+            // A fresh count already includes this change
+            None => match self.scan_usage(bucket).await {
+                Ok(usage) => usage,
+                Err(e) => {
+                    tracing::warn!("failed to count usage of bucket {}: {}", bucket, e);
+                    return;
+                }
+            },
+        };
+        if let Err(e) = self.store_usage(&mut cache, bucket, usage).await {
+            tracing::warn!("failed to save usage of bucket {}: {}", bucket, e);
+        }
+    }
+
+    async fn stored_usage(&self, cache: &HashMap<String, BucketUsage>, bucket: &str) -> Option<BucketUsage> {
+        if let Some(usage) = cache.get(bucket) {
+            return Some(*usage);
+        }
+        let data = fs::read_to_string(self.buckets_dir.join(bucket).join(".usage.json")).await.ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    async fn store_usage(
+        &self,
+        cache: &mut HashMap<String, BucketUsage>,
+        bucket: &str,
+        usage: BucketUsage,
+    ) -> Result<(), StorageError> {
+        // Never recreate the directory of a bucket being deleted
+        if !self.head_bucket(bucket).await? {
+            return Err(StorageError::NotFound(bucket.to_string()));
+        }
+        cache.insert(bucket.to_string(), usage);
+        fs::write(self.buckets_dir.join(bucket).join(".usage.json"), serde_json::to_string_pretty(&usage)?).await?;
+        Ok(())
+    }
+
+    async fn scan_usage(&self, bucket: &str) -> Result<BucketUsage, StorageError> {
+        if !self.head_bucket(bucket).await? {
+            return Err(StorageError::NotFound(bucket.to_string()));
+        }
+        let mut keys = std::collections::BTreeSet::new();
+        keys.extend(self.list_objects(bucket, "").await?.into_iter().map(|m| m.key));
+        keys.extend(self.list_object_versions(bucket, "").await?.into_iter().map(|m| m.key));
+        let mut usage = BucketUsage::default();
+        for key in &keys {
+            usage.add(self.key_usage(bucket, key).await);
+        }
+        for upload in self.list_multipart_uploads(bucket).await? {
+            usage.add(self.upload_usage(bucket, &upload.upload_id).await);
+        }
+        Ok(usage)
+    }
+
     // --- Internal helpers ---
 
     fn has_objects<'a>(
@@ -1145,14 +1940,15 @@ impl FilesystemStorage {
             while let Some(entry) = entries.next_entry().await? {
                 let fname = entry.file_name().to_string_lossy().to_string();
                 if fname == ".bucket.json"
+                    || fname == ".usage.json"
                     || fname == ".uploads"
                     || fname == ".versions"
                     || fname.ends_with(".meta.json")
                 {
                     continue;
                 }
-                // EC chunk directory counts as an object
-                if fname.ends_with(".ec") && entry.file_type().await?.is_dir() {
+                // EC chunk and composite part directories count as objects
+                if (fname.ends_with(".ec") || fname.ends_with(".mp")) && entry.file_type().await?.is_dir() {
                     return Ok(true);
                 }
                 if entry.file_type().await?.is_dir() {
@@ -1204,6 +2000,7 @@ impl FilesystemStorage {
 
                 if fname.ends_with(".meta.json")
                     || fname == ".bucket.json"
+                    || fname == ".usage.json"
                     || fname == ".uploads"
                     || fname == ".versions"
                     || fname == ".folder"
@@ -1211,12 +2008,16 @@ impl FilesystemStorage {
                     continue;
                 }
 
-                // EC chunk directory: derive the object key and read its metadata
-                if fname.ends_with(".ec") && entry.file_type().await?.is_dir() {
+                // EC chunk or composite part directory: derive the object key and read its metadata
+                if (fname.ends_with(".ec") || fname.ends_with(".mp")) && entry.file_type().await?.is_dir() {
                     if let Ok(rel) = path.strip_prefix(base) {
                         let rel_str = rel.to_string_lossy();
-                        // Strip the .ec suffix to get the key
-                        let key = rel_str.strip_suffix(".ec").unwrap_or(&rel_str).to_string();
+                        // Strip the .ec/.mp suffix to get the key
+                        let key = rel_str
+                            .strip_suffix(".ec")
+                            .or_else(|| rel_str.strip_suffix(".mp"))
+                            .unwrap_or(&rel_str)
+                            .to_string();
                         if key.starts_with(prefix) {
                             if let Ok(meta) = self.read_object_meta(
                                 base.file_name().unwrap().to_str().unwrap(),
@@ -1304,6 +2105,76 @@ impl FilesystemStorage {
         format!("{:016}-{:08x}", micros, rand_suffix)
     }
 
+    /// Version ID (in versioned buckets) and timestamp for a new object:
+    /// fresh ones, or the source's for a replicated write.
+    async fn new_version_identity(
+        &self,
+        bucket: &str,
+        replica: Option<&ReplicaSource>,
+    ) -> (Option<String>, String) {
+        let versioned = self.is_versioned(bucket).await.unwrap_or(false);
+        let version_id = versioned.then(|| {
+            replica
+                .and_then(|r| r.version_id.clone())
+                .unwrap_or_else(Self::generate_version_id)
+        });
+        let last_modified = match replica {
+            Some(r) => r.last_modified.clone(),
+            None => chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        };
+        (version_id, last_modified)
+    }
+
+    /// Whether a replicated write should be applied. A version that already
+    /// exists is not written twice; otherwise the later write wins, with the
+    /// ETag breaking ties so that both sites pick the same one.
+    async fn replica_is_newer(
+        &self,
+        bucket: &str,
+        key: &str,
+        source: &ReplicaSource,
+    ) -> Result<bool, StorageError> {
+        if let Some(vid) = &source.version_id
+            && self.is_versioned(bucket).await.unwrap_or(false)
+        {
+            return Ok(!fs::try_exists(self.version_meta_path(bucket, key, vid)).await?);
+        }
+        let current = match self.read_object_meta(bucket, key).await {
+            Ok(meta) => meta,
+            Err(StorageError::NotFound(_)) => return Ok(true),
+            Err(e) => return Err(e),
+        };
+        let source_etag = source.etag.as_deref().unwrap_or("");
+        Ok((current.last_modified.as_str(), current.etag.as_str()) < (source.last_modified.as_str(), source_etag))
+    }
+
+    /// After writing version `written`, make sure the top-level files still
+    /// show the latest version; a replicated version may be older.
+    async fn restore_latest_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        written: Option<&str>,
+    ) -> Result<(), StorageError> {
+        let Some(written) = written else {
+            return Ok(());
+        };
+        let mut latest = String::new();
+        let mut entries = fs::read_dir(self.versions_dir(bucket, key)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let fname = entry.file_name().to_string_lossy().to_string();
+            if let Some(vid) = fname.strip_suffix(".meta.json")
+                && vid > latest.as_str()
+            {
+                latest = vid.to_string();
+            }
+        }
+        if latest != written {
+            self.update_current_version(bucket, key).await?;
+        }
+        Ok(())
+    }
+
     /// Directory holding versions for a given key.
     /// For key `photos/vacation.jpg` → `{bucket}/photos/.versions/vacation.jpg/`
     fn versions_dir(&self, bucket: &str, key: &str) -> PathBuf {
@@ -1327,7 +2198,7 @@ impl FilesystemStorage {
             .join(format!("{}.meta.json", version_id))
     }
 
-    pub async fn is_versioned(&self, bucket: &str) -> Result<bool, StorageError> {
+    async fn read_bucket_meta(&self, bucket: &str) -> Result<BucketMeta, StorageError> {
         let meta_path = self.buckets_dir.join(bucket).join(".bucket.json");
         let data = fs::read_to_string(&meta_path).await.map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
@@ -1336,8 +2207,17 @@ impl FilesystemStorage {
                 StorageError::Io(e)
             }
         })?;
-        let meta: BucketMeta = serde_json::from_str(&data)?;
-        Ok(meta.versioning)
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    async fn write_bucket_meta(&self, meta: &BucketMeta) -> Result<(), StorageError> {
+        let meta_path = self.buckets_dir.join(&meta.name).join(".bucket.json");
+        fs::write(&meta_path, serde_json::to_string_pretty(meta)?).await?;
+        Ok(())
+    }
+
+    pub async fn is_versioned(&self, bucket: &str) -> Result<bool, StorageError> {
+        Ok(self.read_bucket_meta(bucket).await?.versioning)
     }
 
     pub async fn set_versioning(
@@ -1345,22 +2225,96 @@ impl FilesystemStorage {
         bucket: &str,
         enabled: bool,
     ) -> Result<(), StorageError> {
-        let meta_path = self.buckets_dir.join(bucket).join(".bucket.json");
-        let data = fs::read_to_string(&meta_path).await.map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                StorageError::NotFound(bucket.to_string())
-            } else {
-                StorageError::Io(e)
-            }
-        })?;
-        let mut meta: BucketMeta = serde_json::from_str(&data)?;
+        let mut meta = self.read_bucket_meta(bucket).await?;
         let was_enabled = meta.versioning;
         meta.versioning = enabled;
-        fs::write(&meta_path, serde_json::to_string_pretty(&meta)?).await?;
+        self.write_bucket_meta(&meta).await?;
 
         // If disabling versioning, clean up old versions
         if was_enabled && !enabled {
             self.cleanup_versions(bucket).await?;
+            self.recalculate_usage(bucket).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_notification(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<NotificationConfig>, StorageError> {
+        Ok(self.read_bucket_meta(bucket).await?.notification)
+    }
+
+    /// Replace the bucket's notification rules; `None` (or no rules) removes them.
+    pub async fn set_notification(
+        &self,
+        bucket: &str,
+        config: Option<NotificationConfig>,
+    ) -> Result<(), StorageError> {
+        let mut meta = self.read_bucket_meta(bucket).await?;
+        meta.notification = config.filter(|c| !c.rules.is_empty());
+        self.write_bucket_meta(&meta).await
+    }
+
+    pub async fn get_logging(&self, bucket: &str) -> Result<Option<BucketLogging>, StorageError> {
+        Ok(self.read_bucket_meta(bucket).await?.logging)
+    }
+
+    /// Replace the bucket's access logging setting; `None` disables it.
+    pub async fn set_logging(
+        &self,
+        bucket: &str,
+        logging: Option<BucketLogging>,
+    ) -> Result<(), StorageError> {
+        let mut meta = self.read_bucket_meta(bucket).await?;
+        meta.logging = logging;
+        self.write_bucket_meta(&meta).await
+    }
+
+    pub async fn get_replication(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<ReplicationConfig>, StorageError> {
+        Ok(self.read_bucket_meta(bucket).await?.replication)
+    }
+
+    /// Replace the bucket's replication rules; `None` removes them.
+    pub async fn set_replication(
+        &self,
+        bucket: &str,
+        config: Option<ReplicationConfig>,
+    ) -> Result<(), StorageError> {
+        let mut meta = self.read_bucket_meta(bucket).await?;
+        meta.replication = config.filter(|c| !c.rules.is_empty());
+        self.write_bucket_meta(&meta).await
+    }
+
+    /// Record the replication status of an object version (the current object
+    /// when `version_id` is `None`). Versions that no longer exist are skipped.
+    pub async fn set_replication_status(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        status: ReplicationStatus,
+    ) -> Result<(), StorageError> {
+        let mut paths = vec![self.meta_path(bucket, key)];
+        if let Some(vid) = version_id {
+            paths.push(self.version_meta_path(bucket, key, vid));
+        }
+        for path in paths {
+            let data = match fs::read_to_string(&path).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut meta: ObjectMeta = serde_json::from_str(&data)?;
+            // The current object may already be a newer version
+            if meta.version_id.as_deref() != version_id {
+                continue;
+            }
+            meta.replication_status = Some(status);
+            fs::write(&path, serde_json::to_string_pretty(&meta)?).await?;
         }
         Ok(())
     }
@@ -1453,11 +2407,15 @@ impl FilesystemStorage {
         &self,
         bucket: &str,
         key: &str,
+        replica: Option<&ReplicaSource>,
     ) -> Result<DeleteResult, StorageError> {
-        let version_id = Self::generate_version_id();
-        let now = chrono::Utc::now()
-            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
-            .to_string();
+        let version_id = replica
+            .and_then(|r| r.version_id.clone())
+            .unwrap_or_else(Self::generate_version_id);
+        let now = match replica {
+            Some(r) => r.last_modified.clone(),
+            None => chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        };
 
         let marker_meta = ObjectMeta {
             key: key.to_string(),
@@ -1470,6 +2428,9 @@ impl FilesystemStorage {
             storage_format: None,
             checksum_algorithm: None,
             checksum_value: None,
+            checksum_type: None,
+            parts: None,
+            replication_status: replica.map(|_| ReplicationStatus::Replica),
         };
 
         let ver_dir = self.versions_dir(bucket, key);
@@ -1481,6 +2442,7 @@ impl FilesystemStorage {
         let _ = fs::remove_file(self.object_path(bucket, key)).await;
         let _ = fs::remove_file(self.meta_path(bucket, key)).await;
         let _ = fs::remove_dir_all(self.ec_dir(bucket, key)).await;
+        let _ = fs::remove_dir_all(self.parts_dir(bucket, key)).await;
 
         Ok(DeleteResult {
             version_id: Some(version_id),
@@ -1499,7 +2461,7 @@ impl FilesystemStorage {
             return Ok(());
         }
 
-        // Find the latest non-delete-marker version (lexicographic sort = chronological)
+        // The latest version (lexicographic sort = chronological) becomes current
         let mut versions = Vec::new();
         let mut entries = fs::read_dir(&ver_dir).await?;
         while let Some(entry) = entries.next_entry().await? {
@@ -1509,16 +2471,17 @@ impl FilesystemStorage {
             }
         }
         versions.sort();
-        versions.reverse(); // newest first
 
-        for meta_fname in &versions {
+        if let Some(meta_fname) = versions.last() {
             let meta_path = ver_dir.join(meta_fname);
             let data = fs::read_to_string(&meta_path).await?;
             let meta: ObjectMeta = serde_json::from_str(&data)?;
+            // A delete marker on top hides every older version
             if !meta.is_delete_marker {
                 // Restore this version as current
                 let vid = meta.version_id.as_ref().unwrap();
                 let obj_meta_path = self.meta_path(bucket, key);
+                let _ = fs::remove_dir_all(self.parts_dir(bucket, key)).await;
 
                 let ver_ec = ver_dir.join(format!("{}.ec", vid));
                 if ver_ec.is_dir() {
@@ -1551,10 +2514,11 @@ impl FilesystemStorage {
             }
         }
 
-        // All versions are delete markers — remove top-level files
+        // No versions left, or the latest is a delete marker — remove top-level files
         let _ = fs::remove_file(self.object_path(bucket, key)).await;
         let _ = fs::remove_file(self.meta_path(bucket, key)).await;
         let _ = fs::remove_dir_all(self.ec_dir(bucket, key)).await;
+        let _ = fs::remove_dir_all(self.parts_dir(bucket, key)).await;
         Ok(())
     }
 
@@ -1606,6 +2570,40 @@ impl FilesystemStorage {
         Ok((Box::pin(BufReader::new(file)), meta))
     }
 
+    /// Read the byte range [offset, offset+length) of a specific version.
+    pub async fn get_object_version_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<(ByteStream, ObjectMeta), StorageError> {
+        let meta = self.head_object_version(bucket, key, version_id).await?;
+        if meta.is_delete_marker {
+            return Err(StorageError::NotFound(key.to_string()));
+        }
+
+        let ver_ec_dir = self.versions_dir(bucket, key).join(format!("{}.ec", version_id));
+        if ver_ec_dir.is_dir() {
+            let manifest_data = fs::read_to_string(ver_ec_dir.join("manifest.json")).await?;
+            let manifest: ChunkManifest = serde_json::from_str(&manifest_data)?;
+            let reader = VerifiedChunkReader::with_range(ver_ec_dir, manifest, offset, length);
+            return Ok((Box::pin(reader), meta));
+        }
+
+        let ver_data_path = self.version_data_path(bucket, key, version_id);
+        let mut file = fs::File::open(&ver_data_path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                StorageError::VersionNotFound(version_id.to_string())
+            } else {
+                StorageError::Io(e)
+            }
+        })?;
+        file.seek(std::io::SeekFrom::Start(offset)).await.map_err(StorageError::Io)?;
+        Ok((Box::pin(file.take(length)), meta))
+    }
+
     pub async fn head_object_version(
         &self,
         bucket: &str,
@@ -1633,8 +2631,46 @@ impl FilesystemStorage {
         bucket: &str,
         key: &str,
         version_id: &str,
+    ) -> Result<ObjectMeta, StorageError> {
+        self.delete_version_as(bucket, key, version_id, false).await
+    }
+
+    /// `delete_object_version` on behalf of a replication peer.
+    pub async fn delete_object_version_replica(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<ObjectMeta, StorageError> {
+        self.delete_version_as(bucket, key, version_id, true).await
+    }
+
+    async fn delete_version_as(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        replica: bool,
     ) -> Result<ObjectMeta, StorageError> {
         validate_key(key)?;
+        let _lock = self.key_locks.lock(bucket, key).await;
+        let before = self.key_usage(bucket, key).await;
+        let result = self.remove_object_version(bucket, key, version_id).await;
+        let after = self.key_usage(bucket, key).await;
+        self.record_usage(bucket, before, after).await;
+        if result.is_ok() {
+            let removed = DeleteResult { version_id: Some(version_id.to_string()), is_delete_marker: false };
+            self.changed(ObjectEvent::removed(bucket, key, &removed).with_replica(replica));
+        }
+        result
+    }
+
+    async fn remove_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<ObjectMeta, StorageError> {
         let ver_meta_path = self.version_meta_path(bucket, key, version_id);
         let data = fs::read_to_string(&ver_meta_path).await.map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
@@ -1,4 +1,6 @@
 pub mod chunk_reader;
+pub mod composite_reader;
+pub mod crawler;
 pub mod filesystem;
 
 use serde::{Deserialize, Serialize};
@@ -13,6 +15,7 @@ pub enum ChecksumAlgorithm {
     CRC32C,
     SHA1,
     SHA256,
+    CRC64NVME,
 }
 
 impl ChecksumAlgorithm {
@@ -22,15 +25,59 @@ impl ChecksumAlgorithm {
             Self::CRC32C => "x-amz-checksum-crc32c",
             Self::SHA1 => "x-amz-checksum-sha1",
             Self::SHA256 => "x-amz-checksum-sha256",
+            Self::CRC64NVME => "x-amz-checksum-crc64nvme",
         }
     }
 
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CRC32 => "CRC32",
+            Self::CRC32C => "CRC32C",
+            Self::SHA1 => "SHA1",
+            Self::SHA256 => "SHA256",
+            Self::CRC64NVME => "CRC64NVME",
+        }
+    }
+
+    /// CRC checksums can be combined across parts into a full-object checksum.
+    pub fn supports_full_object(&self) -> bool {
+        matches!(self, Self::CRC32 | Self::CRC32C | Self::CRC64NVME)
+    }
+
     pub fn from_header_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
             "CRC32" => Some(Self::CRC32),
             "CRC32C" => Some(Self::CRC32C),
             "SHA1" => Some(Self::SHA1),
             "SHA256" => Some(Self::SHA256),
+            "CRC64NVME" => Some(Self::CRC64NVME),
+            _ => None,
+        }
+    }
+}
+
+/// How an object-level checksum relates to the object's bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChecksumType {
+    /// Checksum of the part checksums, suffixed with `-N` (multipart uploads).
+    Composite,
+    /// Checksum of the whole object's bytes.
+    FullObject,
+}
+
+impl ChecksumType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Composite => "COMPOSITE",
+            Self::FullObject => "FULL_OBJECT",
+        }
+    }
+
+    pub fn from_header_str(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "COMPOSITE" => Some(Self::Composite),
+            "FULL_OBJECT" => Some(Self::FullObject),
             _ => None,
         }
     }
@@ -42,6 +89,15 @@ pub struct PutResult {
     pub version_id: Option<String>,
     pub checksum_algorithm: Option<ChecksumAlgorithm>,
     pub checksum_value: Option<String>,
+    pub checksum_type: Option<ChecksumType>,
+}
+
+/// A part listed in a CompleteMultipartUpload request.
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub etag: String,
+    /// Part checksum echoed back by the client, if any.
+    pub checksum_value: Option<String>,
 }
 
 pub struct DeleteResult {
@@ -60,6 +116,169 @@ pub struct BucketMeta {
     pub region: String,
     #[serde(default, skip_serializing_if = "is_false")]
     pub versioning: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notification: Option<NotificationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication: Option<ReplicationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<BucketLogging>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<BucketQuota>,
+}
+
+/// Hard limits on what a bucket may hold; 0 leaves that dimension unlimited.
+/// Noncurrent versions count like any other object.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BucketQuota {
+    #[serde(default)]
+    pub max_size: u64,
+    #[serde(default)]
+    pub max_objects: u64,
+}
+
+/// What a bucket holds. `size` and `objects` count every version that isn't
+/// a delete marker, which is what quotas are checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketUsage {
+    pub size: u64,
+    pub objects: u64,
+    /// Noncurrent versions among `objects`, and their bytes.
+    #[serde(default)]
+    pub versions: u64,
+    #[serde(default)]
+    pub versions_size: u64,
+    #[serde(default)]
+    pub delete_markers: u64,
+    /// Multipart uploads in progress and the bytes of their parts so far.
+    #[serde(default)]
+    pub multipart_uploads: u64,
+    #[serde(default)]
+    pub multipart_size: u64,
+    /// Parity bytes stored on top of the data of erasure-coded objects.
+    #[serde(default)]
+    pub ec_overhead: u64,
+}
+
+impl BucketUsage {
+    fn fields(&mut self) -> [&mut u64; 8] {
+        [
+            &mut self.size,
+            &mut self.objects,
+            &mut self.versions,
+            &mut self.versions_size,
+            &mut self.delete_markers,
+            &mut self.multipart_uploads,
+            &mut self.multipart_size,
+            &mut self.ec_overhead,
+        ]
+    }
+
+    pub fn add(&mut self, mut other: BucketUsage) {
+        for (total, n) in self.fields().into_iter().zip(other.fields()) {
+            *total += *n;
+        }
+    }
+
+    /// These totals once the share `before` has become `after`.
+    pub fn replace(mut self, mut before: BucketUsage, mut after: BucketUsage) -> BucketUsage {
+        for ((total, old), new) in self.fields().into_iter().zip(before.fields()).zip(after.fields()) {
+            *total = (*total + *new).saturating_sub(*old);
+        }
+        self
+    }
+}
+
+/// Server access logging set with `PUT /{bucket}?logging`: requests on the
+/// bucket are written as log objects into `target_bucket`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketLogging {
+    pub target_bucket: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_prefix: String,
+}
+
+/// Event notification rules set with `PUT /{bucket}?notification`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    pub rules: Vec<NotificationRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationRule {
+    pub id: String,
+    /// Target ARN, e.g. `arn:maxio:sqs:us-east-1:hook:webhook`.
+    pub arn: String,
+    /// Event patterns such as `s3:ObjectCreated:*`.
+    pub events: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub suffix: String,
+}
+
+/// Replication rules set with `PUT /{bucket}?replication`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    pub rules: Vec<ReplicationRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationRule {
+    pub id: String,
+    pub enabled: bool,
+    /// Among matching rules the highest priority wins.
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+    /// Destination ARN, e.g. `arn:maxio:replication::dr:backup-bucket`.
+    pub destination: String,
+    /// Replicate deletes (delete markers in versioned buckets).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub delete_markers: bool,
+    /// Replicate permanent deletes of specific versions.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deletes: bool,
+}
+
+/// Value of `x-amz-replication-status` for an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ReplicationStatus {
+    Pending,
+    Completed,
+    Failed,
+    /// Written by a replication peer; never replicated onwards.
+    Replica,
+}
+
+impl ReplicationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Completed => "COMPLETED",
+            Self::Failed => "FAILED",
+            Self::Replica => "REPLICA",
+        }
+    }
+}
+
+/// Identity of a replicated write on the source side, kept by the replica so
+/// both sites converge on the same version ID, timestamp and ETag.
+#[derive(Debug, Clone)]
+pub struct ReplicaSource {
+    pub version_id: Option<String>,
+    pub last_modified: String,
+    pub etag: Option<String>,
+}
+
+/// A name directly below a listed prefix, as `list_level` returns it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ListingEntry {
+    Object(String),
+    /// A `/`-terminated prefix with at least one object below it.
+    Prefix(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,6 +298,31 @@ pub struct ObjectMeta {
     pub checksum_algorithm: Option<ChecksumAlgorithm>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub checksum_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_type: Option<ChecksumType>,
+    /// Part layout of objects created by CompleteMultipartUpload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parts: Option<Vec<PartInfo>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication_status: Option<ReplicationStatus>,
+}
+
+impl ObjectMeta {
+    /// Number of parts the object was uploaded in (1 for single PUTs).
+    pub fn parts_count(&self) -> u32 {
+        self.parts.as_ref().map(|p| p.len() as u32).unwrap_or(1)
+    }
+
+    /// Byte range (offset, length) of a 1-based part number, if it exists.
+    pub fn part_range(&self, part_number: u32) -> Option<(u64, u64)> {
+        let Some(parts) = &self.parts else {
+            return (part_number == 1).then_some((0, self.size));
+        };
+        let index = (part_number as usize).checked_sub(1)?;
+        let part = parts.get(index)?;
+        let offset = parts[..index].iter().map(|p| p.size).sum();
+        Some((offset, part.size))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +334,8 @@ pub struct MultipartUploadMeta {
     pub initiated: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_type: Option<ChecksumType>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,6 +391,42 @@ pub struct ChunkInfo {
     pub kind: ChunkKind,
 }
 
+/// Layout of a multipart object whose final bytes live in the original part
+/// files (`{key}.mp/`) instead of a single concatenated file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartsManifest {
+    pub version: u32,
+    pub total_size: u64,
+    pub parts: Vec<PartInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartInfo {
+    pub part_number: u32,
+    pub size: u64,
+    pub etag: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_value: Option<String>,
+}
+
+impl From<&PartMeta> for PartInfo {
+    fn from(part: &PartMeta) -> Self {
+        Self {
+            part_number: part.part_number,
+            size: part.size,
+            etag: part.etag.clone(),
+            checksum_value: part.checksum_value.clone(),
+        }
+    }
+}
+
+impl PartInfo {
+    /// File name of this part inside the `{key}.mp/` directory.
+    pub fn file_name(&self) -> String {
+        format!("{:05}", self.part_number)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum StorageError {
     #[error("IO error: {0}")]
@@ -163,4 +445,6 @@ pub enum StorageError {
     VersionNotFound(String),
     #[error("Checksum mismatch: {0}")]
     ChecksumMismatch(String),
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
 }
@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header::RETRY_AFTER,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::Principal;
+use crate::config::Config;
+use crate::error::S3Error;
+use crate::metrics::METRICS;
+use crate::server::AppState;
+
+/// Subjects idle this long are forgotten once the table grows large.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Limits of one access key or bucket; 0 means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limit {
+    #[serde(default)]
+    pub requests_per_second: u32,
+    #[serde(default)]
+    pub bytes_per_second: u64,
+}
+
+/// The default limit plus exceptions for particular access keys or buckets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitSet {
+    #[serde(default)]
+    pub default: Limit,
+    #[serde(default)]
+    pub overrides: BTreeMap<String, Limit>,
+}
+
+impl LimitSet {
+    fn get(&self, name: &str) -> Limit {
+        self.overrides.get(name).copied().unwrap_or(self.default)
+    }
+}
+
+/// Everything adjustable through `/minio/admin/v3/rate-limits`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimits {
+    #[serde(default)]
+    pub access_keys: LimitSet,
+    #[serde(default)]
+    pub buckets: LimitSet,
+}
+
+/// Tokens accrue at `rate` per second up to one second's worth.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let earned = now.duration_since(self.updated).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + earned).min(self.rate);
+        self.updated = now;
+    }
+
+    /// Time until a whole token is available.
+    fn wait_for_one(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    /// Take `n` tokens, going into debt if needed; returns the pause that
+    /// pays the debt back.
+    fn take(&mut self, n: f64) -> Duration {
+        self.refill();
+        self.tokens -= n;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+type SharedBucket = Arc<Mutex<TokenBucket>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    AccessKey,
+    Bucket,
+}
+
+struct Subject {
+    requests: Option<TokenBucket>,
+    bytes: Option<SharedBucket>,
+    last_seen: Instant,
+}
+
+/// Token-bucket request rate and bandwidth limits per access key and bucket.
+pub struct Throttle {
+    limits: RwLock<RateLimits>,
+    subjects: Mutex<HashMap<(Kind, String), Subject>>,
+}
+
+impl Throttle {
+    pub fn new(config: &Config) -> Self {
+        let default_set = |requests_per_second, bytes_per_second| LimitSet {
+            default: Limit {
+                requests_per_second,
+                bytes_per_second,
+            },
+            overrides: BTreeMap::new(),
+        };
+        Self {
+            limits: RwLock::new(RateLimits {
+                access_keys: default_set(config.key_request_rate, config.key_bandwidth),
+                buckets: default_set(config.bucket_request_rate, config.bucket_bandwidth),
+            }),
+            subjects: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits.read().unwrap().clone()
+    }
+
+    /// Replace the limits; counting starts afresh under the new ones.
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().unwrap() = limits;
+        self.subjects.lock().unwrap().clear();
+    }
+
+    /// Count a request against its access key and bucket. Returns the
+    /// bandwidth buckets its bodies are paced by, or how long to back off.
+    fn admit(&self, access_key: Option<&str>, bucket: Option<&str>) -> Result<Vec<SharedBucket>, Duration> {
+        let limits = self.limits.read().unwrap();
+        let mut wanted = Vec::new();
+        if let Some(key) = access_key {
+            wanted.push((Kind::AccessKey, key, limits.access_keys.get(key)));
+        }
+        if let Some(bucket) = bucket {
+            wanted.push((Kind::Bucket, bucket, limits.buckets.get(bucket)));
+        }
+        wanted.retain(|(_, _, limit)| *limit != Limit::default());
+        if wanted.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut subjects = self.subjects.lock().unwrap();
+        let now = Instant::now();
+        if subjects.len() > PRUNE_THRESHOLD {
+            subjects.retain(|_, s| now.duration_since(s.last_seen) < IDLE_TIMEOUT);
+        }
+        let mut wait = Duration::ZERO;
+        for (kind, name, limit) in &wanted {
+            let subject = subjects.entry((*kind, name.to_string())).or_insert_with(|| Subject {
+                requests: (limit.requests_per_second > 0).then(|| TokenBucket::new(limit.requests_per_second as f64)),
+                bytes: (limit.bytes_per_second > 0)
+                    .then(|| Arc::new(Mutex::new(TokenBucket::new(limit.bytes_per_second as f64)))),
+                last_seen: now,
+            });
+            subject.last_seen = now;
+            if let Some(ref mut requests) = subject.requests {
+                wait = wait.max(requests.wait_for_one());
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        let mut bandwidth = Vec::new();
+        for (kind, name, _) in &wanted {
+            let subject = subjects.get_mut(&(*kind, name.to_string())).expect("subject was just inserted");
+            if let Some(ref mut requests) = subject.requests {
+                requests.take(1.0);
+            }
+            bandwidth.extend(subject.bytes.clone());
+        }
+        Ok(bandwidth)
+    }
+}
+
+/// Passes a body through at the pace its bandwidth buckets allow: each
+/// frame is paid for once read, and the next one waits out any debt.
+struct ThrottledBody {
+    inner: Body,
+    buckets: Vec<SharedBucket>,
+    pause: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl http_body::Body for ThrottledBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(pause) = self.pause.as_mut() {
+            ready!(pause.as_mut().poll(cx));
+            self.pause = None;
+        }
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &polled
+            && let Some(data) = frame.data_ref()
+            && !data.is_empty()
+        {
+            let len = data.len() as f64;
+            let wait = self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.lock().unwrap().take(len))
+                .max()
+                .unwrap_or_default();
+            if !wait.is_zero() {
+                self.pause = Some(Box::pin(tokio::time::sleep(wait)));
+            }
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn throttled(body: Body, buckets: Vec<SharedBucket>) -> Body {
+    Body::new(ThrottledBody {
+        inner: body,
+        buckets,
+        pause: None,
+    })
+}
+
+/// Enforce the limits of the request's access key and bucket: refuse
+/// requests over the rate with `SlowDown`, and pace request and response
+/// bodies to the bandwidth.
+pub async fn throttle_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let access_key = request.extensions().get::<Principal>().map(|p| p.0.clone());
+    let bucket = request
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .filter(|b| !b.is_empty())
+        .map(str::to_string);
+
+    let bandwidth = match state.throttle.admit(access_key.as_deref(), bucket.as_deref()) {
+        Ok(bandwidth) => bandwidth,
+        Err(wait) => {
+            METRICS.request_throttled();
+            let mut response = S3Error::slow_down().into_response();
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            response.headers_mut().insert(RETRY_AFTER, retry_after.into());
+            return response;
+        }
+    };
+    if bandwidth.is_empty() {
+        return next.run(request).await;
+    }
+    let request = request.map(|body| throttled(body, bandwidth.clone()));
+    next.run(request).await.map(|body| throttled(body, bandwidth))
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use axum::{
+    Router,
+    http::{HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+use crate::config::Config;
+
+/// How often certificate files are checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+/// Handshakes that take longer than this are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// File names inside a certs directory (same layout as MinIO).
+const CERT_FILE: &str = "public.crt";
+const KEY_FILE: &str = "private.key";
+
+/// A certificate/key pair on disk. `server_name` is `None` for the default pair.
+#[derive(Debug, Clone, PartialEq)]
+struct CertSource {
+    server_name: Option<String>,
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+/// Certificates currently served, swapped atomically on reload.
+#[derive(Debug, Default)]
+struct CertSet {
+    default: Option<Arc<CertifiedKey>>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+}
+
+/// Picks the certificate by SNI name, falling back to the default one.
+#[derive(Debug)]
+struct CertResolver {
+    certs: RwLock<CertSet>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.read().unwrap();
+        client_hello
+            .server_name()
+            .and_then(|name| certs.by_name.get(&name.to_ascii_lowercase()))
+            .or(certs.default.as_ref())
+            .or_else(|| certs.by_name.values().next())
+            .cloned()
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Certificate/key pairs named by the configuration. `--tls-cert`/`--tls-key`
+/// give the default pair; `--certs-dir` contributes `public.crt`/`private.key`
+/// at its root (default) and in `<hostname>/` subdirectories (SNI).
+fn cert_sources(config: &Config) -> anyhow::Result<Vec<CertSource>> {
+    let mut sources = Vec::new();
+    if let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) {
+        sources.push(CertSource {
+            server_name: None,
+            cert: cert.into(),
+            key: key.into(),
+        });
+    }
+
+    if let Some(dir) = &config.certs_dir {
+        let dir = Path::new(dir);
+        if sources.is_empty() && dir.join(CERT_FILE).exists() {
+            sources.push(CertSource {
+                server_name: None,
+                cert: dir.join(CERT_FILE),
+                key: dir.join(KEY_FILE),
+            });
+        }
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("reading certs directory {}", dir.display()))?;
+        let mut named: Vec<CertSource> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join(CERT_FILE).exists())
+            .filter_map(|e| {
+                let name = e.file_name().to_str()?.to_ascii_lowercase();
+                // "CAs" holds trusted CAs in MinIO's layout, not a server certificate
+                (name != "cas").then(|| CertSource {
+                    server_name: Some(name),
+                    cert: e.path().join(CERT_FILE),
+                    key: e.path().join(KEY_FILE),
+                })
+            })
+            .collect();
+        named.sort_by(|a, b| a.server_name.cmp(&b.server_name));
+        sources.extend(named);
+    }
+
+    if sources.is_empty() {
+        anyhow::bail!("no TLS certificate found");
+    }
+    Ok(sources)
+}
+
+fn load_certified_key(source: &CertSource, provider: &CryptoProvider) -> anyhow::Result<CertifiedKey> {
+    let certs: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(&source.cert)
+        .with_context(|| format!("reading {}", source.cert.display()))?
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("parsing {}", source.cert.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("{} contains no certificates", source.cert.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(&source.key)
+        .with_context(|| format!("reading private key {}", source.key.display()))?;
+    CertifiedKey::from_der(certs, key, provider)
+        .with_context(|| format!("{} does not match {}", source.key.display(), source.cert.display()))
+}
+
+fn load_cert_set(sources: &[CertSource], provider: &CryptoProvider) -> anyhow::Result<CertSet> {
+    let mut set = CertSet::default();
+    for source in sources {
+        let key = Arc::new(load_certified_key(source, provider)?);
+        match &source.server_name {
+            Some(name) => {
+                set.by_name.insert(name.clone(), key);
+            }
+            None => set.default = Some(key),
+        }
+    }
+    Ok(set)
+}
+
+/// Modification time and size of every certificate file, to detect changes.
+fn fingerprint(sources: &[CertSource]) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    sources
+        .iter()
+        .flat_map(|s| [&s.cert, &s.key])
+        .map(|path| {
+            let meta = std::fs::metadata(path).ok();
+            (
+                path.clone(),
+                meta.as_ref().and_then(|m| m.modified().ok()),
+                meta.map(|m| m.len()).unwrap_or(0),
+            )
+        })
+        .collect()
+}
+
+/// Poll the certificate files and swap in new certificates when they change.
+/// A failed reload keeps serving the previous certificates.
+fn spawn_reloader(config: Config, resolver: Arc<CertResolver>, provider: Arc<CryptoProvider>) {
+    tokio::spawn(async move {
+        let mut last = cert_sources(&config).map(|s| fingerprint(&s)).unwrap_or_default();
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            let sources = match cert_sources(&config) {
+                Ok(sources) => sources,
+                Err(e) => {
+                    tracing::warn!("TLS certificate reload skipped: {:#}", e);
+                    continue;
+                }
+            };
+            let current = fingerprint(&sources);
+            if current == last {
+                continue;
+            }
+            match load_cert_set(&sources, &provider) {
+                Ok(set) => {
+                    *resolver.certs.write().unwrap() = set;
+                    last = current;
+                    tracing::info!("Reloaded TLS certificates ({} pair(s))", sources.len());
+                }
+                // Files may be mid-rotation; retry on the next tick
+                Err(e) => tracing::warn!("TLS certificate reload failed: {:#}", e),
+            }
+        }
+    });
+}
+
+/// Refuse TLS options that would be ignored without a certificate, so asking
+/// for e.g. client certificates never silently starts a plain HTTP listener.
+fn check_options(config: &Config) -> anyhow::Result<()> {
+    if config.tls_cert.is_some() != config.tls_key.is_some() {
+        anyhow::bail!("--tls-cert and --tls-key must be given together");
+    }
+    if config.tls_enabled() {
+        return Ok(());
+    }
+    if config.tls_client_ca.is_some() {
+        anyhow::bail!("--tls-client-ca requires a certificate (--tls-cert/--tls-key or --certs-dir)");
+    }
+    if config.tls_redirect_port.is_some() {
+        anyhow::bail!("--tls-redirect-port requires a certificate (--tls-cert/--tls-key or --certs-dir)");
+    }
+    Ok(())
+}
+
+/// Build the TLS acceptor from the configuration, or `None` if TLS is disabled.
+/// Certificates are reloaded in the background when their files change.
+pub fn acceptor(config: &Config) -> anyhow::Result<Option<TlsAcceptor>> {
+    check_options(config)?;
+    if !config.tls_enabled() {
+        return Ok(None);
+    }
+    let provider = provider();
+    let sources = cert_sources(config)?;
+    let resolver = Arc::new(CertResolver {
+        certs: RwLock::new(load_cert_set(&sources, &provider)?),
+    });
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("configuring TLS protocol versions")?;
+    let builder = match &config.tls_client_ca {
+        Some(ca_path) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(ca_path)
+                .with_context(|| format!("reading client CA {}", ca_path))?
+            {
+                roots
+                    .add(cert.with_context(|| format!("parsing client CA {}", ca_path))?)
+                    .with_context(|| format!("adding client CA from {}", ca_path))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .context("building client certificate verifier")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder.with_cert_resolver(resolver.clone());
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    spawn_reloader(config.clone(), resolver, provider);
+    Ok(Some(TlsAcceptor::from(Arc::new(server_config))))
+}
+
+/// Listener that performs TLS handshakes off the accept loop, so a slow or
+/// failed handshake never blocks other connections.
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, acceptor: TlsAcceptor) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, incoming) = mpsc::channel(64);
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::debug!("TCP accept error: {}", e);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls)) => {
+                            let _ = tx.send((tls, addr)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => tracing::debug!("TLS handshake with {} timed out", addr),
+                    }
+                });
+            }
+        });
+        Ok(Self { incoming, local_addr })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(conn) => conn,
+            // The accept loop never exits while the listener is alive
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Plain-HTTP router that redirects every request to the HTTPS port.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect_to_https(&headers, &uri, https_port)
+    })
+}
+
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let Some(host) = headers.get("host").and_then(|v| v.to_str().ok()) else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    };
+    // Drop the plain-HTTP port, keeping bracketed IPv6 literals intact
+    let host = match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+    let location = if https_port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    };
+    (StatusCode::PERMANENT_REDIRECT, [("location", location)]).into_response()
+}
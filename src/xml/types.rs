@@ -196,6 +196,105 @@ pub struct VersioningConfiguration {
     pub status: Option<String>,
 }
 
+#[derive(Serialize)]
+#[serde(rename = "NotificationConfiguration")]
+pub struct NotificationConfiguration {
+    #[serde(rename = "QueueConfiguration")]
+    pub queue_configurations: Vec<QueueConfiguration>,
+}
+
+#[derive(Serialize)]
+pub struct QueueConfiguration {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Queue")]
+    pub queue: String,
+    #[serde(rename = "Event")]
+    pub events: Vec<String>,
+    #[serde(rename = "Filter", skip_serializing_if = "Option::is_none")]
+    pub filter: Option<NotificationFilter>,
+}
+
+#[derive(Serialize)]
+pub struct NotificationFilter {
+    #[serde(rename = "S3Key")]
+    pub s3_key: S3KeyFilter,
+}
+
+#[derive(Serialize)]
+pub struct S3KeyFilter {
+    #[serde(rename = "FilterRule")]
+    pub rules: Vec<FilterRule>,
+}
+
+#[derive(Serialize)]
+pub struct FilterRule {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "BucketLoggingStatus")]
+pub struct BucketLoggingStatus {
+    #[serde(rename = "LoggingEnabled", skip_serializing_if = "Option::is_none")]
+    pub logging_enabled: Option<LoggingEnabled>,
+}
+
+#[derive(Serialize)]
+pub struct LoggingEnabled {
+    #[serde(rename = "TargetBucket")]
+    pub target_bucket: String,
+    #[serde(rename = "TargetPrefix")]
+    pub target_prefix: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "ReplicationConfiguration")]
+pub struct ReplicationConfiguration {
+    #[serde(rename = "Role")]
+    pub role: String,
+    #[serde(rename = "Rule")]
+    pub rules: Vec<ReplicationRuleXml>,
+}
+
+#[derive(Serialize)]
+pub struct ReplicationRuleXml {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Priority")]
+    pub priority: u32,
+    #[serde(rename = "Filter")]
+    pub filter: ReplicationFilter,
+    #[serde(rename = "DeleteMarkerReplication")]
+    pub delete_marker_replication: ReplicationToggle,
+    #[serde(rename = "DeleteReplication")]
+    pub delete_replication: ReplicationToggle,
+    #[serde(rename = "Destination")]
+    pub destination: ReplicationDestination,
+}
+
+#[derive(Serialize)]
+pub struct ReplicationFilter {
+    #[serde(rename = "Prefix")]
+    pub prefix: String,
+}
+
+#[derive(Serialize)]
+pub struct ReplicationToggle {
+    #[serde(rename = "Status")]
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct ReplicationDestination {
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+}
+
 #[derive(Serialize)]
 #[serde(rename = "ListVersionsResult")]
 pub struct ListVersionsResult {
@@ -246,3 +345,136 @@ pub struct DeleteMarkerEntry {
     #[serde(rename = "LastModified")]
     pub last_modified: String,
 }
+
+/// Per-algorithm checksum elements; only the one matching the object's algorithm is set.
+#[derive(Serialize, Default)]
+pub struct ChecksumFields {
+    #[serde(rename = "ChecksumCRC32", skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<String>,
+    #[serde(rename = "ChecksumCRC32C", skip_serializing_if = "Option::is_none")]
+    pub crc32c: Option<String>,
+    #[serde(rename = "ChecksumCRC64NVME", skip_serializing_if = "Option::is_none")]
+    pub crc64nvme: Option<String>,
+    #[serde(rename = "ChecksumSHA1", skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(rename = "ChecksumSHA256", skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ObjectChecksum {
+    #[serde(flatten)]
+    pub fields: ChecksumFields,
+    #[serde(rename = "ChecksumType", skip_serializing_if = "Option::is_none")]
+    pub checksum_type: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ObjectPartEntry {
+    #[serde(rename = "PartNumber")]
+    pub part_number: u32,
+    #[serde(rename = "Size")]
+    pub size: u64,
+    #[serde(flatten)]
+    pub checksum: ChecksumFields,
+}
+
+#[derive(Serialize)]
+pub struct ObjectParts {
+    #[serde(rename = "TotalPartsCount")]
+    pub total_parts_count: u32,
+    #[serde(rename = "PartNumberMarker")]
+    pub part_number_marker: u32,
+    #[serde(rename = "NextPartNumberMarker")]
+    pub next_part_number_marker: u32,
+    #[serde(rename = "MaxParts")]
+    pub max_parts: u32,
+    #[serde(rename = "IsTruncated")]
+    pub is_truncated: bool,
+    #[serde(rename = "Part", default)]
+    pub parts: Vec<ObjectPartEntry>,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "GetObjectAttributesResponse")]
+pub struct GetObjectAttributesResponse {
+    #[serde(rename = "ETag", skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(rename = "Checksum", skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<ObjectChecksum>,
+    #[serde(rename = "ObjectParts", skip_serializing_if = "Option::is_none")]
+    pub object_parts: Option<ObjectParts>,
+    #[serde(rename = "StorageClass", skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
+    #[serde(rename = "ObjectSize", skip_serializing_if = "Option::is_none")]
+    pub object_size: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "PostResponse")]
+pub struct PostResponse {
+    #[serde(rename = "Location")]
+    pub location: String,
+    #[serde(rename = "Bucket")]
+    pub bucket: String,
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+pub const STS_XMLNS: &str = "https://sts.amazonaws.com/doc/2011-06-15/";
+
+#[derive(Serialize)]
+pub struct StsCredentials {
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    pub secret_access_key: String,
+    #[serde(rename = "SessionToken")]
+    pub session_token: String,
+    #[serde(rename = "Expiration")]
+    pub expiration: String,
+}
+
+#[derive(Serialize)]
+pub struct StsResponseMetadata {
+    #[serde(rename = "RequestId")]
+    pub request_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "AssumeRoleResponse")]
+pub struct AssumeRoleResponse {
+    #[serde(rename = "@xmlns")]
+    pub xmlns: &'static str,
+    #[serde(rename = "AssumeRoleResult")]
+    pub result: AssumeRoleResult,
+    #[serde(rename = "ResponseMetadata")]
+    pub metadata: StsResponseMetadata,
+}
+
+#[derive(Serialize)]
+pub struct AssumeRoleResult {
+    #[serde(rename = "Credentials")]
+    pub credentials: StsCredentials,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "AssumeRoleWithWebIdentityResponse")]
+pub struct AssumeRoleWithWebIdentityResponse {
+    #[serde(rename = "@xmlns")]
+    pub xmlns: &'static str,
+    #[serde(rename = "AssumeRoleWithWebIdentityResult")]
+    pub result: AssumeRoleWithWebIdentityResult,
+    #[serde(rename = "ResponseMetadata")]
+    pub metadata: StsResponseMetadata,
+}
+
+#[derive(Serialize)]
+pub struct AssumeRoleWithWebIdentityResult {
+    #[serde(rename = "Credentials")]
+    pub credentials: StsCredentials,
+    #[serde(rename = "SubjectFromWebIdentityToken")]
+    pub subject: String,
+}
@@ -16,36 +16,110 @@ const REGION: &str = "us-east-1";
 
 /// Spin up a test server on a random port, return the base URL.
 async fn start_server() -> (String, TempDir) {
-    let tmp = TempDir::new().unwrap();
+    start_server_with(|_| {}).await
+}
+
+/// Start a server with default settings adjusted by `configure`.
+async fn start_server_with(configure: impl FnOnce(&mut Config)) -> (String, TempDir) {
+    start_server_in(TempDir::new().unwrap(), configure).await
+}
+
+/// Start a server over an existing data directory, e.g. to simulate a restart.
+async fn start_server_in(tmp: TempDir, configure: impl FnOnce(&mut Config)) -> (String, TempDir) {
     let data_dir = tmp.path().to_str().unwrap().to_string();
 
     let storage = FilesystemStorage::new(&data_dir, false, 10 * 1024 * 1024, 0).await.unwrap();
 
-    let config = Config {
+    let mut config = Config {
         port: 0,
         address: "127.0.0.1".to_string(),
         data_dir,
         access_key: ACCESS_KEY.to_string(),
         secret_key: SECRET_KEY.to_string(),
+        users: vec![],
         region: REGION.to_string(),
         erasure_coding: false,
         chunk_size: 10 * 1024 * 1024,
         parity_shards: 0,
+        domains: vec![],
+        tls_cert: None,
+        tls_key: None,
+        certs_dir: None,
+        tls_client_ca: None,
+        tls_redirect_port: None,
+        notify_webhooks: vec![],
+        replication_targets: vec![],
+        replication_access_keys: vec![],
+        metrics_token: None,
+        ready_min_free_bytes: 0,
+        audit_log_file: None,
+        audit_log_max_size: 100 * 1024 * 1024,
+        audit_webhooks: vec![],
+        access_log_flush_interval: 60,
+        console_session_idle_timeout: 43200,
+        oidc_config_url: None,
+        oidc_client_id: None,
+        oidc_client_secret: None,
+        oidc_redirect_url: None,
+        oidc_scopes: vec!["openid".to_string(), "profile".to_string(), "email".to_string()],
+        oidc_username_claim: "preferred_username".to_string(),
+        oidc_policy_claim: "policy".to_string(),
+        oidc_groups_claim: "groups".to_string(),
+        oidc_group_policies: vec![],
+        oidc_display_name: "SSO".to_string(),
+        trusted_proxies: vec![],
+        key_request_rate: 0,
+        key_bandwidth: 0,
+        bucket_request_rate: 0,
+        bucket_bandwidth: 0,
+        usage_scan_interval: 3600,
+        archive_max_size: 10 * 1024 * 1024 * 1024,
+        archive_max_objects: 10_000,
     };
-
+    configure(&mut config);
+    let tls = maxio::tls::acceptor(&config).unwrap();
+    let port = config.port;
+
+    let storage = Arc::new(storage);
+    maxio::storage::crawler::spawn(storage.clone(), std::time::Duration::from_secs(config.usage_scan_interval));
+    let notifier = maxio::notify::Notifier::new(&config).unwrap();
+    let replicator = maxio::replication::Replicator::new(&config, storage.clone()).unwrap();
+    let audit = maxio::audit::AuditLog::new(&config, storage.clone()).unwrap();
+    let credentials = maxio::auth::credentials::Credentials::new(&config).unwrap();
+    let sessions = maxio::auth::session::SessionStore::open(&config).unwrap();
+    let oidc = maxio::auth::oidc::Oidc::new(&config).unwrap();
+    let trusted_proxies = maxio::proxy::TrustedProxies::new(&config).unwrap();
+    let throttle = maxio::throttle::Throttle::new(&config);
     let state = AppState {
-        storage: Arc::new(storage),
+        storage,
         config: Arc::new(config),
         login_rate_limiter: Arc::new(maxio::api::console::LoginRateLimiter::new()),
+        notifier: Arc::new(notifier),
+        replicator: Arc::new(replicator),
+        audit: Arc::new(audit),
+        credentials: Arc::new(credentials),
+        sessions: Arc::new(sessions),
+        oidc: oidc.map(Arc::new),
+        trusted_proxies: Arc::new(trusted_proxies),
+        throttle: Arc::new(throttle),
     };
 
     let app = server::build_router(state);
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await.unwrap();
     let addr = listener.local_addr().unwrap();
-    let base_url = format!("http://{}", addr);
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let base_url = format!("{}://{}", scheme, addr);
 
     tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
+        let make_service = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+        match tls {
+            Some(acceptor) => {
+                use axum::serve::ListenerExt;
+                let listener = maxio::tls::TlsListener::new(listener, acceptor).unwrap().tap_io(|_| {});
+                axum::serve(listener, make_service).await.unwrap();
+            }
+            None => axum::serve(listener, make_service).await.unwrap(),
+        }
     });
 
     (base_url, tmp)
@@ -246,11 +320,20 @@ async fn s3_request(
     method: &str,
     url: &str,
     body: Vec<u8>,
+) -> reqwest::Response {
+    s3_request_via(&client(), method, url, body).await
+}
+
+/// Like s3_request but sent through the given client.
+async fn s3_request_via(
+    client: &reqwest::Client,
+    method: &str,
+    url: &str,
+    body: Vec<u8>,
 ) -> reqwest::Response {
     let mut headers = Vec::new();
     sign_request(method, url, &mut headers, &body);
 
-    let client = client();
     let mut builder = match method {
         "GET" => client.get(url),
         "PUT" => client.put(url),
@@ -370,6 +453,11 @@ async fn s3_put_chunked(
     url: &str,
     data: &[u8],
 ) -> reqwest::Response {
+    s3_put_chunked_declaring(url, data, data.len()).await
+}
+
+/// Like s3_put_chunked, but with `declared` as x-amz-decoded-content-length.
+async fn s3_put_chunked_declaring(url: &str, data: &[u8], declared: usize) -> reqwest::Response {
     let parsed = reqwest::Url::parse(url).unwrap();
     let host = parsed.host_str().unwrap();
     let port = parsed.port().unwrap();
@@ -388,7 +476,7 @@ async fn s3_put_chunked(
         ("host".to_string(), host_header.clone()),
         ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
         ("x-amz-date".to_string(), amz_date.clone()),
-        ("x-amz-decoded-content-length".to_string(), data.len().to_string()),
+        ("x-amz-decoded-content-length".to_string(), declared.to_string()),
     ];
     sign_headers.sort_by(|a, b| a.0.cmp(&b.0));
 
@@ -455,7 +543,7 @@ async fn s3_put_chunked(
         .header("host", &host_header)
         .header("x-amz-date", &amz_date)
         .header("x-amz-content-sha256", payload_hash)
-        .header("x-amz-decoded-content-length", data.len().to_string())
+        .header("x-amz-decoded-content-length", declared.to_string())
         .header("authorization", &auth)
         .header("content-type", "application/octet-stream")
         .body(chunked_body)
@@ -1290,6 +1378,185 @@ async fn test_multipart_etag_format() {
     assert!(etag.contains("-2"));
 }
 
+/// Run a full multipart upload of `parts` to `path` and return the complete response.
+async fn multipart_upload(base_url: &str, path: &str, parts: Vec<Vec<u8>>) -> reqwest::Response {
+    multipart_upload_with_headers(base_url, path, vec![], parts, vec![]).await
+}
+
+/// Like `multipart_upload`, with extra headers on the create and complete requests.
+async fn multipart_upload_with_headers(
+    base_url: &str,
+    path: &str,
+    create_headers: Vec<(&str, &str)>,
+    parts: Vec<Vec<u8>>,
+    complete_headers: Vec<(&str, &str)>,
+) -> reqwest::Response {
+    let create = s3_request_with_headers(
+        "POST",
+        &format!("{}{}?uploads=", base_url, path),
+        vec![],
+        create_headers,
+    )
+    .await;
+    let upload_id = extract_xml_tag(&create.text().await.unwrap(), "UploadId").unwrap();
+
+    let mut complete_xml = String::from("<CompleteMultipartUpload>");
+    for (i, part) in parts.into_iter().enumerate() {
+        let n = i + 1;
+        let resp = s3_request(
+            "PUT",
+            &format!("{}{}?partNumber={}&uploadId={}", base_url, path, n, upload_id),
+            part,
+        )
+        .await;
+        let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+        complete_xml.push_str(&format!(
+            "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+            n, etag
+        ));
+    }
+    complete_xml.push_str("</CompleteMultipartUpload>");
+
+    s3_request_with_headers(
+        "POST",
+        &format!("{}{}?uploadId={}", base_url, path, upload_id),
+        complete_xml.into_bytes(),
+        complete_headers,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_multipart_complete_keeps_parts() {
+    let (base_url, tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/mybucket", base_url), vec![]).await;
+    let p1 = vec![b'a'; 5 * 1024 * 1024];
+    let complete = multipart_upload(&base_url, "/mybucket/dir/large.bin", vec![p1, b"tail".to_vec()]).await;
+    assert_eq!(complete.status(), 200);
+
+    // Parts are moved into a composite object instead of being concatenated
+    let bucket_dir = tmp.path().join("buckets").join("mybucket");
+    assert!(bucket_dir.join("dir/large.bin.mp/manifest.json").is_file());
+    assert!(bucket_dir.join("dir/large.bin.mp/00001").is_file());
+    assert!(bucket_dir.join("dir/large.bin.mp/00002").is_file());
+    assert!(!bucket_dir.join("dir/large.bin").exists());
+    let uploads: Vec<_> = std::fs::read_dir(bucket_dir.join(".uploads")).unwrap().collect();
+    assert!(uploads.is_empty());
+
+    let list = s3_request("GET", &format!("{}/mybucket?list-type=2", base_url), vec![]).await;
+    let body = list.text().await.unwrap();
+    assert!(body.contains("<Key>dir/large.bin</Key>"));
+    assert!(body.contains(&format!("<Size>{}</Size>", 5 * 1024 * 1024 + 4)));
+
+    // Overwriting with a plain PUT replaces the composite object
+    s3_request("PUT", &format!("{}/mybucket/dir/large.bin", base_url), b"small".to_vec()).await;
+    assert!(!bucket_dir.join("dir/large.bin.mp").exists());
+    let get = s3_request("GET", &format!("{}/mybucket/dir/large.bin", base_url), vec![]).await;
+    assert_eq!(get.bytes().await.unwrap().as_ref(), b"small");
+
+    let complete = multipart_upload(&base_url, "/mybucket/dir/large.bin", vec![b"again".to_vec()]).await;
+    assert_eq!(complete.status(), 200);
+    let del = s3_request("DELETE", &format!("{}/mybucket/dir/large.bin", base_url), vec![]).await;
+    assert_eq!(del.status(), 204);
+    assert!(!bucket_dir.join("dir/large.bin.mp").exists());
+}
+
+#[tokio::test]
+async fn test_multipart_range_across_parts() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/mybucket", base_url), vec![]).await;
+    let part_size = 5 * 1024 * 1024;
+    let p1: Vec<u8> = (0..part_size).map(|i| (i % 251) as u8).collect();
+    let p2: Vec<u8> = (0..1000).map(|i| (i % 13) as u8).collect();
+    multipart_upload(&base_url, "/mybucket/mp.bin", vec![p1.clone(), p2.clone()]).await;
+    let mut full = p1;
+    full.extend_from_slice(&p2);
+
+    let start = part_size - 10;
+    let end = part_size + 9;
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/mybucket/mp.bin", base_url),
+        vec![],
+        vec![("range", &format!("bytes={}-{}", start, end))],
+    )
+    .await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(
+        resp.headers()["content-range"],
+        format!("bytes {}-{}/{}", start, end, full.len()).as_str()
+    );
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), &full[start..=end]);
+
+    let get = s3_request("GET", &format!("{}/mybucket/mp.bin", base_url), vec![]).await;
+    assert_eq!(get.bytes().await.unwrap().as_ref(), full.as_slice());
+}
+
+#[tokio::test]
+async fn test_get_object_part_number() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/mybucket", base_url), vec![]).await;
+    let part_size = 5 * 1024 * 1024;
+    let p1 = vec![b'a'; part_size];
+    multipart_upload(&base_url, "/mybucket/mp.bin", vec![p1, b"tail".to_vec()]).await;
+
+    let resp = s3_request("GET", &format!("{}/mybucket/mp.bin?partNumber=2", base_url), vec![]).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers()["x-amz-mp-parts-count"], "2");
+    assert_eq!(resp.headers()["content-length"], "4");
+    assert_eq!(
+        resp.headers()["content-range"],
+        format!("bytes {}-{}/{}", part_size, part_size + 3, part_size + 4).as_str()
+    );
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"tail");
+
+    let head = s3_request("HEAD", &format!("{}/mybucket/mp.bin?partNumber=1", base_url), vec![]).await;
+    assert_eq!(head.status(), 206);
+    assert_eq!(head.headers()["content-length"], part_size.to_string().as_str());
+    assert_eq!(head.headers()["x-amz-mp-parts-count"], "2");
+
+    let missing = s3_request("GET", &format!("{}/mybucket/mp.bin?partNumber=3", base_url), vec![]).await;
+    assert_eq!(missing.status(), 416);
+    assert!(missing.text().await.unwrap().contains("InvalidPartNumber"));
+
+    // Single-PUT objects have exactly one part
+    s3_request("PUT", &format!("{}/mybucket/plain.txt", base_url), b"hello".to_vec()).await;
+    let resp = s3_request("GET", &format!("{}/mybucket/plain.txt?partNumber=1", base_url), vec![]).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers()["x-amz-mp-parts-count"], "1");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"hello");
+    let resp = s3_request("GET", &format!("{}/mybucket/plain.txt?partNumber=2", base_url), vec![]).await;
+    assert_eq!(resp.status(), 416);
+}
+
+#[tokio::test]
+async fn test_failed_put_keeps_multipart_object() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/mybucket", base_url), vec![]).await;
+    let mut full = vec![b'a'; 5 * 1024 * 1024];
+    multipart_upload(&base_url, "/mybucket/mp.bin", vec![full.clone(), b"tail".to_vec()]).await;
+    full.extend_from_slice(b"tail");
+
+    // An overwrite refused for its checksum leaves the composite object intact
+    let resp = s3_request_with_headers(
+        "PUT",
+        &format!("{}/mybucket/mp.bin", base_url),
+        b"replacement".to_vec(),
+        vec![("x-amz-checksum-crc32", "AAAAAAAA")],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let resp = s3_request("GET", &format!("{}/mybucket/mp.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), full.as_slice());
+
+    // A successful one replaces it
+    s3_request("PUT", &format!("{}/mybucket/mp.bin", base_url), b"replacement".to_vec()).await;
+    let resp = s3_request("GET", &format!("{}/mybucket/mp.bin?partNumber=1", base_url), vec![]).await;
+    assert_eq!(resp.headers()["x-amz-mp-parts-count"], "1");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"replacement");
+}
+
 #[tokio::test]
 async fn test_copy_object_basic() {
     let (base_url, _tmp) = start_server().await;
@@ -1546,6 +1813,66 @@ async fn test_presigned_put_object() {
     assert_eq!(resp.bytes().await.unwrap().as_ref(), body);
 }
 
+/// Keys whose URI encoding differs from the key itself. Clients sign the
+/// path encoded once (`/` kept), which is also what they send.
+const AWKWARD_KEYS: [&str; 5] = ["100% done.txt", "a+b.txt", "my file.txt", "already%20encoded.txt", "dir/ünï (1).txt"];
+
+fn encoded_key_path(bucket: &str, key: &str) -> String {
+    let segments: Vec<String> = key.split('/').map(percent_encode_s3).collect();
+    format!("/{}/{}", bucket, segments.join("/"))
+}
+
+#[tokio::test]
+async fn test_signed_requests_with_awkward_keys() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/awkward", base_url), vec![]).await;
+
+    for key in AWKWARD_KEYS {
+        let url = format!("{}{}", base_url, encoded_key_path("awkward", key));
+        let resp = s3_request("PUT", &url, key.as_bytes().to_vec()).await;
+        assert_eq!(resp.status(), 200, "PUT {}", key);
+        let resp = s3_request("GET", &url, vec![]).await;
+        assert_eq!(resp.status(), 200, "GET {}", key);
+        assert_eq!(resp.bytes().await.unwrap().as_ref(), key.as_bytes());
+    }
+
+    // Stored under the decoded key, not the encoded path
+    let body = s3_request("GET", &format!("{}/awkward?list-type=2", base_url), vec![])
+        .await
+        .text()
+        .await
+        .unwrap();
+    for key in AWKWARD_KEYS {
+        assert!(body.contains(&format!("<Key>{}</Key>", key)), "{} missing from {}", key, body);
+    }
+    assert!(!body.contains("%2520"));
+}
+
+#[tokio::test]
+async fn test_presigned_requests_with_awkward_keys() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/awkward", base_url), vec![]).await;
+
+    for key in AWKWARD_KEYS {
+        let path = encoded_key_path("awkward", key);
+        let resp = client()
+            .put(presign_url(&base_url, "PUT", &path, 300))
+            .body(key.as_bytes().to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200, "presigned PUT {}", key);
+        let resp = client().get(presign_url(&base_url, "GET", &path, 300)).send().await.unwrap();
+        assert_eq!(resp.status(), 200, "presigned GET {}", key);
+        assert_eq!(resp.bytes().await.unwrap().as_ref(), key.as_bytes());
+    }
+
+    // A signature over a differently encoded path is refused
+    let signed = presign_url(&base_url, "GET", "/awkward/my%20file.txt", 300);
+    let resp = client().get(signed.replace("my%20file.txt", "my%2520file.txt")).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+}
+
 #[tokio::test]
 async fn test_presigned_head_object() {
     let (base_url, _tmp) = start_server().await;
@@ -2004,16 +2331,68 @@ async fn start_server_ec() -> (String, TempDir) {
         data_dir,
         access_key: ACCESS_KEY.to_string(),
         secret_key: SECRET_KEY.to_string(),
+        users: vec![],
         region: REGION.to_string(),
         erasure_coding: true,
         chunk_size: 1024,
         parity_shards: 0,
+        domains: vec![],
+        tls_cert: None,
+        tls_key: None,
+        certs_dir: None,
+        tls_client_ca: None,
+        tls_redirect_port: None,
+        notify_webhooks: vec![],
+        replication_targets: vec![],
+        replication_access_keys: vec![],
+        metrics_token: None,
+        ready_min_free_bytes: 0,
+        audit_log_file: None,
+        audit_log_max_size: 100 * 1024 * 1024,
+        audit_webhooks: vec![],
+        access_log_flush_interval: 60,
+        console_session_idle_timeout: 43200,
+        oidc_config_url: None,
+        oidc_client_id: None,
+        oidc_client_secret: None,
+        oidc_redirect_url: None,
+        oidc_scopes: vec!["openid".to_string(), "profile".to_string(), "email".to_string()],
+        oidc_username_claim: "preferred_username".to_string(),
+        oidc_policy_claim: "policy".to_string(),
+        oidc_groups_claim: "groups".to_string(),
+        oidc_group_policies: vec![],
+        oidc_display_name: "SSO".to_string(),
+        trusted_proxies: vec![],
+        key_request_rate: 0,
+        key_bandwidth: 0,
+        bucket_request_rate: 0,
+        bucket_bandwidth: 0,
+        usage_scan_interval: 3600,
+        archive_max_size: 10 * 1024 * 1024 * 1024,
+        archive_max_objects: 10_000,
     };
 
+    let storage = Arc::new(storage);
+    let notifier = maxio::notify::Notifier::new(&config).unwrap();
+    let replicator = maxio::replication::Replicator::new(&config, storage.clone()).unwrap();
+    let audit = maxio::audit::AuditLog::new(&config, storage.clone()).unwrap();
+    let credentials = maxio::auth::credentials::Credentials::new(&config).unwrap();
+    let sessions = maxio::auth::session::SessionStore::open(&config).unwrap();
+    let oidc = maxio::auth::oidc::Oidc::new(&config).unwrap();
+    let trusted_proxies = maxio::proxy::TrustedProxies::new(&config).unwrap();
+    let throttle = maxio::throttle::Throttle::new(&config);
     let state = AppState {
-        storage: Arc::new(storage),
+        storage,
         config: Arc::new(config),
         login_rate_limiter: Arc::new(maxio::api::console::LoginRateLimiter::new()),
+        notifier: Arc::new(notifier),
+        replicator: Arc::new(replicator),
+        audit: Arc::new(audit),
+        credentials: Arc::new(credentials),
+        sessions: Arc::new(sessions),
+        oidc: oidc.map(Arc::new),
+        trusted_proxies: Arc::new(trusted_proxies),
+        throttle: Arc::new(throttle),
     };
 
     let app = server::build_router(state);
@@ -2225,6 +2604,52 @@ async fn test_ec_list_objects() {
     assert!(!body.contains(".ec"), "body should not contain .ec: {}", body);
 }
 
+#[tokio::test]
+async fn test_ec_get_object_part_number() {
+    let (base_url, _tmp) = start_server_ec().await;
+    s3_request("PUT", &format!("{}/testbucket", base_url), vec![]).await;
+    let part_size = 5 * 1024 * 1024;
+    let p1: Vec<u8> = (0..part_size).map(|i| (i % 199) as u8).collect();
+    let p2 = b"ec tail".to_vec();
+    let complete = multipart_upload(&base_url, "/testbucket/mp.bin", vec![p1.clone(), p2.clone()]).await;
+    assert_eq!(complete.status(), 200);
+
+    let resp = s3_request("GET", &format!("{}/testbucket/mp.bin?partNumber=1", base_url), vec![]).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers()["x-amz-mp-parts-count"], "2");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), p1.as_slice());
+
+    let resp = s3_request("GET", &format!("{}/testbucket/mp.bin?partNumber=2", base_url), vec![]).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), p2.as_slice());
+}
+
+#[tokio::test]
+async fn test_get_object_version_part_number() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/verbucket", base_url), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/verbucket?versioning", base_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let put = s3_request("PUT", &format!("{}/verbucket/obj.txt", base_url), b"first".to_vec()).await;
+    let vid = put.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    s3_request("PUT", &format!("{}/verbucket/obj.txt", base_url), b"second!".to_vec()).await;
+
+    let resp = s3_request(
+        "GET",
+        &format!("{}/verbucket/obj.txt?versionId={}&partNumber=1", base_url, vid),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers()["x-amz-version-id"], vid.as_str());
+    assert_eq!(resp.headers()["x-amz-mp-parts-count"], "1");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"first");
+}
+
 // --- Checksum tests ---
 
 #[tokio::test]
@@ -2361,237 +2786,4101 @@ async fn test_put_object_with_sha256_checksum() {
     );
 }
 
-// --- Parity / Reed-Solomon Tests ---
+#[tokio::test]
+async fn test_multipart_composite_checksum() {
+    use base64::Engine;
+    let b64 = base64::engine::general_purpose::STANDARD;
 
-/// Start a server with erasure coding + parity enabled (small chunks for testing).
-async fn start_server_parity(parity_shards: u32) -> (String, TempDir) {
-    let tmp = TempDir::new().unwrap();
-    let data_dir = tmp.path().to_str().unwrap().to_string();
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/checksum-bucket", base_url), vec![]).await;
+    let create = s3_request_with_headers(
+        "POST",
+        &format!("{}/checksum-bucket/mp.bin?uploads=", base_url),
+        vec![],
+        vec![("x-amz-checksum-algorithm", "CRC32")],
+    )
+    .await;
+    let upload_id = extract_xml_tag(&create.text().await.unwrap(), "UploadId").unwrap();
 
-    // 100-byte chunks for easy multi-chunk testing
-    let storage = FilesystemStorage::new(&data_dir, true, 100, parity_shards).await.unwrap();
+    let parts = [vec![b'x'; 5 * 1024 * 1024], b"last part".to_vec()];
+    let mut part_crcs = Vec::new();
+    let mut complete_xml = String::from("<CompleteMultipartUpload>");
+    for (i, data) in parts.iter().enumerate() {
+        let crc = crc32fast::hash(data).to_be_bytes();
+        let crc_b64 = b64.encode(crc);
+        let resp = s3_request_with_headers(
+            "PUT",
+            &format!("{}/checksum-bucket/mp.bin?partNumber={}&uploadId={}", base_url, i + 1, upload_id),
+            data.clone(),
+            vec![("x-amz-checksum-crc32", &crc_b64)],
+        )
+        .await;
+        let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+        complete_xml.push_str(&format!(
+            "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+            i + 1,
+            etag
+        ));
+        part_crcs.push(crc);
+    }
+    complete_xml.push_str("</CompleteMultipartUpload>");
+    let complete = s3_request(
+        "POST",
+        &format!("{}/checksum-bucket/mp.bin?uploadId={}", base_url, upload_id),
+        complete_xml.into_bytes(),
+    )
+    .await;
+    assert_eq!(complete.status(), 200);
+    assert_eq!(complete.headers()["x-amz-checksum-type"], "COMPOSITE");
+
+    let expected = format!("{}-2", b64.encode(crc32fast::hash(&part_crcs.concat()).to_be_bytes()));
+    let head = s3_request("HEAD", &format!("{}/checksum-bucket/mp.bin", base_url), vec![]).await;
+    assert_eq!(head.headers()["x-amz-checksum-crc32"], expected.as_str());
+    assert_eq!(head.headers()["x-amz-checksum-type"], "COMPOSITE");
+
+    // partNumber requests report the part's own checksum and the object's ETag
+    let head_part = s3_request("HEAD", &format!("{}/checksum-bucket/mp.bin?partNumber=2", base_url), vec![]).await;
+    assert_eq!(head_part.status(), 206);
+    assert_eq!(head_part.headers()["x-amz-checksum-crc32"], b64.encode(part_crcs[1]).as_str());
+    assert_eq!(head_part.headers()["etag"], head.headers()["etag"]);
+    assert!(head.headers()["etag"].to_str().unwrap().ends_with("-2\""));
+
+    // Single PUT checksums are full-object checksums
+    let put = s3_request_with_headers(
+        "PUT",
+        &format!("{}/checksum-bucket/single.txt", base_url),
+        b"single".to_vec(),
+        vec![("x-amz-checksum-algorithm", "CRC32")],
+    )
+    .await;
+    assert_eq!(put.status(), 200);
+    let head = s3_request("HEAD", &format!("{}/checksum-bucket/single.txt", base_url), vec![]).await;
+    assert_eq!(head.headers()["x-amz-checksum-type"], "FULL_OBJECT");
+}
 
-    let config = Config {
-        port: 0,
-        address: "127.0.0.1".to_string(),
-        data_dir,
-        access_key: ACCESS_KEY.to_string(),
-        secret_key: SECRET_KEY.to_string(),
-        region: REGION.to_string(),
-        erasure_coding: true,
-        chunk_size: 100,
-        parity_shards,
-    };
+#[tokio::test]
+async fn test_put_object_with_crc64nvme_checksum() {
+    use base64::Engine;
 
-    let state = AppState {
-        storage: Arc::new(storage),
-        config: Arc::new(config),
-        login_rate_limiter: Arc::new(maxio::api::console::LoginRateLimiter::new()),
-    };
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/checksum-bucket", base_url), vec![]).await;
 
-    let app = server::build_router(state);
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let addr = listener.local_addr().unwrap();
-    let base_url = format!("http://{}", addr);
+    let body = b"crc64 nvme test data";
+    let crc = crc::Crc::<u64>::new(&crc::CRC_64_NVME).checksum(body);
+    let crc_b64 = base64::engine::general_purpose::STANDARD.encode(crc.to_be_bytes());
 
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
+    let resp = s3_request_with_headers(
+        "PUT",
+        &format!("{}/checksum-bucket/nvme.txt", base_url),
+        body.to_vec(),
+        vec![("x-amz-checksum-crc64nvme", &crc_b64)],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-amz-checksum-crc64nvme"], crc_b64.as_str());
 
-    (base_url, tmp)
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/checksum-bucket/nvme.txt", base_url),
+        vec![],
+        vec![("x-amz-checksum-mode", "ENABLED")],
+    )
+    .await;
+    assert_eq!(resp.headers()["x-amz-checksum-crc64nvme"], crc_b64.as_str());
+    assert_eq!(resp.headers()["x-amz-checksum-type"], "FULL_OBJECT");
+
+    let resp = s3_request_with_headers(
+        "PUT",
+        &format!("{}/checksum-bucket/bad.txt", base_url),
+        body.to_vec(),
+        vec![("x-amz-checksum-crc64nvme", "AAAAAAAAAAA=")],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
 }
 
 #[tokio::test]
-async fn test_parity_write_creates_parity_chunks() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_multipart_full_object_checksum() {
+    use base64::Engine;
+    let b64 = base64::engine::general_purpose::STANDARD;
 
-    // Create bucket
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/checksum-bucket", base_url), vec![]).await;
+    let p1: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 241) as u8).collect();
+    let p2 = b"the final part".to_vec();
+    let full = [p1.clone(), p2.clone()].concat();
+
+    // CRC64NVME defaults to a full-object checksum; parts without checksum headers get one computed
+    let nvme = b64.encode(crc::Crc::<u64>::new(&crc::CRC_64_NVME).checksum(&full).to_be_bytes());
+    let complete = multipart_upload_with_headers(
+        &base_url,
+        "/checksum-bucket/nvme.bin",
+        vec![("x-amz-checksum-algorithm", "CRC64NVME")],
+        vec![p1.clone(), p2.clone()],
+        vec![("x-amz-checksum-crc64nvme", &nvme)],
+    )
+    .await;
+    assert_eq!(complete.status(), 200);
+    assert_eq!(complete.headers()["x-amz-checksum-crc64nvme"], nvme.as_str());
+    assert_eq!(complete.headers()["x-amz-checksum-type"], "FULL_OBJECT");
+    let head = s3_request("HEAD", &format!("{}/checksum-bucket/nvme.bin", base_url), vec![]).await;
+    assert_eq!(head.headers()["x-amz-checksum-crc64nvme"], nvme.as_str());
+    assert_eq!(head.headers()["x-amz-checksum-type"], "FULL_OBJECT");
+
+    // CRC32 and CRC32C opt in to full-object checksums explicitly
+    let crc32 = b64.encode(crc32fast::hash(&full).to_be_bytes());
+    let complete = multipart_upload_with_headers(
+        &base_url,
+        "/checksum-bucket/crc32.bin",
+        vec![("x-amz-checksum-algorithm", "CRC32"), ("x-amz-checksum-type", "FULL_OBJECT")],
+        vec![p1.clone(), p2.clone()],
+        vec![],
+    )
+    .await;
+    assert_eq!(complete.status(), 200);
+    assert_eq!(complete.headers()["x-amz-checksum-crc32"], crc32.as_str());
+
+    let crc32c = b64.encode(crc32c::crc32c(&full).to_be_bytes());
+    let complete = multipart_upload_with_headers(
+        &base_url,
+        "/checksum-bucket/crc32c.bin",
+        vec![("x-amz-checksum-algorithm", "CRC32C"), ("x-amz-checksum-type", "FULL_OBJECT")],
+        vec![p1.clone(), p2.clone()],
+        vec![("x-amz-checksum-crc32c", &crc32c)],
+    )
+    .await;
+    assert_eq!(complete.status(), 200);
 
-    // Write 350 bytes → 4 data chunks (100+100+100+50) + 2 parity
-    let data = vec![0xABu8; 350];
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data).await;
+    // A wrong full-object checksum on completion is rejected
+    let complete = multipart_upload_with_headers(
+        &base_url,
+        "/checksum-bucket/wrong.bin",
+        vec![("x-amz-checksum-algorithm", "CRC64NVME")],
+        vec![p1, p2],
+        vec![("x-amz-checksum-crc64nvme", "AAAAAAAAAAA=")],
+    )
+    .await;
+    assert_eq!(complete.status(), 400);
+    assert!(complete.text().await.unwrap().contains("BadDigest"));
+    let head = s3_request("HEAD", &format!("{}/checksum-bucket/wrong.bin", base_url), vec![]).await;
+    assert_eq!(head.status(), 404);
+}
 
-    // Check the .ec directory
-    let ec_dir = tmp.path().join("buckets/parity-test/file.bin.ec");
-    assert!(ec_dir.is_dir());
+#[tokio::test]
+async fn test_multipart_checksum_validation() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/checksum-bucket", base_url), vec![]).await;
 
-    // Should have 6 chunk files + manifest.json = 7 entries
-    let entries: Vec<_> = std::fs::read_dir(&ec_dir).unwrap().collect();
-    assert_eq!(entries.len(), 7, "expected 4 data + 2 parity + 1 manifest");
+    // SHA digests cannot be combined into a full-object checksum
+    let create = s3_request_with_headers(
+        "POST",
+        &format!("{}/checksum-bucket/sha.bin?uploads=", base_url),
+        vec![],
+        vec![("x-amz-checksum-algorithm", "SHA256"), ("x-amz-checksum-type", "FULL_OBJECT")],
+    )
+    .await;
+    assert_eq!(create.status(), 400);
 
-    // Verify manifest
-    let manifest: serde_json::Value = serde_json::from_str(
-        &std::fs::read_to_string(ec_dir.join("manifest.json")).unwrap()
-    ).unwrap();
-    assert_eq!(manifest["version"], 2);
+    let create = s3_request_with_headers(
+        "POST",
+        &format!("{}/checksum-bucket/mp.bin?uploads=", base_url),
+        vec![],
+        vec![("x-amz-checksum-algorithm", "CRC32")],
+    )
+    .await;
+    assert_eq!(create.headers()["x-amz-checksum-algorithm"], "CRC32");
+    assert_eq!(create.headers()["x-amz-checksum-type"], "COMPOSITE");
+    let upload_id = extract_xml_tag(&create.text().await.unwrap(), "UploadId").unwrap();
+
+    // A part checksum with a different algorithm than the upload's is refused
+    let resp = s3_request_with_headers(
+        "PUT",
+        &format!("{}/checksum-bucket/mp.bin?partNumber=1&uploadId={}", base_url, upload_id),
+        b"data".to_vec(),
+        vec![("x-amz-checksum-algorithm", "SHA1")],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    let resp = s3_request(
+        "PUT",
+        &format!("{}/checksum-bucket/mp.bin?partNumber=1&uploadId={}", base_url, upload_id),
+        b"data".to_vec(),
+    )
+    .await;
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    let part_crc = resp.headers()["x-amz-checksum-crc32"].to_str().unwrap().to_string();
+
+    // Part checksums echoed in the completion body must match what was stored
+    let bad_xml = format!(
+        "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber><ETag>{}</ETag><ChecksumCRC32>AAAAAA==</ChecksumCRC32></Part></CompleteMultipartUpload>",
+        etag
+    );
+    let complete = s3_request(
+        "POST",
+        &format!("{}/checksum-bucket/mp.bin?uploadId={}", base_url, upload_id),
+        bad_xml.into_bytes(),
+    )
+    .await;
+    assert_eq!(complete.status(), 400);
+    assert!(complete.text().await.unwrap().contains("InvalidPart"));
+
+    let good_xml = format!(
+        "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber><ETag>{}</ETag><ChecksumCRC32>{}</ChecksumCRC32></Part></CompleteMultipartUpload>",
+        etag, part_crc
+    );
+    let complete = s3_request(
+        "POST",
+        &format!("{}/checksum-bucket/mp.bin?uploadId={}", base_url, upload_id),
+        good_xml.into_bytes(),
+    )
+    .await;
+    assert_eq!(complete.status(), 200);
+}
+
+#[tokio::test]
+async fn test_get_object_attributes() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/attr-bucket", base_url), vec![]).await;
+    let p1 = vec![b'a'; 5 * 1024 * 1024];
+    let complete = multipart_upload_with_headers(
+        &base_url,
+        "/attr-bucket/mp.bin",
+        vec![("x-amz-checksum-algorithm", "CRC32")],
+        vec![p1, b"tail".to_vec()],
+        vec![],
+    )
+    .await;
+    let checksum = complete.headers()["x-amz-checksum-crc32"].to_str().unwrap().to_string();
+    let etag = extract_xml_tag(&complete.text().await.unwrap(), "ETag").unwrap();
+
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/attr-bucket/mp.bin?attributes", base_url),
+        vec![],
+        vec![("x-amz-object-attributes", "ETag,Checksum,ObjectParts,StorageClass,ObjectSize")],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.text().await.unwrap();
+    assert_eq!(extract_xml_tag(&body, "ETag").unwrap(), etag.trim_matches('"'));
+    assert!(body.contains(&format!("<ChecksumCRC32>{}</ChecksumCRC32>", checksum)));
+    assert!(body.contains("<ChecksumType>COMPOSITE</ChecksumType>"));
+    assert_eq!(extract_xml_tag(&body, "TotalPartsCount").unwrap(), "2");
+    assert_eq!(extract_xml_tag(&body, "StorageClass").unwrap(), "STANDARD");
+    assert_eq!(extract_xml_tag(&body, "ObjectSize").unwrap(), (5 * 1024 * 1024 + 4).to_string());
+    assert_eq!(body.matches("<Part>").count(), 2);
+
+    // Paging through the part list
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/attr-bucket/mp.bin?attributes", base_url),
+        vec![],
+        vec![
+            ("x-amz-object-attributes", "ObjectParts"),
+            ("x-amz-max-parts", "1"),
+            ("x-amz-part-number-marker", "1"),
+        ],
+    )
+    .await;
+    let body = resp.text().await.unwrap();
+    assert!(!body.contains("<ETag>"));
+    assert_eq!(extract_xml_tag(&body, "IsTruncated").unwrap(), "false");
+    assert_eq!(extract_xml_tag(&body, "NextPartNumberMarker").unwrap(), "2");
+    assert!(body.contains("<PartNumber>2</PartNumber><Size>4</Size>"));
+
+    // Missing attribute selection is rejected
+    let resp = s3_request("GET", &format!("{}/attr-bucket/mp.bin?attributes", base_url), vec![]).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_get_object_attributes_version() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/attr-bucket", base_url), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/attr-bucket?versioning", base_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let put = s3_request("PUT", &format!("{}/attr-bucket/obj.txt", base_url), b"v1".to_vec()).await;
+    let vid = put.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    s3_request("PUT", &format!("{}/attr-bucket/obj.txt", base_url), b"version 2".to_vec()).await;
+
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/attr-bucket/obj.txt?attributes&versionId={}", base_url, vid),
+        vec![],
+        vec![("x-amz-object-attributes", "ObjectSize,ObjectParts")],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-amz-version-id"], vid.as_str());
+    let body = resp.text().await.unwrap();
+    assert_eq!(extract_xml_tag(&body, "ObjectSize").unwrap(), "2");
+    // Single-PUT objects have no part list
+    assert!(!body.contains("ObjectParts"));
+}
+
+// --- Browser POST uploads ---
+
+/// Build a signed POST policy form. Returns the fields to send before the file.
+fn post_policy_fields(conditions: Vec<serde_json::Value>, fields: Vec<(&str, &str)>) -> Vec<(String, String)> {
+    let now = chrono::Utc::now();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential = format!("{}/{}/{}/s3/aws4_request", ACCESS_KEY, date_stamp, REGION);
+    let expiration = (now + chrono::Duration::minutes(10))
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string();
+
+    let mut all_conditions = conditions;
+    all_conditions.push(serde_json::json!({"x-amz-algorithm": "AWS4-HMAC-SHA256"}));
+    all_conditions.push(serde_json::json!({"x-amz-credential": credential}));
+    all_conditions.push(serde_json::json!({"x-amz-date": amz_date}));
+    let policy = serde_json::json!({"expiration": expiration, "conditions": all_conditions});
+    let policy_b64 = base64::engine::general_purpose::STANDARD.encode(policy.to_string());
+
+    let key = format!("AWS4{}", SECRET_KEY);
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).unwrap();
+    mac.update(date_stamp.as_bytes());
+    let k = mac.finalize().into_bytes();
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(REGION.as_bytes());
+    let k = mac.finalize().into_bytes();
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(b"s3");
+    let k = mac.finalize().into_bytes();
+    let mut mac = HmacSha256::new_from_slice(&k).unwrap();
+    mac.update(b"aws4_request");
+    let signing_key = mac.finalize().into_bytes();
+    let mut mac = HmacSha256::new_from_slice(&signing_key).unwrap();
+    mac.update(policy_b64.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    let mut out: Vec<(String, String)> = fields
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    out.push(("x-amz-algorithm".into(), "AWS4-HMAC-SHA256".into()));
+    out.push(("x-amz-credential".into(), credential));
+    out.push(("x-amz-date".into(), amz_date));
+    out.push(("policy".into(), policy_b64));
+    out.push(("x-amz-signature".into(), signature));
+    out
+}
+
+/// POST a multipart/form-data body (no Authorization header) to a bucket.
+async fn post_form(url: &str, fields: &[(String, String)], filename: &str, file: &[u8]) -> reqwest::Response {
+    let boundary = "----maxioformboundary";
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value)
+                .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: text/plain\r\n\r\n",
+            boundary, filename
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    // Don't follow success_action_redirect
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .post(url)
+        .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+        .body(body)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_post_object_upload() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/post-bucket", base_url), vec![]).await;
+
+    let fields = post_policy_fields(
+        vec![
+            serde_json::json!({"bucket": "post-bucket"}),
+            serde_json::json!(["starts-with", "$key", "uploads/"]),
+            serde_json::json!(["content-length-range", 1, 1024]),
+        ],
+        vec![("key", "uploads/${filename}")],
+    );
+    let resp = post_form(&format!("{}/post-bucket", base_url), &fields, "hello.txt", b"hello form").await;
+    assert_eq!(resp.status(), 204);
+    assert!(resp.headers().contains_key("etag"));
+    assert_eq!(resp.headers()["location"], "/post-bucket/uploads/hello.txt");
+
+    let resp = s3_request("GET", &format!("{}/post-bucket/uploads/hello.txt", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "text/plain");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"hello form");
+}
+
+#[tokio::test]
+async fn test_post_object_success_action() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/post-bucket", base_url), vec![]).await;
+    let url = format!("{}/post-bucket", base_url);
+
+    let fields = post_policy_fields(
+        vec![
+            serde_json::json!({"bucket": "post-bucket"}),
+            serde_json::json!({"key": "a.txt"}),
+            serde_json::json!({"success_action_status": "201"}),
+        ],
+        vec![("key", "a.txt"), ("success_action_status", "201")],
+    );
+    let resp = post_form(&url, &fields, "a.txt", b"aaa").await;
+    assert_eq!(resp.status(), 201);
+    let body = resp.text().await.unwrap();
+    assert_eq!(extract_xml_tag(&body, "Bucket").unwrap(), "post-bucket");
+    assert_eq!(extract_xml_tag(&body, "Key").unwrap(), "a.txt");
+
+    let fields = post_policy_fields(
+        vec![
+            serde_json::json!({"bucket": "post-bucket"}),
+            serde_json::json!({"key": "b.txt"}),
+            serde_json::json!(["starts-with", "$success_action_redirect", "https://example.com/"]),
+        ],
+        vec![("key", "b.txt"), ("success_action_redirect", "https://example.com/done")],
+    );
+    let resp = post_form(&url, &fields, "b.txt", b"bbb").await;
+    assert_eq!(resp.status(), 303);
+    let location = resp.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("https://example.com/done?bucket=post-bucket&key=b.txt&etag="));
+}
+
+#[tokio::test]
+async fn test_post_object_rejected() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/post-bucket", base_url), vec![]).await;
+    let url = format!("{}/post-bucket", base_url);
+    let conditions = || {
+        vec![
+            serde_json::json!({"bucket": "post-bucket"}),
+            serde_json::json!(["starts-with", "$key", "uploads/"]),
+            serde_json::json!(["content-length-range", 0, 4]),
+        ]
+    };
+
+    // Tampered signature
+    let mut fields = post_policy_fields(conditions(), vec![("key", "uploads/x.txt")]);
+    fields.last_mut().unwrap().1 = "0".repeat(64);
+    let resp = post_form(&url, &fields, "x.txt", b"abc").await;
+    assert_eq!(resp.status(), 403);
+    assert!(resp.text().await.unwrap().contains("SignatureDoesNotMatch"));
+
+    // Key outside the allowed prefix
+    let fields = post_policy_fields(conditions(), vec![("key", "other/x.txt")]);
+    let resp = post_form(&url, &fields, "x.txt", b"abc").await;
+    assert_eq!(resp.status(), 403);
+    assert!(resp.text().await.unwrap().contains("AccessDenied"));
+
+    // Field not covered by the policy
+    let fields = post_policy_fields(conditions(), vec![("key", "uploads/x.txt"), ("acl", "public-read")]);
+    let resp = post_form(&url, &fields, "x.txt", b"abc").await;
+    assert_eq!(resp.status(), 403);
+
+    // File larger than content-length-range
+    let fields = post_policy_fields(conditions(), vec![("key", "uploads/x.txt")]);
+    let resp = post_form(&url, &fields, "x.txt", b"too large").await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("EntityTooLarge"));
+
+    // No policy at all
+    let fields = vec![("key".to_string(), "uploads/x.txt".to_string())];
+    let resp = post_form(&url, &fields, "x.txt", b"abc").await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = s3_request("GET", &format!("{}/post-bucket/uploads/x.txt", base_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+}
+
+// --- Console presigned URLs ---
+
+/// Log in to the console API and return the session cookie.
+async fn console_login(base_url: &str) -> String {
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let cookie = resp.headers()["set-cookie"].to_str().unwrap();
+    cookie.split(';').next().unwrap().to_string()
+}
+
+async fn console_presign(base_url: &str, cookie: &str, path: &str, query: &str) -> serde_json::Value {
+    let resp = client()
+        .get(format!("{}/api/buckets/{}?{}", base_url, path, query))
+        .header("cookie", cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_console_presign_put_and_get() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/presign-bucket", base_url), vec![]).await;
+    let cookie = console_login(&base_url).await;
+
+    let json = console_presign(
+        &base_url,
+        &cookie,
+        "presign-bucket/presign/dir/my file.txt",
+        "method=PUT&contentType=text/plain",
+    )
+    .await;
+    assert_eq!(json["method"], "PUT");
+    assert_eq!(json["headers"]["content-type"], "text/plain");
+    let url = json["url"].as_str().unwrap();
+
+    // The signed Content-Type must be sent as-is
+    let resp = client()
+        .put(url)
+        .header("content-type", "application/json")
+        .body("{}")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    let resp = client()
+        .put(url)
+        .header("content-type", "text/plain")
+        .body("presigned upload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let json = console_presign(
+        &base_url,
+        &cookie,
+        "presign-bucket/presign/dir/my file.txt",
+        "responseContentDisposition=attachment%3B%20filename%3D%22a.txt%22&responseContentType=application/octet-stream",
+    )
+    .await;
+    let resp = client().get(json["url"].as_str().unwrap()).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-disposition"], "attachment; filename=\"a.txt\"");
+    assert_eq!(resp.headers()["content-type"], "application/octet-stream");
+    assert_eq!(resp.text().await.unwrap(), "presigned upload");
+
+    let json = console_presign(&base_url, &cookie, "presign-bucket/presign/dir/my file.txt", "method=HEAD").await;
+    let resp = client().head(json["url"].as_str().unwrap()).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-length"], "16");
+
+    let json = console_presign(&base_url, &cookie, "presign-bucket/presign/dir/my file.txt", "method=DELETE").await;
+    let resp = client().delete(json["url"].as_str().unwrap()).send().await.unwrap();
+    assert_eq!(resp.status(), 204);
+    let resp = s3_request("GET", &format!("{}/presign-bucket/dir/my%20file.txt", base_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+
+    // Invalid combinations are rejected
+    let resp = client()
+        .get(format!(
+            "{}/api/buckets/presign-bucket/presign/x.txt?method=PUT&responseContentType=text/plain",
+            base_url
+        ))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_console_presign_checksum_and_version() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/presign-bucket", base_url), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/presign-bucket?versioning", base_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let cookie = console_login(&base_url).await;
+
+    let data = b"checksummed";
+    let checksum = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(data));
+    let encoded = encode_query_value(&checksum);
+    let json = console_presign(
+        &base_url,
+        &cookie,
+        "presign-bucket/presign/sum.txt",
+        &format!("method=PUT&checksumAlgorithm=SHA256&checksum={}", encoded),
+    )
+    .await;
+    assert_eq!(json["headers"]["x-amz-checksum-sha256"], checksum.as_str());
+    let url = json["url"].as_str().unwrap().to_string();
+
+    // Missing the signed checksum header fails
+    let resp = client().put(&url).body(data.to_vec()).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+    let resp = client()
+        .put(&url)
+        .header("x-amz-checksum-sha256", &checksum)
+        .body(data.to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let vid = resp.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    s3_request("PUT", &format!("{}/presign-bucket/sum.txt", base_url), b"newer".to_vec()).await;
+
+    let json = console_presign(
+        &base_url,
+        &cookie,
+        "presign-bucket/presign/sum.txt",
+        &format!("versionId={}", vid),
+    )
+    .await;
+    let resp = client().get(json["url"].as_str().unwrap()).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), data);
+}
+
+/// Percent-encode a base64 value for use in a query string.
+fn encode_query_value(value: &str) -> String {
+    value.replace('+', "%2B").replace('/', "%2F").replace('=', "%3D")
+}
+
+#[tokio::test]
+async fn test_console_presign_upload_parts() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/presign-bucket", base_url), vec![]).await;
+    let cookie = console_login(&base_url).await;
+
+    let resp = s3_request("POST", &format!("{}/presign-bucket/big.bin?uploads", base_url), vec![]).await;
+    let upload_id = extract_xml_tag(&resp.text().await.unwrap(), "UploadId").unwrap();
+
+    let json = console_presign(
+        &base_url,
+        &cookie,
+        "presign-bucket/presign/big.bin",
+        &format!("method=PUT&uploadId={}&parts=2", upload_id),
+    )
+    .await;
+    let parts = json["parts"].as_array().unwrap();
+    assert_eq!(parts.len(), 2);
+
+    let part1 = vec![b'a'; 5 * 1024 * 1024];
+    let part2 = b"tail".to_vec();
+    let mut etags = Vec::new();
+    for (entry, data) in parts.iter().zip([part1.clone(), part2.clone()]) {
+        let resp = client()
+            .put(entry["url"].as_str().unwrap())
+            .body(data)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        etags.push(resp.headers()["etag"].to_str().unwrap().to_string());
+    }
+
+    let complete = format!(
+        "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber><ETag>{}</ETag></Part><Part><PartNumber>2</PartNumber><ETag>{}</ETag></Part></CompleteMultipartUpload>",
+        etags[0], etags[1]
+    );
+    let resp = s3_request(
+        "POST",
+        &format!("{}/presign-bucket/big.bin?uploadId={}", base_url, upload_id),
+        complete.into_bytes(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("HEAD", &format!("{}/presign-bucket/big.bin", base_url), vec![]).await;
+    assert_eq!(resp.headers()["content-length"], (part1.len() + part2.len()).to_string().as_str());
+
+    // Part URLs need an upload that exists for this key
+    let resp = client()
+        .get(format!(
+            "{}/api/buckets/presign-bucket/presign/other.bin?method=PUT&uploadId={}",
+            base_url, upload_id
+        ))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+// --- Virtual-hosted-style addressing ---
+
+#[tokio::test]
+async fn test_virtual_hosted_style_requests() {
+    let (base_url, _tmp) = start_server_with(|c| c.domains = vec!["s3.test".to_string()]).await;
+    let addr: std::net::SocketAddr = base_url.trim_start_matches("http://").parse().unwrap();
+    let port = addr.port();
+    let vclient = reqwest::Client::builder()
+        .resolve("s3.test", addr)
+        .resolve("vbucket.s3.test", addr)
+        .resolve("my.dotted.bucket.s3.test", addr)
+        .build()
+        .unwrap();
+
+    // Bare domain stays path-style
+    let resp = s3_request_via(&vclient, "PUT", &format!("http://s3.test:{}/vbucket", port), vec![]).await;
+    assert_eq!(resp.status(), 200);
+
+    let vhost = format!("http://vbucket.s3.test:{}", port);
+    let resp = s3_request_via(&vclient, "PUT", &format!("{}/dir/hello.txt", vhost), b"virtual".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request_via(&vclient, "GET", &format!("{}/dir/hello.txt", vhost), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "virtual");
+
+    // Bucket-level operations on the root path
+    let resp = s3_request_via(&vclient, "HEAD", &format!("{}/", vhost), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request_via(&vclient, "GET", &format!("{}/?list-type=2", vhost), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("<Key>dir/hello.txt</Key>"));
+    assert_eq!(extract_xml_tag(&body, "Name").unwrap(), "vbucket");
+
+    // Path-style access to the same object keeps working
+    let resp = s3_request("GET", &format!("{}/vbucket/dir/hello.txt", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+
+    // Bucket names may contain dots
+    let dotted = format!("http://my.dotted.bucket.s3.test:{}", port);
+    let resp = s3_request_via(&vclient, "PUT", &format!("{}/", dotted), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("HEAD", &format!("{}/my.dotted.bucket", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = s3_request_via(&vclient, "DELETE", &format!("{}/dir/hello.txt", vhost), vec![]).await;
+    assert_eq!(resp.status(), 204);
+    let resp = s3_request("GET", &format!("{}/vbucket/dir/hello.txt", base_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_virtual_hosted_style_disabled_without_domain() {
+    let (base_url, _tmp) = start_server().await;
+    let addr: std::net::SocketAddr = base_url.trim_start_matches("http://").parse().unwrap();
+    let vclient = reqwest::Client::builder()
+        .resolve("vbucket.s3.test", addr)
+        .build()
+        .unwrap();
+    s3_request("PUT", &format!("{}/vbucket", base_url), vec![]).await;
+
+    // Without a configured domain the Host header is ignored: this is a bucket-level GET on "hello.txt"
+    let resp = s3_request_via(
+        &vclient,
+        "GET",
+        &format!("http://vbucket.s3.test:{}/hello.txt", addr.port()),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 404);
+    assert!(resp.text().await.unwrap().contains("NoSuchBucket"));
+}
+
+// --- TLS ---
+
+/// Test CA that issues server and client certificates.
+struct TestCa {
+    cert: rcgen::Certificate,
+    key: rcgen::KeyPair,
+}
+
+impl TestCa {
+    fn new() -> Self {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(rcgen::DnType::CommonName, "maxio test CA");
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        params.key_usages = vec![rcgen::KeyUsagePurpose::KeyCertSign, rcgen::KeyUsagePurpose::DigitalSignature];
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        Self { cert, key }
+    }
+
+    /// Issue a certificate; returns (cert PEM, key PEM).
+    fn issue(&self, names: &[&str], client: bool) -> (String, String) {
+        let common_name = names[0];
+        let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        let mut params = rcgen::CertificateParams::new(names).unwrap();
+        params.distinguished_name.push(rcgen::DnType::CommonName, common_name);
+        params.extended_key_usages = vec![if client {
+            rcgen::ExtendedKeyUsagePurpose::ClientAuth
+        } else {
+            rcgen::ExtendedKeyUsagePurpose::ServerAuth
+        }];
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        (cert.pem(), key.serialize_pem())
+    }
+
+    fn client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(self.cert.pem().as_bytes()).unwrap())
+            .build()
+            .unwrap()
+    }
+}
+
+/// Write a server certificate for 127.0.0.1 into `dir`; returns (cert path, key path).
+fn write_server_cert(ca: &TestCa, dir: &std::path::Path) -> (String, String) {
+    let (cert, key) = ca.issue(&["127.0.0.1", "localhost"], false);
+    let cert_path = dir.join("public.crt");
+    let key_path = dir.join("private.key");
+    std::fs::write(&cert_path, cert).unwrap();
+    std::fs::write(&key_path, key).unwrap();
+    (cert_path.to_str().unwrap().to_string(), key_path.to_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn test_tls_serves_s3_and_console() {
+    let ca = TestCa::new();
+    let certs = TempDir::new().unwrap();
+    let (cert, key) = write_server_cert(&ca, certs.path());
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.tls_cert = Some(cert);
+        c.tls_key = Some(key);
+    })
+    .await;
+    assert!(base_url.starts_with("https://"));
+    let https = ca.client();
+
+    let resp = s3_request_via(&https, "PUT", &format!("{}/tls-bucket", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request_via(&https, "PUT", &format!("{}/tls-bucket/a.txt", base_url), b"secure".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request_via(&https, "GET", &format!("{}/tls-bucket/a.txt", base_url), vec![]).await;
+    assert_eq!(resp.text().await.unwrap(), "secure");
+
+    // Plain HTTP on the TLS port fails
+    let plain = base_url.replace("https://", "http://");
+    assert!(client().get(format!("{}/", plain)).send().await.is_err());
+
+    // Session cookies are Secure without relying on x-forwarded-proto
+    let resp = https
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["set-cookie"].to_str().unwrap().contains("; Secure"));
+}
+
+#[tokio::test]
+async fn test_tls_certificate_reload() {
+    let ca = TestCa::new();
+    let certs = TempDir::new().unwrap();
+    write_server_cert(&ca, certs.path());
+    let certs_dir = certs.path().to_str().unwrap().to_string();
+    let (base_url, _tmp) = start_server_with(|c| c.certs_dir = Some(certs_dir)).await;
+
+    let resp = ca.client().get(format!("{}/", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+
+    // Rotate to a certificate from a different CA
+    let new_ca = TestCa::new();
+    write_server_cert(&new_ca, certs.path());
+    let mut reloaded = false;
+    for _ in 0..30 {
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        if new_ca.client().get(format!("{}/", base_url)).send().await.is_ok() {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "server should pick up the rotated certificate");
+    assert!(ca.client().get(format!("{}/", base_url)).send().await.is_err());
+}
+
+#[tokio::test]
+async fn test_tls_client_certificates() {
+    let ca = TestCa::new();
+    let certs = TempDir::new().unwrap();
+    let (cert, key) = write_server_cert(&ca, certs.path());
+    let ca_path = certs.path().join("client-ca.crt");
+    std::fs::write(&ca_path, ca.cert.pem()).unwrap();
+    let ca_path = ca_path.to_str().unwrap().to_string();
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.tls_cert = Some(cert);
+        c.tls_key = Some(key);
+        c.tls_client_ca = Some(ca_path);
+    })
+    .await;
+
+    // No client certificate: handshake is rejected
+    assert!(ca.client().get(format!("{}/", base_url)).send().await.is_err());
+
+    // Certificate from another CA is rejected too
+    let (other_cert, other_key) = TestCa::new().issue(&["client"], true);
+    let untrusted = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(ca.cert.pem().as_bytes()).unwrap())
+        .identity(reqwest::Identity::from_pkcs8_pem(other_cert.as_bytes(), other_key.as_bytes()).unwrap())
+        .build()
+        .unwrap();
+    assert!(untrusted.get(format!("{}/", base_url)).send().await.is_err());
+
+    let (client_cert, client_key) = ca.issue(&["client"], true);
+    let trusted = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(ca.cert.pem().as_bytes()).unwrap())
+        .identity(reqwest::Identity::from_pkcs8_pem(client_cert.as_bytes(), client_key.as_bytes()).unwrap())
+        .build()
+        .unwrap();
+    let resp = s3_request_via(&trusted, "PUT", &format!("{}/mtls-bucket", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[test]
+fn test_tls_options_require_certificate() {
+    use clap::Parser;
+    let config = |args: &[&str]| Config::parse_from(["maxio"].iter().chain(args));
+
+    // Each of these would otherwise start a plain HTTP listener
+    for args in [
+        &["--tls-key", "server.key"][..],
+        &["--tls-cert", "server.crt"],
+        &["--tls-client-ca", "ca.crt"],
+        &["--tls-redirect-port", "8080"],
+    ] {
+        let err = maxio::tls::acceptor(&config(args)).err().unwrap();
+        assert!(err.to_string().contains(args[0]), "{}", err);
+    }
+    assert!(maxio::tls::acceptor(&config(&[])).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_tls_http_redirect() {
+    let app = maxio::tls::redirect_router(8443);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let no_redirect = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let resp = no_redirect
+        .get(format!("http://{}/bucket/key.txt?versionId=1", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 308);
+    assert_eq!(resp.headers()["location"], "https://127.0.0.1:8443/bucket/key.txt?versionId=1");
+}
+
+// --- Bucket Notification Tests ---
+
+/// Local webhook endpoint that records every event message POSTed to it.
+fn start_webhook_receiver(
+    listener: tokio::net::TcpListener,
+) -> Arc<std::sync::Mutex<Vec<serde_json::Value>>> {
+    let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let app = axum::Router::new().route(
+        "/events",
+        axum::routing::post(move |axum::Json(event): axum::Json<serde_json::Value>| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push(event);
+                axum::http::StatusCode::OK
+            }
+        }),
+    );
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    received
+}
+
+/// Wait until the receiver has at least `count` events and return them.
+async fn wait_for_events(
+    received: &std::sync::Mutex<Vec<serde_json::Value>>,
+    count: usize,
+) -> Vec<serde_json::Value> {
+    for _ in 0..300 {
+        let events = received.lock().unwrap().clone();
+        if events.len() >= count {
+            return events;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("expected {} webhook event(s)", count);
+}
+
+const WEBHOOK_ARN: &str = "arn:maxio:sqs:us-east-1:hook:webhook";
+
+fn notification_xml(id: &str, arn: &str, events: &[&str], filters: &[(&str, &str)]) -> String {
+    let events: String = events.iter().map(|e| format!("<Event>{}</Event>", e)).collect();
+    let filter = if filters.is_empty() {
+        String::new()
+    } else {
+        let rules: String = filters
+            .iter()
+            .map(|(n, v)| format!("<FilterRule><Name>{}</Name><Value>{}</Value></FilterRule>", n, v))
+            .collect();
+        format!("<Filter><S3Key>{}</S3Key></Filter>", rules)
+    };
+    format!(
+        "<QueueConfiguration><Id>{}</Id><Queue>{}</Queue>{}{}</QueueConfiguration>",
+        id, arn, events, filter
+    )
+}
+
+#[tokio::test]
+async fn test_bucket_notification_config() {
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.notify_webhooks = vec!["hook=http://127.0.0.1:9/events".to_string()];
+    })
+    .await;
+    s3_request("PUT", &format!("{}/notify-bucket", base_url), vec![]).await;
+    let url = format!("{}/notify-bucket?notification", base_url);
+
+    // Nothing configured yet
+    let resp = s3_request("GET", &url, vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert!(!resp.text().await.unwrap().contains("QueueConfiguration"));
+
+    let unknown_arn = format!(
+        "<NotificationConfiguration>{}</NotificationConfiguration>",
+        notification_xml("a", "arn:maxio:sqs:us-east-1:other:webhook", &["s3:ObjectCreated:*"], &[])
+    );
+    let resp = s3_request("PUT", &url, unknown_arn.into_bytes()).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("InvalidArgument"));
+
+    let bad_event = format!(
+        "<NotificationConfiguration>{}</NotificationConfiguration>",
+        notification_xml("a", WEBHOOK_ARN, &["s3:ObjectRestore:*"], &[])
+    );
+    let resp = s3_request("PUT", &url, bad_event.into_bytes()).await;
+    assert_eq!(resp.status(), 400);
+
+    let config = format!(
+        "<NotificationConfiguration xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">{}{}</NotificationConfiguration>",
+        notification_xml("uploads", WEBHOOK_ARN, &["s3:ObjectCreated:*"], &[("prefix", "images/"), ("suffix", ".jpg")]),
+        notification_xml("deletes", "arn:minio:sqs::hook:webhook", &["s3:ObjectRemoved:Delete"], &[]),
+    );
+    let resp = s3_request("PUT", &url, config.into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+
+    let body = s3_request("GET", &url, vec![]).await.text().await.unwrap();
+    assert_eq!(body.matches("<QueueConfiguration>").count(), 2);
+    assert!(body.contains("<Id>uploads</Id>"));
+    assert!(body.contains(&format!("<Queue>{}</Queue>", WEBHOOK_ARN)));
+    assert!(body.contains("<Event>s3:ObjectCreated:*</Event>"));
+    assert!(body.contains("<FilterRule><Name>prefix</Name><Value>images/</Value></FilterRule>"));
+    assert!(body.contains("<FilterRule><Name>suffix</Name><Value>.jpg</Value></FilterRule>"));
+    assert!(body.contains("<Event>s3:ObjectRemoved:Delete</Event>"));
+
+    // An empty configuration removes all rules
+    let resp = s3_request("PUT", &url, b"<NotificationConfiguration/>".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let body = s3_request("GET", &url, vec![]).await.text().await.unwrap();
+    assert!(!body.contains("QueueConfiguration"));
+}
+
+#[tokio::test]
+async fn test_bucket_notification_delivery() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hook_url = format!("http://{}/events", listener.local_addr().unwrap());
+    let received = start_webhook_receiver(listener);
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.notify_webhooks = vec![format!("hook={}", hook_url)];
+    })
+    .await;
+
+    s3_request("PUT", &format!("{}/notify-bucket", base_url), vec![]).await;
+    let config = format!(
+        "<NotificationConfiguration>{}{}</NotificationConfiguration>",
+        notification_xml("uploads", WEBHOOK_ARN, &["s3:ObjectCreated:*"], &[("prefix", "images/"), ("suffix", ".jpg")]),
+        notification_xml("deletes", WEBHOOK_ARN, &["s3:ObjectRemoved:*"], &[]),
+    );
+    let resp = s3_request("PUT", &format!("{}/notify-bucket?notification", base_url), config.into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = s3_request("PUT", &format!("{}/notify-bucket/images/my%20cat.jpg", base_url), b"meow".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let etag = resp.headers()["etag"].to_str().unwrap().trim_matches('"').to_string();
+    // Filtered out by prefix and suffix
+    s3_request("PUT", &format!("{}/notify-bucket/docs/cat.jpg", base_url), b"x".to_vec()).await;
+    s3_request("PUT", &format!("{}/notify-bucket/images/cat.png", base_url), b"x".to_vec()).await;
+    let resp = s3_request("DELETE", &format!("{}/notify-bucket/images/my%20cat.jpg", base_url), vec![]).await;
+    assert_eq!(resp.status(), 204);
+
+    let events = wait_for_events(&received, 2).await;
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(received.lock().unwrap().len(), 2, "filtered objects must not notify");
+
+    let created = &events[0];
+    assert_eq!(created["EventName"], "s3:ObjectCreated:Put");
+    assert_eq!(created["Key"], "notify-bucket/images/my cat.jpg");
+    let record = &created["Records"][0];
+    assert_eq!(record["eventName"], "ObjectCreated:Put");
+    assert_eq!(record["awsRegion"], REGION);
+    assert_eq!(record["s3"]["configurationId"], "uploads");
+    assert_eq!(record["s3"]["bucket"]["name"], "notify-bucket");
+    assert_eq!(record["s3"]["object"]["key"], "images/my+cat.jpg");
+    assert_eq!(record["s3"]["object"]["size"], 4);
+    assert_eq!(record["s3"]["object"]["eTag"], etag.as_str());
+
+    let removed = &events[1]["Records"][0];
+    assert_eq!(removed["eventName"], "ObjectRemoved:Delete");
+    assert_eq!(removed["s3"]["configurationId"], "deletes");
+    assert_eq!(removed["s3"]["object"]["key"], "images/my+cat.jpg");
+}
+
+#[tokio::test]
+async fn test_bucket_notification_queue_survives_restart() {
+    // A target that refuses connections: events must stay queued on disk
+    let dead = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let dead_url = format!("http://{}/events", dead.local_addr().unwrap());
+    drop(dead);
+    let (base_url, tmp) = start_server_with(|c| {
+        c.notify_webhooks = vec![format!("hook={}", dead_url)];
+    })
+    .await;
+
+    s3_request("PUT", &format!("{}/notify-bucket", base_url), vec![]).await;
+    let config = format!(
+        "<NotificationConfiguration>{}</NotificationConfiguration>",
+        notification_xml("all", WEBHOOK_ARN, &["s3:ObjectCreated:*"], &[])
+    );
+    s3_request("PUT", &format!("{}/notify-bucket?notification", base_url), config.into_bytes()).await;
+    let resp = s3_request("PUT", &format!("{}/notify-bucket/queued.txt", base_url), b"later".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+
+    let queue_dir = tmp.path().join(".notify/hook");
+    let queued = || std::fs::read_dir(&queue_dir).unwrap().count();
+    // Events are queued just after the write is acknowledged
+    for _ in 0..50 {
+        if queued() > 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(queued(), 1);
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert_eq!(queued(), 1, "undelivered events must stay queued");
+
+    // "Restart" over the same data directory with the target reachable
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hook_url = format!("http://{}/events", listener.local_addr().unwrap());
+    let received = start_webhook_receiver(listener);
+    let (_base_url, tmp) = start_server_in(tmp, |c| {
+        c.notify_webhooks = vec![format!("hook={}", hook_url)];
+    })
+    .await;
+
+    let events = wait_for_events(&received, 1).await;
+    assert_eq!(events[0]["Records"][0]["s3"]["object"]["key"], "queued.txt");
+    assert_eq!(events[0]["Records"][0]["s3"]["configurationId"], "all");
+    for _ in 0..100 {
+        if std::fs::read_dir(tmp.path().join(".notify/hook")).unwrap().count() == 0 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("delivered event was not removed from the queue");
+}
+
+/// Read from a streaming response until `count` complete lines have arrived.
+async fn read_stream_lines(resp: &mut reqwest::Response, count: usize) -> Vec<String> {
+    let mut buf = String::new();
+    let deadline = std::time::Duration::from_secs(15);
+    tokio::time::timeout(deadline, async {
+        while buf.matches('\n').count() < count {
+            let chunk = resp.chunk().await.unwrap().expect("stream ended early");
+            buf.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    })
+    .await
+    .expect("timed out waiting for streamed events");
+    buf.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect()
+}
+
+#[tokio::test]
+async fn test_listen_bucket_notification() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/listen-bucket", base_url), vec![]).await;
+
+    let resp = s3_request(
+        "GET",
+        &format!("{}/listen-bucket?events=s3%3ABad%3A%2A", base_url),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    let mut resp = s3_request(
+        "GET",
+        &format!("{}/listen-bucket?events=s3%3AObjectCreated%3A%2A&prefix=logs%2F", base_url),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    s3_request("PUT", &format!("{}/listen-bucket/logs/one.txt", base_url), b"1".to_vec()).await;
+    // Different prefix, event type, and bucket: none of these are streamed
+    s3_request("PUT", &format!("{}/listen-bucket/other.txt", base_url), b"2".to_vec()).await;
+    s3_request("DELETE", &format!("{}/listen-bucket/logs/one.txt", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/other-bucket", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/other-bucket/logs/x.txt", base_url), b"3".to_vec()).await;
+    multipart_upload(
+        &base_url,
+        "/listen-bucket/logs/two.bin",
+        vec![vec![b'a'; 5 * 1024 * 1024], b"tail".to_vec()],
+    )
+    .await;
+
+    let lines = read_stream_lines(&mut resp, 2).await;
+    assert_eq!(lines.len(), 2);
+    let first: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(first["EventName"], "s3:ObjectCreated:Put");
+    assert_eq!(first["Records"][0]["s3"]["bucket"]["name"], "listen-bucket");
+    assert_eq!(first["Records"][0]["s3"]["object"]["key"], "logs/one.txt");
+    let second: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+    assert_eq!(second["EventName"], "s3:ObjectCreated:CompleteMultipartUpload");
+    assert_eq!(second["Records"][0]["s3"]["object"]["key"], "logs/two.bin");
+    assert_eq!(second["Records"][0]["s3"]["object"]["size"], 5 * 1024 * 1024 + 4);
+}
+
+#[tokio::test]
+async fn test_console_event_feed() {
+    let (base_url, _tmp) = start_server().await;
+    let cookie = console_login(&base_url).await;
+    s3_request("PUT", &format!("{}/feed-bucket", base_url), vec![]).await;
+
+    let resp = client()
+        .get(format!("{}/api/buckets/feed-bucket/events", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let mut resp = client()
+        .get(format!("{}/api/buckets/feed-bucket/events?prefix=docs/", base_url))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/event-stream"));
+
+    s3_request("PUT", &format!("{}/feed-bucket/skip.txt", base_url), b"x".to_vec()).await;
+    s3_request("PUT", &format!("{}/feed-bucket/docs/a.txt", base_url), b"hello".to_vec()).await;
+    s3_request("DELETE", &format!("{}/feed-bucket/docs/a.txt", base_url), vec![]).await;
+
+    // Each SSE message is an `event:` line followed by a `data:` line
+    let lines = read_stream_lines(&mut resp, 4).await;
+    let data: Vec<serde_json::Value> = lines
+        .iter()
+        .filter_map(|l| l.strip_prefix("data:"))
+        .map(|d| serde_json::from_str(d.trim()).unwrap())
+        .collect();
+    assert_eq!(data.len(), 2);
+    assert!(lines.contains(&"event: object".to_string()));
+    assert_eq!(data[0]["eventName"], "s3:ObjectCreated:Put");
+    assert_eq!(data[0]["key"], "docs/a.txt");
+    assert_eq!(data[0]["size"], 5);
+    assert_eq!(data[1]["eventName"], "s3:ObjectRemoved:Delete");
+    assert_eq!(data[1]["key"], "docs/a.txt");
+}
+
+#[tokio::test]
+async fn test_every_write_path_is_streamed() {
+    let (base_url, _tmp) = start_server().await;
+    let cookie = console_login(&base_url).await;
+    let bucket_url = format!("{}/streamed", base_url);
+    s3_request("PUT", &bucket_url, vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}?versioning", bucket_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let mut resp = s3_request(
+        "GET",
+        &format!("{}?events=s3%3AObjectCreated%3A%2A&events=s3%3AObjectRemoved%3A%2A", bucket_url),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    // S3 API: put, copy, browser form upload, multipart, delete, version
+    // delete and batch delete
+    let put = s3_request("PUT", &format!("{}/s3.txt", bucket_url), b"s3".to_vec()).await;
+    let put_vid = put.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    let copy = s3_request_with_headers(
+        "PUT",
+        &format!("{}/copy.txt", bucket_url),
+        vec![],
+        vec![("x-amz-copy-source", "/streamed/s3.txt")],
+    )
+    .await;
+    assert_eq!(copy.status(), 200);
+    let copy_vid = copy.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    let fields = post_policy_fields(
+        vec![serde_json::json!({"bucket": "streamed"}), serde_json::json!({"key": "post.txt"})],
+        vec![("key", "post.txt")],
+    );
+    assert_eq!(post_form(&bucket_url, &fields, "post.txt", b"form").await.status(), 204);
+    multipart_upload(&base_url, "/streamed/mp.bin", vec![b"whole".to_vec()]).await;
+    assert_eq!(s3_request("DELETE", &format!("{}/copy.txt", bucket_url), vec![]).await.status(), 204);
+    let resp_version = s3_request("DELETE", &format!("{}/s3.txt?versionId={}", bucket_url, put_vid), vec![]).await;
+    assert_eq!(resp_version.status(), 204);
+    let batch = b"<Delete><Object><Key>post.txt</Key></Object></Delete>".to_vec();
+    assert_eq!(s3_request("POST", &format!("{}?delete", bucket_url), batch).await.status(), 200);
+
+    // Console: upload, folder, multipart, delete and version delete
+    let upload = console_send(&base_url, &cookie, reqwest::Method::PUT, "/buckets/streamed/upload/console.txt")
+        .await
+        .body("console")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(upload.status(), 200);
+    let folder = console_send(&base_url, &cookie, reqwest::Method::POST, "/buckets/streamed/folders")
+        .await
+        .json(&serde_json::json!({"name": "dir"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(folder.status(), 200);
+    let created = console_send(&base_url, &cookie, reqwest::Method::POST, "/buckets/streamed/uploads")
+        .await
+        .json(&serde_json::json!({"key": "big.bin"}))
+        .send()
+        .await
+        .unwrap();
+    let upload_id = created.json::<serde_json::Value>().await.unwrap()["uploadId"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let path = format!("/buckets/streamed/uploads/{}", upload_id);
+    let part = console_send(&base_url, &cookie, reqwest::Method::PUT, &format!("{}/parts/1", path))
+        .await
+        .body(b"whole".to_vec())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(part.status(), 200);
+    let done = console_send(&base_url, &cookie, reqwest::Method::POST, &format!("{}/complete", path))
+        .await
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(done.status(), 200);
+    let deleted = console_send(&base_url, &cookie, reqwest::Method::DELETE, "/buckets/streamed/objects/console.txt")
+        .await
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 200);
+    let deleted = console_send(
+        &base_url,
+        &cookie,
+        reqwest::Method::DELETE,
+        &format!("/buckets/streamed/versions/{}/objects/copy.txt", copy_vid),
+    )
+    .await
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(deleted.status(), 200);
+
+    let expected = [
+        ("s3:ObjectCreated:Put", "s3.txt"),
+        ("s3:ObjectCreated:Copy", "copy.txt"),
+        ("s3:ObjectCreated:Post", "post.txt"),
+        ("s3:ObjectCreated:CompleteMultipartUpload", "mp.bin"),
+        ("s3:ObjectRemoved:DeleteMarkerCreated", "copy.txt"),
+        ("s3:ObjectRemoved:Delete", "s3.txt"),
+        ("s3:ObjectRemoved:DeleteMarkerCreated", "post.txt"),
+        ("s3:ObjectCreated:Put", "console.txt"),
+        ("s3:ObjectCreated:Put", "dir/"),
+        ("s3:ObjectCreated:CompleteMultipartUpload", "big.bin"),
+        ("s3:ObjectRemoved:DeleteMarkerCreated", "console.txt"),
+        ("s3:ObjectRemoved:Delete", "copy.txt"),
+    ];
+    let lines = read_stream_lines(&mut resp, expected.len()).await;
+    let events: Vec<serde_json::Value> = lines.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let seen: Vec<(&str, &str)> = events
+        .iter()
+        .map(|e| (e["EventName"].as_str().unwrap(), e["Records"][0]["s3"]["object"]["key"].as_str().unwrap()))
+        .collect();
+    assert_eq!(seen, expected);
+    assert_eq!(events[5]["Records"][0]["s3"]["object"]["versionId"], put_vid.as_str());
+    assert_eq!(events[11]["Records"][0]["s3"]["object"]["versionId"], copy_vid.as_str());
+}
+
+// --- Replication Tests ---
+
+/// A source server replicating to a second server under target ID `dr`.
+async fn start_replication_pair() -> ((String, TempDir), (String, TempDir)) {
+    let target = start_server_with(|c| c.replication_access_keys = vec![ACCESS_KEY.to_string()]).await;
+    let target_url = target
+        .0
+        .replacen("http://", &format!("http://{}:{}@", ACCESS_KEY, SECRET_KEY), 1);
+    let source = start_server_with(|c| {
+        c.replication_targets = vec![format!("dr={}", target_url)];
+    })
+    .await;
+    (source, target)
+}
+
+/// Two servers replicating to each other, each calling the other `dr`.
+async fn start_mirrored_pair() -> ((String, TempDir), (String, TempDir)) {
+    let with_credentials = |url: &str| url.replacen("http://", &format!("http://{}:{}@", ACCESS_KEY, SECRET_KEY), 1);
+    // The second server's address has to be known before it starts
+    let reserved = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port_b = reserved.local_addr().unwrap().port();
+    drop(reserved);
+
+    let url_b = with_credentials(&format!("http://127.0.0.1:{}", port_b));
+    let a = start_server_with(|c| {
+        c.replication_targets = vec![format!("dr={}", url_b)];
+        c.replication_access_keys = vec![ACCESS_KEY.to_string()];
+    })
+    .await;
+    let url_a = with_credentials(&a.0);
+    let b = start_server_with(|c| {
+        c.port = port_b;
+        c.replication_targets = vec![format!("dr={}", url_a)];
+        c.replication_access_keys = vec![ACCESS_KEY.to_string()];
+    })
+    .await;
+    (a, b)
+}
+
+/// Create a versioned bucket replicating everything (deletes included) to `dr`.
+async fn create_mirrored_bucket(base_url: &str, bucket: &str) {
+    s3_request("PUT", &format!("{}/{}", base_url, bucket), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/{}?versioning", base_url, bucket),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let config = replication_xml("mirror", "", bucket, true);
+    let resp = s3_request("PUT", &format!("{}/{}?replication", base_url, bucket), config.into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+}
+
+/// Poll until GET returns `expected` (`None`: until it returns 404).
+async fn wait_for_body(url: &str, expected: Option<&[u8]>) {
+    for _ in 0..100 {
+        let resp = s3_request("GET", url, vec![]).await;
+        let reached = match expected {
+            None => resp.status() == 404,
+            Some(body) => resp.status() == 200 && resp.bytes().await.unwrap().as_ref() == body,
+        };
+        if reached {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("{} never reached the expected state", url);
+}
+
+fn replication_xml(id: &str, prefix: &str, dest_bucket: &str, delete_markers: bool) -> String {
+    format!(
+        "<ReplicationConfiguration><Role></Role><Rule><ID>{}</ID><Status>Enabled</Status><Priority>1</Priority>\
+         <Filter><Prefix>{}</Prefix></Filter>\
+         <DeleteMarkerReplication><Status>{}</Status></DeleteMarkerReplication>\
+         <Destination><Bucket>arn:maxio:replication::dr:{}</Bucket></Destination></Rule></ReplicationConfiguration>",
+        id,
+        prefix,
+        if delete_markers { "Enabled" } else { "Disabled" },
+        dest_bucket
+    )
+}
+
+/// Poll HEAD until the object reports the wanted replication status.
+async fn wait_for_replication_status(url: &str, status: &str) {
+    for _ in 0..100 {
+        let resp = s3_request("HEAD", url, vec![]).await;
+        if resp
+            .headers()
+            .get("x-amz-replication-status")
+            .is_some_and(|v| v == status)
+        {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("{} never reached replication status {}", url, status);
+}
+
+#[tokio::test]
+async fn test_bucket_replication_config() {
+    let ((base_url, _tmp), (_target_url, _target_tmp)) = start_replication_pair().await;
+    s3_request("PUT", &format!("{}/repl-src", base_url), vec![]).await;
+
+    let resp = s3_request("GET", &format!("{}/repl-src?replication", base_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+    assert!(resp.text().await.unwrap().contains("ReplicationConfigurationNotFoundError"));
+
+    let bad = replication_xml("r1", "", "x", false).replace(":dr:", ":nowhere:");
+    let resp = s3_request("PUT", &format!("{}/repl-src?replication", base_url), bad.into_bytes()).await;
+    assert_eq!(resp.status(), 400);
+
+    let config = replication_xml("r1", "logs/", "repl-dst", true);
+    let resp = s3_request("PUT", &format!("{}/repl-src?replication", base_url), config.into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = s3_request("GET", &format!("{}/repl-src?replication", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("<ID>r1</ID>"));
+    assert!(body.contains("<Status>Enabled</Status>"));
+    assert!(body.contains("<Prefix>logs/</Prefix>"));
+    assert!(body.contains("<Bucket>arn:maxio:replication::dr:repl-dst</Bucket>"));
+
+    let resp = s3_request("DELETE", &format!("{}/repl-src?replication", base_url), vec![]).await;
+    assert_eq!(resp.status(), 204);
+    let resp = s3_request("GET", &format!("{}/repl-src?replication", base_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+    // The bucket itself is untouched
+    let resp = s3_request("HEAD", &format!("{}/repl-src", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_bucket_replication_put_and_delete() {
+    let ((base_url, _tmp), (target_url, _target_tmp)) = start_replication_pair().await;
+    s3_request("PUT", &format!("{}/repl-src", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/repl-dst", target_url), vec![]).await;
+    let config = replication_xml("r1", "docs/", "repl-dst", true);
+    s3_request("PUT", &format!("{}/repl-src?replication", base_url), config.into_bytes()).await;
+
+    let src = format!("{}/repl-src/docs/a%20b.txt", base_url);
+    let resp = s3_request("PUT", &src, b"replicate me".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    wait_for_replication_status(&src, "COMPLETED").await;
+
+    let resp = s3_request("GET", &format!("{}/repl-dst/docs/a%20b.txt", target_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"replicate me");
+
+    // Outside the rule's prefix: no status, not replicated
+    let other = format!("{}/repl-src/other.txt", base_url);
+    s3_request("PUT", &other, b"local".to_vec()).await;
+    let resp = s3_request("HEAD", &other, vec![]).await;
+    assert!(resp.headers().get("x-amz-replication-status").is_none());
+
+    let resp = s3_request("DELETE", &src, vec![]).await;
+    assert_eq!(resp.status(), 204);
+    let dst = format!("{}/repl-dst/docs/a%20b.txt", target_url);
+    for _ in 0..100 {
+        if s3_request("HEAD", &dst, vec![]).await.status() == 404 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(s3_request("HEAD", &dst, vec![]).await.status(), 404);
+    let resp = s3_request("HEAD", &format!("{}/repl-dst/other.txt", target_url), vec![]).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_bucket_replication_retries_failed_items() {
+    let ((base_url, _tmp), (target_url, _target_tmp)) = start_replication_pair().await;
+    s3_request("PUT", &format!("{}/repl-src", base_url), vec![]).await;
+    let config = replication_xml("r1", "", "late-bucket", false);
+    s3_request("PUT", &format!("{}/repl-src?replication", base_url), config.into_bytes()).await;
+
+    // The destination bucket does not exist yet
+    let src = format!("{}/repl-src/pending.txt", base_url);
+    s3_request("PUT", &src, b"eventually".to_vec()).await;
+    wait_for_replication_status(&src, "FAILED").await;
+
+    s3_request("PUT", &format!("{}/late-bucket", target_url), vec![]).await;
+    wait_for_replication_status(&src, "COMPLETED").await;
+    let resp = s3_request("GET", &format!("{}/late-bucket/pending.txt", target_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"eventually");
+}
+
+#[tokio::test]
+async fn test_replication_parks_items_that_keep_failing() {
+    let ((base_url, _tmp), (target_url, _target_tmp)) = start_replication_pair().await;
+    s3_request("PUT", &format!("{}/repl-src", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/repl-dst", target_url), vec![]).await;
+    // Keys under stuck/ go to a bucket that never appears
+    let config = replication_xml("r1", "", "repl-dst", false).replace(
+        "</ReplicationConfiguration>",
+        "<Rule><ID>r2</ID><Status>Enabled</Status><Priority>2</Priority><Filter><Prefix>stuck/</Prefix></Filter>\
+         <DeleteMarkerReplication><Status>Disabled</Status></DeleteMarkerReplication>\
+         <Destination><Bucket>arn:maxio:replication::dr:missing</Bucket></Destination></Rule></ReplicationConfiguration>",
+    );
+    let resp = s3_request("PUT", &format!("{}/repl-src?replication", base_url), config.into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+
+    let stuck = format!("{}/repl-src/stuck/a.txt", base_url);
+    s3_request("PUT", &stuck, b"stuck".to_vec()).await;
+    let behind = format!("{}/repl-src/behind.txt", base_url);
+    s3_request("PUT", &behind, b"behind".to_vec()).await;
+
+    // After a few attempts the stuck item is set aside and the next one goes out
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+    loop {
+        let resp = s3_request("GET", &format!("{}/repl-dst/behind.txt", target_url), vec![]).await;
+        if resp.status() == 200 {
+            assert_eq!(resp.bytes().await.unwrap().as_ref(), b"behind");
+            break;
+        }
+        assert!(std::time::Instant::now() < deadline, "item behind a failing one was never replicated");
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+    wait_for_replication_status(&behind, "COMPLETED").await;
+    wait_for_replication_status(&stuck, "FAILED").await;
+}
+
+#[tokio::test]
+async fn test_active_active_replication() {
+    let ((url_a, _tmp_a), (url_b, _tmp_b)) = start_mirrored_pair().await;
+    create_mirrored_bucket(&url_a, "mirror").await;
+    create_mirrored_bucket(&url_b, "mirror").await;
+
+    // Written on A: B gets it with the same version ID, marked as a replica
+    let resp = s3_request("PUT", &format!("{}/mirror/one.txt", url_a), b"from a".to_vec()).await;
+    let vid = resp.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    wait_for_replication_status(&format!("{}/mirror/one.txt", url_a), "COMPLETED").await;
+    let resp = s3_request("HEAD", &format!("{}/mirror/one.txt", url_b), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-amz-version-id"], vid.as_str());
+    assert_eq!(resp.headers()["x-amz-replication-status"], "REPLICA");
+
+    // Written on B: flows back to A
+    s3_request("PUT", &format!("{}/mirror/two.txt", url_b), b"from b".to_vec()).await;
+    wait_for_body(&format!("{}/mirror/two.txt", url_a), Some(b"from b")).await;
+
+    // Concurrent writes to one key: both sites keep both versions and agree
+    // that the later one is current
+    s3_request("PUT", &format!("{}/mirror/both.txt", url_a), b"older".to_vec()).await;
+    s3_request("PUT", &format!("{}/mirror/both.txt", url_b), b"newer".to_vec()).await;
+    wait_for_body(&format!("{}/mirror/both.txt", url_a), Some(b"newer")).await;
+    wait_for_body(&format!("{}/mirror/both.txt", url_b), Some(b"newer")).await;
+
+    // Replicas are not sent back: every key has exactly its own versions
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    for base_url in [&url_a, &url_b] {
+        let body = s3_request("GET", &format!("{}/mirror?versions", base_url), vec![])
+            .await
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body.matches("<Version>").count(), 4, "{}", body);
+        assert!(body.contains(&format!("<VersionId>{}</VersionId>", vid)));
+    }
+
+    // Delete markers converge with the same version ID
+    let resp = s3_request("DELETE", &format!("{}/mirror/one.txt", url_b), vec![]).await;
+    let marker = resp.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    wait_for_body(&format!("{}/mirror/one.txt", url_a), None).await;
+    let body = s3_request("GET", &format!("{}/mirror?versions&prefix=one", url_a), vec![])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains(&format!("<VersionId>{}</VersionId>", marker)), "{}", body);
+}
+
+#[tokio::test]
+async fn test_replica_conflict_resolution() {
+    let (base_url, _tmp) = start_server_with(|c| c.replication_access_keys = vec![ACCESS_KEY.to_string()]).await;
+    s3_request("PUT", &format!("{}/peer", base_url), vec![]).await;
+    let url = format!("{}/peer/doc.txt", base_url);
+    s3_request("PUT", &url, b"local".to_vec()).await;
+
+    // A replicated write from before the local one loses
+    let etag = format!("\"{}\"", hex::encode(<md5::Md5 as md5::Digest>::digest(b"stale")));
+    let resp = s3_request_with_headers(
+        "PUT",
+        &url,
+        b"stale".to_vec(),
+        vec![
+            ("x-maxio-replication-request", "true"),
+            ("x-maxio-source-mtime", "2001-01-01T00:00:00.000Z"),
+            ("x-maxio-source-etag", &etag),
+        ],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("GET", &url, vec![]).await;
+    assert!(resp.headers().get("x-amz-replication-status").is_none());
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"local");
+
+    // A later one wins and keeps the source's timestamp
+    let etag = format!("\"{}\"", hex::encode(<md5::Md5 as md5::Digest>::digest(b"remote")));
+    let resp = s3_request_with_headers(
+        "PUT",
+        &url,
+        b"remote".to_vec(),
+        vec![
+            ("x-maxio-replication-request", "true"),
+            ("x-maxio-source-mtime", "2099-01-01T00:00:00.000Z"),
+            ("x-maxio-source-etag", &etag),
+        ],
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("GET", &url, vec![]).await;
+    assert_eq!(resp.headers()["x-amz-replication-status"], "REPLICA");
+    assert_eq!(resp.headers()["last-modified"], "Thu, 01 Jan 2099 00:00:00 GMT");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"remote");
+
+    // So does a delete only when it happened after the current write
+    let resp = s3_request_with_headers(
+        "DELETE",
+        &url,
+        vec![],
+        vec![
+            ("x-maxio-replication-request", "true"),
+            ("x-maxio-source-mtime", "2050-01-01T00:00:00.000Z"),
+        ],
+    )
+    .await;
+    assert_eq!(resp.status(), 204);
+    assert_eq!(s3_request("HEAD", &url, vec![]).await.status(), 200);
+
+    // An ETag that does not match the data is rejected
+    let resp = s3_request_with_headers(
+        "PUT",
+        &url,
+        b"corrupt".to_vec(),
+        vec![
+            ("x-maxio-replication-request", "true"),
+            ("x-maxio-source-mtime", "2100-01-01T00:00:00.000Z"),
+            ("x-maxio-source-etag", "\"00000000000000000000000000000000\""),
+        ],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_replica_headers_require_replication_key() {
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.users = vec!["peer=peer-secret".to_string()];
+        c.replication_access_keys = vec!["peer".to_string()];
+    })
+    .await;
+    s3_request("PUT", &format!("{}/peer", base_url), vec![]).await;
+    let url = format!("{}/peer/doc.txt", base_url);
+    let replica_headers = vec![
+        ("x-maxio-replication-request", "true"),
+        ("x-maxio-source-mtime", "2099-01-01T00:00:00.000Z"),
+    ];
+
+    // A key that is not a replication key cannot pass its writes off as replicas
+    let resp = s3_request_with_headers("PUT", &url, b"forged".to_vec(), replica_headers.clone()).await;
+    assert_eq!(resp.status(), 403);
+    assert!(resp.text().await.unwrap().contains("<Code>AccessDenied</Code>"));
+    s3_request("PUT", &url, b"local".to_vec()).await;
+    let resp = s3_request_with_headers("DELETE", &url, vec![], replica_headers).await;
+    assert_eq!(resp.status(), 403);
+
+    // Nor can a presigned URL, which never signs these headers
+    let presigned = presign_url(&base_url, "PUT", "/peer/doc.txt", 300);
+    let resp = client()
+        .put(&presigned)
+        .header("x-maxio-replication-request", "true")
+        .header("x-maxio-source-mtime", "2099-01-01T00:00:00.000Z")
+        .body("forged")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+
+    let resp = s3_request("GET", &url, vec![]).await;
+    assert!(resp.headers().get("x-amz-replication-status").is_none());
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"local");
+}
+
+#[tokio::test]
+async fn test_replication_resync() {
+    let ((base_url, _tmp), (target_url, _target_tmp)) = start_replication_pair().await;
+    s3_request("PUT", &format!("{}/repl-src", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/repl-dst", target_url), vec![]).await;
+    // Objects from before replication was configured
+    s3_request("PUT", &format!("{}/repl-src/old/a.txt", base_url), b"a".to_vec()).await;
+    s3_request("PUT", &format!("{}/repl-src/old/b.txt", base_url), b"b".to_vec()).await;
+    let config = replication_xml("r1", "", "repl-dst", false);
+    s3_request("PUT", &format!("{}/repl-src?replication", base_url), config.into_bytes()).await;
+    assert_eq!(
+        s3_request("HEAD", &format!("{}/repl-dst/old/a.txt", target_url), vec![]).await.status(),
+        404
+    );
+
+    let resp = s3_request("PUT", &format!("{}/repl-src?replication-reset", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["Targets"][0]["Arn"], "arn:maxio:replication::dr:repl-dst");
+    assert_eq!(body["Targets"][0]["Queued"], 2);
+
+    wait_for_body(&format!("{}/repl-dst/old/a.txt", target_url), Some(b"a")).await;
+    wait_for_body(&format!("{}/repl-dst/old/b.txt", target_url), Some(b"b")).await;
+    wait_for_replication_status(&format!("{}/repl-src/old/b.txt", base_url), "COMPLETED").await;
+
+    let resp = s3_request(
+        "PUT",
+        &format!("{}/repl-src?replication-reset&arn=arn%3Amaxio%3Areplication%3A%3Adr%3Aother", base_url),
+        vec![],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+}
+
+// --- Parity / Reed-Solomon Tests ---
+
+/// Start a server with erasure coding + parity enabled (small chunks for testing).
+async fn start_server_parity(parity_shards: u32) -> (String, TempDir) {
+    let tmp = TempDir::new().unwrap();
+    let data_dir = tmp.path().to_str().unwrap().to_string();
+
+    // 100-byte chunks for easy multi-chunk testing
+    let storage = FilesystemStorage::new(&data_dir, true, 100, parity_shards).await.unwrap();
+
+    let config = Config {
+        port: 0,
+        address: "127.0.0.1".to_string(),
+        data_dir,
+        access_key: ACCESS_KEY.to_string(),
+        secret_key: SECRET_KEY.to_string(),
+        users: vec![],
+        region: REGION.to_string(),
+        erasure_coding: true,
+        chunk_size: 100,
+        parity_shards,
+        domains: vec![],
+        tls_cert: None,
+        tls_key: None,
+        certs_dir: None,
+        tls_client_ca: None,
+        tls_redirect_port: None,
+        notify_webhooks: vec![],
+        replication_targets: vec![],
+        replication_access_keys: vec![],
+        metrics_token: None,
+        ready_min_free_bytes: 0,
+        audit_log_file: None,
+        audit_log_max_size: 100 * 1024 * 1024,
+        audit_webhooks: vec![],
+        access_log_flush_interval: 60,
+        console_session_idle_timeout: 43200,
+        oidc_config_url: None,
+        oidc_client_id: None,
+        oidc_client_secret: None,
+        oidc_redirect_url: None,
+        oidc_scopes: vec!["openid".to_string(), "profile".to_string(), "email".to_string()],
+        oidc_username_claim: "preferred_username".to_string(),
+        oidc_policy_claim: "policy".to_string(),
+        oidc_groups_claim: "groups".to_string(),
+        oidc_group_policies: vec![],
+        oidc_display_name: "SSO".to_string(),
+        trusted_proxies: vec![],
+        key_request_rate: 0,
+        key_bandwidth: 0,
+        bucket_request_rate: 0,
+        bucket_bandwidth: 0,
+        usage_scan_interval: 3600,
+        archive_max_size: 10 * 1024 * 1024 * 1024,
+        archive_max_objects: 10_000,
+    };
+
+    let storage = Arc::new(storage);
+    let notifier = maxio::notify::Notifier::new(&config).unwrap();
+    let replicator = maxio::replication::Replicator::new(&config, storage.clone()).unwrap();
+    let audit = maxio::audit::AuditLog::new(&config, storage.clone()).unwrap();
+    let credentials = maxio::auth::credentials::Credentials::new(&config).unwrap();
+    let sessions = maxio::auth::session::SessionStore::open(&config).unwrap();
+    let oidc = maxio::auth::oidc::Oidc::new(&config).unwrap();
+    let trusted_proxies = maxio::proxy::TrustedProxies::new(&config).unwrap();
+    let throttle = maxio::throttle::Throttle::new(&config);
+    let state = AppState {
+        storage,
+        config: Arc::new(config),
+        login_rate_limiter: Arc::new(maxio::api::console::LoginRateLimiter::new()),
+        notifier: Arc::new(notifier),
+        replicator: Arc::new(replicator),
+        audit: Arc::new(audit),
+        credentials: Arc::new(credentials),
+        sessions: Arc::new(sessions),
+        oidc: oidc.map(Arc::new),
+        trusted_proxies: Arc::new(trusted_proxies),
+        throttle: Arc::new(throttle),
+    };
+
+    let app = server::build_router(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let base_url = format!("http://{}", addr);
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (base_url, tmp)
+}
+
+#[tokio::test]
+async fn test_parity_write_creates_parity_chunks() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    // Create bucket
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    // Write 350 bytes → 4 data chunks (100+100+100+50) + 2 parity
+    let data = vec![0xABu8; 350];
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data).await;
+
+    // Check the .ec directory
+    let ec_dir = tmp.path().join("buckets/parity-test/file.bin.ec");
+    assert!(ec_dir.is_dir());
+
+    // Should have 6 chunk files + manifest.json = 7 entries
+    let entries: Vec<_> = std::fs::read_dir(&ec_dir).unwrap().collect();
+    assert_eq!(entries.len(), 7, "expected 4 data + 2 parity + 1 manifest");
+
+    // Verify manifest
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(ec_dir.join("manifest.json")).unwrap()
+    ).unwrap();
+    assert_eq!(manifest["version"], 2);
     assert_eq!(manifest["chunk_count"], 4);
     assert_eq!(manifest["parity_shards"], 2);
     assert_eq!(manifest["chunks"].as_array().unwrap().len(), 6);
 
-    // Verify parity chunks have kind: "parity"
-    let chunks = manifest["chunks"].as_array().unwrap();
-    for i in 0..4 {
-        // data chunks should not have "kind" field (skipped when data) or be "data"
-        let kind = chunks[i].get("kind");
-        assert!(kind.is_none() || kind.unwrap() == "data");
+    // Verify parity chunks have kind: "parity"
+    let chunks = manifest["chunks"].as_array().unwrap();
+    for i in 0..4 {
+        // data chunks should not have "kind" field (skipped when data) or be "data"
+        let kind = chunks[i].get("kind");
+        assert!(kind.is_none() || kind.unwrap() == "data");
+    }
+    assert_eq!(chunks[4]["kind"], "parity");
+    assert_eq!(chunks[5]["kind"], "parity");
+}
+
+#[tokio::test]
+async fn test_parity_read_healthy() {
+    let (base_url, _tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    let data = vec![0xCDu8; 350];
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+
+    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.bytes().await.unwrap();
+    assert_eq!(body.as_ref(), &data[..]);
+}
+
+#[tokio::test]
+async fn test_parity_recovery_corrupted_chunk() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    let data = vec![0xEFu8; 350];
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+
+    // Corrupt data chunk 1 (overwrite with zeros)
+    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000001");
+    std::fs::write(&chunk_path, vec![0u8; 100]).unwrap();
+
+    // Read should still succeed via RS recovery
+    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.bytes().await.unwrap();
+    assert_eq!(body.as_ref(), &data[..]);
+}
+
+#[tokio::test]
+async fn test_parity_recovery_missing_chunk() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    let data = vec![0x42u8; 350];
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+
+    // Delete data chunk 0
+    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000000");
+    std::fs::remove_file(&chunk_path).unwrap();
+
+    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.bytes().await.unwrap();
+    assert_eq!(body.as_ref(), &data[..]);
+}
+
+#[tokio::test]
+async fn test_parity_too_many_failures() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    let data = vec![0x77u8; 350];
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data).await;
+
+    // Delete 3 chunks (more than m=2 parity can handle)
+    for i in 0..3 {
+        let chunk_path = tmp.path().join(format!("buckets/parity-test/file.bin.ec/{:06}", i));
+        std::fs::remove_file(&chunk_path).unwrap();
+    }
+
+    // The server will return an error or drop the connection when RS recovery fails.
+    // Since the object is streamed, the error may manifest as a connection reset
+    // rather than a clean HTTP error status.
+    let result = s3_request_result("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+    match result {
+        Err(_) => {} // Connection error — expected
+        Ok(resp) => {
+            // Either a server error status, or streaming started but body will be incomplete
+            if resp.status() == 200 {
+                let body_result = resp.bytes().await;
+                assert!(body_result.is_err() || body_result.unwrap().len() != 350);
+            } else {
+                assert!(resp.status().is_server_error());
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_parity_range_read_degraded() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    // Create data with distinct bytes per chunk for easy verification
+    let mut data = Vec::new();
+    for i in 0u8..4 {
+        let chunk_len = if i < 3 { 100 } else { 50 };
+        data.extend(std::iter::repeat(i + 1).take(chunk_len));
+    }
+    assert_eq!(data.len(), 350);
+    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+
+    // Corrupt chunk 1
+    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000001");
+    std::fs::write(&chunk_path, vec![0u8; 100]).unwrap();
+
+    // Range read spanning chunk 0 and chunk 1 (bytes 50-149)
+    let resp = s3_request_with_headers(
+        "GET",
+        &format!("{}/parity-test/file.bin", base_url),
+        vec![],
+        vec![("range", "bytes=50-149")],
+    )
+    .await;
+    assert_eq!(resp.status(), 206);
+    let body = resp.bytes().await.unwrap();
+    assert_eq!(body.as_ref(), &data[50..150]);
+}
+
+#[tokio::test]
+async fn test_parity_backward_compat_v1_manifest() {
+    // EC without parity should still work (v1 manifest, no parity fields)
+    let (base_url, _tmp) = start_server_ec().await;
+
+    s3_request("PUT", &format!("{}/compat-test", base_url), vec![]).await;
+
+    let data = vec![0xAAu8; 2048];
+    s3_request("PUT", &format!("{}/compat-test/file.bin", base_url), data.clone()).await;
+
+    let resp = s3_request("GET", &format!("{}/compat-test/file.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let body = resp.bytes().await.unwrap();
+    assert_eq!(body.as_ref(), &data[..]);
+}
+
+#[tokio::test]
+async fn test_parity_empty_object() {
+    let (base_url, tmp) = start_server_parity(2).await;
+
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+
+    // Empty object — should skip parity
+    s3_request("PUT", &format!("{}/parity-test/empty.bin", base_url), vec![]).await;
+
+    let ec_dir = tmp.path().join("buckets/parity-test/empty.bin.ec");
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(ec_dir.join("manifest.json")).unwrap()
+    ).unwrap();
+    assert_eq!(manifest["version"], 1); // no parity for empty
+    assert!(manifest.get("parity_shards").is_none() || manifest["parity_shards"].is_null());
+
+    let resp = s3_request("GET", &format!("{}/parity-test/empty.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.bytes().await.unwrap().len(), 0);
+}
+
+// --- Metrics ---
+
+/// Value of the sample whose name and labels are exactly `series`.
+fn metric_value(body: &str, series: &str) -> Option<f64> {
+    body.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/metered", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/metered/a.txt", base_url), b"hello".to_vec()).await;
+    s3_request("PUT", &format!("{}/metered/b.txt", base_url), b"world!".to_vec()).await;
+    s3_request("POST", &format!("{}/metered/big.bin?uploads=", base_url), vec![]).await;
+    let resp = client().get(format!("{}/metered/a.txt", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+
+    let resp = client().get(format!("{}/metrics", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let body = resp.text().await.unwrap();
+
+    assert!(metric_value(&body, r#"maxio_s3_requests_total{api="PutObject",code="200"}"#).unwrap() >= 2.0);
+    assert!(metric_value(&body, r#"maxio_s3_requests_total{api="GetObject",code="403"}"#).unwrap() >= 1.0);
+    assert!(metric_value(&body, r#"maxio_s3_request_duration_seconds_count{api="CreateBucket"}"#).unwrap() >= 1.0);
+    assert!(metric_value(&body, "maxio_s3_received_bytes_total").unwrap() >= 11.0);
+    assert_eq!(metric_value(&body, r#"maxio_bucket_objects{bucket="metered"}"#), Some(2.0));
+    assert_eq!(metric_value(&body, r#"maxio_bucket_size_bytes{bucket="metered"}"#), Some(11.0));
+    assert_eq!(metric_value(&body, r#"maxio_multipart_uploads_in_flight{bucket="metered"}"#), Some(1.0));
+    assert!(body.contains("# TYPE maxio_ec_reconstructions_total counter"));
+}
+
+#[tokio::test]
+async fn test_metrics_bearer_token() {
+    let (base_url, _tmp) = start_server_with(|c| c.metrics_token = Some("scrape-me".to_string())).await;
+
+    let resp = client().get(format!("{}/metrics", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let resp = client()
+        .get(format!("{}/metrics", base_url))
+        .header("authorization", "Bearer wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    let resp = client()
+        .get(format!("{}/metrics", base_url))
+        .header("authorization", "Bearer scrape-me")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.unwrap().contains("maxio_s3_requests_total"));
+}
+
+#[tokio::test]
+async fn test_metrics_bucket_name_and_login_rejections() {
+    let (base_url, _tmp) = start_server().await;
+
+    // Signed requests still reach a bucket called "metrics".
+    let resp = s3_request("PUT", &format!("{}/metrics", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    s3_request("PUT", &format!("{}/metrics/k.txt", base_url), b"v".to_vec()).await;
+    let resp = s3_request("GET", &format!("{}/metrics", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.text().await.unwrap().contains("<Key>k.txt</Key>"));
+
+    let mut limited = false;
+    for _ in 0..12 {
+        let resp = client()
+            .post(format!("{}/api/auth/login", base_url))
+            .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": "wrong"}))
+            .send()
+            .await
+            .unwrap();
+        limited |= resp.status() == 429;
+    }
+    assert!(limited);
+
+    let body = client().get(format!("{}/metrics", base_url)).send().await.unwrap().text().await.unwrap();
+    assert!(metric_value(&body, "maxio_console_login_rate_limited_total").unwrap() >= 1.0);
+    assert_eq!(metric_value(&body, r#"maxio_bucket_objects{bucket="metrics"}"#), Some(1.0));
+}
+
+// --- Health and admin info ---
+
+#[tokio::test]
+async fn test_health_endpoints() {
+    let (base_url, _tmp) = start_server().await;
+
+    for path in ["/minio/health/live", "/minio/health/ready"] {
+        let resp = client().get(format!("{}{}", base_url, path)).send().await.unwrap();
+        assert_eq!(resp.status(), 200, "{}", path);
+        let resp = client().head(format!("{}{}", base_url, path)).send().await.unwrap();
+        assert_eq!(resp.status(), 200, "{}", path);
+    }
+
+    // The root still requires credentials.
+    let resp = client().get(&base_url).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn test_health_ready_without_free_space() {
+    let (base_url, _tmp) = start_server_with(|c| c.ready_min_free_bytes = u64::MAX).await;
+
+    let resp = client().get(format!("{}/minio/health/ready", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 503);
+    assert!(resp.text().await.unwrap().contains("bytes free"));
+    let resp = client().get(format!("{}/minio/health/live", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_admin_server_info() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/info-bucket", base_url), vec![]).await;
+
+    let resp = client().get(format!("{}/minio/admin/v3/info", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+
+    let resp = s3_request("GET", &format!("{}/minio/admin/v3/info", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let info: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(info["mode"], "online");
+    assert_eq!(info["version"], env!("MAXIO_VERSION"));
+    assert_eq!(info["region"], REGION);
+    assert!(info["uptime"].is_u64());
+    assert_eq!(info["buckets"], 1);
+    assert!(info["disk"]["totalBytes"].as_u64().unwrap() > 0);
+    assert!(info["disk"]["freeBytes"].as_u64().unwrap() <= info["disk"]["totalBytes"].as_u64().unwrap());
+    assert_eq!(info["erasureCoding"]["enabled"], false);
+    assert_eq!(info["erasureCoding"]["chunkSize"], 10 * 1024 * 1024);
+    assert_eq!(info["erasureCoding"]["parityShards"], 0);
+}
+
+// --- Audit and access logging ---
+
+/// Wait until the audit log has a record matching `pred` and return it.
+async fn wait_for_audit_record(
+    path: &std::path::Path,
+    pred: impl Fn(&serde_json::Value) -> bool,
+) -> serde_json::Value {
+    for _ in 0..100 {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let found = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|record| pred(record));
+        if let Some(record) = found {
+            return record;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("no matching audit record in {}", path.display());
+}
+
+#[tokio::test]
+async fn test_audit_log_file() {
+    let log_dir = TempDir::new().unwrap();
+    let log_path = log_dir.path().join("audit.log");
+    let file = log_path.to_str().unwrap().to_string();
+    let (base_url, _tmp) = start_server_with(|c| c.audit_log_file = Some(file)).await;
+
+    s3_request("PUT", &format!("{}/audited", base_url), vec![]).await;
+    let resp = s3_request("PUT", &format!("{}/audited/dir/a%20b.txt", base_url), b"hello".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let request_id = resp.headers()["x-amz-request-id"].to_str().unwrap().to_string();
+    let resp = s3_request("GET", &format!("{}/audited/dir/a%20b.txt", base_url), vec![]).await;
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"hello");
+    let resp = client().get(format!("{}/audited/dir/a%20b.txt", base_url)).send().await.unwrap();
+    assert_eq!(resp.status(), 403);
+
+    let cookie = console_login(&base_url).await;
+    let resp = client()
+        .get(format!("{}/api/buckets/audited/objects", base_url))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let put = wait_for_audit_record(&log_path, |r| r["operation"] == "PutObject").await;
+    assert_eq!(put["api"], "s3");
+    assert_eq!(put["requestId"], request_id.as_str());
+    assert_eq!(put["accessKey"], ACCESS_KEY);
+    assert_eq!(put["sourceIp"], "127.0.0.1");
+    assert_eq!(put["method"], "PUT");
+    assert_eq!(put["bucket"], "audited");
+    assert_eq!(put["key"], "dir/a b.txt");
+    assert_eq!(put["status"], 200);
+    assert_eq!(put["bytesIn"], 5);
+    assert!(put["durationMs"].is_u64());
+    assert!(put["time"].is_string());
+
+    let get = wait_for_audit_record(&log_path, |r| r["operation"] == "GetObject" && r["status"] == 200).await;
+    assert_eq!(get["bytesOut"], 5);
+
+    let denied = wait_for_audit_record(&log_path, |r| r["operation"] == "GetObject" && r["status"] == 403).await;
+    assert_eq!(denied["errorCode"], "AccessDenied");
+    assert!(denied.get("accessKey").is_none());
+
+    let login = wait_for_audit_record(&log_path, |r| r["operation"] == "POST /api/auth/login").await;
+    assert_eq!(login["api"], "console");
+    assert_eq!(login["accessKey"], ACCESS_KEY);
+    let console = wait_for_audit_record(&log_path, |r| r["operation"] == "GET /api/buckets/{bucket}/objects").await;
+    assert_eq!(console["bucket"], "audited");
+    assert_eq!(console["accessKey"], ACCESS_KEY);
+    assert_eq!(console["status"], 200);
+}
+
+#[tokio::test]
+async fn test_audit_log_rotation() {
+    let log_dir = TempDir::new().unwrap();
+    let log_path = log_dir.path().join("audit.log");
+    let file = log_path.to_str().unwrap().to_string();
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.audit_log_file = Some(file);
+        c.audit_log_max_size = 1024;
+    })
+    .await;
+
+    s3_request("PUT", &format!("{}/rotated", base_url), vec![]).await;
+    for i in 0..20 {
+        s3_request("PUT", &format!("{}/rotated/obj-{}", base_url, i), b"x".to_vec()).await;
+    }
+    wait_for_audit_record(&log_path, |r| r["key"] == "obj-19").await;
+
+    let rotated = log_dir.path().join("audit.log.1");
+    assert!(rotated.exists());
+    assert!(std::fs::metadata(&log_path).unwrap().len() <= 1024);
+    let content = std::fs::read_to_string(&rotated).unwrap();
+    assert!(content.lines().all(|l| serde_json::from_str::<serde_json::Value>(l).is_ok()));
+    assert!(!log_dir.path().join("audit.log.6").exists());
+}
+
+#[tokio::test]
+async fn test_audit_webhook() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let hook = format!("siem=http://{}/events", listener.local_addr().unwrap());
+    let received = start_webhook_receiver(listener);
+    let (base_url, _tmp) = start_server_with(|c| c.audit_webhooks = vec![hook]).await;
+
+    s3_request("PUT", &format!("{}/hooked", base_url), vec![]).await;
+    s3_request("DELETE", &format!("{}/hooked/missing.txt?versionId=v1", base_url), vec![]).await;
+
+    let records = wait_for_events(&received, 2).await;
+    assert_eq!(records[0]["operation"], "CreateBucket");
+    assert_eq!(records[0]["bucket"], "hooked");
+    assert_eq!(records[1]["operation"], "DeleteObject");
+    assert_eq!(records[1]["key"], "missing.txt");
+    assert_eq!(records[1]["versionId"], "v1");
+    assert_eq!(records[1]["accessKey"], ACCESS_KEY);
+}
+
+#[tokio::test]
+async fn test_bucket_access_logging() {
+    let (base_url, _tmp) = start_server_with(|c| c.access_log_flush_interval = 1).await;
+    s3_request("PUT", &format!("{}/served", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/access-logs", base_url), vec![]).await;
+
+    let resp = s3_request("GET", &format!("{}/served?logging", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert!(!resp.text().await.unwrap().contains("LoggingEnabled"));
+
+    let config = |target: &str| {
+        format!(
+            "<BucketLoggingStatus xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"><LoggingEnabled>\
+             <TargetBucket>{}</TargetBucket><TargetPrefix>served/</TargetPrefix>\
+             </LoggingEnabled></BucketLoggingStatus>",
+            target
+        )
+    };
+    let resp = s3_request("PUT", &format!("{}/served?logging", base_url), config("nowhere").into_bytes()).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("InvalidTargetBucketForLogging"));
+
+    let resp = s3_request("PUT", &format!("{}/served?logging", base_url), config("access-logs").into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("GET", &format!("{}/served?logging", base_url), vec![]).await;
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("<TargetBucket>access-logs</TargetBucket>"));
+    assert!(body.contains("<TargetPrefix>served/</TargetPrefix>"));
+
+    let resp = s3_request("PUT", &format!("{}/served/page.html", base_url), b"<html/>".to_vec()).await;
+    let request_id = resp.headers()["x-amz-request-id"].to_str().unwrap().to_string();
+    client().get(format!("{}/served/page.html", base_url)).send().await.unwrap();
+
+    // Log objects appear under the prefix once the buffer is flushed; the
+    // two records may land in different flushes
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(15);
+    let log = loop {
+        let resp = s3_request("GET", &format!("{}/access-logs?list-type=2&prefix=served%2F", base_url), vec![]).await;
+        let body = resp.text().await.unwrap();
+        let mut log = String::new();
+        for key in body.split("<Key>").skip(1).map(|s| s.split("</Key>").next().unwrap()) {
+            let resp = s3_request("GET", &format!("{}/access-logs/{}", base_url, key), vec![]).await;
+            log.push_str(&resp.text().await.unwrap());
+        }
+        if log.contains("REST.PUT.OBJECT") && log.contains("REST.GET.OBJECT") {
+            break log;
+        }
+        assert!(std::time::Instant::now() < deadline, "access log records never all appeared:\n{}", log);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    };
+    let put = log.lines().find(|l| l.contains("REST.PUT.OBJECT")).unwrap();
+    assert!(put.starts_with(&format!("{} served [", ACCESS_KEY)));
+    assert!(put.contains(&format!(" 127.0.0.1 {} {} REST.PUT.OBJECT page.html ", ACCESS_KEY, request_id)));
+    assert!(put.contains("\"PUT /served/page.html HTTP/1.1\" 200 - "));
+    assert!(put.contains(" SigV4 - AuthHeader "));
+    let denied = log.lines().find(|l| l.contains("REST.GET.OBJECT")).unwrap();
+    assert!(denied.contains(" 403 AccessDenied "));
+    // The logging configuration requests themselves are logged too
+    assert!(log.contains("REST.GET.LOGGING"));
+
+    let resp = s3_request(
+        "PUT",
+        &format!("{}/served?logging", base_url),
+        b"<BucketLoggingStatus xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"/>".to_vec(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let resp = s3_request("GET", &format!("{}/served?logging", base_url), vec![]).await;
+    assert!(!resp.text().await.unwrap().contains("LoggingEnabled"));
+}
+
+// --- Console sessions ---
+
+async fn console_get(base_url: &str, cookie: &str, path: &str) -> reqwest::Response {
+    client()
+        .get(format!("{}/api{}", base_url, path))
+        .header("cookie", cookie)
+        .send()
+        .await
+        .unwrap()
+}
+
+/// The CSRF token the console hands out for a session, if it is still valid.
+async fn csrf_token(base_url: &str, cookie: &str) -> String {
+    let resp = console_get(base_url, cookie, "/auth/check").await;
+    let json: serde_json::Value = resp.json().await.unwrap();
+    json["csrfToken"].as_str().unwrap_or_default().to_string()
+}
+
+async fn console_post(base_url: &str, cookie: &str, path: &str) -> reqwest::Response {
+    client()
+        .post(format!("{}/api{}", base_url, path))
+        .header("cookie", cookie)
+        .header("x-csrf-token", csrf_token(base_url, cookie).await)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_console_session_revocation() {
+    let (base_url, _tmp) = start_server().await;
+    let first = console_login(&base_url).await;
+    let second = console_login(&base_url).await;
+
+    let resp = console_get(&base_url, &first, "/auth/sessions").await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    let sessions = json["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let other = sessions.iter().find(|s| s["current"] == false).unwrap();
+
+    // Revoking a session by ID invalidates its cookie immediately
+    let resp = client()
+        .delete(format!("{}/api/auth/sessions/{}", base_url, other["id"].as_str().unwrap()))
+        .header("cookie", &first)
+        .header("x-csrf-token", csrf_token(&base_url, &first).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(console_get(&base_url, &second, "/auth/check").await.status(), 401);
+    assert_eq!(console_get(&base_url, &first, "/buckets").await.status(), 200);
+
+    // A logged-out cookie cannot be replayed
+    let resp = console_post(&base_url, &first, "/auth/logout").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(console_get(&base_url, &first, "/buckets").await.status(), 401);
+
+    // Logging out everywhere ends every session of the user
+    let a = console_login(&base_url).await;
+    let b = console_login(&base_url).await;
+    let resp = console_post(&base_url, &a, "/auth/logout-all").await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["revoked"], 2);
+    assert_eq!(console_get(&base_url, &a, "/auth/check").await.status(), 401);
+    assert_eq!(console_get(&base_url, &b, "/auth/check").await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_console_sessions_survive_restart() {
+    let (base_url, tmp) = start_server().await;
+    let kept = console_login(&base_url).await;
+    let revoked = console_login(&base_url).await;
+    console_post(&base_url, &revoked, "/auth/logout").await;
+
+    let (base_url, _tmp) = start_server_in(tmp, |_| {}).await;
+    assert_eq!(console_get(&base_url, &kept, "/auth/check").await.status(), 200);
+    assert_eq!(console_get(&base_url, &revoked, "/auth/check").await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_console_session_idle_timeout() {
+    let (base_url, _tmp) = start_server_with(|c| c.console_session_idle_timeout = 1).await;
+    let cookie = console_login(&base_url).await;
+    assert_eq!(console_get(&base_url, &cookie, "/auth/check").await.status(), 200);
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert_eq!(console_get(&base_url, &cookie, "/auth/check").await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_console_login_as_user() {
+    let (base_url, _tmp) =
+        start_server_with(|c| c.users = vec!["alice=alice-secret".to_string()]).await;
+    s3_request("PUT", &format!("{}/shared", base_url), vec![]).await;
+
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": "alice", "secretKey": "wrong-secret"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": "alice", "secretKey": "alice-secret"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let cookie = resp.headers()["set-cookie"].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    let json: serde_json::Value = console_get(&base_url, &cookie, "/auth/check").await.json().await.unwrap();
+    assert_eq!(json["accessKey"], "alice");
+    assert_eq!(json["root"], false);
+
+    // Each user only sees their own sessions
+    let root = console_login(&base_url).await;
+    let json: serde_json::Value = console_get(&base_url, &root, "/auth/sessions").await.json().await.unwrap();
+    assert_eq!(json["sessions"].as_array().unwrap().len(), 1);
+
+    // Presigned URLs are signed with the user's own key
+    let json = console_presign(&base_url, &cookie, "shared/presign/from-alice.txt", "method=PUT").await;
+    let url = json["url"].as_str().unwrap();
+    assert!(url.contains("X-Amz-Credential=alice%2F"));
+    let resp = client().put(url).body("hi").send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
+// --- Console single sign-on ---
+
+/// Codes the mock identity provider redeems: code → (PKCE challenge, ID token claims).
+type MockCodes = Arc<std::sync::Mutex<std::collections::HashMap<String, (String, serde_json::Value)>>>;
+
+struct MockIdp {
+    url: String,
+    codes: MockCodes,
+    key: Arc<ring::signature::EcdsaKeyPair>,
+}
+
+impl MockIdp {
+    /// An ID token signed by the provider, with `iss` and `exp` filled in.
+    fn id_token(&self, claims: serde_json::Value) -> String {
+        let mut claims = claims;
+        let defaults = serde_json::json!({"iss": self.url, "exp": chrono::Utc::now().timestamp() + 300});
+        for (k, v) in defaults.as_object().unwrap() {
+            claims.as_object_mut().unwrap().entry(k.clone()).or_insert(v.clone());
+        }
+        sign_id_token(&self.key, &claims)
+    }
+}
+
+fn sign_id_token(key: &ring::signature::EcdsaKeyPair, claims: &serde_json::Value) -> String {
+    let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let header = b64.encode(r#"{"alg":"ES256","kid":"test-key","typ":"JWT"}"#);
+    let payload = b64.encode(claims.to_string());
+    let message = format!("{}.{}", header, payload);
+    let rng = ring::rand::SystemRandom::new();
+    let signature = key.sign(&rng, message.as_bytes()).unwrap();
+    format!("{}.{}", message, b64.encode(signature.as_ref()))
+}
+
+/// Serve discovery, JWKS and a token endpoint issuing ES256-signed ID tokens.
+async fn start_mock_idp() -> MockIdp {
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+    let key = Arc::new(EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap());
+    let point = key.public_key().as_ref().to_vec();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let discovery = serde_json::json!({
+        "issuer": url,
+        "authorization_endpoint": format!("{}/authorize", url),
+        "token_endpoint": format!("{}/token", url),
+        "jwks_uri": format!("{}/jwks", url),
+    });
+    let jwks = serde_json::json!({"keys": [{
+        "kty": "EC",
+        "crv": "P-256",
+        "kid": "test-key",
+        "x": b64.encode(&point[1..33]),
+        "y": b64.encode(&point[33..]),
+    }]});
+    let codes: MockCodes = Default::default();
+
+    let token_codes = codes.clone();
+    let token_key = key.clone();
+    let app = axum::Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            axum::routing::get(move || async move { axum::Json(discovery) }),
+        )
+        .route("/jwks", axum::routing::get(move || async move { axum::Json(jwks) }))
+        .route(
+            "/token",
+            axum::routing::post(
+                move |axum::Form(form): axum::Form<std::collections::HashMap<String, String>>| async move {
+                    let Some((challenge, claims)) = token_codes.lock().unwrap().remove(&form["code"]) else {
+                        return Err(axum::http::StatusCode::BAD_REQUEST);
+                    };
+                    let verifier = &form["code_verifier"];
+                    if b64.encode(Sha256::digest(verifier.as_bytes())) != challenge {
+                        return Err(axum::http::StatusCode::BAD_REQUEST);
+                    }
+                    Ok(axum::Json(serde_json::json!({
+                        "access_token": "unused",
+                        "token_type": "Bearer",
+                        "id_token": sign_id_token(&token_key, &claims),
+                    })))
+                },
+            ),
+        );
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    MockIdp { url, codes, key }
+}
+
+fn no_redirect_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+/// Run the SSO flow, with the provider authenticating a user with `claims`
+/// (standard claims are filled in). Returns the callback response.
+async fn sso_login(base_url: &str, idp: &MockIdp, claims: serde_json::Value) -> reqwest::Response {
+    let resp = no_redirect_client()
+        .get(format!("{}/api/auth/oidc/login", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 303);
+    let location = reqwest::Url::parse(resp.headers()["location"].to_str().unwrap()).unwrap();
+    assert!(location.as_str().starts_with(&format!("{}/authorize?", idp.url)));
+    let param = |name: &str| {
+        location
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .unwrap()
+    };
+    assert_eq!(param("response_type"), "code");
+    assert_eq!(param("code_challenge_method"), "S256");
+
+    let mut claims = claims;
+    let defaults = serde_json::json!({
+        "iss": idp.url,
+        "aud": param("client_id"),
+        "sub": "user-1234",
+        "exp": chrono::Utc::now().timestamp() + 300,
+        "nonce": param("nonce"),
+    });
+    for (k, v) in defaults.as_object().unwrap() {
+        claims.as_object_mut().unwrap().entry(k.clone()).or_insert(v.clone());
     }
-    assert_eq!(chunks[4]["kind"], "parity");
-    assert_eq!(chunks[5]["kind"], "parity");
+    let code = uuid::Uuid::new_v4().to_string();
+    idp.codes.lock().unwrap().insert(code.clone(), (param("code_challenge"), claims));
+
+    no_redirect_client()
+        .get(format!("{}?code={}&state={}", param("redirect_uri"), code, param("state")))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn start_server_with_sso(idp: &MockIdp) -> (String, TempDir) {
+    let config_url = format!("{}/.well-known/openid-configuration", idp.url);
+    start_server_with(|c| {
+        c.oidc_config_url = Some(config_url);
+        c.oidc_client_id = Some("maxio-console".to_string());
+        c.oidc_group_policies = vec!["storage-readers=readonly".to_string(), "storage-admins=readwrite".to_string()];
+    })
+    .await
+}
+
+fn session_cookie(resp: &reqwest::Response) -> String {
+    let cookie = resp.headers()["set-cookie"].to_str().unwrap();
+    cookie.split(';').next().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_oidc_login_maps_claims_to_policies() {
+    let idp = start_mock_idp().await;
+    let (base_url, _tmp) = start_server_with_sso(&idp).await;
+    s3_request("PUT", &format!("{}/sso-bucket", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/sso-bucket/a.txt", base_url), b"a".to_vec()).await;
+
+    let json: serde_json::Value = client()
+        .get(format!("{}/api/auth/oidc", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(json["enabled"], true);
+
+    // Group membership grants read-only access
+    let resp = sso_login(
+        &base_url,
+        &idp,
+        serde_json::json!({"preferred_username": "alice", "groups": ["staff", "storage-readers"]}),
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(resp.headers()["location"], "/ui/");
+    let reader = session_cookie(&resp);
+    let json: serde_json::Value = console_get(&base_url, &reader, "/auth/check").await.json().await.unwrap();
+    assert_eq!(json["accessKey"], "oidc:alice");
+    assert_eq!(json["policy"], "readonly");
+    assert_eq!(console_get(&base_url, &reader, "/buckets").await.status(), 200);
+    assert_eq!(console_post(&base_url, &reader, "/buckets/sso-bucket/folders").await.status(), 403);
+    let resp = console_get(&base_url, &reader, "/buckets/sso-bucket/presign/a.txt?method=PUT").await;
+    assert_eq!(resp.status(), 403);
+    let resp = console_get(&base_url, &reader, "/buckets/sso-bucket/presign/a.txt?method=GET").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(console_get(&base_url, &reader, "/auth/sessions").await.status(), 200);
+
+    // The policy claim grants read-write access
+    let resp = sso_login(&base_url, &idp, serde_json::json!({"policy": "readwrite"})).await;
+    let writer = session_cookie(&resp);
+    let json: serde_json::Value = console_get(&base_url, &writer, "/auth/check").await.json().await.unwrap();
+    assert_eq!(json["accessKey"], "oidc:user-1234");
+    assert_eq!(json["policy"], "readwrite");
+    let json = console_presign(&base_url, &writer, "sso-bucket/presign/b.txt", "method=PUT").await;
+    let resp = client().put(json["url"].as_str().unwrap()).body("sso").send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // Logging out one SSO user leaves the other signed in
+    console_post(&base_url, &writer, "/auth/logout-all").await;
+    assert_eq!(console_get(&base_url, &writer, "/auth/check").await.status(), 401);
+    assert_eq!(console_get(&base_url, &reader, "/auth/check").await.status(), 200);
+}
+
+#[tokio::test]
+async fn test_oidc_login_rejections() {
+    let idp = start_mock_idp().await;
+    let (base_url, _tmp) = start_server_with_sso(&idp).await;
+
+    // Authenticated at the provider, but no policy assigned
+    let resp = sso_login(&base_url, &idp, serde_json::json!({"groups": ["staff"]})).await;
+    assert_eq!(resp.status(), 303);
+    assert!(resp.headers()["location"].to_str().unwrap().starts_with("/ui/?sso_error="));
+    assert!(resp.headers().get("set-cookie").is_none());
+
+    // ID tokens minted for another login, client or issuer are refused
+    for claims in [
+        serde_json::json!({"policy": "readwrite", "nonce": "replayed"}),
+        serde_json::json!({"policy": "readwrite", "aud": "another-client"}),
+        serde_json::json!({"policy": "readwrite", "iss": "https://evil.example.com"}),
+        serde_json::json!({"policy": "readwrite", "exp": chrono::Utc::now().timestamp() - 3600}),
+    ] {
+        let resp = sso_login(&base_url, &idp, claims).await;
+        assert!(resp.headers()["location"].to_str().unwrap().starts_with("/ui/?sso_error="));
+        assert!(resp.headers().get("set-cookie").is_none());
+    }
+
+    // A callback with an unknown state is refused
+    let resp = no_redirect_client()
+        .get(format!("{}/api/auth/oidc/callback?code=abc&state=forged", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.headers()["location"].to_str().unwrap().starts_with("/ui/?sso_error="));
+
+    // Without SSO configured the endpoints are absent
+    let (base_url, _tmp) = start_server().await;
+    let resp = no_redirect_client()
+        .get(format!("{}/api/auth/oidc/login", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+// --- STS temporary credentials ---
+
+/// A key pair to sign with, plus the session token of temporary credentials.
+struct TestCredentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl TestCredentials {
+    fn new(access_key: &str, secret_key: &str) -> Self {
+        Self {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            session_token: None,
+        }
+    }
+
+    /// Parse the `<Credentials>` of an STS response.
+    fn from_sts_response(xml: &str) -> Self {
+        Self {
+            access_key: extract_xml_tag(xml, "AccessKeyId").unwrap(),
+            secret_key: extract_xml_tag(xml, "SecretAccessKey").unwrap(),
+            session_token: Some(extract_xml_tag(xml, "SessionToken").unwrap()),
+        }
+    }
+}
+
+fn derive_key(secret_key: &str, date_stamp: &str, service: &str) -> Vec<u8> {
+    let mut key = format!("AWS4{}", secret_key).into_bytes();
+    for part in [date_stamp, REGION, service, "aws4_request"] {
+        let mut mac = HmacSha256::new_from_slice(&key).unwrap();
+        mac.update(part.as_bytes());
+        key = mac.finalize().into_bytes().to_vec();
+    }
+    key
+}
+
+/// Send a request signed with `creds` for `service`. Like the AWS SDKs, STS
+/// requests sign the body hash without sending `x-amz-content-sha256`.
+async fn signed_request_as(
+    creds: &TestCredentials,
+    service: &str,
+    method: &str,
+    url: &str,
+    content_type: Option<&str>,
+    body: Vec<u8>,
+) -> reqwest::Response {
+    let parsed = reqwest::Url::parse(url).unwrap();
+    let now = chrono::Utc::now();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let payload_hash = hex::encode(Sha256::digest(&body));
+
+    let mut headers = vec![
+        ("host".to_string(), format!("{}:{}", parsed.host_str().unwrap(), parsed.port().unwrap())),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if service == "s3" {
+        headers.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+    }
+    if let Some(ct) = content_type {
+        headers.push(("content-type".to_string(), ct.to_string()));
+    }
+    if let Some(ref token) = creds.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.sort();
+    let signed_headers = headers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    let mut query: Vec<&str> = parsed.query().unwrap_or("").split('&').filter(|s| !s.is_empty()).collect();
+    query.sort();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        parsed.path(),
+        query.join("&"),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date_stamp, REGION, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut mac = HmacSha256::new_from_slice(&derive_key(&creds.secret_key, &date_stamp, service)).unwrap();
+    mac.update(string_to_sign.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            creds.access_key, scope, signed_headers, signature
+        ),
+    ));
+
+    let mut builder = client().request(method.parse().unwrap(), url).body(body);
+    for (k, v) in headers {
+        if k != "host" {
+            builder = builder.header(k, v);
+        }
+    }
+    builder.send().await.unwrap()
+}
+
+/// Presigned GET URL for `path`, carrying the session token of `creds`.
+fn presign_get_as(creds: &TestCredentials, base_url: &str, path: &str) -> String {
+    let parsed = reqwest::Url::parse(&format!("{}{}", base_url, path)).unwrap();
+    let now = chrono::Utc::now();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential = format!("{}/{}/{}/s3/aws4_request", creds.access_key, date_stamp, REGION);
+    let mut params = vec![
+        ("X-Amz-Algorithm".to_string(), "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential".to_string(), credential),
+        ("X-Amz-Date".to_string(), amz_date.clone()),
+        ("X-Amz-Expires".to_string(), "300".to_string()),
+        ("X-Amz-SignedHeaders".to_string(), "host".to_string()),
+    ];
+    if let Some(ref token) = creds.session_token {
+        params.push(("X-Amz-Security-Token".to_string(), token.clone()));
+    }
+    params.sort();
+    let canonical_qs = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", percent_encode_s3(k), percent_encode_s3(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let canonical_request = format!(
+        "GET\n{}\n{}\nhost:{}:{}\n\nhost\nUNSIGNED-PAYLOAD",
+        path,
+        canonical_qs,
+        parsed.host_str().unwrap(),
+        parsed.port().unwrap()
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}/{}/s3/aws4_request\n{}",
+        amz_date,
+        date_stamp,
+        REGION,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut mac = HmacSha256::new_from_slice(&derive_key(&creds.secret_key, &date_stamp, "s3")).unwrap();
+    mac.update(string_to_sign.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+    format!("{}{}?{}&X-Amz-Signature={}", base_url, path, canonical_qs, signature)
+}
+
+async fn sts_call(base_url: &str, creds: Option<&TestCredentials>, form: &str) -> reqwest::Response {
+    let url = format!("{}/", base_url);
+    let content_type = "application/x-www-form-urlencoded; charset=utf-8";
+    match creds {
+        Some(creds) => signed_request_as(creds, "sts", "POST", &url, Some(content_type), form.as_bytes().to_vec()).await,
+        None => client()
+            .post(url)
+            .header("content-type", content_type)
+            .body(form.to_string())
+            .send()
+            .await
+            .unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn test_sts_assume_role() {
+    let (base_url, _tmp) = start_server_with(|c| c.users = vec!["ci-user=ci-user-secret".to_string()]).await;
+    s3_request("PUT", &format!("{}/sts-bucket", base_url), vec![]).await;
+    let user = TestCredentials::new("ci-user", "ci-user-secret");
+
+    let resp = sts_call(&base_url, Some(&user), "Action=AssumeRole&Version=2011-06-15&DurationSeconds=900").await;
+    assert_eq!(resp.status(), 200);
+    let xml = resp.text().await.unwrap();
+    assert!(xml.contains("<AssumeRoleResponse"));
+    assert!(extract_xml_tag(&xml, "Expiration").unwrap().ends_with('Z'));
+    let temp = TestCredentials::from_sts_response(&xml);
+    assert!(temp.access_key.starts_with("ASIA"));
+
+    // Temporary credentials sign S3 requests together with their token
+    let resp = signed_request_as(&temp, "s3", "PUT", &format!("{}/sts-bucket/a.txt", base_url), None, b"temp".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+    let resp = client().get(presign_get_as(&temp, &base_url, "/sts-bucket/a.txt")).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "temp");
+
+    // ... and are useless without it, or with a forged one
+    let no_token = TestCredentials::new(&temp.access_key, &temp.secret_key);
+    let resp = signed_request_as(&no_token, "s3", "GET", &format!("{}/sts-bucket/a.txt", base_url), None, vec![]).await;
+    assert_eq!(resp.status(), 403);
+    assert!(resp.text().await.unwrap().contains("InvalidAccessKeyId"));
+    let token = temp.session_token.clone().unwrap();
+    let (payload, seal) = token.split_once('.').unwrap();
+    let forged = TestCredentials {
+        session_token: Some(format!("{}x.{}", payload, seal)),
+        ..TestCredentials::new(&temp.access_key, &temp.secret_key)
+    };
+    let resp = signed_request_as(&forged, "s3", "GET", &format!("{}/sts-bucket/a.txt", base_url), None, vec![]).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("InvalidToken"));
+
+    // Temporary credentials cannot chain into further roles
+    let resp = sts_call(&base_url, Some(&temp), "Action=AssumeRole").await;
+    assert_eq!(resp.status(), 403);
+
+    // A session policy narrows the credentials to reading
+    let policy = percent_encode_s3(
+        r#"{"Version":"2012-10-17","Statement":[{"Effect":"Allow","Action":["s3:GetObject","s3:ListBucket"],"Resource":"arn:aws:s3:::*"}]}"#,
+    );
+    let resp = sts_call(&base_url, Some(&user), &format!("Action=AssumeRole&Policy={}", policy)).await;
+    assert_eq!(resp.status(), 200);
+    let reader = TestCredentials::from_sts_response(&resp.text().await.unwrap());
+    let resp = signed_request_as(&reader, "s3", "GET", &format!("{}/sts-bucket/a.txt", base_url), None, vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let resp = signed_request_as(&reader, "s3", "PUT", &format!("{}/sts-bucket/b.txt", base_url), None, b"no".to_vec()).await;
+    assert_eq!(resp.status(), 403);
+
+    // Policies MaxIO cannot represent, and out-of-range durations, are refused
+    let policy = percent_encode_s3(
+        r#"{"Statement":[{"Effect":"Allow","Action":"s3:GetObject","Resource":"arn:aws:s3:::sts-bucket/*"}]}"#,
+    );
+    let resp = sts_call(&base_url, Some(&user), &format!("Action=AssumeRole&Policy={}", policy)).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>MalformedPolicyDocument</Code>"));
+    let resp = sts_call(&base_url, Some(&user), "Action=AssumeRole&DurationSeconds=60").await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>InvalidParameterValue</Code>"));
+
+    // AssumeRole must be signed
+    let resp = sts_call(&base_url, None, "Action=AssumeRole").await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn test_sts_credentials_expire_with_parent_user() {
+    let (base_url, tmp) = start_server_in(TempDir::new().unwrap(), |c| c.users = vec!["ci-user=ci-user-secret".to_string()]).await;
+    s3_request("PUT", &format!("{}/sts-bucket", base_url), vec![]).await;
+    let user = TestCredentials::new("ci-user", "ci-user-secret");
+    let resp = sts_call(&base_url, Some(&user), "Action=AssumeRole").await;
+    let temp = TestCredentials::from_sts_response(&resp.text().await.unwrap());
+
+    // Still valid after a restart: the token is self-contained
+    let (base_url, tmp) = start_server_in(tmp, |c| c.users = vec!["ci-user=ci-user-secret".to_string()]).await;
+    let resp = signed_request_as(&temp, "s3", "GET", &format!("{}/sts-bucket", base_url), None, vec![]).await;
+    assert_eq!(resp.status(), 200);
+
+    // Removing the user revokes what it assumed
+    let (base_url, _tmp) = start_server_in(tmp, |_| {}).await;
+    let resp = signed_request_as(&temp, "s3", "GET", &format!("{}/sts-bucket", base_url), None, vec![]).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("InvalidToken"));
+}
+
+#[tokio::test]
+async fn test_sts_assume_role_with_web_identity() {
+    let idp = start_mock_idp().await;
+    let (base_url, _tmp) = start_server_with_sso(&idp).await;
+    s3_request("PUT", &format!("{}/ci-bucket", base_url), vec![]).await;
+
+    // A pod or CI runner's ID token, as minted by the provider
+    let token = idp.id_token(serde_json::json!({
+        "sub": "repo:acme/app:ref:refs/heads/main",
+        "aud": "maxio-console",
+        "groups": ["storage-admins"],
+    }));
+
+    let resp = sts_call(
+        &base_url,
+        None,
+        &format!("Action=AssumeRoleWithWebIdentity&Version=2011-06-15&WebIdentityToken={}", token),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let xml = resp.text().await.unwrap();
+    assert_eq!(
+        extract_xml_tag(&xml, "SubjectFromWebIdentityToken").unwrap(),
+        "repo:acme/app:ref:refs/heads/main"
+    );
+    let temp = TestCredentials::from_sts_response(&xml);
+    let resp = signed_request_as(&temp, "s3", "PUT", &format!("{}/ci-bucket/build.tar", base_url), None, b"tar".to_vec()).await;
+    assert_eq!(resp.status(), 200);
+
+    // Tokens for another audience, or without a mapped policy, are refused
+    let token = idp.id_token(serde_json::json!({"aud": "other-app", "groups": ["storage-admins"]}));
+    let resp = sts_call(&base_url, None, &format!("Action=AssumeRoleWithWebIdentity&WebIdentityToken={}", token)).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>InvalidIdentityToken</Code>"));
+    let token = idp.id_token(serde_json::json!({"aud": "maxio-console"}));
+    let resp = sts_call(&base_url, None, &format!("Action=AssumeRoleWithWebIdentity&WebIdentityToken={}", token)).await;
+    assert_eq!(resp.status(), 403);
+    let resp = sts_call(&base_url, None, "Action=AssumeRoleWithWebIdentity&WebIdentityToken=not.a.jwt").await;
+    assert_eq!(resp.status(), 400);
+}
+
+// --- Console CSRF protection and trusted proxies ---
+
+async fn console_create_bucket(
+    base_url: &str,
+    cookie: &str,
+    name: &str,
+    headers: &[(&str, &str)],
+) -> reqwest::Response {
+    let mut builder = client()
+        .post(format!("{}/api/buckets", base_url))
+        .header("cookie", cookie)
+        .json(&serde_json::json!({"name": name}));
+    for (k, v) in headers {
+        builder = builder.header(*k, *v);
+    }
+    builder.send().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_console_csrf_protection() {
+    let (base_url, _tmp) = start_server().await;
+    let cookie = console_login(&base_url).await;
+    let token = csrf_token(&base_url, &cookie).await;
+    assert_eq!(token.len(), 64);
+    let origin = base_url.clone();
+
+    // The session cookie alone cannot change anything
+    let resp = console_create_bucket(&base_url, &cookie, "csrf-a", &[]).await;
+    assert_eq!(resp.status(), 403);
+    let resp = console_create_bucket(&base_url, &cookie, "csrf-a", &[("x-csrf-token", "0000")]).await;
+    assert_eq!(resp.status(), 403);
+    let other = console_login(&base_url).await;
+    let other_token = csrf_token(&base_url, &other).await;
+    assert_ne!(other_token, token);
+    let resp = console_create_bucket(&base_url, &cookie, "csrf-a", &[("x-csrf-token", &other_token)]).await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = console_create_bucket(&base_url, &cookie, "csrf-a", &[("x-csrf-token", &token), ("origin", &origin)]).await;
+    assert_eq!(resp.status(), 200);
+
+    // Pages on other origins are refused even with a valid token
+    let resp = console_create_bucket(
+        &base_url,
+        &cookie,
+        "csrf-b",
+        &[("x-csrf-token", &token), ("origin", "http://evil.example.com")],
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    let resp = console_create_bucket(
+        &base_url,
+        &cookie,
+        "csrf-b",
+        &[("x-csrf-token", &token), ("referer", "http://evil.example.com/ui/")],
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .header("origin", "http://evil.example.com")
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    let resp = client()
+        .post(format!("{}/api/auth/logout", base_url))
+        .header("cookie", &cookie)
+        .header("origin", "http://evil.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+
+    // A cross-site form post whose Origin and Referer were stripped
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body(format!("accessKey={}&secretKey={}", ACCESS_KEY, SECRET_KEY))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "Cross-origin request refused");
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .header("content-type", "text/plain;charset=UTF-8")
+        .body(serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}).to_string())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    // A JSON login needs a preflight, so it may omit Origin
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // Reads need no token
+    assert_eq!(console_get(&base_url, &cookie, "/buckets").await.status(), 200);
+}
+
+#[tokio::test]
+async fn test_forwarded_headers_need_trusted_proxy() {
+    let (base_url, _tmp) = start_server().await;
+    // Spoofed client addresses do not escape the login rate limit
+    let mut limited = false;
+    for i in 0..11 {
+        let resp = client()
+            .post(format!("{}/api/auth/login", base_url))
+            .header("x-forwarded-for", format!("198.51.100.{}", i))
+            .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": "wrong"}))
+            .send()
+            .await
+            .unwrap();
+        limited |= resp.status() == 429;
+    }
+    assert!(limited);
+
+    // Nor do they show up as the session's address, or make the cookie Secure
+    let (base_url, _tmp) = start_server().await;
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .header("x-forwarded-for", "198.51.100.1")
+        .header("x-forwarded-proto", "https")
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert!(!resp.headers()["set-cookie"].to_str().unwrap().contains("Secure"));
+    let cookie = session_cookie(&resp);
+    let json: serde_json::Value = console_get(&base_url, &cookie, "/auth/sessions").await.json().await.unwrap();
+    assert_eq!(json["sessions"][0]["sourceIp"], "127.0.0.1");
+}
+
+#[tokio::test]
+async fn test_trusted_proxy_forwarded_headers() {
+    let (base_url, _tmp) =
+        start_server_with(|c| c.trusted_proxies = vec!["127.0.0.1".to_string(), "10.0.0.0/8".to_string()]).await;
+
+    // The client is the nearest address not belonging to a trusted proxy
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .header("x-forwarded-for", "192.0.2.99, 203.0.113.7, 10.1.2.3")
+        .header("x-forwarded-proto", "https")
+        .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": SECRET_KEY}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["set-cookie"].to_str().unwrap().contains("; Secure"));
+    let cookie = session_cookie(&resp);
+    let json: serde_json::Value = console_get(&base_url, &cookie, "/auth/sessions").await.json().await.unwrap();
+    assert_eq!(json["sessions"][0]["sourceIp"], "203.0.113.7");
+
+    // Presigned URLs use the scheme the client used
+    s3_request("PUT", &format!("{}/proxied", base_url), vec![]).await;
+    let resp = client()
+        .get(format!("{}/api/buckets/proxied/presign/a.txt?method=PUT", base_url))
+        .header("cookie", &cookie)
+        .header("x-forwarded-proto", "https")
+        .send()
+        .await
+        .unwrap();
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert!(json["url"].as_str().unwrap().starts_with("https://"));
+
+    // The origin check follows the forwarded scheme as well
+    let token = csrf_token(&base_url, &cookie).await;
+    let https_origin = base_url.replace("http://", "https://");
+    let resp = client()
+        .post(format!("{}/api/buckets", base_url))
+        .header("cookie", &cookie)
+        .header("x-csrf-token", &token)
+        .header("x-forwarded-proto", "https")
+        .header("origin", &https_origin)
+        .json(&serde_json::json!({"name": "via-proxy"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // Each real client has its own login rate limit
+    for i in 0..11 {
+        let resp = client()
+            .post(format!("{}/api/auth/login", base_url))
+            .header("x-forwarded-for", format!("198.51.100.{}", i))
+            .json(&serde_json::json!({"accessKey": ACCESS_KEY, "secretKey": "wrong"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+    }
+}
+
+// --- Request rate limits and bandwidth throttling ---
+
+/// Send `count` concurrent GETs to `url` as `creds` and return how many were refused with SlowDown.
+async fn count_slow_downs(creds: &TestCredentials, url: &str, count: usize) -> usize {
+    // Sent all at once so the bucket can't refill between requests
+    let burst = (0..count).map(|_| signed_request_as(creds, "s3", "GET", url, None, vec![]));
+    let mut refused = 0;
+    for resp in futures::future::join_all(burst).await {
+        if resp.status() == 503 {
+            assert!(resp.headers()["retry-after"].to_str().unwrap().parse::<u64>().unwrap() >= 1);
+            assert!(resp.text().await.unwrap().contains("<Code>SlowDown</Code>"));
+            refused += 1;
+        } else {
+            assert_eq!(resp.status(), 200);
+        }
+    }
+    refused
+}
+
+#[tokio::test]
+async fn test_rate_limit_per_access_key() {
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.key_request_rate = 5;
+        c.users = vec!["batch-job=batch-job-secret".to_string()];
+    })
+    .await;
+    let root = TestCredentials::new(ACCESS_KEY, SECRET_KEY);
+    let batch = TestCredentials::new("batch-job", "batch-job-secret");
+    s3_request("PUT", &format!("{}/shared", base_url), vec![]).await;
+
+    // A burst beyond the rate is refused with SlowDown...
+    let url = format!("{}/shared", base_url);
+    assert!(count_slow_downs(&batch, &url, 20).await > 0);
+    // ...without touching other keys' budgets
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(count_slow_downs(&root, &url, 4).await, 0);
+
+    let metrics = client().get(format!("{}/metrics", base_url)).send().await.unwrap().text().await.unwrap();
+    let throttled = metrics
+        .lines()
+        .find_map(|l| l.strip_prefix("maxio_s3_requests_throttled_total "))
+        .unwrap();
+    assert!(throttled.parse::<u64>().unwrap() > 0);
 }
 
 #[tokio::test]
-async fn test_parity_read_healthy() {
-    let (base_url, _tmp) = start_server_parity(2).await;
+async fn test_rate_limits_admin_endpoint() {
+    let (base_url, _tmp) = start_server_with(|c| c.users = vec!["ci-user=ci-user-secret".to_string()]).await;
+    let admin_url = format!("{}/minio/admin/v3/rate-limits", base_url);
+    s3_request("PUT", &format!("{}/hot", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/cold", base_url), vec![]).await;
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    let resp = s3_request("GET", &admin_url, vec![]).await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["accessKeys"]["default"]["requestsPerSecond"], 0);
+    assert_eq!(json["buckets"]["default"]["bytesPerSecond"], 0);
 
-    let data = vec![0xCDu8; 350];
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+    // Tighten one bucket at runtime
+    let limits = serde_json::json!({"buckets": {"overrides": {"hot": {"requestsPerSecond": 2}}}});
+    let resp = s3_request("PUT", &admin_url, limits.to_string().into_bytes()).await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = s3_request("GET", &admin_url, vec![]).await.json().await.unwrap();
+    assert_eq!(json["buckets"]["overrides"]["hot"]["requestsPerSecond"], 2);
 
-    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+    let root = TestCredentials::new(ACCESS_KEY, SECRET_KEY);
+    assert!(count_slow_downs(&root, &format!("{}/hot", base_url), 10).await > 0);
+    assert_eq!(count_slow_downs(&root, &format!("{}/cold", base_url), 10).await, 0);
+
+    // Lifting the limit takes effect immediately
+    let resp = s3_request("PUT", &admin_url, b"{}".to_vec()).await;
     assert_eq!(resp.status(), 200);
-    let body = resp.bytes().await.unwrap();
-    assert_eq!(body.as_ref(), &data[..]);
+    assert_eq!(count_slow_downs(&root, &format!("{}/hot", base_url), 10).await, 0);
+
+    // Only root may see or change limits, and only with a valid document
+    let user = TestCredentials::new("ci-user", "ci-user-secret");
+    let resp = signed_request_as(&user, "s3", "PUT", &admin_url, None, b"{}".to_vec()).await;
+    assert_eq!(resp.status(), 403);
+    let resp = signed_request_as(&user, "s3", "GET", &admin_url, None, vec![]).await;
+    assert_eq!(resp.status(), 403);
+    let resp = s3_request("PUT", &admin_url, b"{\"buckets\": 5}".to_vec()).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(client().get(&admin_url).send().await.unwrap().status(), 403);
 }
 
 #[tokio::test]
-async fn test_parity_recovery_corrupted_chunk() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_bandwidth_throttling() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/throttled", base_url), vec![]).await;
+    let data = vec![7u8; 128 * 1024];
+    s3_request("PUT", &format!("{}/throttled/big.bin", base_url), data.clone()).await;
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    let limits = serde_json::json!({"buckets": {"overrides": {"throttled": {"bytesPerSecond": 32 * 1024}}}});
+    let admin_url = format!("{}/minio/admin/v3/rate-limits", base_url);
+    assert_eq!(s3_request("PUT", &admin_url, limits.to_string().into_bytes()).await.status(), 200);
 
-    let data = vec![0xEFu8; 350];
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+    // One second of burst, then 96 KiB at 32 KiB/s
+    let start = std::time::Instant::now();
+    let resp = s3_request("GET", &format!("{}/throttled/big.bin", base_url), vec![]).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-length"], data.len().to_string().as_str());
+    assert_eq!(resp.bytes().await.unwrap().len(), data.len());
+    let elapsed = start.elapsed();
+    assert!(elapsed >= std::time::Duration::from_secs(2), "download took {:?}", elapsed);
+}
 
-    // Corrupt data chunk 1 (overwrite with zeros)
-    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000001");
-    std::fs::write(&chunk_path, vec![0u8; 100]).unwrap();
+async fn console_set_quota(base_url: &str, cookie: &str, bucket: &str, max_size: u64, max_objects: u64) -> reqwest::Response {
+    client()
+        .put(format!("{}/api/buckets/{}/quota", base_url, bucket))
+        .header("cookie", cookie)
+        .header("x-csrf-token", csrf_token(base_url, cookie).await)
+        .json(&serde_json::json!({"maxSize": max_size, "maxObjects": max_objects}))
+        .send()
+        .await
+        .unwrap()
+}
 
-    // Read should still succeed via RS recovery
-    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
+async fn console_quota(base_url: &str, cookie: &str, bucket: &str) -> serde_json::Value {
+    let resp = console_get(base_url, cookie, &format!("/buckets/{}/quota", bucket)).await;
     assert_eq!(resp.status(), 200);
-    let body = resp.bytes().await.unwrap();
-    assert_eq!(body.as_ref(), &data[..]);
+    resp.json().await.unwrap()
 }
 
 #[tokio::test]
-async fn test_parity_recovery_missing_chunk() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_bucket_quota() {
+    let (base_url, _tmp) =
+        start_server_with(|c| c.users = vec!["alice=alice-secret".to_string()]).await;
+    s3_request("PUT", &format!("{}/capped", base_url), vec![]).await;
+    let root = console_login(&base_url).await;
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    // Only the root user may change quotas
+    let resp = client()
+        .post(format!("{}/api/auth/login", base_url))
+        .json(&serde_json::json!({"accessKey": "alice", "secretKey": "alice-secret"}))
+        .send()
+        .await
+        .unwrap();
+    let alice = session_cookie(&resp);
+    assert_eq!(console_set_quota(&base_url, &alice, "capped", 100, 3).await.status(), 403);
+    assert_eq!(console_set_quota(&base_url, &root, "capped", 100, 3).await.status(), 200);
 
-    let data = vec![0x42u8; 350];
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+    let url = |key: &str| format!("{}/capped/{}", base_url, key);
+    assert_eq!(s3_request("PUT", &url("a.txt"), vec![b'a'; 60]).await.status(), 200);
+    let resp = s3_request("PUT", &url("b.txt"), vec![b'b'; 50]).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>QuotaExceeded</Code>"));
+    assert_eq!(s3_request("GET", &url("b.txt"), vec![]).await.status(), 404);
+
+    // Overwriting frees the space of the replaced object
+    assert_eq!(s3_request("PUT", &url("a.txt"), vec![b'a'; 90]).await.status(), 200);
+    let quota = console_quota(&base_url, &root, "capped").await;
+    assert_eq!(quota["maxSize"], 100);
+    assert_eq!(quota["maxObjects"], 3);
+    assert_eq!(quota["size"], 90);
+    assert_eq!(quota["objects"], 1);
+
+    // Object count is capped too, folder markers included
+    assert_eq!(s3_request("PUT", &url("b.txt"), vec![b'b'; 5]).await.status(), 200);
+    assert_eq!(s3_request("PUT", &url("dir/"), vec![]).await.status(), 200);
+    assert_eq!(s3_request("PUT", &url("c.txt"), vec![]).await.status(), 400);
+    let resp = client()
+        .put(format!("{}/api/buckets/capped/upload/c.txt", base_url))
+        .header("cookie", &root)
+        .header("x-csrf-token", csrf_token(&base_url, &root).await)
+        .body("c")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
 
-    // Delete data chunk 0
-    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000000");
-    std::fs::remove_file(&chunk_path).unwrap();
+    // Deleting gives the room back
+    assert_eq!(s3_request("DELETE", &url("a.txt"), vec![]).await.status(), 204);
+    assert_eq!(s3_request("PUT", &url("c.txt"), vec![b'c'; 50]).await.status(), 200);
+    let quota = console_quota(&base_url, &root, "capped").await;
+    assert_eq!(quota["size"], 55);
+    assert_eq!(quota["objects"], 3);
 
-    let resp = s3_request("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
-    assert_eq!(resp.status(), 200);
-    let body = resp.bytes().await.unwrap();
-    assert_eq!(body.as_ref(), &data[..]);
+    // A copy is checked against the source's size
+    let resp = s3_request_with_headers("PUT", &url("d.txt"), vec![], vec![("x-amz-copy-source", "/capped/c.txt")]).await;
+    assert_eq!(resp.status(), 400);
+
+    // Clearing the quota lifts the limits
+    assert_eq!(console_set_quota(&base_url, &root, "capped", 0, 0).await.status(), 200);
+    assert_eq!(s3_request("PUT", &url("d.txt"), vec![b'd'; 500]).await.status(), 200);
+    let quota = console_quota(&base_url, &root, "capped").await;
+    assert_eq!(quota["maxSize"], 0);
+    assert_eq!(quota["size"], 555);
 }
 
 #[tokio::test]
-async fn test_parity_too_many_failures() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_bucket_quota_counts_versions_and_multipart() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/vcapped", base_url), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/vcapped?versioning", base_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let root = console_login(&base_url).await;
+    assert_eq!(console_set_quota(&base_url, &root, "vcapped", 100, 0).await.status(), 200);
+
+    // Noncurrent versions keep counting; delete markers don't
+    let url = format!("{}/vcapped/obj.txt", base_url);
+    let put = s3_request("PUT", &url, vec![b'1'; 60]).await;
+    let first = put.headers()["x-amz-version-id"].to_str().unwrap().to_string();
+    assert_eq!(s3_request("PUT", &url, vec![b'2'; 30]).await.status(), 200);
+    assert_eq!(s3_request("DELETE", &url, vec![]).await.status(), 204);
+    let quota = console_quota(&base_url, &root, "vcapped").await;
+    assert_eq!(quota["size"], 90);
+    assert_eq!(quota["objects"], 2);
+    assert_eq!(s3_request("PUT", &url, vec![b'3'; 20]).await.status(), 400);
+
+    let resp = s3_request("DELETE", &format!("{}?versionId={}", url, first), vec![]).await;
+    assert_eq!(resp.status(), 204);
+    assert_eq!(s3_request("PUT", &url, vec![b'3'; 20]).await.status(), 200);
+    let quota = console_quota(&base_url, &root, "vcapped").await;
+    assert_eq!(quota["size"], 50);
+    assert_eq!(quota["objects"], 2);
+
+    // Multipart uploads are checked when completed
+    assert_eq!(console_set_quota(&base_url, &root, "vcapped", 6 * 1024 * 1024, 0).await.status(), 200);
+    let resp = multipart_upload(
+        &base_url,
+        "/vcapped/big.bin",
+        vec![vec![b'x'; 5 * 1024 * 1024], vec![b'y'; 2 * 1024 * 1024]],
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>QuotaExceeded</Code>"));
+    assert_eq!(s3_request("HEAD", &format!("{}/vcapped/big.bin", base_url), vec![]).await.status(), 404);
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    let resp = multipart_upload(&base_url, "/vcapped/big.bin", vec![vec![b'x'; 5 * 1024 * 1024], vec![b'y'; 1024]]).await;
+    assert_eq!(resp.status(), 200);
+    let quota = console_quota(&base_url, &root, "vcapped").await;
+    assert_eq!(quota["size"], 5 * 1024 * 1024 + 1024 + 50);
+    assert_eq!(quota["objects"], 3);
+}
 
-    let data = vec![0x77u8; 350];
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data).await;
+#[tokio::test]
+async fn test_bucket_quota_under_concurrent_and_chunked_writes() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/busy", base_url), vec![]).await;
+    let root = console_login(&base_url).await;
+    assert_eq!(console_set_quota(&base_url, &root, "busy", 100, 0).await.status(), 200);
+    let url = |key: &str| format!("{}/busy/{}", base_url, key);
 
-    // Delete 3 chunks (more than m=2 parity can handle)
-    for i in 0..3 {
-        let chunk_path = tmp.path().join(format!("buckets/parity-test/file.bin.ec/{:06}", i));
-        std::fs::remove_file(&chunk_path).unwrap();
+    // An aws-chunked body is held to its declared length
+    let resp = s3_put_chunked_declaring(&url("big.bin"), &[b'x'; 1000], 0).await;
+    assert_eq!(resp.status(), 400);
+    assert!(resp.text().await.unwrap().contains("<Code>IncompleteBody</Code>"));
+    let resp = s3_put_chunked_declaring(&url("big.bin"), &[b'x'; 10], 20).await;
+    assert_eq!(resp.status(), 400);
+    assert_eq!(s3_request("HEAD", &url("big.bin"), vec![]).await.status(), 404);
+
+    // Concurrent overwrites of one key count it once
+    let same = url("same.txt");
+    let overwrites = (0..8).map(|_| s3_request("PUT", &same, vec![b's'; 10]));
+    for resp in futures::future::join_all(overwrites).await {
+        assert_eq!(resp.status(), 200);
     }
+    let quota = console_quota(&base_url, &root, "busy").await;
+    assert_eq!(quota["size"], 10);
+    assert_eq!(quota["objects"], 1);
+    assert_eq!(s3_request("DELETE", &url("same.txt"), vec![]).await.status(), 204);
+
+    // Concurrent writes cannot all fit into the same headroom
+    let urls: Vec<String> = (0..8).map(|i| url(&format!("{}.txt", i))).collect();
+    let writes = urls.iter().map(|u| s3_request("PUT", u, vec![b'c'; 30]));
+    let stored = futures::future::join_all(writes).await.iter().filter(|r| r.status() == 200).count();
+    assert_eq!(stored, 3);
+    let quota = console_quota(&base_url, &root, "busy").await;
+    assert_eq!(quota["size"], 90);
+    assert_eq!(quota["objects"], 3);
+}
 
-    // The server will return an error or drop the connection when RS recovery fails.
-    // Since the object is streamed, the error may manifest as a connection reset
-    // rather than a clean HTTP error status.
-    let result = s3_request_result("GET", &format!("{}/parity-test/file.bin", base_url), vec![]).await;
-    match result {
-        Err(_) => {} // Connection error — expected
-        Ok(resp) => {
-            // Either a server error status, or streaming started but body will be incomplete
-            if resp.status() == 200 {
-                let body_result = resp.bytes().await;
-                assert!(body_result.is_err() || body_result.unwrap().len() != 350);
-            } else {
-                assert!(resp.status().is_server_error());
-            }
+async fn console_usage(base_url: &str, cookie: &str, bucket: &str) -> serde_json::Value {
+    let resp = console_get(base_url, cookie, "/usage").await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    json["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|b| b["name"] == bucket)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_data_usage_api() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/counted", base_url), vec![]).await;
+    s3_request(
+        "PUT",
+        &format!("{}/counted?versioning", base_url),
+        b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    s3_request("PUT", &format!("{}/other", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/other/file", base_url), vec![0u8; 7]).await;
+
+    s3_request("PUT", &format!("{}/counted/a", base_url), vec![0u8; 100]).await;
+    s3_request("PUT", &format!("{}/counted/a", base_url), vec![0u8; 50]).await;
+    s3_request("PUT", &format!("{}/counted/b", base_url), vec![0u8; 10]).await;
+    s3_request("DELETE", &format!("{}/counted/b", base_url), vec![]).await;
+
+    let create = s3_request("POST", &format!("{}/counted/big?uploads=", base_url), vec![]).await;
+    let upload_id = extract_xml_tag(&create.text().await.unwrap(), "UploadId").unwrap();
+    let part_url = format!("{}/counted/big?partNumber=1&uploadId={}", base_url, upload_id);
+    s3_request("PUT", &part_url, vec![0u8; 2000]).await;
+    // A part uploaded again replaces the earlier one
+    s3_request("PUT", &part_url, vec![0u8; 1000]).await;
+
+    let cookie = console_login(&base_url).await;
+    let usage = console_usage(&base_url, &cookie, "counted").await;
+    assert_eq!(usage["objects"], 3);
+    assert_eq!(usage["size"], 160);
+    assert_eq!(usage["versions"], 2);
+    assert_eq!(usage["versionsSize"], 110);
+    assert_eq!(usage["deleteMarkers"], 1);
+    assert_eq!(usage["multipartUploads"], 1);
+    assert_eq!(usage["multipartSize"], 1000);
+    assert_eq!(usage["ecOverhead"], 0);
+
+    let json: serde_json::Value = console_get(&base_url, &cookie, "/usage").await.json().await.unwrap();
+    assert_eq!(json["total"]["objects"], 4);
+    assert_eq!(json["total"]["size"], 167);
+
+    s3_request("DELETE", &format!("{}/counted/big?uploadId={}", base_url, upload_id), vec![]).await;
+    let usage = console_usage(&base_url, &cookie, "counted").await;
+    assert_eq!(usage["multipartUploads"], 0);
+    assert_eq!(usage["multipartSize"], 0);
+
+    // Turning versioning off drops the old versions and delete markers
+    s3_request(
+        "PUT",
+        &format!("{}/counted?versioning", base_url),
+        b"<VersioningConfiguration><Status>Suspended</Status></VersioningConfiguration>".to_vec(),
+    )
+    .await;
+    let usage = console_usage(&base_url, &cookie, "counted").await;
+    assert_eq!(usage["objects"], 1);
+    assert_eq!(usage["size"], 50);
+    assert_eq!(usage["versions"], 0);
+    assert_eq!(usage["deleteMarkers"], 0);
+}
+
+#[tokio::test]
+async fn test_usage_crawler_corrects_drift() {
+    let (base_url, tmp) = start_server_with(|c| c.usage_scan_interval = 1).await;
+    s3_request("PUT", &format!("{}/crawled", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/crawled/one", base_url), vec![0u8; 10]).await;
+    let cookie = console_login(&base_url).await;
+    assert_eq!(console_usage(&base_url, &cookie, "crawled").await["objects"], 1);
+
+    // An object placed behind the server's back is found by the next crawl
+    let dir = tmp.path().join("buckets/crawled");
+    std::fs::copy(dir.join("one"), dir.join("two")).unwrap();
+    let meta = std::fs::read_to_string(dir.join("one.meta.json")).unwrap();
+    std::fs::write(dir.join("two.meta.json"), meta.replace("\"one\"", "\"two\"")).unwrap();
+
+    for _ in 0..50 {
+        if console_usage(&base_url, &cookie, "crawled").await["objects"] == 2 {
+            break;
         }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     }
+    let usage = console_usage(&base_url, &cookie, "crawled").await;
+    assert_eq!(usage["objects"], 2);
+    assert_eq!(usage["size"], 20);
 }
 
 #[tokio::test]
-async fn test_parity_range_read_degraded() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_data_usage_ec_overhead() {
+    let (base_url, _tmp) = start_server_parity(2).await;
+    s3_request("PUT", &format!("{}/parity-usage", base_url), vec![]).await;
+    // 350 bytes in 100-byte chunks: two 100-byte parity shards
+    s3_request("PUT", &format!("{}/parity-usage/file.bin", base_url), vec![0xABu8; 350]).await;
+
+    let cookie = console_login(&base_url).await;
+    let usage = console_usage(&base_url, &cookie, "parity-usage").await;
+    assert_eq!(usage["objects"], 1);
+    assert_eq!(usage["size"], 350);
+    assert_eq!(usage["ecOverhead"], 200);
+
+    s3_request("DELETE", &format!("{}/parity-usage/file.bin", base_url), vec![]).await;
+    let usage = console_usage(&base_url, &cookie, "parity-usage").await;
+    assert_eq!(usage["objects"], 0);
+    assert_eq!(usage["ecOverhead"], 0);
+}
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+async fn console_list(base_url: &str, cookie: &str, bucket: &str, query: &str) -> serde_json::Value {
+    let resp = console_get(base_url, cookie, &format!("/buckets/{}/objects?{}", bucket, query)).await;
+    assert_eq!(resp.status(), 200);
+    resp.json().await.unwrap()
+}
 
-    // Create data with distinct bytes per chunk for easy verification
-    let mut data = Vec::new();
-    for i in 0u8..4 {
-        let chunk_len = if i < 3 { 100 } else { 50 };
-        data.extend(std::iter::repeat(i + 1).take(chunk_len));
-    }
-    assert_eq!(data.len(), 350);
-    s3_request("PUT", &format!("{}/parity-test/file.bin", base_url), data.clone()).await;
+fn listed_names(page: &serde_json::Value) -> Vec<String> {
+    let prefixes = page["prefixes"].as_array().unwrap().iter().map(|p| p.as_str().unwrap().to_string());
+    let files = page["files"].as_array().unwrap().iter().map(|f| f["key"].as_str().unwrap().to_string());
+    prefixes.chain(files).collect()
+}
 
-    // Corrupt chunk 1
-    let chunk_path = tmp.path().join("buckets/parity-test/file.bin.ec/000001");
-    std::fs::write(&chunk_path, vec![0u8; 100]).unwrap();
+#[tokio::test]
+async fn test_console_list_pagination_and_search() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/browse", base_url), vec![]).await;
+    for i in 0..25 {
+        s3_request("PUT", &format!("{}/browse/f{:02}", base_url, i), vec![b'x'; i]).await;
+    }
+    s3_request("PUT", &format!("{}/browse/docs/readme", base_url), b"hi".to_vec()).await;
+    s3_request("PUT", &format!("{}/browse/empty/", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/browse/nested/", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/browse/nested/deeper/", base_url), vec![]).await;
+    let cookie = console_login(&base_url).await;
+
+    // Pages follow the cursor, folders first, until none is returned
+    let mut names = Vec::new();
+    let mut query = "limit=10".to_string();
+    let mut pages = 0;
+    loop {
+        let page = console_list(&base_url, &cookie, "browse", &query).await;
+        names.extend(listed_names(&page));
+        pages += 1;
+        match page["nextCursor"].as_str() {
+            Some(cursor) => query = format!("limit=10&cursor={}", cursor),
+            None => break,
+        }
+    }
+    assert_eq!(pages, 3);
+    let mut expected = vec!["docs/".to_string(), "empty/".to_string(), "nested/".to_string()];
+    expected.extend((0..25).map(|i| format!("f{:02}", i)));
+    assert_eq!(names, expected);
+
+    let page = console_list(&base_url, &cookie, "browse", "").await;
+    assert_eq!(page["emptyPrefixes"], serde_json::json!(["empty/"]));
+    assert!(page["nextCursor"].is_null());
+    let page = console_list(&base_url, &cookie, "browse", "prefix=nested/").await;
+    assert_eq!(listed_names(&page), vec!["nested/deeper/"]);
+    assert_eq!(page["emptyPrefixes"], serde_json::json!(["nested/deeper/"]));
+
+    let page = console_list(&base_url, &cookie, "browse", "search=F1").await;
+    assert_eq!(listed_names(&page), (10..20).map(|i| format!("f{:02}", i)).collect::<Vec<_>>());
+    let page = console_list(&base_url, &cookie, "browse", "search=f%3F5").await;
+    assert_eq!(listed_names(&page), vec!["f05", "f15"]);
+    let page = console_list(&base_url, &cookie, "browse", "search=*s").await;
+    assert_eq!(listed_names(&page), vec!["docs/"]);
+
+    let page = console_list(&base_url, &cookie, "browse", "sort=size&order=desc&limit=5").await;
+    assert_eq!(listed_names(&page), vec!["docs/", "empty/", "nested/", "f24", "f23"]);
+    let cursor = page["nextCursor"].as_str().unwrap().to_string();
+    let page = console_list(&base_url, &cookie, "browse", &format!("sort=size&order=desc&limit=2&cursor={}", cursor)).await;
+    assert_eq!(listed_names(&page), vec!["f22", "f21"]);
+
+    // Without a delimiter the whole subtree is searched
+    let page = console_list(&base_url, &cookie, "browse", "delimiter=&search=read").await;
+    assert_eq!(listed_names(&page), vec!["docs/readme"]);
+
+    let resp = console_get(&base_url, &cookie, "/buckets/browse/objects?cursor=bogus").await;
+    assert_eq!(resp.status(), 400);
+    let resp = console_get(&base_url, &cookie, "/buckets/browse/objects?sort=owner").await;
+    assert_eq!(resp.status(), 400);
+}
 
-    // Range read spanning chunk 0 and chunk 1 (bytes 50-149)
-    let resp = s3_request_with_headers(
-        "GET",
-        &format!("{}/parity-test/file.bin", base_url),
-        vec![],
-        vec![("range", "bytes=50-149")],
-    )
-    .await;
-    assert_eq!(resp.status(), 206);
-    let body = resp.bytes().await.unwrap();
-    assert_eq!(body.as_ref(), &data[50..150]);
+async fn console_send(
+    base_url: &str,
+    cookie: &str,
+    method: reqwest::Method,
+    path: &str,
+) -> reqwest::RequestBuilder {
+    client()
+        .request(method, format!("{}/api{}", base_url, path))
+        .header("cookie", cookie)
+        .header("x-csrf-token", csrf_token(base_url, cookie).await)
 }
 
 #[tokio::test]
-async fn test_parity_backward_compat_v1_manifest() {
-    // EC without parity should still work (v1 manifest, no parity fields)
-    let (base_url, _tmp) = start_server_ec().await;
+async fn test_console_multipart_upload() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/resume", base_url), vec![]).await;
+    let cookie = console_login(&base_url).await;
+    let sha256 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(Sha256::digest(data));
 
-    s3_request("PUT", &format!("{}/compat-test", base_url), vec![]).await;
+    let resp = console_send(&base_url, &cookie, reqwest::Method::POST, "/buckets/resume/uploads")
+        .await
+        .json(&serde_json::json!({"key": "big.bin", "checksumAlgorithm": "SHA256"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let upload: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(upload["checksumAlgorithm"], "SHA256");
+    let upload_id = upload["uploadId"].as_str().unwrap().to_string();
+    let part_path = |n: u32| format!("/buckets/resume/uploads/{}/parts/{}", upload_id, n);
+
+    // Parts can arrive in any order
+    let first = vec![b'a'; 5 * 1024 * 1024];
+    let second = vec![b'b'; 100];
+    let resp = console_send(&base_url, &cookie, reqwest::Method::PUT, &part_path(2))
+        .await
+        .header("x-amz-checksum-sha256", sha256(&second))
+        .body(second.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let part: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(part["size"], 100);
+    assert_eq!(part["checksum"], sha256(&second));
 
-    let data = vec![0xAAu8; 2048];
-    s3_request("PUT", &format!("{}/compat-test/file.bin", base_url), data.clone()).await;
+    // A part that doesn't match its checksum is refused
+    let resp = console_send(&base_url, &cookie, reqwest::Method::PUT, &part_path(1))
+        .await
+        .header("x-amz-checksum-sha256", sha256(b"something else"))
+        .body(first.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
 
-    let resp = s3_request("GET", &format!("{}/compat-test/file.bin", base_url), vec![]).await;
+    // Resuming starts from the parts the server already holds
+    let resp = console_get(&base_url, &cookie, "/buckets/resume/uploads?key=big.bin").await;
+    let uploads: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(uploads["uploads"].as_array().unwrap().len(), 1);
+    assert_eq!(uploads["uploads"][0]["uploadId"], upload_id.as_str());
+    let resp = console_get(&base_url, &cookie, &format!("/buckets/resume/uploads/{}", upload_id)).await;
+    let status: serde_json::Value = resp.json().await.unwrap();
+    let parts = status["parts"].as_array().unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0]["partNumber"], 2);
+
+    let resp = console_send(&base_url, &cookie, reqwest::Method::PUT, &part_path(1))
+        .await
+        .header("x-amz-checksum-sha256", sha256(&first))
+        .body(first.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = console_send(&base_url, &cookie, reqwest::Method::POST, &format!("/buckets/resume/uploads/{}/complete", upload_id))
+        .await
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
     assert_eq!(resp.status(), 200);
+    let done: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(done["key"], "big.bin");
+    assert_eq!(done["size"], first.len() + second.len());
+    assert!(done["checksum"].as_str().unwrap().ends_with("-2"));
+
+    let resp = s3_request("GET", &format!("{}/resume/big.bin", base_url), vec![]).await;
     let body = resp.bytes().await.unwrap();
-    assert_eq!(body.as_ref(), &data[..]);
+    assert_eq!(body.len(), first.len() + second.len());
+    assert_eq!(&body[..first.len()], &first[..]);
+    assert_eq!(&body[first.len()..], &second[..]);
+    let resp = console_get(&base_url, &cookie, &format!("/buckets/resume/uploads/{}", upload_id)).await;
+    assert_eq!(resp.status(), 404);
+
+    // An abandoned upload can be aborted
+    let resp = console_send(&base_url, &cookie, reqwest::Method::POST, "/buckets/resume/uploads")
+        .await
+        .json(&serde_json::json!({"key": "dropped.bin"}))
+        .send()
+        .await
+        .unwrap();
+    let upload: serde_json::Value = resp.json().await.unwrap();
+    let upload_id = upload["uploadId"].as_str().unwrap();
+    let resp = console_send(&base_url, &cookie, reqwest::Method::DELETE, &format!("/buckets/resume/uploads/{}", upload_id))
+        .await
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = console_get(&base_url, &cookie, "/buckets/resume/uploads").await;
+    let uploads: serde_json::Value = resp.json().await.unwrap();
+    assert!(uploads["uploads"].as_array().unwrap().is_empty());
+
+    let resp = console_send(&base_url, &cookie, reqwest::Method::POST, "/buckets/resume/uploads")
+        .await
+        .json(&serde_json::json!({"key": "x", "checksumAlgorithm": "MD4"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+/// Entries of a ZIP, read through its central directory, with CRCs checked.
+fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |at: usize| u16::from_le_bytes(archive[at..at + 2].try_into().unwrap()) as usize;
+    let u32_at = |at: usize| u32::from_le_bytes(archive[at..at + 4].try_into().unwrap());
+    let end = archive.len() - 22;
+    assert_eq!(u32_at(end), 0x0605_4b50);
+    let count = u16_at(end + 10);
+    let mut at = u32_at(end + 16) as usize;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(at), 0x0201_4b50);
+        let crc = u32_at(at + 16);
+        let size = u32_at(at + 24) as usize;
+        let name_len = u16_at(at + 28);
+        let extra_len = u16_at(at + 30);
+        let offset = u32_at(at + 42) as usize;
+        let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+        assert_eq!(u32_at(offset), 0x0403_4b50);
+        let data_start = offset + 30 + u16_at(offset + 26) + u16_at(offset + 28);
+        let data = archive[data_start..data_start + size].to_vec();
+        assert_eq!(crc32fast::hash(&data), crc, "CRC of {}", name);
+        entries.push((name, data));
+        at += 46 + name_len + extra_len;
+    }
+    entries
+}
+
+/// Entries of a tar.gz, following PAX `path` records.
+fn untar_gz(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    use std::io::Read;
+    let mut tar = Vec::new();
+    flate2::read::GzDecoder::new(archive).read_to_end(&mut tar).unwrap();
+    let mut entries = Vec::new();
+    let mut at = 0;
+    let mut pax_path = None;
+    while tar[at..at + 512].iter().any(|b| *b != 0) {
+        let header = &tar[at..at + 512];
+        let field = |range: std::ops::Range<usize>| {
+            let raw = &header[range];
+            String::from_utf8(raw[..raw.iter().position(|b| *b == 0).unwrap_or(raw.len())].to_vec()).unwrap()
+        };
+        let size = usize::from_str_radix(field(124..136).trim(), 8).unwrap();
+        let checksum = u32::from_str_radix(field(148..155).trim(), 8).unwrap();
+        let sum: u32 = header.iter().enumerate().map(|(i, b)| if (148..156).contains(&i) { 32 } else { *b as u32 }).sum();
+        assert_eq!(checksum, sum);
+        let data = tar[at + 512..at + 512 + size].to_vec();
+        at += 512 + size.div_ceil(512) * 512;
+        if header[156] == b'x' {
+            let records = String::from_utf8(data).unwrap();
+            pax_path = records.lines().find_map(|r| r.split_once(" path=").map(|(_, p)| p.to_string()));
+            continue;
+        }
+        entries.push((pax_path.take().unwrap_or_else(|| field(0..100)), data));
+    }
+    entries
+}
+
+async fn console_archive(base_url: &str, cookie: &str, bucket: &str, query: &str) -> reqwest::Response {
+    console_get(base_url, cookie, &format!("/buckets/{}/archive?{}", bucket, query)).await
 }
 
 #[tokio::test]
-async fn test_parity_empty_object() {
-    let (base_url, tmp) = start_server_parity(2).await;
+async fn test_console_archive_download() {
+    let (base_url, _tmp) = start_server().await;
+    s3_request("PUT", &format!("{}/arch", base_url), vec![]).await;
+    let long_name = format!("photos/{}.txt", "n".repeat(120));
+    let objects: Vec<(String, Vec<u8>)> = vec![
+        ("photos/a.txt".to_string(), b"hello".to_vec()),
+        (long_name.clone(), b"long".to_vec()),
+        ("photos/sub/b.bin".to_string(), (0..=255u8).cycle().take(70_000).collect()),
+        ("other.txt".to_string(), b"elsewhere".to_vec()),
+    ];
+    for (key, data) in &objects {
+        s3_request("PUT", &format!("{}/arch/{}", base_url, key), data.clone()).await;
+    }
+    s3_request("PUT", &format!("{}/arch/photos/empty/", base_url), vec![]).await;
+    let cookie = console_login(&base_url).await;
+    let under_photos: Vec<(String, Vec<u8>)> = {
+        let mut v: Vec<_> = objects.iter().filter(|(k, _)| k.starts_with("photos/")).cloned().collect();
+        v.sort();
+        v
+    };
 
-    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    // A whole prefix keeps its folder name
+    let resp = console_archive(&base_url, &cookie, "arch", "prefix=photos/").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/zip");
+    assert!(resp.headers()["content-disposition"].to_str().unwrap().contains("filename=\"photos.zip\""));
+    let entries = unzip(&resp.bytes().await.unwrap());
+    assert_eq!(entries, under_photos);
 
-    // Empty object — should skip parity
-    s3_request("PUT", &format!("{}/parity-test/empty.bin", base_url), vec![]).await;
+    let resp = console_archive(&base_url, &cookie, "arch", "prefix=photos/&format=tar.gz").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/gzip");
+    assert_eq!(untar_gz(&resp.bytes().await.unwrap()), under_photos);
 
-    let ec_dir = tmp.path().join("buckets/parity-test/empty.bin.ec");
-    let manifest: serde_json::Value = serde_json::from_str(
-        &std::fs::read_to_string(ec_dir.join("manifest.json")).unwrap()
-    ).unwrap();
-    assert_eq!(manifest["version"], 1); // no parity for empty
-    assert!(manifest.get("parity_shards").is_none() || manifest["parity_shards"].is_null());
+    // A selection is named relative to the folder it was made in
+    let resp = console_archive(&base_url, &cookie, "arch", "prefix=photos/&key=photos/a.txt&key=photos/sub/").await;
+    let names: Vec<String> = unzip(&resp.bytes().await.unwrap()).into_iter().map(|(n, _)| n).collect();
+    assert_eq!(names, vec!["a.txt", "sub/b.bin"]);
 
-    let resp = s3_request("GET", &format!("{}/parity-test/empty.bin", base_url), vec![]).await;
+    // The whole bucket
+    let resp = console_archive(&base_url, &cookie, "arch", "format=tgz").await;
+    assert!(resp.headers()["content-disposition"].to_str().unwrap().contains("filename=\"arch.tar.gz\""));
+    assert_eq!(untar_gz(&resp.bytes().await.unwrap()).len(), 4);
+
+    let resp = console_archive(&base_url, &cookie, "arch", "key=missing.txt").await;
+    assert_eq!(resp.status(), 404);
+    let resp = console_archive(&base_url, &cookie, "arch", "prefix=photos/empty/").await;
+    assert_eq!(resp.status(), 404);
+    let resp = console_archive(&base_url, &cookie, "arch", "format=rar").await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_console_archive_limits() {
+    let (base_url, _tmp) = start_server_with(|c| {
+        c.archive_max_objects = 2;
+        c.archive_max_size = 1000;
+    })
+    .await;
+    s3_request("PUT", &format!("{}/capped", base_url), vec![]).await;
+    s3_request("PUT", &format!("{}/capped/a", base_url), vec![0u8; 600]).await;
+    s3_request("PUT", &format!("{}/capped/b", base_url), vec![0u8; 300]).await;
+    let cookie = console_login(&base_url).await;
+    assert_eq!(console_archive(&base_url, &cookie, "capped", "").await.status(), 200);
+
+    s3_request("PUT", &format!("{}/capped/c", base_url), vec![0u8; 10]).await;
+    let resp = console_archive(&base_url, &cookie, "capped", "").await;
+    assert_eq!(resp.status(), 413);
+    assert_eq!(console_archive(&base_url, &cookie, "capped", "key=a&key=b").await.status(), 200);
+
+    s3_request("PUT", &format!("{}/capped/b", base_url), vec![0u8; 500]).await;
+    let resp = console_archive(&base_url, &cookie, "capped", "key=a&key=b").await;
+    assert_eq!(resp.status(), 413);
+}
+
+#[tokio::test]
+async fn test_console_archive_erasure_coded() {
+    let (base_url, tmp) = start_server_parity(2).await;
+    s3_request("PUT", &format!("{}/parity-test", base_url), vec![]).await;
+    let data: Vec<u8> = (0..350u32).map(|i| i as u8).collect();
+    s3_request("PUT", &format!("{}/parity-test/dir/file.bin", base_url), data.clone()).await;
+    s3_request("PUT", &format!("{}/parity-test/dir/small.txt", base_url), b"tiny".to_vec()).await;
+
+    // A lost data chunk is rebuilt from parity on the way into the archive
+    std::fs::remove_file(tmp.path().join("buckets/parity-test/dir/file.bin.ec/000000")).unwrap();
+
+    let cookie = console_login(&base_url).await;
+    let resp = console_archive(&base_url, &cookie, "parity-test", "prefix=dir/").await;
     assert_eq!(resp.status(), 200);
-    assert_eq!(resp.bytes().await.unwrap().len(), 0);
+    let entries = unzip(&resp.bytes().await.unwrap());
+    assert_eq!(entries, vec![("dir/file.bin".to_string(), data), ("dir/small.txt".to_string(), b"tiny".to_vec())]);
 }
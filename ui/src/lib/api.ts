@@ -0,0 +1,17 @@
+let csrfToken = ''
+
+/** Remember the CSRF token issued with the current session. */
+export function setCsrfToken(token: string | undefined) {
+  csrfToken = token ?? ''
+}
+
+/** `fetch` for the console API; state-changing requests carry the CSRF token. */
+export function apiFetch(input: string, init: RequestInit = {}): Promise<Response> {
+  const method = (init.method ?? 'GET').toUpperCase()
+  if (method !== 'GET' && method !== 'HEAD' && csrfToken) {
+    const headers = new Headers(init.headers)
+    headers.set('X-CSRF-Token', csrfToken)
+    init = { ...init, headers }
+  }
+  return fetch(input, init)
+}
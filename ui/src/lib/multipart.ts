@@ -0,0 +1,124 @@
+import { apiFetch } from '$lib/api'
+
+/** Files at least this large are sent in parts rather than a single PUT. */
+export const MULTIPART_THRESHOLD = 64 * 1024 * 1024
+const PART_SIZE = 16 * 1024 * 1024
+const PARALLEL_PARTS = 4
+const PART_ATTEMPTS = 3
+
+interface UploadedPart {
+  partNumber: number
+  etag: string
+  size: number
+  checksum: string | null
+}
+
+// Upload IDs are remembered per file so a page reload picks up where it left off
+function resumeKey(bucket: string, key: string, file: File): string {
+  return `maxio-upload:${bucket}/${key}:${file.size}:${file.lastModified}`
+}
+
+async function errorOf(res: Response, fallback: string): Promise<Error> {
+  const data = await res.json().catch(() => ({}))
+  return new Error(data.error || `${fallback} (${res.status})`)
+}
+
+// WebCrypto is only available in secure contexts; parts go unchecked elsewhere
+async function sha256(blob: Blob): Promise<string | null> {
+  if (!globalThis.crypto?.subtle) return null
+  const digest = await crypto.subtle.digest('SHA-256', await blob.arrayBuffer())
+  return btoa(String.fromCharCode(...new Uint8Array(digest)))
+}
+
+async function startUpload(base: string, key: string, file: File): Promise<string> {
+  const res = await apiFetch(`${base}/uploads`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      key,
+      contentType: file.type || 'application/octet-stream',
+      checksumAlgorithm: globalThis.crypto?.subtle ? 'SHA256' : undefined,
+    }),
+  })
+  if (!res.ok) throw await errorOf(res, 'Failed to start upload')
+  return (await res.json()).uploadId
+}
+
+/** Parts already stored for a remembered upload, or null if it is gone. */
+async function uploadedParts(base: string, uploadId: string): Promise<UploadedPart[] | null> {
+  const res = await apiFetch(`${base}/uploads/${encodeURIComponent(uploadId)}`)
+  if (!res.ok) return null
+  return (await res.json()).parts
+}
+
+async function sendPart(base: string, uploadId: string, partNumber: number, blob: Blob): Promise<void> {
+  const checksum = await sha256(blob)
+  for (let attempt = 1; ; attempt++) {
+    try {
+      const res = await apiFetch(`${base}/uploads/${encodeURIComponent(uploadId)}/parts/${partNumber}`, {
+        method: 'PUT',
+        headers: checksum ? { 'x-amz-checksum-sha256': checksum } : {},
+        body: blob,
+      })
+      if (res.ok) return
+      // Client errors such as a quota or checksum refusal won't go away on retry
+      if (res.status < 500 || attempt >= PART_ATTEMPTS) throw await errorOf(res, `Part ${partNumber} failed`)
+    } catch (err) {
+      if (attempt >= PART_ATTEMPTS || !(err instanceof TypeError)) throw err
+    }
+    await new Promise((resolve) => setTimeout(resolve, 1000 * attempt))
+  }
+}
+
+/**
+ * Upload a large file as a multipart upload: parts go up several at a time
+ * with SHA-256 checksums, failed parts are retried, and an interrupted upload
+ * of the same file resumes from the parts the server already has.
+ */
+export async function uploadMultipart(
+  bucket: string,
+  key: string,
+  file: File,
+  onProgress: (fraction: number) => void,
+): Promise<void> {
+  const base = `/api/buckets/${encodeURIComponent(bucket)}`
+  const remembered = resumeKey(bucket, key, file)
+  const partCount = Math.max(1, Math.ceil(file.size / PART_SIZE))
+  const partSize = (n: number) => Math.min(PART_SIZE, file.size - (n - 1) * PART_SIZE)
+
+  let uploadId = localStorage.getItem(remembered)
+  let done = new Set<number>()
+  if (uploadId) {
+    const parts = await uploadedParts(base, uploadId)
+    if (parts) {
+      done = new Set(parts.filter((p) => p.size === partSize(p.partNumber)).map((p) => p.partNumber))
+    } else {
+      uploadId = null
+    }
+  }
+  if (!uploadId) {
+    uploadId = await startUpload(base, key, file)
+    localStorage.setItem(remembered, uploadId)
+  }
+
+  let sent = [...done].reduce((total, n) => total + partSize(n), 0)
+  onProgress(file.size ? sent / file.size : 0)
+  const pending = Array.from({ length: partCount }, (_, i) => i + 1).filter((n) => !done.has(n))
+  const worker = async () => {
+    for (let n = pending.shift(); n !== undefined; n = pending.shift()) {
+      const start = (n - 1) * PART_SIZE
+      await sendPart(base, uploadId!, n, file.slice(start, start + partSize(n)))
+      sent += partSize(n)
+      onProgress(sent / file.size)
+    }
+  }
+  await Promise.all(Array.from({ length: PARALLEL_PARTS }, worker))
+
+  const res = await apiFetch(`${base}/uploads/${encodeURIComponent(uploadId)}/complete`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: '{}',
+  })
+  if (!res.ok) throw await errorOf(res, 'Failed to complete upload')
+  localStorage.removeItem(remembered)
+}